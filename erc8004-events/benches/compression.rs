@@ -0,0 +1,120 @@
+//! Write and read throughput of the Parquet [`Compression`] codecs on a
+//! synthetic archive of about 100 MB (in Arrow memory).
+//!
+//! ```bash
+//! cargo bench -p erc8004-events --bench compression
+//! ```
+
+#![expect(
+    unused_crate_dependencies,
+    reason = "the benchmark only needs alloy, arrow and the library"
+)]
+#![expect(clippy::print_stdout, reason = "benchmark results go to stdout")]
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use alloy::primitives::{Address, B256, Bytes, LogData};
+use alloy::rpc::types::Log;
+use arrow_array::RecordBatch;
+use erc8004_events::parquet::{self, Compression, ParquetWriterOptions};
+
+/// Logs per generated batch.
+const BATCH_ROWS: u64 = 10_000;
+/// Target in-memory size of the archive.
+const ARCHIVE_BYTES: usize = 100 * 1024 * 1024;
+
+fn main() -> anyhow::Result<()> {
+    let batches = archive()?;
+    let mb: usize = batches
+        .iter()
+        .map(RecordBatch::get_array_memory_size)
+        .sum::<usize>()
+        >> 20;
+    let rows: usize = batches.iter().map(RecordBatch::num_rows).sum();
+    println!("{rows} rows, {mb} MB in memory");
+    let mb = f64::from(u32::try_from(mb)?);
+
+    let path = std::env::temp_dir().join(format!(
+        "erc8004-events-bench-{}.parquet",
+        std::process::id()
+    ));
+    // `zstd-3/nd` shows what Parquet's dictionary encoding of `address`
+    // and `topic0` saves on top of the codec.
+    for (name, compression, dictionary_encoding) in [
+        ("zstd-3", Compression::Zstd, true),
+        ("zstd-3/nd", Compression::Zstd, false),
+        ("lz4_raw", Compression::Lz4Raw, true),
+        ("snappy", Compression::Snappy, true),
+        ("none", Compression::None, true),
+    ] {
+        let opts = ParquetWriterOptions {
+            compression,
+            dictionary_encoding,
+            ..Default::default()
+        };
+        let start = Instant::now();
+        parquet::write_with(&path, &batches, opts)?;
+        let write = start.elapsed();
+        let size = std::fs::metadata(&path)?.len() >> 20;
+
+        let start = Instant::now();
+        black_box(parquet::read(&path)?);
+        let read = start.elapsed();
+
+        println!(
+            "{name:<9} {size:>5} MB on disk  write {:>7.1} MB/s  read {:>7.1} MB/s",
+            mb / write.as_secs_f64(),
+            mb / read.as_secs_f64(),
+        );
+    }
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+/// Batches of realistic-looking logs: a few contracts and event
+/// signatures, random-looking hashes and 128-byte payloads.
+fn archive() -> anyhow::Result<Vec<RecordBatch>> {
+    let hash = |n: u64, salt: u64| {
+        let mut bytes = [0u8; 32];
+        for (i, chunk) in (0u64..).zip(bytes.chunks_mut(8)) {
+            let x = (n ^ salt)
+                .wrapping_add(i)
+                .wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            chunk.copy_from_slice(&x.to_be_bytes());
+        }
+        B256::from(bytes)
+    };
+
+    let mut batches = Vec::new();
+    let mut bytes = 0;
+    let mut block = 0;
+    while bytes < ARCHIVE_BYTES {
+        let logs: Vec<Log> = (block..block + BATCH_ROWS)
+            .map(|n| Log {
+                inner: alloy::primitives::Log {
+                    address: Address::repeat_byte(if n % 2 == 0 { 0x80 } else { 0x04 }),
+                    data: LogData::new_unchecked(
+                        vec![
+                            B256::with_last_byte(u8::try_from(n % 3).unwrap_or(0)),
+                            hash(n, 1),
+                        ],
+                        Bytes::from(
+                            [hash(n, 2).0, hash(n, 3).0, hash(n, 4).0, hash(n, 5).0].concat(),
+                        ),
+                    ),
+                },
+                block_number: Some(n),
+                transaction_hash: Some(hash(n, 0)),
+                transaction_index: Some(0),
+                log_index: Some(0),
+                ..Default::default()
+            })
+            .collect();
+        let (batch, _) = parquet::logs_to_batch(&logs)?;
+        bytes += batch.get_array_memory_size();
+        batches.push(batch);
+        block += BATCH_ROWS;
+    }
+    Ok(batches)
+}
@@ -0,0 +1,85 @@
+//! Write time of one 500k-row batch with sequential and column-parallel
+//! Parquet encoding ([`ParquetWriterOptions::parallel_encode`]).
+//!
+//! ```bash
+//! cargo bench -p erc8004-events --features rayon --bench parallel_encode
+//! ```
+
+#![expect(
+    unused_crate_dependencies,
+    reason = "the benchmark only needs alloy, arrow and the library"
+)]
+#![expect(clippy::print_stdout, reason = "benchmark results go to stdout")]
+
+use std::time::Instant;
+
+use alloy::primitives::{Address, B256, Bytes, LogData};
+use alloy::rpc::types::Log;
+use erc8004_events::parquet::{self, ParquetWriterOptions};
+
+/// Rows in the benchmarked batch.
+const ROWS: u64 = 500_000;
+/// Writes per mode; the fastest is reported.
+const RUNS: usize = 5;
+
+fn main() -> anyhow::Result<()> {
+    let (batch, _) = parquet::logs_to_batch(&logs())?;
+    let mb = batch.get_array_memory_size() >> 20;
+    let batches = [batch];
+    let cores = std::thread::available_parallelism().map_or(1, std::num::NonZero::get);
+    println!("{ROWS} rows, {mb} MB in memory, {cores} cores");
+
+    let path = std::env::temp_dir().join(format!(
+        "erc8004-events-bench-parallel-{}.parquet",
+        std::process::id()
+    ));
+    let mut sequential = None;
+    for (name, parallel_encode) in [("sequential", false), ("parallel", true)] {
+        let opts = ParquetWriterOptions {
+            parallel_encode,
+            ..Default::default()
+        };
+        let mut best = f64::INFINITY;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            parquet::write_with(&path, &batches, opts)?;
+            best = best.min(start.elapsed().as_secs_f64());
+        }
+        let speedup = sequential.map_or(1.0, |s: f64| s / best);
+        sequential.get_or_insert(best);
+        println!("{name:<10} {:>8.1} ms  {speedup:.2}x", best * 1_000.0);
+    }
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+/// Logs with dense string columns: distinct transaction hashes, topics and
+/// 128-byte payloads on every row.
+fn logs() -> Vec<Log> {
+    let hash = |n: u64, salt: u64| {
+        let mut bytes = [0u8; 32];
+        for (i, chunk) in (0u64..).zip(bytes.chunks_mut(8)) {
+            let x = (n ^ salt)
+                .wrapping_add(i)
+                .wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            chunk.copy_from_slice(&x.to_be_bytes());
+        }
+        B256::from(bytes)
+    };
+    (0..ROWS)
+        .map(|n| Log {
+            inner: alloy::primitives::Log {
+                address: Address::repeat_byte(if n % 2 == 0 { 0x80 } else { 0x04 }),
+                data: LogData::new_unchecked(
+                    vec![hash(n % 3, 6), hash(n, 1)],
+                    Bytes::from([hash(n, 2).0, hash(n, 3).0, hash(n, 4).0, hash(n, 5).0].concat()),
+                ),
+            },
+            block_number: Some(n),
+            transaction_hash: Some(hash(n, 0)),
+            transaction_index: Some(0),
+            log_index: Some(0),
+            ..Default::default()
+        })
+        .collect()
+}
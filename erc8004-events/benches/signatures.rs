@@ -0,0 +1,58 @@
+//! Classifying `topic0` by per-log string comparison versus the shared
+//! [`signatures`] lookup table.
+//!
+//! ```bash
+//! cargo bench -p erc8004-events --bench signatures
+//! ```
+
+#![expect(
+    unused_crate_dependencies,
+    reason = "the benchmark only needs alloy and the library"
+)]
+#![expect(clippy::print_stdout, reason = "benchmark results go to stdout")]
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use alloy::primitives::B256;
+use erc8004_events::signatures::{self, EventKind};
+
+/// Lookups per measurement.
+const ROUNDS: u32 = 1_000_000;
+
+fn main() {
+    // Every known event plus one unknown topic, cycled.
+    let topics: Vec<B256> = EventKind::ALL
+        .iter()
+        .map(|k| k.signature_hash())
+        .chain([B256::ZERO])
+        .collect();
+    let hex: Vec<String> = topics.iter().map(|t| format!("{t:#x}")).collect();
+    let known: Vec<(String, EventKind)> = EventKind::ALL
+        .iter()
+        .map(|&k| (format!("{:#x}", k.signature_hash()), k))
+        .collect();
+
+    measure("string match", || {
+        for topic0 in hex.iter().cycle().take(ROUNDS as usize) {
+            black_box(known.iter().find(|(h, _)| h == topic0).map(|&(_, k)| k));
+        }
+    });
+    measure("map (B256)", || {
+        for topic0 in topics.iter().cycle().take(ROUNDS as usize) {
+            black_box(signatures::lookup(black_box(topic0)));
+        }
+    });
+    measure("map (hex)", || {
+        for topic0 in hex.iter().cycle().take(ROUNDS as usize) {
+            black_box(signatures::lookup_hex(black_box(topic0)));
+        }
+    });
+}
+
+/// Run `f` once and print the mean time per lookup.
+fn measure(name: &str, f: impl FnOnce()) {
+    let start = Instant::now();
+    f();
+    println!("{name:<14} {:?}/lookup", start.elapsed() / ROUNDS);
+}
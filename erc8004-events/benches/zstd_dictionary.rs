@@ -0,0 +1,101 @@
+//! Zstd with and without a [`train_zstd_dictionary`] dictionary on the
+//! `address` and `topic0` values of a synthetic 1M-row archive.
+//!
+//! Each chunk of [`CHUNK_ROWS`] rows is compressed on its own, column by
+//! column, like a Parquet page of plain-encoded values.
+//!
+//! ```bash
+//! cargo bench -p erc8004-events --bench zstd_dictionary
+//! ```
+
+#![expect(
+    unused_crate_dependencies,
+    reason = "the benchmark only needs alloy, arrow, zstd and the library"
+)]
+#![expect(clippy::print_stdout, reason = "benchmark results go to stdout")]
+
+use std::time::Instant;
+
+use alloy::primitives::{Address, B256, Bytes, LogData};
+use alloy::rpc::types::Log;
+use anyhow::Context;
+use arrow_array::{Array, RecordBatch, StringArray};
+use erc8004_events::parquet::{self, DICTIONARY_COLUMNS, train_zstd_dictionary};
+
+/// Rows in the archive.
+const ROWS: u64 = 1_000_000;
+/// Rows per generated batch and per compressed chunk.
+const CHUNK_ROWS: u64 = 10_000;
+/// Chunks the dictionary is trained on.
+const TRAINING_CHUNKS: usize = 10;
+/// Zstd level of the archives (the `parquet` crate's default).
+const LEVEL: i32 = 3;
+
+fn main() -> anyhow::Result<()> {
+    let batches = archive()?;
+    let start = Instant::now();
+    let training = batches.get(..TRAINING_CHUNKS).unwrap_or(&batches);
+    let dictionary = train_zstd_dictionary(training, &DICTIONARY_COLUMNS)?;
+    println!(
+        "{ROWS} rows; {} byte dictionary trained in {:.1?}",
+        dictionary.len(),
+        start.elapsed()
+    );
+
+    let mut plain = zstd::bulk::Compressor::new(LEVEL)?;
+    let mut with_dictionary = zstd::bulk::Compressor::with_dictionary(LEVEL, &dictionary)?;
+    let (mut raw, mut plain_bytes, mut dictionary_bytes) = (0, 0, 0);
+    for batch in &batches {
+        for name in DICTIONARY_COLUMNS {
+            let column = batch
+                .column_by_name(name)
+                .and_then(|c| c.as_any().downcast_ref::<StringArray>())
+                .with_context(|| format!("missing column {name}"))?;
+            let values: Vec<u8> = (0..column.len())
+                .flat_map(|i| column.value(i).bytes())
+                .collect();
+            raw += values.len();
+            plain_bytes += plain.compress(&values)?.len();
+            dictionary_bytes += with_dictionary.compress(&values)?.len();
+        }
+    }
+
+    let ratio = |bytes: usize| -> anyhow::Result<f64> {
+        Ok(f64::from(u32::try_from(raw)?) / f64::from(u32::try_from(bytes)?))
+    };
+    let (plain_ratio, dictionary_ratio) = (ratio(plain_bytes)?, ratio(dictionary_bytes)?);
+    println!("raw         {raw:>11} bytes");
+    println!("zstd-{LEVEL}      {plain_bytes:>11} bytes  ratio {plain_ratio:>7.1}");
+    println!("zstd-{LEVEL}+dict {dictionary_bytes:>11} bytes  ratio {dictionary_ratio:>7.1}");
+    println!(
+        "dictionary gain {:+.1}%",
+        (dictionary_ratio / plain_ratio - 1.0) * 100.0
+    );
+    Ok(())
+}
+
+/// [`CHUNK_ROWS`]-row batches of logs from two contracts with three event
+/// signatures, like a registry archive.
+fn archive() -> anyhow::Result<Vec<RecordBatch>> {
+    (0..ROWS / CHUNK_ROWS)
+        .map(|chunk| {
+            let logs: Vec<Log> = (chunk * CHUNK_ROWS..(chunk + 1) * CHUNK_ROWS)
+                .map(|n| Log {
+                    inner: alloy::primitives::Log {
+                        address: Address::repeat_byte(if n % 2 == 0 { 0x80 } else { 0x04 }),
+                        data: LogData::new_unchecked(
+                            vec![B256::with_last_byte(u8::try_from(n % 3).unwrap_or(0))],
+                            Bytes::new(),
+                        ),
+                    },
+                    block_number: Some(n),
+                    transaction_hash: Some(B256::ZERO),
+                    transaction_index: Some(0),
+                    log_index: Some(0),
+                    ..Default::default()
+                })
+                .collect();
+            Ok(parquet::logs_to_batch(&logs)?.0)
+        })
+        .collect()
+}
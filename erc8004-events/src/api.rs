@@ -0,0 +1,225 @@
+//! Data model and OpenAPI 3.1 document of the planned read API
+//! (`--features openapi`).
+//!
+//! The archiver does not serve HTTP yet.  This module pins down the
+//! surface a future server will implement, so that clients can be
+//! generated from the spec ahead of it:
+//!
+//! - `GET /api/v1/events/{chain_id}/{contract}` — archived events as
+//!   [`LogRow`]s, filtered by [`EventsQuery`].
+//! - `GET /api/v1/chains` — the known chains as [`ChainInfo`].
+//! - `GET /api/v1/status` — per-chain sync progress as [`ChainStatus`].
+//!
+//! `erc8004-events generate-openapi --output openapi.json` writes the
+//! document returned by [`openapi_json`].
+
+use serde::{Deserialize, Serialize};
+use utoipa::openapi::path::{OperationBuilder, Parameter, ParameterBuilder, ParameterIn};
+use utoipa::openapi::{
+    ArrayBuilder, ContentBuilder, HttpMethod, OpenApi as OpenApiDoc, Ref, RefOr, Required,
+    ResponseBuilder, Schema,
+};
+use utoipa::{IntoParams, Modify, OpenApi, PartialSchema, ToSchema};
+
+use crate::chains::ChainConfig;
+use crate::fetcher::{ChainSyncReport, ContractOutcome};
+use crate::parquet::LogRow;
+
+/// Filters of `GET /api/v1/events/{chain_id}/{contract}`, mirroring
+/// [`EventQuery`](crate::parquet::EventQuery).
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventsQuery {
+    /// First block to return (inclusive).
+    pub from_block: Option<u64>,
+    /// Last block to return (inclusive).
+    pub to_block: Option<u64>,
+    /// Only events emitted by this address (`0x`-prefixed hex).
+    pub address: Option<String>,
+    /// Maximum number of events to return (default 100).
+    pub limit: Option<usize>,
+}
+
+/// A chain the archiver knows, as listed by `GET /api/v1/chains`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ChainInfo {
+    /// EIP-155 chain ID.
+    pub chain_id: u64,
+    /// Human-readable chain name.
+    pub name: &'static str,
+    /// Whether this is a testnet deployment.
+    pub is_testnet: bool,
+    /// Block at which the Identity Registry was deployed.
+    pub deployment_block: u64,
+    /// Identity Registry address (`0x`-prefixed hex).
+    pub identity: String,
+    /// Reputation Registry address (`0x`-prefixed hex).
+    pub reputation: String,
+}
+
+impl From<&ChainConfig> for ChainInfo {
+    fn from(chain: &ChainConfig) -> Self {
+        let addrs = chain.addresses();
+        Self {
+            chain_id: chain.chain_id(),
+            name: chain.name,
+            is_testnet: chain.is_testnet,
+            deployment_block: chain.deployment_block,
+            identity: addrs.identity.to_string(),
+            reputation: addrs.reputation.to_string(),
+        }
+    }
+}
+
+/// Sync progress of one chain, as listed by `GET /api/v1/status`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ChainStatus {
+    /// EIP-155 chain ID.
+    pub chain_id: u64,
+    /// Last fully synced block, from the chain's cursor.
+    pub last_block: Option<u64>,
+    /// Unix timestamp (seconds) of the last successful sync.
+    pub synced_at: Option<u64>,
+    /// Report of the most recent sync run, if the server ran one.
+    pub last_report: Option<ChainSyncReport>,
+}
+
+/// Error body of every non-2xx response.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiError {
+    /// What went wrong.
+    pub error: String,
+}
+
+/// Adds the planned endpoints to [`ApiDoc`].
+///
+/// `#[utoipa::path]` only documents existing handler functions, and there
+/// are none until the server exists, so the operations are built here.
+#[derive(Debug)]
+struct PlannedPaths;
+
+impl Modify for PlannedPaths {
+    fn modify(&self, openapi: &mut OpenApiDoc) {
+        let mut events_params = vec![
+            path_param("chain_id", "EIP-155 chain ID", u64::schema()),
+            path_param("contract", "`identity` or `reputation`", String::schema()),
+        ];
+        events_params.extend(EventsQuery::into_params(|| Some(ParameterIn::Query)));
+        let events = OperationBuilder::new()
+            .summary(Some("Archived events of one contract of one chain."))
+            .operation_id(Some("events"))
+            .parameters(Some(events_params))
+            .response(
+                "200",
+                json_response("Matching events in on-chain order", list_of("LogRow")),
+            )
+            .response("400", json_response("Invalid filter", error_ref()))
+            .response(
+                "404",
+                json_response("Unknown chain or contract", error_ref()),
+            );
+        let chains = OperationBuilder::new()
+            .summary(Some("Every chain the archiver knows."))
+            .operation_id(Some("chains"))
+            .response("200", json_response("Known chains", list_of("ChainInfo")));
+        let status = OperationBuilder::new()
+            .summary(Some("Sync progress of every archived chain."))
+            .operation_id(Some("status"))
+            .response(
+                "200",
+                json_response("Per-chain sync status", list_of("ChainStatus")),
+            );
+
+        let paths = &mut openapi.paths;
+        paths.add_path_operation(
+            "/api/v1/events/{chain_id}/{contract}",
+            vec![HttpMethod::Get],
+            events,
+        );
+        paths.add_path_operation("/api/v1/chains", vec![HttpMethod::Get], chains);
+        paths.add_path_operation("/api/v1/status", vec![HttpMethod::Get], status);
+    }
+}
+
+/// A required path parameter.
+fn path_param(name: &str, description: &str, schema: RefOr<Schema>) -> Parameter {
+    ParameterBuilder::new()
+        .name(name)
+        .parameter_in(ParameterIn::Path)
+        .required(Required::True)
+        .description(Some(description))
+        .schema(Some(schema))
+        .build()
+}
+
+/// An array of the component schema `name`.
+fn list_of(name: &str) -> RefOr<Schema> {
+    ArrayBuilder::new()
+        .items(Ref::from_schema_name(name))
+        .into()
+}
+
+/// The [`ApiError`] component schema.
+fn error_ref() -> RefOr<Schema> {
+    Ref::from_schema_name("ApiError").into()
+}
+
+/// A response with a JSON body of `schema`.
+fn json_response(description: &str, schema: RefOr<Schema>) -> ResponseBuilder {
+    ResponseBuilder::new().description(description).content(
+        "application/json",
+        ContentBuilder::new().schema(Some(schema)).build(),
+    )
+}
+
+/// The OpenAPI document of the planned API.
+#[derive(Debug, OpenApi)]
+#[openapi(
+    info(
+        title = "ERC-8004 Events API",
+        description = "Read API over archived ERC-8004 registry events (planned)."
+    ),
+    modifiers(&PlannedPaths),
+    components(schemas(
+        LogRow,
+        ChainInfo,
+        ChainStatus,
+        ChainSyncReport,
+        ContractOutcome,
+        ApiError
+    ))
+)]
+pub struct ApiDoc;
+
+/// The OpenAPI 3.1 document of the planned API as pretty-printed JSON.
+///
+/// # Errors
+///
+/// Returns an error if the document cannot be serialized.
+pub fn openapi_json() -> serde_json::Result<String> {
+    ApiDoc::openapi().to_pretty_json()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_lists_planned_paths() -> anyhow::Result<()> {
+        let doc: serde_json::Value = serde_json::from_str(&openapi_json()?)?;
+        assert!(
+            doc["openapi"]
+                .as_str()
+                .is_some_and(|v| v.starts_with("3.1"))
+        );
+        for path in [
+            "/api/v1/events/{chain_id}/{contract}",
+            "/api/v1/chains",
+            "/api/v1/status",
+        ] {
+            assert!(doc["paths"][path]["get"].is_object(), "missing {path}");
+        }
+        assert!(doc["components"]["schemas"]["LogRow"].is_object());
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+//! JWT-authenticated HTTP transport for private RPC endpoints.
+//!
+//! Some nodes (Engine API style endpoints, private Erigon/Reth deployments)
+//! require an HS256 bearer token signed with a shared secret.  [`JwtHttp`]
+//! mints a fresh token with `iat = now` for every request so that long
+//! syncs never run into the short token lifetime such nodes enforce.
+
+use std::path::Path;
+use std::task::{Context as TaskContext, Poll};
+
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::http::reqwest::{self, Url, header::CONTENT_TYPE};
+use alloy::transports::{TransportError, TransportErrorKind, TransportFut, TransportResult};
+use anyhow::{Context, Result};
+use jsonwebtoken::{EncodingKey, Header};
+use serde::Serialize;
+use tower::Service;
+
+use crate::cursor::unix_now;
+
+/// Token lifetime in seconds.
+const TOKEN_TTL: u64 = 60;
+
+/// Engine API style claims.
+#[derive(Serialize)]
+struct Claims {
+    iat: u64,
+    exp: u64,
+}
+
+/// HTTP transport that attaches `Authorization: Bearer <jwt>` to every
+/// JSON-RPC request.
+#[derive(Clone)]
+pub struct JwtHttp {
+    client: reqwest::Client,
+    url: Url,
+    key: EncodingKey,
+}
+
+impl std::fmt::Debug for JwtHttp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtHttp")
+            .field("url", &self.url.as_str())
+            .finish_non_exhaustive()
+    }
+}
+
+impl JwtHttp {
+    /// Create a transport for `url` using the hex-encoded secret stored at
+    /// `secret_path` (optionally `0x`-prefixed, surrounding whitespace ignored).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not valid hex.
+    pub fn from_secret_file(url: Url, secret_path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(secret_path)
+            .with_context(|| format!("reading {}", secret_path.display()))?;
+        let secret = alloy::hex::decode(text.trim())
+            .with_context(|| format!("decoding JWT secret in {}", secret_path.display()))?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url,
+            key: EncodingKey::from_secret(&secret),
+        })
+    }
+
+    /// Sign a fresh token valid for [`TOKEN_TTL`] seconds.
+    fn token(&self) -> Result<String, jsonwebtoken::errors::Error> {
+        let iat = unix_now();
+        let claims = Claims {
+            iat,
+            exp: iat + TOKEN_TTL,
+        };
+        jsonwebtoken::encode(&Header::default(), &claims, &self.key)
+    }
+
+    /// Send a single request packet with a freshly signed token.
+    async fn send(self, req: RequestPacket) -> TransportResult<ResponsePacket> {
+        let token = self.token().map_err(TransportErrorKind::custom)?;
+        let body = serde_json::to_vec(&req).map_err(TransportError::ser_err)?;
+
+        let resp = self
+            .client
+            .post(self.url)
+            .bearer_auth(token)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .map_err(TransportErrorKind::custom)?;
+        let status = resp.status();
+        let body = resp.bytes().await.map_err(TransportErrorKind::custom)?;
+
+        if !status.is_success() {
+            return Err(TransportErrorKind::http_error(
+                status.as_u16(),
+                String::from_utf8_lossy(&body).into_owned(),
+            ));
+        }
+        serde_json::from_slice(&body)
+            .map_err(|e| TransportError::deser_err(e, String::from_utf8_lossy(&body)))
+    }
+}
+
+impl Service<RequestPacket> for JwtHttp {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().send(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    use alloy::providers::{Provider, ProviderBuilder};
+    use alloy::rpc::client::RpcClient;
+    use jsonwebtoken::{DecodingKey, Validation};
+
+    use super::*;
+    use crate::testutil::TempDir;
+
+    /// Answer one HTTP request with a JSON-RPC `eth_chainId` result and
+    /// return its `Authorization` header.
+    fn serve_once(listener: &TcpListener) -> Option<String> {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let (mut auth, mut len) = (None, 0);
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = line.split_once(':').unwrap_or((line, ""));
+            match name.to_ascii_lowercase().as_str() {
+                "authorization" => auth = Some(value.trim().to_owned()),
+                "content-length" => len = value.trim().parse().unwrap(),
+                _ => {}
+            }
+        }
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body).unwrap();
+        let req: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let resp = serde_json::json!({"jsonrpc": "2.0", "id": req["id"], "result": "0x2105"});
+        let resp = resp.to_string();
+        write!(
+            reader.get_mut(),
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{resp}",
+            resp.len()
+        )
+        .unwrap();
+        auth
+    }
+
+    #[tokio::test]
+    async fn test_requests_carry_signed_bearer_token() {
+        let dir = TempDir::new("jwt");
+        let secret = [0x42_u8; 32];
+        let secret_path = dir.join("jwt.hex");
+        std::fs::write(&secret_path, format!("0x{}\n", alloy::hex::encode(secret))).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url: Url = format!("http://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let server = std::thread::spawn(move || serve_once(&listener));
+
+        let transport = JwtHttp::from_secret_file(url, &secret_path).unwrap();
+        let provider = ProviderBuilder::new().connect_client(RpcClient::new(transport, true));
+        assert_eq!(provider.get_chain_id().await.unwrap(), 8453);
+
+        let auth = server.join().unwrap().unwrap();
+        let token = auth.strip_prefix("Bearer ").unwrap();
+        let claims = jsonwebtoken::decode::<serde_json::Value>(
+            token,
+            &DecodingKey::from_secret(&secret),
+            &Validation::default(),
+        )
+        .unwrap()
+        .claims;
+        let (iat, exp) = (
+            claims["iat"].as_u64().unwrap(),
+            claims["exp"].as_u64().unwrap(),
+        );
+        assert_eq!(exp - iat, TOKEN_TTL);
+        assert!(iat.abs_diff(unix_now()) <= 5);
+    }
+}
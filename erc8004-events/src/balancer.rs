@@ -0,0 +1,221 @@
+//! Round-robin load balancing of `eth_getLogs` across a chain's RPCs.
+//!
+//! Without it, a chain is synced from one endpoint at a time and the next
+//! one is only used after the current one failed.  With
+//! [`SyncOptions::load_balance`](crate::fetcher::SyncOptions::load_balance)
+//! every request goes to the next endpoint of an [`RpcLoadBalancer`]; an
+//! endpoint whose request failed is cooled down for [`COOLDOWN`] and
+//! skipped until then.  When every endpoint is cooling, the one that
+//! failed first is used anyway rather than stalling the sync.
+//!
+//! An endpoint lagging behind the chain's head answers `[]` for blocks it
+//! has not seen, so a window is only sent to an endpoint that
+//! [`RpcEndpoint::reaches`] its last block; the sync falls back to its
+//! primary RPC otherwise.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use alloy::providers::{DynProvider, Provider};
+
+/// How long an endpoint is skipped after a failed request.
+pub const COOLDOWN: Duration = Duration::from_secs(30);
+
+/// One RPC endpoint of an [`RpcLoadBalancer`].
+#[derive(Debug)]
+pub struct RpcEndpoint {
+    url: String,
+    provider: DynProvider,
+    /// Milliseconds since the Unix epoch of the last failure (0 = never).
+    last_error_at: AtomicU64,
+    /// Highest head block the endpoint reported (0 = not asked yet).
+    head: AtomicU64,
+}
+
+impl RpcEndpoint {
+    /// Wrap a connected provider.
+    #[must_use]
+    pub const fn new(url: String, provider: DynProvider) -> Self {
+        Self {
+            url,
+            provider,
+            last_error_at: AtomicU64::new(0),
+            head: AtomicU64::new(0),
+        }
+    }
+
+    /// The endpoint's URL.
+    #[must_use]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The endpoint's provider.
+    #[must_use]
+    pub const fn provider(&self) -> &DynProvider {
+        &self.provider
+    }
+
+    /// Start the endpoint's cooldown.
+    pub fn mark_failed(&self) {
+        self.last_error_at.store(now_ms().max(1), Ordering::Relaxed);
+    }
+
+    /// Whether the endpoint has seen `block`.  Its head is only asked for
+    /// when an earlier answer does not cover `block`; an endpoint that does
+    /// not answer within `timeout` counts as behind.
+    pub async fn reaches(&self, block: u64, timeout: Duration) -> bool {
+        if self.head.load(Ordering::Relaxed) >= block {
+            return true;
+        }
+        let Ok(Ok(head)) = tokio::time::timeout(timeout, self.provider.get_block_number()).await
+        else {
+            return false;
+        };
+        self.head.fetch_max(head, Ordering::Relaxed);
+        head >= block
+    }
+
+    /// Whether the endpoint failed less than [`COOLDOWN`] before `now_ms`.
+    fn is_cooling(&self, now_ms: u64) -> bool {
+        let at = self.last_error_at.load(Ordering::Relaxed);
+        at != 0 && now_ms.saturating_sub(at) < cooldown_ms()
+    }
+}
+
+/// Hands out a chain's RPC endpoints in rotation; see the
+/// [module docs](self).
+#[derive(Debug)]
+pub struct RpcLoadBalancer {
+    rpcs: Vec<RpcEndpoint>,
+    current: AtomicUsize,
+}
+
+impl RpcLoadBalancer {
+    /// Balance requests across `rpcs`.
+    #[must_use]
+    pub const fn new(rpcs: Vec<RpcEndpoint>) -> Self {
+        Self {
+            rpcs,
+            current: AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of endpoints.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.rpcs.len()
+    }
+
+    /// Whether there are no endpoints.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.rpcs.is_empty()
+    }
+
+    /// The endpoint for the next request, or `None` without endpoints.
+    #[must_use]
+    pub fn pick(&self) -> Option<&RpcEndpoint> {
+        self.pick_at(now_ms())
+    }
+
+    /// [`Self::pick`] at a given time.
+    fn pick_at(&self, now_ms: u64) -> Option<&RpcEndpoint> {
+        let n = self.rpcs.len();
+        (0..n)
+            .filter_map(|_| {
+                let i = self.current.fetch_add(1, Ordering::Relaxed);
+                self.rpcs.get(i % n)
+            })
+            .find(|rpc| !rpc.is_cooling(now_ms))
+            .or_else(|| {
+                self.rpcs
+                    .iter()
+                    .min_by_key(|rpc| rpc.last_error_at.load(Ordering::Relaxed))
+            })
+    }
+}
+
+/// The current time in milliseconds since the Unix epoch.
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// [`COOLDOWN`] in milliseconds.
+fn cooldown_ms() -> u64 {
+    u64::try_from(COOLDOWN.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U64;
+    use alloy::providers::ProviderBuilder;
+    use alloy::transports::mock::Asserter;
+
+    use super::*;
+
+    fn balancer(urls: &[&str]) -> RpcLoadBalancer {
+        RpcLoadBalancer::new(
+            urls.iter()
+                .map(|url| {
+                    let provider = ProviderBuilder::new()
+                        .connect_mocked_client(Asserter::new())
+                        .erased();
+                    RpcEndpoint::new((*url).to_owned(), provider)
+                })
+                .collect(),
+        )
+    }
+
+    fn picks(lb: &RpcLoadBalancer, now_ms: u64, n: usize) -> Vec<String> {
+        (0..n)
+            .filter_map(|_| lb.pick_at(now_ms).map(|rpc| rpc.url().to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn test_round_robin_skips_cooling_endpoints() {
+        let lb = balancer(&["a", "b", "c"]);
+        assert_eq!(picks(&lb, 1_000, 4), ["a", "b", "c", "a"]);
+
+        if let Some(b) = lb.rpcs.get(1) {
+            b.last_error_at.store(1_000, Ordering::Relaxed);
+        }
+        assert_eq!(picks(&lb, 1_000, 4), ["c", "a", "c", "a"]);
+
+        // Back in rotation once the cooldown has passed.
+        assert_eq!(picks(&lb, 1_000 + cooldown_ms(), 3), ["b", "c", "a"]);
+    }
+
+    #[test]
+    fn test_all_cooling_uses_oldest_failure() {
+        let lb = balancer(&["a", "b"]);
+        for (rpc, at) in lb.rpcs.iter().zip([2_000, 1_000]) {
+            rpc.last_error_at.store(at, Ordering::Relaxed);
+        }
+        assert_eq!(picks(&lb, 2_000, 2), ["b", "b"]);
+        assert!(balancer(&[]).pick().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reaches_asks_for_head_only_when_behind() {
+        let asserter = Asserter::new();
+        let rpc = RpcEndpoint::new(
+            "a".to_owned(),
+            ProviderBuilder::new()
+                .connect_mocked_client(asserter.clone())
+                .erased(),
+        );
+        let timeout = Duration::from_secs(1);
+
+        asserter.push_success(&U64::from(90));
+        assert!(!rpc.reaches(100, timeout).await);
+        assert!(rpc.reaches(90, timeout).await);
+        asserter.push_success(&U64::from(120));
+        assert!(rpc.reaches(100, timeout).await);
+        asserter.push_failure_msg("unavailable");
+        assert!(!rpc.reaches(200, timeout).await);
+    }
+}
@@ -0,0 +1,458 @@
+//! Agent census: one row per registered agent.
+//!
+//! [`onchain_agents`] replays a chain's archived Identity Registry events in
+//! on-chain order and keeps, per agent, the registration block, the latest
+//! `agentURI` (from `Registered` and `URIUpdated`) and the current owner
+//! (from `Registered` and later ERC-721 transfers).  [`resolve`] then fills
+//! in the off-chain columns from each agent's registration file.  `data:`
+//! URIs are always decoded inline; other URIs are only fetched when a
+//! network resolver is passed, so an offline census is fully reproducible
+//! from the archive.  Agents whose file cannot be resolved or parsed keep
+//! their on-chain fields and null off-chain columns.
+//!
+//! Rows are sorted by agent ID and written as Parquet or CSV with
+//! [`write`].
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use ::parquet::arrow::ArrowWriter;
+use alloy::primitives::{Address, U256};
+use anyhow::{Context, Result, bail};
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::{ArrayRef, BooleanArray, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use erc8004::events::IdentityEvent;
+use erc8004::types::RegistrationFile;
+use erc8004::uri::UriResolver;
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::decode::decode_event;
+#[cfg(feature = "delta")]
+use crate::delta;
+use crate::parquet::{self, LogRow, ParquetWriterOptions};
+
+/// Registration files fetched concurrently by [`resolve`].
+const FETCH_CONCURRENCY: usize = 16;
+
+/// Per-file deadline in [`resolve`]; a slow gateway must not stall the
+/// census.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Output schema of [`write`] in Parquet format.
+static CENSUS_SCHEMA: LazyLock<Arc<Schema>> = LazyLock::new(|| {
+    Arc::new(Schema::new(vec![
+        Field::new("agent_id", DataType::Utf8, false),
+        Field::new("owner", DataType::Utf8, false),
+        Field::new("uri", DataType::Utf8, false),
+        Field::new("registered_block", DataType::UInt64, false),
+        Field::new("name", DataType::Utf8, true),
+        Field::new("description", DataType::Utf8, true),
+        Field::new("active", DataType::Boolean, true),
+        Field::new(
+            "protocols",
+            DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+            true,
+        ),
+    ]))
+});
+
+/// One agent of the census.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AgentRecord {
+    /// Token ID in the Identity Registry, as a decimal string (`uint256`).
+    pub agent_id: String,
+    /// Current owner (`0x`-prefixed, lowercase).
+    pub owner: String,
+    /// Latest `agentURI`.
+    pub uri: String,
+    /// Block of the `Registered` event.
+    pub registered_block: u64,
+    /// `name` from the registration file.
+    pub name: Option<String>,
+    /// `description` from the registration file.
+    pub description: Option<String>,
+    /// `active` from the registration file.
+    pub active: Option<bool>,
+    /// Distinct service names (`A2A`, `MCP`, ...) from the registration
+    /// file, sorted.
+    pub protocols: Option<Vec<String>>,
+}
+
+impl AgentRecord {
+    /// Copy the off-chain columns from a registration file.
+    fn fill(&mut self, reg: RegistrationFile) {
+        let protocols: BTreeSet<String> = reg.services.into_iter().map(|s| s.name).collect();
+        self.name = Some(reg.name);
+        self.description = Some(reg.description);
+        self.active = Some(reg.active);
+        self.protocols = Some(protocols.into_iter().collect());
+    }
+}
+
+/// Output format of [`write`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CensusFormat {
+    /// Parquet with a `list<string>` `protocols` column.
+    #[default]
+    Parquet,
+    /// RFC 4180 CSV with a header row; `protocols` is `;`-separated.
+    Csv,
+}
+
+impl FromStr for CensusFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "parquet" => Ok(Self::Parquet),
+            "csv" => Ok(Self::Csv),
+            other => bail!("unknown census format `{other}` (expected parquet or csv)"),
+        }
+    }
+}
+
+impl fmt::Display for CensusFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Parquet => "parquet",
+            Self::Csv => "csv",
+        })
+    }
+}
+
+/// Read a chain's archived Identity Registry events from `data_dir`, from
+/// the Delta table if there is one and from `<chain_id>/identity.parquet`
+/// (and its rotated files) otherwise.
+///
+/// # Errors
+///
+/// Returns an error if the archive cannot be read.
+pub fn identity_batches(data_dir: &Path, chain_id: u64) -> Result<Vec<RecordBatch>> {
+    #[cfg(feature = "delta")]
+    if delta::is_table(data_dir) {
+        return delta::read(data_dir, chain_id, Some("identity"), None);
+    }
+    parquet::read_rotated(&data_dir.join(chain_id.to_string()).join("identity.parquet"))
+}
+
+/// Replay identity events into one [`AgentRecord`] per registered agent,
+/// sorted by agent ID, with the off-chain columns unset.
+///
+/// Removed (reorged) logs and logs that do not decode as Identity Registry
+/// events are skipped.  Transfers of agents without a `Registered` event
+/// are ignored.
+///
+/// # Errors
+///
+/// Returns an error if a batch does not have the event schema.
+pub fn onchain_agents(batches: &[RecordBatch]) -> Result<Vec<AgentRecord>> {
+    let mut rows = Vec::new();
+    for batch in batches {
+        rows.extend(parquet::batch_to_rows(batch)?);
+    }
+    rows.retain(|r| !r.removed);
+    rows.sort_by_key(|r| (r.block_number, r.tx_index, r.log_index));
+
+    let mut agents: BTreeMap<U256, AgentRecord> = BTreeMap::new();
+    for row in &rows {
+        let Some(event) = decode_event::<IdentityEvent>(row) else {
+            continue;
+        };
+        match event {
+            IdentityEvent::Registered(e) => {
+                agents.insert(
+                    e.agentId,
+                    AgentRecord {
+                        agent_id: e.agentId.to_string(),
+                        owner: hex_address(e.owner),
+                        uri: e.agentURI,
+                        registered_block: row.block_number,
+                        name: None,
+                        description: None,
+                        active: None,
+                        protocols: None,
+                    },
+                );
+            }
+            IdentityEvent::URIUpdated(e) => {
+                if let Some(agent) = agents.get_mut(&e.agentId) {
+                    agent.uri = e.newURI;
+                }
+            }
+            IdentityEvent::Transfer(e) if !e.from.is_zero() => {
+                if let Some(agent) = agents.get_mut(&e.tokenId) {
+                    agent.owner = hex_address(e.to);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(agents.into_values().collect())
+}
+
+/// Fill in the off-chain columns of `agents` from their registration
+/// files.
+///
+/// `data:` URIs are decoded inline; everything else goes to `resolver`
+/// (pass [`CompositeUriResolver::empty`] to stay offline).  Failures are
+/// logged and leave the agent's off-chain columns null.  Returns the number
+/// of agents that were resolved.
+///
+/// [`CompositeUriResolver::empty`]: erc8004::uri::CompositeUriResolver::empty
+pub async fn resolve(agents: &mut [AgentRecord], resolver: &dyn UriResolver) -> usize {
+    let files: Vec<_> = futures::stream::iter(agents.iter())
+        .map(|agent| async move {
+            tokio::time::timeout(
+                FETCH_TIMEOUT,
+                RegistrationFile::from_uri(&agent.uri, resolver),
+            )
+            .await
+        })
+        .buffered(FETCH_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut resolved = 0;
+    for (agent, file) in agents.iter_mut().zip(files) {
+        match file {
+            Ok(Ok(reg)) => {
+                agent.fill(reg);
+                resolved += 1;
+            }
+            Ok(Err(e)) => {
+                tracing::debug!(agent_id = %agent.agent_id, uri = %agent.uri, error = %e, "registration file unavailable");
+            }
+            Err(_) => {
+                tracing::warn!(agent_id = %agent.agent_id, uri = %agent.uri, "registration file fetch timed out");
+            }
+        }
+    }
+    resolved
+}
+
+/// Write `agents` to `path` in `format`, atomically (temp file + rename).
+///
+/// # Errors
+///
+/// Returns an error on I/O failure or if the Parquet writer rejects the
+/// data.
+pub fn write(
+    path: &Path,
+    agents: &[AgentRecord],
+    format: CensusFormat,
+    opts: ParquetWriterOptions,
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension(format!("{format}.tmp"));
+    match format {
+        CensusFormat::Parquet => {
+            let file = std::fs::File::create(&tmp)
+                .with_context(|| format!("creating {}", tmp.display()))?;
+            let props = parquet::writer_properties(opts)?;
+            let mut writer = ArrowWriter::try_new(file, Arc::clone(&CENSUS_SCHEMA), Some(props))?;
+            writer.write(&to_batch(agents)?)?;
+            writer.close()?;
+        }
+        CensusFormat::Csv => {
+            std::fs::write(&tmp, to_csv(agents))
+                .with_context(|| format!("writing {}", tmp.display()))?;
+        }
+    }
+    std::fs::rename(&tmp, path)
+        .with_context(|| format!("renaming {} → {}", tmp.display(), path.display()))
+}
+
+/// Format an address the way the archive stores hex (`0x`-prefixed,
+/// lowercase).
+fn hex_address(a: Address) -> String {
+    format!("0x{}", alloy::hex::encode(a))
+}
+
+/// Build the [`CENSUS_SCHEMA`] batch.
+fn to_batch(agents: &[AgentRecord]) -> Result<RecordBatch> {
+    let mut protocols = ListBuilder::new(StringBuilder::new());
+    for agent in agents {
+        match &agent.protocols {
+            Some(list) => {
+                for p in list {
+                    protocols.values().append_value(p);
+                }
+                protocols.append(true);
+            }
+            None => protocols.append(false),
+        }
+    }
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(
+            agents.iter().map(|a| &a.agent_id),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            agents.iter().map(|a| &a.owner),
+        )),
+        Arc::new(StringArray::from_iter_values(agents.iter().map(|a| &a.uri))),
+        Arc::new(UInt64Array::from_iter_values(
+            agents.iter().map(|a| a.registered_block),
+        )),
+        Arc::new(StringArray::from_iter(
+            agents.iter().map(|a| a.name.as_deref()),
+        )),
+        Arc::new(StringArray::from_iter(
+            agents.iter().map(|a| a.description.as_deref()),
+        )),
+        Arc::new(BooleanArray::from_iter(agents.iter().map(|a| a.active))),
+        Arc::new(protocols.finish()),
+    ];
+    Ok(RecordBatch::try_new(Arc::clone(&CENSUS_SCHEMA), columns)?)
+}
+
+/// Render `agents` as CSV; null columns are empty fields.
+fn to_csv(agents: &[AgentRecord]) -> String {
+    let mut out =
+        String::from("agent_id,owner,uri,registered_block,name,description,active,protocols\n");
+    for a in agents {
+        let fields = [
+            a.agent_id.clone(),
+            a.owner.clone(),
+            a.uri.clone(),
+            a.registered_block.to_string(),
+            a.name.clone().unwrap_or_default(),
+            a.description.clone().unwrap_or_default(),
+            a.active.map(|b| b.to_string()).unwrap_or_default(),
+            a.protocols
+                .as_ref()
+                .map(|p| p.join(";"))
+                .unwrap_or_default(),
+        ];
+        let line: Vec<String> = fields.iter().map(String::as_str).map(csv_field).collect();
+        out.push_str(&line.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{B256, LogData, address};
+    use alloy::rpc::types::Log;
+    use alloy::sol_types::SolEvent;
+    use erc8004::contracts::IdentityRegistry;
+    use erc8004::uri::CompositeUriResolver;
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    fn log(block: u64, data: LogData) -> Log {
+        testutil::log(block)
+            .data(data)
+            .tx_hash(B256::with_last_byte(u8::try_from(block).unwrap_or(0)))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_census_joins_events_and_registration_files() -> Result<()> {
+        let alice = address!("0x00000000000000000000000000000000000000a1");
+        let bob = address!("0x00000000000000000000000000000000000000b0");
+        let json = r#"{"type":"https://eips.ethereum.org/EIPS/eip-8004#registration-v1","name":"Bot","description":"a, \"quoted\" bot","services":[{"name":"MCP","endpoint":"https://x"},{"name":"A2A","endpoint":"https://y"}],"active":true}"#;
+        let logs = [
+            log(
+                10,
+                IdentityRegistry::Registered {
+                    agentId: U256::from(2),
+                    agentURI: "ipfs://unreachable".to_owned(),
+                    owner: alice,
+                }
+                .encode_log_data(),
+            ),
+            log(
+                11,
+                IdentityRegistry::Registered {
+                    agentId: U256::from(1),
+                    agentURI: "ipfs://old".to_owned(),
+                    owner: alice,
+                }
+                .encode_log_data(),
+            ),
+            log(
+                12,
+                IdentityRegistry::URIUpdated {
+                    agentId: U256::from(1),
+                    newURI: format!("data:application/json,{}", json.replace('%', "%25")),
+                    updatedBy: alice,
+                }
+                .encode_log_data(),
+            ),
+            log(
+                13,
+                IdentityRegistry::Transfer {
+                    from: alice,
+                    to: bob,
+                    tokenId: U256::from(1),
+                }
+                .encode_log_data(),
+            ),
+        ];
+        let (batch, _) = parquet::logs_to_batch(&logs)?;
+
+        let mut agents = onchain_agents(&[batch])?;
+        assert_eq!(
+            resolve(&mut agents, &CompositeUriResolver::empty()).await,
+            1
+        );
+        let [first, second] = agents.as_slice() else {
+            bail!("expected two agents, got {agents:?}");
+        };
+        assert_eq!(first.agent_id, "1");
+        assert_eq!(first.owner, hex_address(bob));
+        assert_eq!(first.registered_block, 11);
+        assert_eq!(first.name.as_deref(), Some("Bot"));
+        assert_eq!(first.active, Some(true));
+        assert_eq!(
+            first.protocols,
+            Some(vec!["A2A".to_owned(), "MCP".to_owned()])
+        );
+        assert_eq!(second.agent_id, "2");
+        assert_eq!(second.uri, "ipfs://unreachable");
+        assert_eq!(second.name, None);
+        assert_eq!(second.protocols, None);
+
+        let csv = to_csv(&agents);
+        assert!(
+            csv.contains(",Bot,\"a, \"\"quoted\"\" bot\",true,A2A;MCP\n"),
+            "{csv}"
+        );
+        assert!(csv.ends_with(",ipfs://unreachable,10,,,,\n"), "{csv}");
+
+        let dir = TempDir::new("census");
+        let path = dir.join("census.parquet");
+        write(
+            &path,
+            &agents,
+            CensusFormat::Parquet,
+            ParquetWriterOptions::default(),
+        )?;
+        let file = std::fs::File::open(&path)?;
+        let rows: usize =
+            ::parquet::arrow::arrow_reader::ParquetRecordBatchReader::try_new(file, 1024)?
+                .map(|b| b.map(|b| b.num_rows()))
+                .sum::<Result<_, _>>()?;
+        assert_eq!(rows, 2);
+        Ok(())
+    }
+}
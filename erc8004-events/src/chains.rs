@@ -4,6 +4,7 @@
 //! (deployment block, default public RPC) that the SDK itself does not track.
 
 use erc8004::Network;
+use erc8004::networks::NetworkAddresses;
 
 /// Operational metadata for a single ERC-8004 chain deployment.
 #[derive(Debug, Clone, Copy)]
@@ -14,18 +15,90 @@ pub struct ChainConfig {
     pub name: &'static str,
     /// Block at which the Identity Registry contract was deployed.
     pub deployment_block: u64,
+    /// First block with a registry event, when known (see
+    /// `discover-first-event`); fresh syncs start here instead of at
+    /// `deployment_block`.
+    pub first_event_block: Option<u64>,
+    /// Registry addresses of a chain deployed outside the shared CREATE2
+    /// addresses that [`Network::addresses`] returns; see
+    /// [`ChainConfig::addresses`].
+    pub address_override: Option<NetworkAddresses>,
     /// Suggested public RPC endpoint.
     pub default_rpc: &'static str,
+    /// Symbol of the native gas token (e.g. `ETH`).
+    pub native_token_symbol: &'static str,
+    /// Decimals of the native gas token.
+    pub native_token_decimals: u8,
+    /// Kind of chain (L1, rollup, sidechain).
+    pub category: Category,
     /// Whether this is a testnet deployment.
     pub is_testnet: bool,
 }
 
+/// Kind of chain, for selecting chains by architecture
+/// (`sync --category`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Category {
+    /// An independent base layer (Ethereum, BNB Smart Chain, Avalanche, …).
+    L1,
+    /// A rollup settling on Ethereum (Base, Arbitrum, Linea, …).
+    L2Rollup,
+    /// An EVM chain with its own validators that bridges to Ethereum
+    /// without settling on it (Polygon PoS, Gnosis).
+    Sidechain,
+}
+
+impl Category {
+    /// Every category, in declaration order.
+    pub const ALL: [Self; 3] = [Self::L1, Self::L2Rollup, Self::Sidechain];
+
+    /// The name accepted by `--category`.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::L1 => "l1",
+            Self::L2Rollup => "l2",
+            Self::Sidechain => "sidechain",
+        }
+    }
+}
+
+impl std::fmt::Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Category {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "l1" => Ok(Self::L1),
+            "l2" | "l2-rollup" => Ok(Self::L2Rollup),
+            "sidechain" => Ok(Self::Sidechain),
+            _ => anyhow::bail!("invalid chain category `{s}`; expected l1, l2 or sidechain"),
+        }
+    }
+}
+
 impl ChainConfig {
     /// Convenience: the EIP-155 chain ID.
     #[must_use]
     pub const fn chain_id(&self) -> u64 {
         self.network.chain_id()
     }
+
+    /// Registry addresses to archive: the override when set, else the
+    /// network's shared addresses.
+    #[must_use]
+    pub const fn addresses(&self) -> NetworkAddresses {
+        match self.address_override {
+            Some(addrs) => addrs,
+            None => self.network.addresses(),
+        }
+    }
 }
 
 /// All known ERC-8004 chain configurations (single source of truth).
@@ -35,112 +108,192 @@ pub const fn chain_id(&self) -> u64 {
         network: Network::BaseMainnet,
         name: "Base",
         deployment_block: 41_663_783,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://base.gateway.tenderly.co",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::EthereumMainnet,
         name: "Ethereum",
         deployment_block: 24_339_871,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://mainnet.gateway.tenderly.co",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L1,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::PolygonMainnet,
         name: "Polygon",
         deployment_block: 82_458_484,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://rpc.sentio.xyz/matic",
+        native_token_symbol: "POL",
+        native_token_decimals: 18,
+        category: Category::Sidechain,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::ArbitrumMainnet,
         name: "Arbitrum One",
         deployment_block: 428_895_443,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://rpc.sentio.xyz/arbitrum-one",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::CeloMainnet,
         name: "Celo",
         deployment_block: 58_396_724,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://celo-json-rpc.stakely.io",
+        native_token_symbol: "CELO",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::GnosisMainnet,
         name: "Gnosis",
         deployment_block: 44_505_010,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://gnosis-rpc.publicnode.com",
+        native_token_symbol: "xDAI",
+        native_token_decimals: 18,
+        category: Category::Sidechain,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::ScrollMainnet,
         name: "Scroll",
         deployment_block: 29_432_417,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://scroll-rpc.publicnode.com",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::TaikoMainnet,
         name: "Taiko",
         deployment_block: 4_305_747,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://rpc.taiko.xyz",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::BscMainnet,
         name: "BNB Smart Chain",
         deployment_block: 79_027_268,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://public-bsc.nownodes.io",
+        native_token_symbol: "BNB",
+        native_token_decimals: 18,
+        category: Category::L1,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::MonadMainnet,
         name: "Monad",
         deployment_block: 52_952_790,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://rpc.sentio.xyz/monad-mainnet",
+        native_token_symbol: "MON",
+        native_token_decimals: 18,
+        category: Category::L1,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::AbstractMainnet,
         name: "Abstract",
         deployment_block: 39_596_871,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://api.mainnet.abs.xyz",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::AvalancheMainnet,
         name: "Avalanche",
         deployment_block: 77_389_000,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://rpc.sentio.xyz/avalanche",
+        native_token_symbol: "AVAX",
+        native_token_decimals: 18,
+        category: Category::L1,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::LineaMainnet,
         name: "Linea",
         deployment_block: 28_662_553,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://linea-rpc.publicnode.com",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::MantleMainnet,
         name: "Mantle",
         deployment_block: 91_333_846,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://rpc.mantle.xyz",
+        native_token_symbol: "MNT",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::MegaEthMainnet,
         name: "MegaETH",
         deployment_block: 7_833_805,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://mainnet.megaeth.com/rpc",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: false,
     },
     ChainConfig {
         network: Network::OptimismMainnet,
         name: "Optimism",
         deployment_block: 147_514_947,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://rpc.sentio.xyz/optimism",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: false,
     },
     // Testnets
@@ -148,84 +301,144 @@ pub const fn chain_id(&self) -> u64 {
         network: Network::BaseSepolia,
         name: "Base Sepolia",
         deployment_block: 36_304_165,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://sepolia.base.org",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: true,
     },
     ChainConfig {
         network: Network::EthereumSepolia,
         name: "Ethereum Sepolia",
         deployment_block: 9_989_393,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://ethereum-sepolia-rpc.publicnode.com",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L1,
         is_testnet: true,
     },
     ChainConfig {
         network: Network::PolygonAmoy,
         name: "Polygon Amoy",
         deployment_block: 33_069_064,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://rpc-amoy.polygon.technology",
+        native_token_symbol: "POL",
+        native_token_decimals: 18,
+        category: Category::Sidechain,
         is_testnet: true,
     },
     ChainConfig {
         network: Network::ArbitrumSepolia,
         name: "Arbitrum Sepolia",
         deployment_block: 239_945_838,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://sepolia-rollup.arbitrum.io/rpc",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: true,
     },
     ChainConfig {
         network: Network::CeloAlfajores,
         name: "Celo Alfajores",
         deployment_block: 17_013_547,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://alfajores-forno.celo-testnet.org",
+        native_token_symbol: "CELO",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: true,
     },
     ChainConfig {
         network: Network::ScrollSepolia,
         name: "Scroll Sepolia",
         deployment_block: 16_543_185,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://sepolia-rpc.scroll.io",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: true,
     },
     ChainConfig {
         network: Network::BscTestnet,
         name: "BSC Testnet",
         deployment_block: 84_555_147,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://bsc-testnet-rpc.publicnode.com",
+        native_token_symbol: "BNB",
+        native_token_decimals: 18,
+        category: Category::L1,
         is_testnet: true,
     },
     ChainConfig {
         network: Network::MonadTestnet,
         name: "Monad Testnet",
         deployment_block: 10_391_697,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://testnet-rpc.monad.xyz",
+        native_token_symbol: "MON",
+        native_token_decimals: 18,
+        category: Category::L1,
         is_testnet: true,
     },
     ChainConfig {
         network: Network::LineaSepolia,
         name: "Linea Sepolia",
         deployment_block: 24_323_547,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://rpc.sepolia.linea.build",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: true,
     },
     ChainConfig {
         network: Network::MantleSepolia,
         name: "Mantle Sepolia",
         deployment_block: 34_586_937,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://rpc.sepolia.mantle.xyz",
+        native_token_symbol: "MNT",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: true,
     },
     ChainConfig {
         network: Network::MegaEthTestnet,
         name: "MegaETH Testnet",
         deployment_block: 11_668_749,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://carrot.megaeth.com/rpc",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: true,
     },
     ChainConfig {
         network: Network::OptimismSepolia,
         name: "Optimism Sepolia",
         deployment_block: 34_412_868,
+        first_event_block: None,
+        address_override: None,
         default_rpc: "https://sepolia.optimism.io",
+        native_token_symbol: "ETH",
+        native_token_decimals: 18,
+        category: Category::L2Rollup,
         is_testnet: true,
     },
 ];
@@ -235,3 +448,117 @@ pub const fn chain_id(&self) -> u64 {
 pub fn by_chain_id(chain_id: u64) -> Option<&'static ChainConfig> {
     ALL.iter().find(|c| c.chain_id() == chain_id)
 }
+
+/// The chains of [`ALL`] for which `predicate` holds, in table order.
+///
+/// ```
+/// use erc8004_events::chains::{self, Category};
+///
+/// let l2_mainnets: Vec<_> =
+///     chains::filter(|c| c.category == Category::L2Rollup && !c.is_testnet).collect();
+/// assert!(l2_mainnets.iter().any(|c| c.name == "Base"));
+/// ```
+pub fn filter<F>(mut predicate: F) -> impl Iterator<Item = &'static ChainConfig>
+where
+    F: FnMut(&ChainConfig) -> bool,
+{
+    ALL.iter().filter(move |c| predicate(c))
+}
+
+/// All mainnet chains, in table order.
+pub fn mainnets() -> impl Iterator<Item = &'static ChainConfig> {
+    filter(|c| !c.is_testnet)
+}
+
+/// All testnet chains, in table order.
+pub fn testnets() -> impl Iterator<Item = &'static ChainConfig> {
+    filter(|c| c.is_testnet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mainnets_and_testnets_partition_all() {
+        assert!(mainnets().all(|c| !c.is_testnet));
+        assert!(testnets().all(|c| c.is_testnet));
+        assert_eq!(mainnets().count() + testnets().count(), ALL.len());
+        assert_eq!(mainnets().next().map(ChainConfig::chain_id), Some(8453));
+    }
+
+    #[test]
+    fn test_filter_by_category_and_rpc() {
+        let l1: Vec<_> = filter(|c| c.category == Category::L1 && !c.is_testnet)
+            .map(|c| c.name)
+            .collect();
+        assert!(l1.contains(&"Ethereum"));
+        assert!(!l1.contains(&"Base"));
+        assert!(!l1.contains(&"Polygon"));
+
+        let sentio: Vec<_> = filter(|c| c.default_rpc.starts_with("https://rpc.sentio.xyz/"))
+            .map(ChainConfig::chain_id)
+            .collect();
+        assert!(sentio.contains(&137));
+        assert!(!sentio.contains(&8453));
+        assert_eq!(filter(|_| false).count(), 0);
+    }
+
+    /// An `address_override` must differ from the shared addresses of its
+    /// chain's kind.  Whether the addresses are right is only checked
+    /// on-chain, by [`test_registries_deployed_at_default_rpc`].
+    #[test]
+    fn test_address_overrides_are_not_redundant() {
+        for c in ALL {
+            let shared = if c.is_testnet {
+                NetworkAddresses::TESTNET
+            } else {
+                NetworkAddresses::MAINNET
+            };
+            if let Some(addrs) = c.address_override {
+                assert_ne!(addrs, shared, "{}: redundant address_override", c.name);
+            }
+        }
+    }
+
+    /// Both registries of every chain have code at its `default_rpc`.
+    /// Needs network access: `cargo test -p erc8004-events -- --ignored`.
+    #[tokio::test]
+    #[ignore = "queries every chain's public RPC"]
+    async fn test_registries_deployed_at_default_rpc() -> anyhow::Result<()> {
+        use alloy::providers::{Provider, ProviderBuilder};
+        use anyhow::Context;
+
+        let mut missing = Vec::new();
+        for c in ALL {
+            let provider = ProviderBuilder::new().connect_http(c.default_rpc.parse()?);
+            let addrs = c.addresses();
+            for (contract, address) in [
+                ("identity", addrs.identity),
+                ("reputation", addrs.reputation),
+            ] {
+                let code = provider
+                    .get_code_at(address)
+                    .await
+                    .with_context(|| format!("{} ({})", c.name, c.default_rpc))?;
+                if code.is_empty() {
+                    missing.push(format!("{} {contract} {address}", c.name));
+                }
+            }
+        }
+        assert!(missing.is_empty(), "no contract code at: {missing:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_every_chain_has_a_known_category() {
+        for c in ALL {
+            assert!(Category::ALL.contains(&c.category), "{}", c.name);
+            assert_eq!(c.category.as_str().parse::<Category>().unwrap(), c.category);
+        }
+        for category in Category::ALL {
+            assert!(ALL.iter().any(|c| c.category == category), "{category}");
+        }
+        assert!("l3".parse::<Category>().is_err());
+    }
+}
@@ -0,0 +1,306 @@
+//! `SHA256SUMS` for a whole data directory, optionally signed.
+//!
+//! [`write`] lists every chain's Parquet archive files (the same files as
+//! each chain's [`manifest`](crate::manifest)) in `<data_dir>/SHA256SUMS`, in the format of
+//! `sha256sum`, so consumers can check a downloaded archive with
+//! `sha256sum -c SHA256SUMS` from the data directory.  Paths are relative
+//! to the data directory (`<chain_id>/<file>`).
+//!
+//! With the `sign` feature, [`sign`] writes a detached Ed25519 signature of
+//! the sums file to `SHA256SUMS.sig` (the raw 64 signature bytes, as
+//! produced by `openssl pkeyutl -sign -rawin`) and [`verify_signature`]
+//! checks it, so a publisher can prove the listed hashes came from them.
+//! Keys are PKCS#8 PEM files, e.g. from `openssl genpkey -algorithm ed25519`.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use crate::fetcher::discover_archived_chains;
+use crate::manifest::{archive_files, sha256_file};
+use crate::retry;
+
+/// File name of the sums file inside the data directory.
+pub const SUMS_FILE: &str = "SHA256SUMS";
+
+/// File name of the detached signature of [`SUMS_FILE`].
+pub const SIG_FILE: &str = "SHA256SUMS.sig";
+
+/// A difference between `SHA256SUMS` and the data directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// A listed file no longer exists.
+    Missing {
+        /// Path from the sums file.
+        path: String,
+    },
+    /// A listed file's content hash differs from the recorded one.
+    Hash {
+        /// Path from the sums file.
+        path: String,
+        /// SHA-256 recorded in the sums file.
+        expected: String,
+        /// SHA-256 of the file on disk.
+        actual: String,
+    },
+    /// An archive file is not listed in the sums file.
+    Unlisted {
+        /// Path relative to the data directory.
+        path: String,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing { path } => write!(f, "{path}: listed but missing"),
+            Self::Hash {
+                path,
+                expected,
+                actual,
+            } => write!(f, "{path}: sha256 {actual}, {SUMS_FILE} has {expected}"),
+            Self::Unlisted { path } => write!(f, "{path}: not in {SUMS_FILE}"),
+        }
+    }
+}
+
+/// Hash every archive file of every chain in `data_dir` and write
+/// `<data_dir>/SHA256SUMS` atomically.  Returns the number of files listed.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read or the sums file cannot be
+/// written.
+pub fn write(data_dir: &Path) -> Result<usize> {
+    let mut lines = Vec::new();
+    for (path, file) in archived_files(data_dir)? {
+        lines.push(format!("{}  {path}\n", sha256_file(&file)?));
+    }
+    write_atomic(&data_dir.join(SUMS_FILE), lines.concat().as_bytes())?;
+    Ok(lines.len())
+}
+
+/// Write `contents` to `<path>.tmp` and rename it to `path`, retrying
+/// transient failures.
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    retry::io(path, retry::DEFAULT_IO_RETRIES, || {
+        std::fs::write(&tmp, contents).with_context(|| format!("writing {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("renaming {} → {}", tmp.display(), path.display()))
+    })
+}
+
+/// Recompute the hash of every file in `<data_dir>/SHA256SUMS` and compare
+/// the listing with the archive files on disk.
+///
+/// # Errors
+///
+/// Returns an error if the sums file is missing or malformed, or a file
+/// cannot be read.
+pub fn verify(data_dir: &Path) -> Result<Vec<Mismatch>> {
+    let sums = read_sums(data_dir)?;
+    let mut mismatches = Vec::new();
+    for (expected, path) in &sums {
+        let file = data_dir.join(path);
+        if !file.exists() {
+            mismatches.push(Mismatch::Missing { path: path.clone() });
+            continue;
+        }
+        let actual = sha256_file(&file)?;
+        if actual != *expected {
+            mismatches.push(Mismatch::Hash {
+                path: path.clone(),
+                expected: expected.clone(),
+                actual,
+            });
+        }
+    }
+    for (path, _) in archived_files(data_dir)? {
+        if !sums.iter().any(|(_, p)| *p == path) {
+            mismatches.push(Mismatch::Unlisted { path });
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Sign `<data_dir>/SHA256SUMS` with the Ed25519 PKCS#8 PEM private key at
+/// `key` and write the detached signature to `SHA256SUMS.sig`.
+///
+/// # Errors
+///
+/// Returns an error if the sums file or key cannot be read, the key is not
+/// an Ed25519 key, or the crate was built without the `sign` feature.
+pub fn sign(data_dir: &Path, key: &Path) -> Result<()> {
+    #[cfg(feature = "sign")]
+    {
+        use ed25519_dalek::Signer as _;
+        use ed25519_dalek::SigningKey;
+        use ed25519_dalek::pkcs8::DecodePrivateKey as _;
+
+        let pem = read_pem(key)?;
+        let signing = SigningKey::from_pkcs8_pem(&pem)
+            .map_err(|e| anyhow::anyhow!("{}: not an Ed25519 private key: {e}", key.display()))?;
+        let sums = read_file(&data_dir.join(SUMS_FILE))?;
+        let signature = signing.sign(&sums).to_bytes();
+        write_atomic(&data_dir.join(SIG_FILE), &signature)
+    }
+    #[cfg(not(feature = "sign"))]
+    bail!(
+        "signing {} with {} requires the `sign` feature",
+        data_dir.join(SUMS_FILE).display(),
+        key.display()
+    )
+}
+
+/// Check `<data_dir>/SHA256SUMS.sig` against `SHA256SUMS` with the Ed25519
+/// PEM public key at `pubkey`.
+///
+/// # Errors
+///
+/// Returns an error if the signature does not match, a file cannot be
+/// read, or the crate was built without the `sign` feature.
+pub fn verify_signature(data_dir: &Path, pubkey: &Path) -> Result<()> {
+    #[cfg(feature = "sign")]
+    {
+        use ed25519_dalek::pkcs8::DecodePublicKey as _;
+        use ed25519_dalek::{Signature, VerifyingKey};
+
+        let pem = read_pem(pubkey)?;
+        let verifying = VerifyingKey::from_public_key_pem(&pem)
+            .map_err(|e| anyhow::anyhow!("{}: not an Ed25519 public key: {e}", pubkey.display()))?;
+        let sig_path = data_dir.join(SIG_FILE);
+        let signature = Signature::from_slice(&read_file(&sig_path)?)
+            .map_err(|e| anyhow::anyhow!("{}: {e}", sig_path.display()))?;
+        let sums = read_file(&data_dir.join(SUMS_FILE))?;
+        verifying.verify_strict(&sums, &signature).map_err(|_| {
+            anyhow::anyhow!(
+                "{} is not a valid signature of {SUMS_FILE} for {}",
+                sig_path.display(),
+                pubkey.display()
+            )
+        })
+    }
+    #[cfg(not(feature = "sign"))]
+    bail!(
+        "verifying {} with {} requires the `sign` feature",
+        data_dir.join(SIG_FILE).display(),
+        pubkey.display()
+    )
+}
+
+/// Archive files of every chain in `data_dir`, as paths relative to it
+/// (`<chain_id>/<file>`) with their full paths, sorted.
+fn archived_files(data_dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    for (config, dir) in discover_archived_chains(data_dir)? {
+        for (name, path) in archive_files(&dir)? {
+            files.push((format!("{}/{name}", config.chain_id()), path));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// `(sha256, path)` entries of `<data_dir>/SHA256SUMS`.
+fn read_sums(data_dir: &Path) -> Result<Vec<(String, String)>> {
+    let path = data_dir.join(SUMS_FILE);
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("reading {} (run `checksum` first)", path.display()))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            // `sha256sum` separates with two spaces, or " *" in binary mode.
+            let Some((hash, name)) = line.split_once(' ') else {
+                bail!("{}:{}: malformed line", path.display(), i + 1);
+            };
+            let name = name.strip_prefix([' ', '*']).unwrap_or(name);
+            if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+                bail!("{}:{}: malformed sha256", path.display(), i + 1);
+            }
+            Ok((hash.to_ascii_lowercase(), name.to_owned()))
+        })
+        .collect()
+}
+
+/// Contents of `path`.
+#[cfg(feature = "sign")]
+fn read_file(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("reading {}", path.display()))
+}
+
+/// Contents of the PEM file at `path`.
+#[cfg(feature = "sign")]
+fn read_pem(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor::Cursor;
+    use crate::parquet;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn test_sums_round_trip_and_detect_changes() {
+        let root = TempDir::new("checksum");
+        let chain_dir = root.join("8453");
+        Cursor::now(100).save(&chain_dir).unwrap();
+        parquet::write(&chain_dir.join("identity.parquet"), &[]).unwrap();
+
+        assert_eq!(write(&root).unwrap(), 1);
+        let sums = std::fs::read_to_string(root.join(SUMS_FILE)).unwrap();
+        assert!(sums.ends_with("  8453/identity.parquet\n"));
+        assert!(verify(&root).unwrap().is_empty());
+
+        parquet::write(&chain_dir.join("reputation.parquet"), &[]).unwrap();
+        std::fs::write(chain_dir.join("identity.parquet"), b"changed").unwrap();
+        let mismatches = verify(&root).unwrap();
+        assert!(matches!(
+            mismatches.as_slice(),
+            [Mismatch::Hash { path, .. }, Mismatch::Unlisted { path: unlisted }]
+                if path == "8453/identity.parquet" && unlisted == "8453/reputation.parquet"
+        ));
+    }
+
+    #[cfg(feature = "sign")]
+    #[test]
+    fn test_signature_round_trip() {
+        use ed25519_dalek::SigningKey;
+        use ed25519_dalek::pkcs8::{EncodePrivateKey as _, EncodePublicKey as _, LineEnding};
+
+        let root = TempDir::new("sign");
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let key_path = root.join("key.pem");
+        let pub_path = root.join("pub.pem");
+        std::fs::write(
+            &key_path,
+            key.to_pkcs8_pem(LineEnding::LF).unwrap().as_bytes(),
+        )
+        .unwrap();
+        std::fs::write(
+            &pub_path,
+            key.verifying_key()
+                .to_public_key_pem(LineEnding::LF)
+                .unwrap(),
+        )
+        .unwrap();
+
+        write(&root).unwrap();
+        sign(&root, &key_path).unwrap();
+        verify_signature(&root, &pub_path).unwrap();
+
+        // Any change to the sums invalidates the signature.
+        std::fs::write(
+            root.join(SUMS_FILE),
+            format!("{}  8453/x.parquet\n", "0".repeat(64)),
+        )
+        .unwrap();
+        assert!(verify_signature(&root, &pub_path).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! Chain-level parallelism of [`sync_all`](crate::fetcher::sync_all).
+//!
+//! A [`ChainConcurrency`] hands out one permit per chain being synced.
+//! With a fixed limit it is a plain semaphore.  An adaptive limit starts at
+//! [`ADAPTIVE_START`] chains and adjusts like TCP congestion control:
+//!
+//! - every chain that syncs without any rate-limit error having been
+//!   reported since the previous chain finished adds one chain, up to the
+//!   configured maximum;
+//! - [`RATE_LIMIT_SPIKE`] rate-limit errors from any chains, with no chain
+//!   finishing in between, halve the limit.
+//!
+//! Chains hold their permit until they finish, so a lower limit takes
+//! effect as running chains complete: their permits are retired instead of
+//! being handed to the next chain.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use tokio::sync::{AcquireError, Semaphore, SemaphorePermit};
+
+/// Chains synced in parallel when an adaptive limit starts.
+pub const ADAPTIVE_START: usize = 2;
+
+/// Rate-limit errors, with no chain finishing in between, that halve an
+/// adaptive limit.
+pub const RATE_LIMIT_SPIKE: u32 = 3;
+
+/// Limit on the number of chains synced at once; see the
+/// [module docs](self).
+#[derive(Debug)]
+pub struct ChainConcurrency {
+    permits: Semaphore,
+    /// Upper bound of an adaptive limit; `None` for a fixed limit.
+    max: Option<usize>,
+    /// Current limit and the number of permits still to retire.
+    state: Mutex<Limit>,
+    /// Rate-limit errors reported since the last adjustment or the last
+    /// chain that finished.
+    rate_limited: AtomicU32,
+}
+
+/// Current limit and the permits that have to be retired to reach it.
+#[derive(Debug, Clone, Copy)]
+struct Limit {
+    limit: usize,
+    debt: usize,
+}
+
+impl ChainConcurrency {
+    /// A fixed limit of `n` chains (at least one).
+    #[must_use]
+    pub fn fixed(n: usize) -> Self {
+        Self::with_limit(n.max(1), None)
+    }
+
+    /// An adaptive limit of up to `max` chains (at least one), starting at
+    /// [`ADAPTIVE_START`].
+    #[must_use]
+    pub fn adaptive(max: usize) -> Self {
+        let max = max.max(1);
+        Self::with_limit(ADAPTIVE_START.min(max), Some(max))
+    }
+
+    fn with_limit(limit: usize, max: Option<usize>) -> Self {
+        Self {
+            permits: Semaphore::new(limit),
+            max,
+            state: Mutex::new(Limit { limit, debt: 0 }),
+            rate_limited: AtomicU32::new(0),
+        }
+    }
+
+    /// The number of chains currently allowed to sync at once.
+    #[must_use]
+    pub fn limit(&self) -> usize {
+        self.lock().limit
+    }
+
+    /// Wait for a chain's permit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the semaphore was closed (never happens).
+    pub async fn acquire(&self) -> Result<SemaphorePermit<'_>, AcquireError> {
+        self.permits.acquire().await
+    }
+
+    /// Give back the permit of a chain that finished, growing an adaptive
+    /// limit if the chain `succeeded` without rate-limit errors reported
+    /// meanwhile.
+    pub fn release(&self, permit: SemaphorePermit<'_>, succeeded: bool) {
+        let mut state = self.lock();
+        if state.debt > 0 {
+            state.debt -= 1;
+            permit.forget();
+        } else {
+            drop(permit);
+        }
+        let quiet = self.rate_limited.swap(0, Ordering::SeqCst) == 0;
+        let Some(max) = self.max else {
+            return;
+        };
+        if succeeded && quiet && state.limit < max {
+            state.limit += 1;
+            if state.debt > 0 {
+                state.debt -= 1;
+            } else {
+                self.permits.add_permits(1);
+            }
+            tracing::debug!(limit = state.limit, "chain concurrency increased");
+        }
+    }
+
+    /// Record a rate-limit error of any chain, halving an adaptive limit
+    /// when they spike.
+    pub fn rate_limited(&self) {
+        if self.max.is_none()
+            || self.rate_limited.fetch_add(1, Ordering::SeqCst) + 1 < RATE_LIMIT_SPIKE
+        {
+            return;
+        }
+        self.rate_limited.store(0, Ordering::SeqCst);
+        let mut state = self.lock();
+        let limit = (state.limit / 2).max(1);
+        if limit < state.limit {
+            state.debt += state.limit - limit;
+            state.limit = limit;
+            tracing::warn!(limit, "rate limits spiking, chain concurrency reduced");
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Limit> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fixed_limit_never_changes() {
+        let c = ChainConcurrency::fixed(4);
+        for _ in 0..RATE_LIMIT_SPIKE {
+            c.rate_limited();
+        }
+        let permit = c.acquire().await.unwrap();
+        c.release(permit, true);
+        assert_eq!(c.limit(), 4);
+        assert_eq!(c.permits.available_permits(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_limit_grows_and_halves() {
+        let c = ChainConcurrency::adaptive(8);
+        assert_eq!(c.limit(), ADAPTIVE_START);
+
+        // Quiet successes grow the limit by one each, up to the maximum.
+        for _ in 0..10 {
+            let permit = c.acquire().await.unwrap();
+            c.release(permit, true);
+        }
+        assert_eq!(c.limit(), 8);
+        assert_eq!(c.permits.available_permits(), 8);
+
+        // A spike halves it; running chains retire their permits on release.
+        let running: Vec<_> = futures::future::join_all((0..8).map(|_| c.acquire()))
+            .await
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        for _ in 0..RATE_LIMIT_SPIKE {
+            c.rate_limited();
+        }
+        assert_eq!(c.limit(), 4);
+        for permit in running {
+            c.release(permit, false);
+        }
+        assert_eq!(c.permits.available_permits(), 4);
+
+        // A success right after a rate-limit error does not grow it.
+        let permit = c.acquire().await.unwrap();
+        c.rate_limited();
+        c.release(permit, true);
+        assert_eq!(c.limit(), 4);
+    }
+}
@@ -5,30 +5,270 @@
 //!
 //! When no config file is present the built-in defaults from
 //! [`crate::chains::ChainConfig::default_rpc`] are used.
+//!
+//! [`Config::load_from`] also accepts gzip-compressed files (`*.gz`) and,
+//! with the `remote-config` feature, `https://` URLs, so that many
+//! archiver instances can share one centrally served config.
+//!
+//! RPC URLs of a local config may reference environment variables as
+//! `{NAME}`, e.g. `https://eth-mainnet.g.alchemy.com/v2/{ALCHEMY_ETH_KEY}`,
+//! so that API keys stay out of the config file; see [`expand_rpc_url`].
+//! Remote configs are not expanded: whoever serves them could otherwise
+//! send any of the archiver's environment variables to an RPC of their
+//! choosing.
 
-use std::collections::BTreeMap;
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::Read as _;
+use std::path::{Path, PathBuf};
 
+use alloy::primitives::Address;
 use anyhow::{Context, Result};
+use erc8004::networks::NetworkAddresses;
+use regex::Regex;
 use serde::Deserialize;
 
+use crate::chains::{self, ChainConfig};
+use crate::fetcher::{ChainSyncStrategy, CustomPatternClassifier, RpcErrorKind};
+use crate::parquet::Compression;
+use crate::storage::StorageConfig;
+
+/// An RPC URL references environment variables that are not set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingEnvVar(pub Vec<String>);
+
+impl fmt::Display for MissingEnvVar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self.0.iter().map(|name| format!("`{name}`")).collect();
+        match names.as_slice() {
+            [name] => write!(f, "environment variable {name} is not set"),
+            _ => write!(f, "environment variables {} are not set", names.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for MissingEnvVar {}
+
+/// Substitute every `{NAME}` in `url` with the environment variable `NAME`.
+///
+/// Braces that do not enclose a variable name (letters, digits and `_`,
+/// not starting with a digit) are left as they are.
+///
+/// # Errors
+///
+/// Returns every variable that is not set (or not valid UTF-8).
+pub fn expand_rpc_url(url: &str) -> Result<String, MissingEnvVar> {
+    expand_with(url, |name| std::env::var(name).ok())
+}
+
+/// Substitute the `{NAME}` placeholders of `url` from `lookup`.
+fn expand_with(
+    url: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, MissingEnvVar> {
+    let mut missing = Vec::new();
+    let mut out = String::with_capacity(url.len());
+    let mut rest = url;
+    while let Some(open) = rest.find('{') {
+        let (before, tail) = rest.split_at(open);
+        out.push_str(before);
+        let name = tail
+            .get(1..)
+            .and_then(|t| t.split_once('}'))
+            .map(|(name, _)| name)
+            .filter(|name| is_var_name(name));
+        let Some(name) = name else {
+            out.push('{');
+            rest = tail.get(1..).unwrap_or_default();
+            continue;
+        };
+        match lookup(name) {
+            Some(value) => out.push_str(&value),
+            None => missing.push(name.to_owned()),
+        }
+        rest = tail.get(name.len() + 2..).unwrap_or_default();
+    }
+    out.push_str(rest);
+    if missing.is_empty() {
+        Ok(out)
+    } else {
+        Err(MissingEnvVar(missing))
+    }
+}
+
+/// Whether `name` is a variable name: letters, digits and `_`, not
+/// starting with a digit.
+fn is_var_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
 /// Top-level configuration.
 #[derive(Debug, Default, Deserialize)]
 pub struct Config {
     /// Per-chain RPC overrides, keyed by chain ID.
     #[serde(default)]
     pub chains: BTreeMap<u64, ChainRpcs>,
+    /// Extra RPC error classification rules, tried before the built-in
+    /// heuristics.
+    #[serde(default)]
+    pub error_patterns: Vec<ErrorPattern>,
+    /// Remote storage that synced Parquet files are uploaded to.
+    #[serde(default)]
+    pub storage: Option<StorageConfig>,
+    /// Parquet compression codec, e.g. `compression = "lz4"`; the
+    /// `--compression` flag takes precedence.
+    #[serde(default)]
+    pub compression: Option<Compression>,
+}
+
+/// A config-driven RPC error classification rule.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErrorPattern {
+    /// Regular expression matched against the error message.
+    pub pattern: String,
+    /// Classification for matching errors.
+    pub kind: RpcErrorKind,
+}
+
+/// One RPC of a chain: a bare URL, or a table giving it a relative
+/// `weight`, e.g. `{ url = "https://...", weight = 3 }`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum RpcUrl {
+    /// A URL without a weight.
+    Plain(String),
+    /// A URL with a weight.
+    Weighted {
+        /// The RPC URL.
+        url: String,
+        /// Weight relative to the chain's other RPCs; 0 only tries the RPC
+        /// after every other one.
+        weight: u32,
+    },
+}
+
+impl RpcUrl {
+    /// The RPC URL.
+    #[must_use]
+    pub fn url(&self) -> &str {
+        match self {
+            Self::Plain(url) | Self::Weighted { url, .. } => url,
+        }
+    }
+
+    /// The configured weight, if any.
+    #[must_use]
+    pub const fn weight(&self) -> Option<u32> {
+        match self {
+            Self::Plain(_) => None,
+            Self::Weighted { weight, .. } => Some(*weight),
+        }
+    }
+
+    /// The RPC URL, for placeholder expansion.
+    const fn url_mut(&mut self) -> &mut String {
+        match self {
+            Self::Plain(url) | Self::Weighted { url, .. } => url,
+        }
+    }
 }
 
 /// RPC endpoint list for a single chain.
 #[derive(Debug, Clone, Deserialize)]
 pub struct ChainRpcs {
-    /// Ordered list of RPC URLs (best first).
+    /// Ordered list of RPCs (best first).  When they carry weights, each
+    /// sync starts from an RPC picked at random by weight instead of
+    /// always the first; either every RPC has a weight or none does.
+    pub rpcs: Vec<RpcUrl>,
+    /// Hex-encoded JWT secret file; when set, every request carries a
+    /// freshly signed `Authorization: Bearer` token.
+    #[serde(default)]
+    pub jwt_secret_path: Option<PathBuf>,
+    /// Hard cap on the `eth_getLogs` block range.  The adaptive batcher
+    /// never grows past it, guarding against providers that silently
+    /// truncate large ranges.  Caps below 10 blocks are honoured as well.
+    #[serde(default)]
+    pub max_block_range: Option<u64>,
+    /// Fixed `eth_getLogs` block range for RPCs with a known, stable limit;
+    /// disables the adaptive batcher.
+    #[serde(default)]
+    pub block_step: Option<u64>,
+    /// Maximum number of logs the provider returns per `eth_getLogs`
+    /// call.  A response of exactly this size is treated as silently
+    /// truncated and its tail is re-queried with a smaller window.
+    #[serde(default)]
+    pub log_result_cap: Option<usize>,
+    /// `eth_getLogs` requests per second allowed on this chain (e.g. the
+    /// provider's rate limit); overrides `--rpcs-per-second`.
+    #[serde(default)]
+    pub rpcs_per_second: Option<f64>,
+    /// Follow `pageKey` pagination in `eth_getLogs` responses, for
+    /// gateways that page large results instead of rejecting them.
+    #[serde(default)]
+    pub paginated_logs: bool,
+    /// Sync strategies to run concurrently, e.g. a historical backfill next
+    /// to a live head follower.  Empty means a single catch-up pass.
+    #[serde(default)]
+    pub strategies: Vec<ChainSyncStrategy>,
+    /// Identity Registry address on this chain, for a deployment that does
+    /// not use the shared address of its kind.
+    #[serde(default)]
+    pub identity_address: Option<Address>,
+    /// Reputation Registry address on this chain; see `identity_address`.
+    #[serde(default)]
+    pub reputation_address: Option<Address>,
+}
+
+/// One entry of a `--targets-file`: a chain to sync and optional RPCs that
+/// take precedence over `config.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TargetSpec {
+    /// EIP-155 chain ID; must be a known chain.
+    pub chain_id: u64,
+    /// Ordered RPC URLs for this run; empty means use the configured ones.
+    #[serde(default)]
     pub rpcs: Vec<String>,
 }
 
+/// Load a JSON array of [`TargetSpec`]s.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or parsed, or if a chain ID
+/// is listed more than once.
+pub fn load_targets(path: &Path) -> Result<Vec<TargetSpec>> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let targets: Vec<TargetSpec> =
+        serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+    let mut seen = std::collections::BTreeSet::new();
+    for t in &targets {
+        anyhow::ensure!(
+            seen.insert(t.chain_id),
+            "{}: chain ID {} listed more than once",
+            path.display(),
+            t.chain_id
+        );
+    }
+    Ok(targets)
+}
+
+/// Download a remote config.
+#[cfg(feature = "remote-config")]
+async fn fetch(url: &str) -> Result<Vec<u8>> {
+    use alloy::transports::http::reqwest;
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()?;
+    let response = client.get(url).send().await?.error_for_status()?;
+    Ok(response.bytes().await?.to_vec())
+}
+
 impl Config {
-    /// Load configuration from a TOML file.
+    /// Load configuration from a TOML file, gzip-compressed if its name
+    /// ends in `.gz`.
     ///
     /// Returns [`Config::default`] if the file does not exist,
     /// allowing the binary to work without any config.
@@ -40,18 +280,421 @@ pub fn load(path: &Path) -> Result<Self> {
         if !path.exists() {
             return Ok(Self::default());
         }
-        let text =
-            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
-        toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+        let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        let gzip = path.extension().is_some_and(|ext| ext == "gz");
+        let source = path.display().to_string();
+        Self::parse(&bytes, gzip, &source)?
+            .expand_rpc_urls(expand_rpc_url, &source)?
+            .check_rpc_weights(&source)
+    }
+
+    /// Load configuration from a local path (see [`Config::load`]) or,
+    /// with the `remote-config` feature, an `https://` URL.
+    ///
+    /// A URL is fetched on every call and, unlike a local path, must
+    /// exist; a path ending in `.gz` is decompressed.  The `{NAME}`
+    /// placeholders of a remote config are left as they are.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config cannot be read or parsed, and for
+    /// plain `http://` URLs, whose content could be tampered with on the
+    /// way.  A failed download is reported as `fetching config from
+    /// <url>`, distinct from `parsing <url>`; without the `remote-config`
+    /// feature every URL is rejected.
+    #[cfg_attr(
+        not(feature = "remote-config"),
+        expect(
+            clippy::unused_async,
+            reason = "only remote configs are fetched asynchronously"
+        )
+    )]
+    pub async fn load_from(source: &str) -> Result<Self> {
+        if source.starts_with("http://") {
+            anyhow::bail!("config URL {source} is not https://; refusing to load it in the clear");
+        }
+        if !source.starts_with("https://") {
+            return Self::load(Path::new(source));
+        }
+        #[cfg(feature = "remote-config")]
+        {
+            let bytes = fetch(source)
+                .await
+                .with_context(|| format!("fetching config from {source}"))?;
+            Self::parse_remote(&bytes, source)
+        }
+        #[cfg(not(feature = "remote-config"))]
+        anyhow::bail!("config URL {source} requires the `remote-config` feature")
+    }
+
+    /// Parse a config downloaded from `url`, without expanding its
+    /// `{NAME}` placeholders.
+    #[cfg(feature = "remote-config")]
+    fn parse_remote(bytes: &[u8], url: &str) -> Result<Self> {
+        let gzip = url
+            .split(['?', '#'])
+            .next()
+            .is_some_and(|path| path.ends_with(".gz"));
+        Self::parse(bytes, gzip, url)?.check_rpc_weights(url)
+    }
+
+    /// Parse TOML bytes read from `source`, gunzipping them first if asked.
+    fn parse(bytes: &[u8], gzip: bool, source: &str) -> Result<Self> {
+        let text = if gzip {
+            let mut text = String::new();
+            flate2::read::GzDecoder::new(bytes)
+                .read_to_string(&mut text)
+                .with_context(|| format!("decompressing {source}"))?;
+            text
+        } else {
+            String::from_utf8(bytes.to_vec())
+                .with_context(|| format!("{source} is not valid UTF-8"))?
+        };
+        toml::from_str(&text).with_context(|| format!("parsing {source}"))
+    }
+
+    /// Expand the `{NAME}` placeholders of every chain's RPC URLs with
+    /// `expand`, [`expand_rpc_url`] outside the tests.
+    ///
+    /// All unset variables are reported together, so that a fresh
+    /// deployment learns every key it is missing in one run.
+    fn expand_rpc_urls(
+        mut self,
+        expand: impl Fn(&str) -> Result<String, MissingEnvVar>,
+        source: &str,
+    ) -> Result<Self> {
+        let mut missing = Vec::new();
+        for chain in self.chains.values_mut() {
+            for rpc in &mut chain.rpcs {
+                match expand(rpc.url()) {
+                    Ok(expanded) => *rpc.url_mut() = expanded,
+                    Err(MissingEnvVar(names)) => missing.extend(names),
+                }
+            }
+        }
+        if !missing.is_empty() {
+            let names: BTreeSet<_> = missing.into_iter().collect();
+            anyhow::bail!(
+                "{source}: RPC URLs reference unset environment variables: {}",
+                names.into_iter().collect::<Vec<_>>().join(", ")
+            );
+        }
+        Ok(self)
+    }
+
+    /// Reject a chain whose RPCs are only partly weighted.
+    fn check_rpc_weights(self, source: &str) -> Result<Self> {
+        for (chain_id, chain) in &self.chains {
+            let weighted = chain.rpcs.iter().filter(|r| r.weight().is_some()).count();
+            if weighted != 0 && weighted != chain.rpcs.len() {
+                anyhow::bail!(
+                    "{source}: chain {chain_id} has weights for {weighted} of its {} rpcs; \
+                     give every RPC a weight or none",
+                    chain.rpcs.len()
+                );
+            }
+        }
+        Ok(self)
     }
 
     /// Return the RPC URL list for a chain, falling back to the built-in
     /// default if the config has no entry for this chain.
+    #[deprecated(note = "use `Config::rpcs_for_chain`; removed in the next major version")]
     #[must_use]
     pub fn rpcs_for(&self, chain_id: u64, default_rpc: &str) -> Vec<String> {
+        self.rpcs_or(chain_id, default_rpc)
+    }
+
+    /// Return the RPC URL list for `chain`, falling back to its
+    /// [`ChainConfig::default_rpc`].
+    #[must_use]
+    pub fn rpcs_for_chain(&self, chain: &ChainConfig) -> Vec<String> {
+        self.rpcs_or(chain.chain_id(), chain.default_rpc)
+    }
+
+    /// Return the RPC URL list for every chain in [`chains::ALL`], with
+    /// config overrides applied.
+    #[must_use]
+    pub fn all_chain_rpcs(&self) -> Vec<(u64, Vec<String>)> {
+        chains::ALL
+            .iter()
+            .map(|c| (c.chain_id(), self.rpcs_for_chain(c)))
+            .collect()
+    }
+
+    /// The configured RPCs of `chain_id`, or `default_rpc` alone.
+    fn rpcs_or(&self, chain_id: u64, default_rpc: &str) -> Vec<String> {
         match self.chains.get(&chain_id) {
-            Some(c) if !c.rpcs.is_empty() => c.rpcs.clone(),
+            Some(c) if !c.rpcs.is_empty() => c.rpcs.iter().map(|r| r.url().to_owned()).collect(),
             _ => vec![default_rpc.to_owned()],
         }
     }
+
+    /// Return the weights of a chain's RPCs, in the order of
+    /// [`Config::rpcs_for_chain`]; empty if they have none.
+    #[must_use]
+    pub fn rpc_weights_for(&self, chain_id: u64) -> Vec<u32> {
+        self.chains
+            .get(&chain_id)
+            .and_then(|c| c.rpcs.iter().map(RpcUrl::weight).collect::<Option<_>>())
+            .unwrap_or_default()
+    }
+
+    /// Return the JWT secret file configured for a chain, if any.
+    #[must_use]
+    pub fn jwt_secret_for(&self, chain_id: u64) -> Option<PathBuf> {
+        self.chains
+            .get(&chain_id)
+            .and_then(|c| c.jwt_secret_path.clone())
+    }
+
+    /// Compile [`Config::error_patterns`] into a classifier, or `None` if
+    /// no patterns are configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a pattern is not a valid regular expression.
+    pub fn error_classifier(&self) -> Result<Option<CustomPatternClassifier>> {
+        if self.error_patterns.is_empty() {
+            return Ok(None);
+        }
+        let patterns = self
+            .error_patterns
+            .iter()
+            .map(|p| {
+                let re = Regex::new(&p.pattern)
+                    .with_context(|| format!("invalid error pattern `{}`", p.pattern))?;
+                Ok((re, p.kind))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Some(CustomPatternClassifier { patterns }))
+    }
+
+    /// Return the block-range hard cap configured for a chain, if any.
+    #[must_use]
+    pub fn max_block_range_for(&self, chain_id: u64) -> Option<u64> {
+        self.chains.get(&chain_id).and_then(|c| c.max_block_range)
+    }
+
+    /// Return the fixed block step configured for a chain, if any.
+    #[must_use]
+    pub fn block_step_for(&self, chain_id: u64) -> Option<u64> {
+        self.chains.get(&chain_id).and_then(|c| c.block_step)
+    }
+
+    /// Return the provider result cap configured for a chain, if any.
+    #[must_use]
+    pub fn log_result_cap_for(&self, chain_id: u64) -> Option<usize> {
+        self.chains.get(&chain_id).and_then(|c| c.log_result_cap)
+    }
+
+    /// Return the request rate limit configured for a chain, if any.
+    #[must_use]
+    pub fn rpcs_per_second_for(&self, chain_id: u64) -> Option<f64> {
+        self.chains.get(&chain_id).and_then(|c| c.rpcs_per_second)
+    }
+
+    /// Whether `eth_getLogs` pagination is enabled for a chain.
+    #[must_use]
+    pub fn paginated_logs_for(&self, chain_id: u64) -> bool {
+        self.chains.get(&chain_id).is_some_and(|c| c.paginated_logs)
+    }
+
+    /// Return the sync strategies configured for a chain.
+    #[must_use]
+    pub fn strategies_for(&self, chain_id: u64) -> Vec<ChainSyncStrategy> {
+        self.chains
+            .get(&chain_id)
+            .map(|c| c.strategies.clone())
+            .unwrap_or_default()
+    }
+
+    /// `chain` with the registry addresses configured for it, if any, as
+    /// its [`ChainConfig::address_override`]; an address that is not
+    /// configured keeps its built-in value.
+    #[must_use]
+    pub fn chain_with_addresses(&self, chain: &ChainConfig) -> ChainConfig {
+        let mut chain = *chain;
+        if let Some(c) = self.chains.get(&chain.chain_id())
+            && (c.identity_address.is_some() || c.reputation_address.is_some())
+        {
+            let addrs = chain.addresses();
+            chain.address_override = Some(NetworkAddresses {
+                identity: c.identity_address.unwrap_or(addrs.identity),
+                reputation: c.reputation_address.unwrap_or(addrs.reputation),
+            });
+        }
+        chain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn test_load_gzip_config() {
+        let dir = TempDir::new("config");
+        let path = dir.join("config.toml.gz");
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(b"[chains.8453]\nrpcs = [\"https://base.example\"]\n")
+            .unwrap();
+        std::fs::write(&path, gz.finish().unwrap()).unwrap();
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(
+            config.chains.get(&8453).unwrap().rpcs,
+            [RpcUrl::Plain("https://base.example".to_owned())]
+        );
+
+        std::fs::write(&path, b"not gzip").unwrap();
+        let err = Config::load(&path).unwrap_err();
+        assert!(format!("{err:#}").contains("decompressing"));
+    }
+
+    #[tokio::test]
+    async fn test_load_from_rejects_plain_http() {
+        let err = Config::load_from("http://configs.example/erc8004.toml")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("is not https://"));
+    }
+
+    #[cfg(not(feature = "remote-config"))]
+    #[tokio::test]
+    async fn test_load_from_url_requires_feature() {
+        let err = Config::load_from("https://configs.example/erc8004.toml")
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("requires the `remote-config` feature")
+        );
+    }
+
+    #[cfg(feature = "remote-config")]
+    #[test]
+    fn test_remote_config_is_not_expanded() {
+        // `PATH` is set wherever the tests run; a remote config must not
+        // be able to read it.
+        let url = "https://configs.example/erc8004.toml";
+        let config = Config::parse_remote(
+            b"[chains.8453]\nrpcs = [\"https://rpc.example/{PATH}\"]\n",
+            url,
+        )
+        .unwrap();
+        assert_eq!(
+            config.chains.get(&8453).unwrap().rpcs,
+            [RpcUrl::Plain("https://rpc.example/{PATH}".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_chain_with_addresses_overrides_configured_registries() {
+        let base = crate::chains::by_chain_id(8453).unwrap();
+        let identity = Address::repeat_byte(0x11);
+        let config: Config = toml::from_str(&format!(
+            "[chains.8453]\nrpcs = []\nidentity_address = \"{identity}\"\n"
+        ))
+        .unwrap();
+        let addrs = config.chain_with_addresses(base).addresses();
+        assert_eq!(addrs.identity, identity);
+        assert_eq!(addrs.reputation, base.addresses().reputation);
+
+        let ethereum = crate::chains::by_chain_id(1).unwrap();
+        assert_eq!(
+            config.chain_with_addresses(ethereum).addresses(),
+            ethereum.addresses()
+        );
+    }
+
+    #[test]
+    fn test_expand_rpc_url() {
+        // `PATH` is set wherever the tests run.
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(
+            expand_rpc_url("https://x/{PATH}/{}/{1}").unwrap(),
+            format!("https://x/{path}/{{}}/{{1}}")
+        );
+        let err =
+            expand_rpc_url("https://x/{ERC8004_UNSET_A}/{PATH}/{ERC8004_UNSET_B}").unwrap_err();
+        assert_eq!(
+            err,
+            MissingEnvVar(vec!["ERC8004_UNSET_A".into(), "ERC8004_UNSET_B".into()])
+        );
+        assert_eq!(
+            err.to_string(),
+            "environment variables `ERC8004_UNSET_A`, `ERC8004_UNSET_B` are not set"
+        );
+    }
+
+    #[test]
+    fn test_expand_rpc_urls_reports_every_missing_variable() {
+        let lookup = |name: &str| (name == "ALCHEMY_ETH_KEY").then(|| "secret".to_owned());
+        assert_eq!(
+            expand_with(
+                "https://eth-mainnet.g.alchemy.com/v2/{ALCHEMY_ETH_KEY}?a={1}",
+                lookup
+            )
+            .unwrap(),
+            "https://eth-mainnet.g.alchemy.com/v2/secret?a={1}"
+        );
+
+        let config: Config = toml::from_str(
+            "[chains.1]\nrpcs = [\"https://x/{ALCHEMY_ETH_KEY}\", \"https://y/{B_KEY}\"]\n\
+             [chains.8453]\nrpcs = [\"https://z/{A_KEY}/{B_KEY}\"]\n",
+        )
+        .unwrap();
+        let err = config
+            .expand_rpc_urls(|url| expand_with(url, lookup), "config.toml")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "config.toml: RPC URLs reference unset environment variables: A_KEY, B_KEY"
+        );
+    }
+
+    #[test]
+    fn test_rpc_weights_are_per_rpc() {
+        let config: Config = toml::from_str(
+            "[chains.8453]\nrpcs = [{ url = \"https://a\", weight = 3 }, \
+             { url = \"https://b\", weight = 1 }]\n\
+             [chains.1]\nrpcs = [\"https://c\"]\n",
+        )
+        .unwrap();
+        let config = config.check_rpc_weights("config.toml").unwrap();
+        let base = crate::chains::by_chain_id(8453).unwrap();
+        assert_eq!(config.rpcs_for_chain(base), ["https://a", "https://b"]);
+        assert_eq!(config.rpc_weights_for(8453), [3, 1]);
+        assert!(config.rpc_weights_for(1).is_empty());
+
+        let config: Config = toml::from_str(
+            "[chains.8453]\nrpcs = [{ url = \"https://a\", weight = 3 }, \"https://b\"]\n",
+        )
+        .unwrap();
+        let err = config.check_rpc_weights("config.toml").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "config.toml: chain 8453 has weights for 1 of its 2 rpcs; give every RPC a weight or none"
+        );
+    }
+
+    #[test]
+    fn test_all_chain_rpcs_applies_overrides() {
+        let config: Config = toml::from_str("[chains.8453]\nrpcs = [\"https://a\"]\n").unwrap();
+        let all = config.all_chain_rpcs();
+        assert_eq!(all.len(), crate::chains::ALL.len());
+        for (chain_id, rpcs) in all {
+            let chain = crate::chains::by_chain_id(chain_id).unwrap();
+            let expected = if chain_id == 8453 {
+                "https://a"
+            } else {
+                chain.default_rpc
+            };
+            assert_eq!(rpcs, [expected]);
+        }
+    }
 }
@@ -2,52 +2,211 @@
 //!
 //! Each chain directory contains a `cursor.json` that records the last
 //! fully-synced block number so that subsequent runs only fetch the delta.
+//! A `cursors.json` at the data-dir root aggregates every chain's cursor for
+//! an at-a-glance view; the per-chain files remain authoritative.
+//!
+//! The per-chain cursor may also be stored compressed, as `cursor.json.zst`
+//! or `cursor.json.gz` (see [`CursorCompression`]).  [`Cursor::load`] reads
+//! whichever exists, and a save replaces the other variants.  This is a
+//! proof of concept for cursor stores other than plain JSON files; the
+//! files are far too small to gain from compression themselves.
 
-use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
 
+use crate::retry;
+
+/// Per-chain cursor file name without the compression extension.
+const CURSOR_FILE: &str = "cursor.json";
+
+/// Zstd level of [`CursorCompression::Zstd`].
+const ZSTD_LEVEL: i32 = 3;
+
+/// Encoding of a chain's cursor file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CursorCompression {
+    /// Plain `cursor.json`.
+    #[default]
+    None,
+    /// `cursor.json.zst`.
+    Zstd,
+    /// `cursor.json.gz`.
+    Gzip,
+}
+
+impl CursorCompression {
+    /// Every encoding, in the order [`Cursor::load`] tries them.
+    pub const ALL: [Self; 3] = [Self::None, Self::Zstd, Self::Gzip];
+
+    /// Path of the cursor file in `dir` with this encoding.
+    #[must_use]
+    pub fn path(self, dir: &Path) -> PathBuf {
+        dir.join(match self {
+            Self::None => CURSOR_FILE.to_owned(),
+            Self::Zstd => format!("{CURSOR_FILE}.zst"),
+            Self::Gzip => format!("{CURSOR_FILE}.gz"),
+        })
+    }
+
+    fn encode(self, json: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(json.to_vec()),
+            Self::Zstd => zstd::encode_all(json, ZSTD_LEVEL).context("zstd encoding"),
+            Self::Gzip => {
+                let mut gz =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                gz.write_all(json)?;
+                gz.finish().context("gzip encoding")
+            }
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(bytes.to_vec()),
+            Self::Zstd => zstd::decode_all(bytes),
+            Self::Gzip => {
+                let mut json = Vec::new();
+                flate2::read::GzDecoder::new(bytes).read_to_end(&mut json)?;
+                Ok(json)
+            }
+        }
+    }
+}
+
+impl FromStr for CursorCompression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Self::None),
+            "zstd" => Ok(Self::Zstd),
+            "gzip" => Ok(Self::Gzip),
+            other => bail!("unknown cursor compression `{other}` (expected none, zstd or gzip)"),
+        }
+    }
+}
+
+impl fmt::Display for CursorCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::None => "none",
+            Self::Zstd => "zstd",
+            Self::Gzip => "gzip",
+        })
+    }
+}
+
 /// Sync progress for a single chain.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cursor {
     /// The last block whose events have been fully written to Parquet.
     pub last_block: u64,
     /// Unix timestamp (seconds) of the last successful sync.
     pub synced_at: u64,
+    /// Per-contract progress of an unfinished run: the last block whose
+    /// logs have been fetched and flushed.  Cleared when a run completes.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checkpoints: BTreeMap<String, u64>,
+    /// First block with a registry event, as found by
+    /// `discover-first-event`.  Kept across cursor updates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_event_block: Option<u64>,
+    /// `since_block` of the last completed re-index, so that a later run
+    /// with the same value resumes from the cursor instead of re-indexing
+    /// again.  Kept across cursor updates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since_block: Option<u64>,
 }
 
 impl Cursor {
     /// Create a new cursor at the given block with the current timestamp.
     #[must_use]
     pub fn now(last_block: u64) -> Self {
-        let synced_at = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
         Self {
             last_block,
-            synced_at,
+            synced_at: unix_now(),
+            checkpoints: BTreeMap::new(),
+            first_event_block: None,
+            since_block: None,
         }
     }
 
-    /// Read cursor from `<dir>/cursor.json`.
+    /// Record that `contract` has been fetched and flushed up to `block`,
+    /// without advancing [`last_block`](Self::last_block).
     ///
-    /// Returns `None` if the file does not exist (first sync) or contains
-    /// invalid JSON (logs a warning and triggers fresh sync).
+    /// `last_block` is used when no cursor exists yet; see
+    /// [`save_compressed`](Self::save_compressed) for `retries`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cursor cannot be read or written.
+    pub fn save_checkpoint(
+        dir: &Path,
+        contract: &str,
+        block: u64,
+        last_block: u64,
+        retries: u32,
+    ) -> Result<()> {
+        let mut cursor = Self::load(dir)?.unwrap_or_else(|| Self::now(last_block));
+        cursor.checkpoints.insert(contract.to_owned(), block);
+        cursor.save_compressed(dir, stored_compression(dir).unwrap_or_default(), retries)
+    }
+
+    /// Record that the chain's first registry event is at `block`.
+    ///
+    /// Only [`first_event_block`](Self::first_event_block) is written; a
+    /// missing cursor is created at block 0 with no sync time.  The next
+    /// sync skips ahead to `block` once its RPC passed the archive probe,
+    /// so a block reported by a pruned RPC cannot skip history.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cursor cannot be read or written.
+    pub fn save_first_event_block(dir: &Path, block: u64) -> Result<()> {
+        let mut cursor = Self::load(dir)?.unwrap_or_else(|| Self {
+            synced_at: 0,
+            ..Self::now(0)
+        });
+        cursor.first_event_block = Some(block);
+        cursor.save(dir)
+    }
+
+    /// Time elapsed since this cursor was written (zero if `synced_at` is
+    /// in the future).
+    #[must_use]
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.synced_at))
+    }
+
+    /// Read cursor from `<dir>/cursor.json`, or else from
+    /// `cursor.json.zst` or `cursor.json.gz`.
+    ///
+    /// Returns `None` if no cursor file exists (first sync) or it cannot be
+    /// decoded (logs a warning and triggers fresh sync).
     ///
     /// # Errors
     ///
     /// Returns an error if the file exists but cannot be read (I/O error).
     pub fn load(dir: &Path) -> Result<Option<Self>> {
-        let path = dir.join("cursor.json");
-        if !path.exists() {
+        let Some(compression) = stored_compression(dir) else {
             return Ok(None);
-        }
-        let data = std::fs::read_to_string(&path)
-            .with_context(|| format!("reading {}", path.display()))?;
-        match serde_json::from_str::<Self>(&data) {
+        };
+        let path = compression.path(dir);
+        let bytes = std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+        let cursor = compression
+            .decode(&bytes)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(serde_json::from_slice::<Self>(&json)?));
+        match cursor {
             Ok(cursor) => Ok(Some(cursor)),
             Err(e) => {
                 tracing::warn!(path = %path.display(), error = %e, "corrupted cursor, starting fresh");
@@ -56,23 +215,301 @@ pub fn load(dir: &Path) -> Result<Option<Self>> {
         }
     }
 
-    /// Persist cursor to `<dir>/cursor.json` atomically.
+    /// Persist cursor atomically, in the encoding of the existing cursor
+    /// file (plain `cursor.json` if there is none).
     ///
     /// # Errors
     ///
     /// Returns an error if the directory cannot be created or the file
     /// cannot be written.
     pub fn save(&self, dir: &Path) -> Result<()> {
-        std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        self.save_compressed(
+            dir,
+            stored_compression(dir).unwrap_or_default(),
+            retry::DEFAULT_IO_RETRIES,
+        )
+    }
 
-        let path = dir.join("cursor.json");
-        let tmp = dir.join("cursor.json.tmp");
+    /// Persist cursor atomically to the file of `format`, removing the
+    /// cursor files of the other encodings.  A failed write is retried up
+    /// to `retries` times (see [`retry`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created or the file
+    /// cannot be written.
+    pub fn save_compressed(
+        &self,
+        dir: &Path,
+        format: CursorCompression,
+        retries: u32,
+    ) -> Result<()> {
+        std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
 
-        std::fs::write(&tmp, serde_json::to_string_pretty(self)?.as_bytes())
-            .with_context(|| format!("writing {}", tmp.display()))?;
-        std::fs::rename(&tmp, &path)
-            .with_context(|| format!("renaming {} → {}", tmp.display(), path.display()))?;
+        let path = format.path(dir);
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
 
+        let bytes = format.encode(serde_json::to_string_pretty(self)?.as_bytes())?;
+        retry::io(&path, retries, || {
+            std::fs::write(&tmp, &bytes).with_context(|| format!("writing {}", tmp.display()))?;
+            std::fs::rename(&tmp, &path)
+                .with_context(|| format!("renaming {} → {}", tmp.display(), path.display()))
+        })?;
+        for other in CursorCompression::ALL.into_iter().filter(|&c| c != format) {
+            let stale = other.path(dir);
+            if stale.exists() {
+                std::fs::remove_file(&stale)
+                    .with_context(|| format!("removing {}", stale.display()))?;
+            }
+        }
         Ok(())
     }
+
+    /// Persist like [`save_compressed`](Self::save_compressed), but refuse
+    /// to move the cursor backwards unless `overwrite` is set.  A `format`
+    /// of `None` keeps the encoding of the existing cursor file.
+    ///
+    /// A backward move usually means a bug or a lagging RPC and would make
+    /// the next run re-fetch blocks, so it is logged loudly and skipped.
+    /// Returns whether the cursor was written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the existing cursor cannot be read or the new one
+    /// cannot be written.
+    pub fn save_checked(
+        &self,
+        dir: &Path,
+        overwrite: bool,
+        format: Option<CursorCompression>,
+        retries: u32,
+    ) -> Result<bool> {
+        let current = Self::load(dir)?;
+        if let Some(current) = &current
+            && current.last_block > self.last_block
+        {
+            if !overwrite {
+                tracing::warn!(
+                    dir = %dir.display(),
+                    current = current.last_block,
+                    requested = self.last_block,
+                    "refusing to move cursor backwards; pass --overwrite-cursor to force"
+                );
+                return Ok(false);
+            }
+            tracing::warn!(
+                dir = %dir.display(),
+                current = current.last_block,
+                requested = self.last_block,
+                "moving cursor backwards (--overwrite-cursor)"
+            );
+        }
+        Self {
+            first_event_block: self
+                .first_event_block
+                .or(current.as_ref().and_then(|c| c.first_event_block)),
+            since_block: self
+                .since_block
+                .or(current.as_ref().and_then(|c| c.since_block)),
+            ..self.clone()
+        }
+        .save_compressed(
+            dir,
+            format
+                .or_else(|| stored_compression(dir))
+                .unwrap_or_default(),
+            retries,
+        )?;
+        Ok(true)
+    }
+}
+
+/// Serialises read-modify-write cycles on `cursors.json` across the
+/// concurrently completing chains of a sync run.
+static SUMMARY_LOCK: Mutex<()> = Mutex::new(());
+
+/// Read the aggregated `<data_dir>/cursors.json`, keyed by chain ID.
+///
+/// Returns an empty map if the file does not exist.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read or parsed.
+pub fn load_summary(data_dir: &Path) -> Result<BTreeMap<u64, Cursor>> {
+    let path = data_dir.join("cursors.json");
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let data =
+        std::fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_str(&data).with_context(|| format!("parsing {}", path.display()))
+}
+
+/// Record `cursor` for `chain_id` in `<data_dir>/cursors.json` atomically.
+///
+/// Safe to call from concurrently completing chains: updates are serialised
+/// so none is lost.
+///
+/// # Errors
+///
+/// Returns an error if the summary cannot be read or written.
+pub fn update_summary(data_dir: &Path, chain_id: u64, cursor: Cursor) -> Result<()> {
+    let _guard = SUMMARY_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let mut summary = load_summary(data_dir)?;
+    summary.insert(chain_id, cursor);
+
+    std::fs::create_dir_all(data_dir)
+        .with_context(|| format!("creating {}", data_dir.display()))?;
+    let path = data_dir.join("cursors.json");
+    let tmp = data_dir.join("cursors.json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(&summary)?.as_bytes())
+        .with_context(|| format!("writing {}", tmp.display()))?;
+    std::fs::rename(&tmp, &path)
+        .with_context(|| format!("renaming {} → {}", tmp.display(), path.display()))?;
+    Ok(())
+}
+
+/// Encoding of the cursor file in `dir`, trying each in
+/// [`CursorCompression::ALL`] order; `None` if there is no cursor.
+fn stored_compression(dir: &Path) -> Option<CursorCompression> {
+    CursorCompression::ALL
+        .into_iter()
+        .find(|c| c.path(dir).exists())
+}
+
+/// Current Unix timestamp in seconds (0 if the clock is before the epoch).
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn test_save_checked_advances_forward() {
+        let dir = TempDir::new("cursor-forward");
+        Cursor::now(100).save(&dir).unwrap();
+        assert!(Cursor::now(200).save_checked(&dir, false, None, 0).unwrap());
+        assert_eq!(Cursor::load(&dir).unwrap().unwrap().last_block, 200);
+    }
+
+    #[test]
+    fn test_save_checked_refuses_backward_move() {
+        let dir = TempDir::new("cursor-backward");
+        Cursor::now(200).save(&dir).unwrap();
+        assert!(!Cursor::now(100).save_checked(&dir, false, None, 0).unwrap());
+        assert_eq!(Cursor::load(&dir).unwrap().unwrap().last_block, 200);
+    }
+
+    #[test]
+    fn test_save_checked_overwrite_allows_backward_move() {
+        let dir = TempDir::new("cursor-overwrite");
+        Cursor::now(200).save(&dir).unwrap();
+        assert!(Cursor::now(100).save_checked(&dir, true, None, 0).unwrap());
+        assert_eq!(Cursor::load(&dir).unwrap().unwrap().last_block, 100);
+    }
+
+    #[test]
+    fn test_checkpoint_kept_until_run_completes() {
+        let dir = TempDir::new("cursor-checkpoint");
+        Cursor::save_checkpoint(&dir, "identity", 500, 99, 0).unwrap();
+        let cursor = Cursor::load(&dir).unwrap().unwrap();
+        assert_eq!(cursor.last_block, 99);
+        assert_eq!(cursor.checkpoints.get("identity"), Some(&500));
+
+        Cursor::now(1_000)
+            .save_checked(&dir, false, None, 0)
+            .unwrap();
+        assert!(Cursor::load(&dir).unwrap().unwrap().checkpoints.is_empty());
+    }
+
+    #[test]
+    fn test_first_event_block_kept_across_updates() {
+        let dir = TempDir::new("cursor-first-event");
+        Cursor::save_first_event_block(&dir, 1_000).unwrap();
+        let cursor = Cursor::load(&dir).unwrap().unwrap();
+        assert_eq!(
+            (
+                cursor.last_block,
+                cursor.synced_at,
+                cursor.first_event_block
+            ),
+            (0, 0, Some(1_000))
+        );
+
+        // Leaves an existing cursor's progress alone.
+        Cursor::now(5_000)
+            .save_checked(&dir, false, None, 0)
+            .unwrap();
+        Cursor::save_first_event_block(&dir, 1_000).unwrap();
+        let cursor = Cursor::load(&dir).unwrap().unwrap();
+        assert_eq!(
+            (cursor.last_block, cursor.first_event_block),
+            (5_000, Some(1_000))
+        );
+    }
+
+    #[test]
+    fn test_update_summary_concurrent_no_lost_writes() {
+        let dir = TempDir::new("cursor-summary");
+        std::thread::scope(|s| {
+            for chain_id in 1..=32u64 {
+                let dir = &dir;
+                s.spawn(move || update_summary(dir, chain_id, Cursor::now(chain_id * 10)).unwrap());
+            }
+        });
+        let summary = load_summary(&dir).unwrap();
+        assert_eq!(summary.len(), 32);
+        assert!(summary.iter().all(|(id, c)| c.last_block == id * 10));
+    }
+
+    #[test]
+    fn test_save_checked_without_existing_cursor() {
+        let dir = TempDir::new("cursor-fresh");
+        assert!(Cursor::now(42).save_checked(&dir, false, None, 0).unwrap());
+        assert_eq!(Cursor::load(&dir).unwrap().unwrap().last_block, 42);
+    }
+
+    #[test]
+    fn test_compressed_cursor_round_trips_and_replaces_others() {
+        let dir = TempDir::new("cursor-compressed");
+        Cursor::now(10).save(&dir).unwrap();
+        for format in [CursorCompression::Zstd, CursorCompression::Gzip] {
+            Cursor::now(20).save_compressed(&dir, format, 0).unwrap();
+            assert_eq!(Cursor::load(&dir).unwrap().unwrap().last_block, 20);
+            let files: Vec<_> = CursorCompression::ALL
+                .into_iter()
+                .filter(|c| c.path(&dir).exists())
+                .collect();
+            assert_eq!(files, [format]);
+        }
+
+        // Checkpoints, and final saves without a format, keep the encoding
+        // on disk.
+        Cursor::save_checkpoint(&dir, "identity", 30, 0, 0).unwrap();
+        assert!(CursorCompression::Gzip.path(&dir).exists());
+        assert_eq!(
+            Cursor::load(&dir)
+                .unwrap()
+                .unwrap()
+                .checkpoints
+                .get("identity"),
+            Some(&30)
+        );
+        assert!(Cursor::now(40).save_checked(&dir, false, None, 0).unwrap());
+        assert!(CursorCompression::Gzip.path(&dir).exists());
+        assert_eq!(
+            "zstd".parse::<CursorCompression>().unwrap(),
+            CursorCompression::Zstd
+        );
+        assert!("lz4".parse::<CursorCompression>().is_err());
+    }
 }
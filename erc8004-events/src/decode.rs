@@ -0,0 +1,297 @@
+//! Decoded copies of raw archives.
+//!
+//! The archive stores logs as raw hex (`topic0` … `topic3`, `data`).
+//! [`decode_contract`] re-derives the event arguments from those columns
+//! without touching an RPC, and writes them next to the raw file as
+//! `<contract>.decoded.parquet` in [`decoded_schema`] layout: every raw
+//! column, an `event` column with the event name, and one nullable column
+//! per argument of the known registry events (see [`ARG_COLUMNS`]).  Logs
+//! whose `topic0` is unknown or that do not match their ABI keep all
+//! decoded columns null.
+//!
+//! All argument values are strings, as produced by [`decode_args`].
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use alloy::primitives::B256;
+use alloy::sol_types::SolEventInterface;
+use anyhow::Result;
+use arrow_array::{ArrayRef, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use erc8004::events::{IdentityEvent, ReputationEvent};
+
+use crate::parquet::{self, LogRow};
+use crate::signatures;
+
+/// Decoded argument columns: the Solidity argument name as returned by
+/// [`decode_args`] and the column it is stored in.
+pub const ARG_COLUMNS: [(&str, &str); 24] = [
+    ("agentId", "agent_id"),
+    ("agentURI", "agent_uri"),
+    ("owner", "owner"),
+    ("newURI", "new_uri"),
+    ("updatedBy", "updated_by"),
+    ("metadataKey", "metadata_key"),
+    ("metadataValue", "metadata_value"),
+    ("from", "from"),
+    ("to", "to"),
+    ("tokenId", "token_id"),
+    ("approved", "approved"),
+    ("operator", "operator"),
+    ("clientAddress", "client_address"),
+    ("feedbackIndex", "feedback_index"),
+    ("value", "value"),
+    ("valueDecimals", "value_decimals"),
+    ("tag1", "tag1"),
+    ("tag2", "tag2"),
+    ("endpoint", "endpoint"),
+    ("feedbackURI", "feedback_uri"),
+    ("feedbackHash", "feedback_hash"),
+    ("responder", "responder"),
+    ("responseURI", "response_uri"),
+    ("responseHash", "response_hash"),
+];
+
+/// [`parquet::event_schema`] followed by a nullable `event` column and the
+/// nullable [`ARG_COLUMNS`].
+#[must_use]
+pub fn decoded_schema() -> Arc<Schema> {
+    let mut fields = parquet::event_schema().fields().to_vec();
+    fields.push(Arc::new(Field::new("event", DataType::Utf8, true)));
+    for (_, column) in ARG_COLUMNS {
+        fields.push(Arc::new(Field::new(column, DataType::Utf8, true)));
+    }
+    Arc::new(Schema::new(fields))
+}
+
+/// Path of the decoded copy of the raw file `base`
+/// (`identity.decoded.parquet`).
+#[must_use]
+pub fn decoded_path(base: &Path) -> PathBuf {
+    base.with_extension("decoded.parquet")
+}
+
+/// Append the decoded columns of [`decoded_schema`] to a batch in
+/// [`parquet::event_schema`] layout.
+///
+/// # Errors
+///
+/// Returns an error if the batch does not match the event schema.
+pub fn decode_batch(batch: &RecordBatch) -> Result<RecordBatch> {
+    let rows = parquet::batch_to_rows(batch)?;
+    let mut events = Vec::with_capacity(rows.len());
+    let mut args: Vec<Vec<Option<String>>> =
+        vec![Vec::with_capacity(rows.len()); ARG_COLUMNS.len()];
+    for row in &rows {
+        let decoded = decode_args(row);
+        events.push(
+            decoded
+                .as_ref()
+                .and_then(|_| signatures::lookup_hex(&row.topic0))
+                .map(signatures::EventKind::name),
+        );
+        for ((name, _), values) in ARG_COLUMNS.iter().zip(&mut args) {
+            values.push(decoded.as_ref().and_then(|d| {
+                d.iter()
+                    .find(|(arg, _)| arg == name)
+                    .map(|(_, value)| value.clone())
+            }));
+        }
+    }
+
+    let mut columns = batch.columns().to_vec();
+    columns.push(Arc::new(StringArray::from(events)) as ArrayRef);
+    columns.extend(
+        args.into_iter()
+            .map(|values| Arc::new(StringArray::from(values)) as ArrayRef),
+    );
+    Ok(RecordBatch::try_new(decoded_schema(), columns)?)
+}
+
+/// Decode the raw archive at `base`, rotated files included, into
+/// [`decoded_path`]`(base)`, replacing any earlier decoded copy.
+///
+/// Returns the number of rows written, or `None` if nothing is archived at
+/// `base`.
+///
+/// # Errors
+///
+/// Returns an error if the raw files cannot be read or do not match the
+/// event schema, or the decoded file cannot be written.
+pub fn decode_contract(base: &Path) -> Result<Option<usize>> {
+    let mut files = parquet::rotated_files(base)?;
+    if base.exists() {
+        files.push(base.to_path_buf());
+    }
+    if files.is_empty() {
+        return Ok(None);
+    }
+    let rows = parquet::write_decoded(&decoded_path(base), &decoded_schema(), || {
+        Ok(parquet::read_each(&files).map(|batch| decode_batch(&batch?)))
+    })?;
+    Ok(Some(rows))
+}
+
+/// Decode an archived row as an event of `E`, e.g.
+/// [`IdentityEvent`]; `None` if its topics or data are not valid hex or do
+/// not match any event of `E`.
+pub(crate) fn decode_event<E: SolEventInterface>(row: &LogRow) -> Option<E> {
+    let topics = [
+        Some(&row.topic0),
+        row.topic1.as_ref(),
+        row.topic2.as_ref(),
+        row.topic3.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|t| t.parse::<B256>())
+    .collect::<Result<Vec<_>, _>>()
+    .ok()?;
+    let data = alloy::hex::decode(&row.data).ok()?;
+    E::decode_raw_log(&topics, &data).ok()
+}
+
+/// The row's decoded event arguments as `(Solidity name, value)` strings.
+///
+/// Numbers are decimal and addresses and bytes lowercase `0x` hex.  Returns
+/// `None` if `topic0` is not a registry event or the log does not match its
+/// ABI.
+#[must_use]
+pub fn decode_args(row: &LogRow) -> Option<Vec<(&'static str, String)>> {
+    let hex = |bytes: &[u8]| format!("0x{}", alloy::hex::encode(bytes));
+
+    if let Some(event) = decode_event::<IdentityEvent>(row) {
+        return Some(match event {
+            IdentityEvent::Registered(e) => vec![
+                ("agentId", e.agentId.to_string()),
+                ("agentURI", e.agentURI),
+                ("owner", hex(e.owner.as_slice())),
+            ],
+            IdentityEvent::URIUpdated(e) => vec![
+                ("agentId", e.agentId.to_string()),
+                ("newURI", e.newURI),
+                ("updatedBy", hex(e.updatedBy.as_slice())),
+            ],
+            IdentityEvent::MetadataSet(e) => vec![
+                ("agentId", e.agentId.to_string()),
+                ("metadataKey", e.metadataKey),
+                ("metadataValue", hex(&e.metadataValue)),
+            ],
+            IdentityEvent::Transfer(e) => vec![
+                ("from", hex(e.from.as_slice())),
+                ("to", hex(e.to.as_slice())),
+                ("tokenId", e.tokenId.to_string()),
+            ],
+            IdentityEvent::Approval(e) => vec![
+                ("owner", hex(e.owner.as_slice())),
+                ("approved", hex(e.approved.as_slice())),
+                ("tokenId", e.tokenId.to_string()),
+            ],
+            IdentityEvent::ApprovalForAll(e) => vec![
+                ("owner", hex(e.owner.as_slice())),
+                ("operator", hex(e.operator.as_slice())),
+                ("approved", e.approved.to_string()),
+            ],
+        });
+    }
+    let event = decode_event::<ReputationEvent>(row)?;
+    Some(match event {
+        ReputationEvent::NewFeedback(e) => vec![
+            ("agentId", e.agentId.to_string()),
+            ("clientAddress", hex(e.clientAddress.as_slice())),
+            ("feedbackIndex", e.feedbackIndex.to_string()),
+            ("value", e.value.to_string()),
+            ("valueDecimals", e.valueDecimals.to_string()),
+            ("tag1", e.tag1),
+            ("tag2", e.tag2),
+            ("endpoint", e.endpoint),
+            ("feedbackURI", e.feedbackURI),
+            ("feedbackHash", hex(e.feedbackHash.as_slice())),
+        ],
+        ReputationEvent::FeedbackRevoked(e) => vec![
+            ("agentId", e.agentId.to_string()),
+            ("clientAddress", hex(e.clientAddress.as_slice())),
+            ("feedbackIndex", e.feedbackIndex.to_string()),
+        ],
+        ReputationEvent::ResponseAppended(e) => vec![
+            ("agentId", e.agentId.to_string()),
+            ("clientAddress", hex(e.clientAddress.as_slice())),
+            ("feedbackIndex", e.feedbackIndex.to_string()),
+            ("responder", hex(e.responder.as_slice())),
+            ("responseURI", e.responseURI),
+            ("responseHash", hex(e.responseHash.as_slice())),
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Address, U256};
+    use alloy::sol_types::SolEvent;
+    use arrow_array::Array;
+    use arrow_select::concat::concat_batches;
+    use erc8004::contracts::IdentityRegistry;
+
+    use super::*;
+    use crate::testutil::{TempDir, log};
+
+    #[test]
+    fn test_decode_contract_fills_known_events_only() {
+        let registered = IdentityRegistry::Registered {
+            agentId: U256::from(42),
+            agentURI: "ipfs://agent".to_owned(),
+            owner: Address::repeat_byte(0x11),
+        }
+        .encode_log_data();
+        let unknown = alloy::primitives::LogData::new_unchecked(
+            vec![B256::repeat_byte(0xee)],
+            alloy::primitives::Bytes::new(),
+        );
+        let logs = [
+            log(100).data(registered).build(),
+            log(101).data(unknown).build(),
+        ];
+
+        let dir = TempDir::new("decode");
+        let base = dir.join("identity.parquet");
+        assert_eq!(decode_contract(&base).unwrap(), None);
+        parquet::write(&base, &[parquet::logs_to_batch(&logs).unwrap().0]).unwrap();
+        assert_eq!(decode_contract(&base).unwrap(), Some(2));
+        // Rotated files are decoded too, ahead of the live file.
+        let rotated = [log(99).build()];
+        parquet::write(
+            &parquet::rotated_path(&base, 1),
+            &[parquet::logs_to_batch(&rotated).unwrap().0],
+        )
+        .unwrap();
+        assert_eq!(decode_contract(&base).unwrap(), Some(3));
+
+        let batches = parquet::read(&decoded_path(&base)).unwrap();
+        let batch = concat_batches(&decoded_schema(), &batches).unwrap();
+        assert_eq!(batch.schema(), decoded_schema());
+        let text = |name: &str| {
+            parquet::column::<StringArray>(&batch, name)
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(
+            parquet::column::<arrow_array::UInt64Array>(&batch, "block_number")
+                .unwrap()
+                .values(),
+            &[99, 100, 101]
+        );
+        assert_eq!(text("event").value(1), "Registered");
+        assert_eq!(text("agent_id").value(1), "42");
+        assert_eq!(text("agent_uri").value(1), "ipfs://agent");
+        assert_eq!(
+            text("owner").value(1),
+            format!("{:#x}", Address::repeat_byte(0x11))
+        );
+        assert!(text("token_id").is_null(1));
+        for (_, column) in ARG_COLUMNS {
+            assert!(text(column).is_null(2));
+        }
+        assert!(text("event").is_null(2));
+    }
+}
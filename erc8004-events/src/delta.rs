@@ -0,0 +1,641 @@
+//! Delta Lake table output (`--sink-format delta`).
+//!
+//! [`DeltaSink`] turns the data directory into a Delta table: every flush
+//! is written as a new Parquet file under
+//! `chain_id=<id>/contract=<name>/` and added in its own atomic commit to
+//! `_delta_log/`.  Files are never rewritten or removed, so every commit is
+//! a blind append and the log doubles as the table's history.  Spark,
+//! Databricks, Polars and DuckDB's `delta` extension read the table
+//! directly; [`snapshot`] and [`read`] replay the log, optionally as of a
+//! point in time, for `erc8004-events query --as-of`.
+//!
+//! The table schema is [`event_schema`] plus the `chain_id` and `contract`
+//! partition columns.  Delta has no unsigned integers, so `block_number`,
+//! `tx_index`, `log_index` and `tx_nonce` are stored as `long`.  Tables
+//! created before `tx_nonce` or `block_hash` existed keep their original
+//! schema in the log, so other engines do not see the columns; [`read`]
+//! returns them, null for files written before them.
+//!
+//! The writer is implemented here rather than with the `deltalake` crate:
+//! it only ever appends, and the part of the protocol that needs (a
+//! `protocol`/`metaData` commit, then one `add` per flush) is small next
+//! to `deltalake`'s DataFusion and object-store dependencies and its own
+//! pinned Arrow release, which the workspace would have to follow.  The
+//! `delta-readers` CI job reads a table written by this module with the
+//! `deltalake` and Polars readers (`scripts/check_delta_table.py`).
+//!
+//! Commits are created by hard-linking a fully written file into place.
+//! That is the put-if-absent the protocol requires of a log store, and
+//! what `object_store`'s local filesystem does for `deltalake` too: the
+//! link fails if another writer took the version first, and the commit
+//! then moves to the next one.  No checkpoints are written; they are an
+//! optional optimisation for readers, which replay a log-only table from
+//! version 0.  A sink replays the log once and afterwards only the commits
+//! added since, so flushes do not slow down as the log grows.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ::parquet::arrow::ArrowWriter;
+use anyhow::{Context, Result, bail};
+use arrow_array::{
+    Array, ArrayRef, Int64Array, RecordBatch, UInt32Array, UInt64Array, new_null_array,
+};
+use arrow_schema::{DataType, Field, Schema};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::output::EventSink;
+use crate::parquet::{self, ParquetWriterOptions, event_schema};
+
+/// Directory of the commit files, relative to the table root.
+const LOG_DIR: &str = "_delta_log";
+
+/// [`event_schema`] with the unsigned columns widened to signed `Int64`.
+static DELTA_SCHEMA: LazyLock<Arc<Schema>> = LazyLock::new(|| {
+    Arc::new(Schema::new(
+        event_schema()
+            .fields()
+            .iter()
+            .map(|f| match f.data_type() {
+                DataType::UInt64 | DataType::UInt32 => {
+                    Field::new(f.name(), DataType::Int64, f.is_nullable())
+                }
+                _ => f.as_ref().clone(),
+            })
+            .collect::<Vec<_>>(),
+    ))
+});
+
+/// Writes flushes to a Delta table; see the [module docs](self).
+#[derive(Debug)]
+pub struct DeltaSink {
+    root: PathBuf,
+    /// Commit timestamp in milliseconds since the Unix epoch.
+    clock: fn() -> u64,
+    /// The log as replayed by the last [`high_water_mark`] call.
+    ///
+    /// [`high_water_mark`]: EventSink::high_water_mark
+    replay: Mutex<Replay>,
+}
+
+impl DeltaSink {
+    /// Open the Delta table at `root`, creating it if it has no commits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the log directory or the initial commit cannot
+    /// be written.
+    pub fn open(root: &Path) -> Result<Self> {
+        Self::open_with_clock(root, now_ms)
+    }
+
+    /// [`DeltaSink::open`] with commit timestamps taken from `clock`.
+    fn open_with_clock(root: &Path, clock: fn() -> u64) -> Result<Self> {
+        let log = root.join(LOG_DIR);
+        std::fs::create_dir_all(&log).with_context(|| format!("creating {}", log.display()))?;
+        if versions(root)?.is_empty() {
+            let now = clock();
+            let actions = [
+                json!({"protocol": {"minReaderVersion": 1, "minWriterVersion": 2}}),
+                json!({"metaData": {
+                    "id": unique_id("table"),
+                    "format": {"provider": "parquet", "options": {}},
+                    "schemaString": schema_string().to_string(),
+                    "partitionColumns": ["chain_id", "contract"],
+                    "configuration": {},
+                    "createdTime": now,
+                }}),
+                commit_info(now, "CREATE TABLE"),
+            ];
+            // Another writer creating the table at the same time is fine.
+            commit(root, 0, &actions, false)?;
+        }
+        Ok(Self {
+            root: root.to_path_buf(),
+            clock,
+            replay: Mutex::new(Replay::default()),
+        })
+    }
+}
+
+impl EventSink for DeltaSink {
+    fn write(&self, chain_id: u64, contract: &str, batch: &RecordBatch) -> Result<()> {
+        let Some((min, max)) = block_range(batch) else {
+            return Ok(());
+        };
+        let rel = format!(
+            "chain_id={chain_id}/contract={contract}/part-{}.parquet",
+            unique_id(contract)
+        );
+        let path = self.root.join(&rel);
+        write_file(&path, &to_delta(batch)?)?;
+        let size = std::fs::metadata(&path)
+            .with_context(|| format!("reading {}", path.display()))?
+            .len();
+
+        let now = (self.clock)();
+        let stats = Stats {
+            num_records: batch.num_rows(),
+            min_values: BlockStats { block_number: min },
+            max_values: BlockStats { block_number: max },
+        };
+        let add = Add {
+            path: rel,
+            partition_values: BTreeMap::from([
+                ("chain_id".to_owned(), chain_id.to_string()),
+                ("contract".to_owned(), contract.to_owned()),
+            ]),
+            size,
+            modification_time: now,
+            data_change: true,
+            stats: Some(serde_json::to_string(&stats)?),
+        };
+        let next = versions(&self.root)?.last().map_or(0, |v| v + 1);
+        commit(
+            &self.root,
+            next,
+            &[json!({ "add": add }), commit_info(now, "WRITE")],
+            true,
+        )?;
+        Ok(())
+    }
+
+    fn high_water_mark(&self, chain_id: u64, contract: &str) -> Result<Option<u64>> {
+        let mut replay = self.replay.lock().unwrap_or_else(PoisonError::into_inner);
+        for v in versions(&self.root)? {
+            if replay.version.is_none_or(|seen| v > seen) {
+                replay.apply(&self.root, v, None)?;
+            }
+        }
+        Ok(replay
+            .files
+            .iter()
+            .filter(|f| f.chain_id == Some(chain_id) && f.contract == contract)
+            .filter_map(|f| f.max_block)
+            .max())
+    }
+}
+
+/// The live data files of a table version.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// Table version the files belong to.
+    pub version: u64,
+    /// Data files, in the order they were added.
+    pub files: Vec<DataFile>,
+}
+
+/// One data file of a [`Snapshot`].
+#[derive(Debug, Clone)]
+pub struct DataFile {
+    /// Path relative to the table root.
+    pub path: String,
+    /// `chain_id` partition value.
+    pub chain_id: Option<u64>,
+    /// `contract` partition value.
+    pub contract: String,
+    /// Highest `block_number` in the file, from the commit statistics.
+    pub max_block: Option<u64>,
+}
+
+/// Replay the table's log up to the last commit at or before `as_of`
+/// (milliseconds since the Unix epoch), or to the latest commit.
+///
+/// # Errors
+///
+/// Returns an error if `root` is not a Delta table, a commit cannot be
+/// parsed, or the table did not exist yet at `as_of`.
+pub fn snapshot(root: &Path, as_of: Option<u64>) -> Result<Snapshot> {
+    let versions = versions(root)?;
+    if versions.is_empty() {
+        bail!("{} is not a Delta table", root.display());
+    }
+    let mut replay = Replay::default();
+    for v in versions {
+        if !replay.apply(root, v, as_of)? {
+            break;
+        }
+    }
+    let Some(version) = replay.version else {
+        bail!(
+            "{} has no commits at or before the requested time",
+            root.display()
+        );
+    };
+    Ok(Snapshot {
+        version,
+        files: replay.files,
+    })
+}
+
+/// The live data files after replaying the log up to `version`.
+#[derive(Debug, Default)]
+struct Replay {
+    version: Option<u64>,
+    files: Vec<DataFile>,
+}
+
+impl Replay {
+    /// Apply commit `version` unless it was committed after `as_of`;
+    /// returns whether it was applied.
+    fn apply(&mut self, root: &Path, version: u64, as_of: Option<u64>) -> Result<bool> {
+        let path = commit_path(root, version);
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        let actions = text
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(serde_json::from_str::<Action>)
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("parsing {}", path.display()))?;
+        let timestamp = match actions
+            .iter()
+            .find_map(|a| a.commit_info.as_ref()?.timestamp)
+        {
+            Some(ts) => ts,
+            None => modified_ms(&path)?,
+        };
+        if as_of.is_some_and(|t| timestamp > t) {
+            return Ok(false);
+        }
+        for action in actions {
+            if let Some(remove) = action.remove {
+                self.files.retain(|f| f.path != remove.path);
+            }
+            if let Some(add) = action.add {
+                let stats: Option<Stats> = add
+                    .stats
+                    .as_deref()
+                    .and_then(|s| serde_json::from_str(s).ok());
+                self.files.push(DataFile {
+                    chain_id: add
+                        .partition_values
+                        .get("chain_id")
+                        .and_then(|c| c.parse().ok()),
+                    contract: add
+                        .partition_values
+                        .get("contract")
+                        .cloned()
+                        .unwrap_or_default(),
+                    max_block: stats.map(|s| s.max_values.block_number),
+                    path: add.path,
+                });
+            }
+        }
+        self.version = Some(version);
+        Ok(true)
+    }
+}
+
+/// Read a chain's rows (optionally one contract's) as of `as_of` (see
+/// [`snapshot`]), converted back to [`event_schema`].
+///
+/// # Errors
+///
+/// Returns an error if the log or a data file cannot be read.
+pub fn read(
+    root: &Path,
+    chain_id: u64,
+    contract: Option<&str>,
+    as_of: Option<u64>,
+) -> Result<Vec<RecordBatch>> {
+    let snapshot = snapshot(root, as_of)?;
+    let mut batches = Vec::new();
+    for file in snapshot
+        .files
+        .iter()
+        .filter(|f| f.chain_id == Some(chain_id) && contract.is_none_or(|c| f.contract == c))
+    {
+        for batch in parquet::read(&root.join(&file.path))? {
+            batches.push(from_delta(&batch)?);
+        }
+    }
+    Ok(batches)
+}
+
+/// Whether `root` holds a Delta table.
+#[must_use]
+pub fn is_table(root: &Path) -> bool {
+    versions(root).is_ok_and(|v| !v.is_empty())
+}
+
+/// `add` action of a commit.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Add {
+    path: String,
+    #[serde(default)]
+    partition_values: BTreeMap<String, String>,
+    size: u64,
+    modification_time: u64,
+    data_change: bool,
+    /// JSON-encoded [`Stats`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stats: Option<String>,
+}
+
+/// `remove` action of a commit (only read; this writer never removes).
+#[derive(Debug, Deserialize)]
+struct Remove {
+    path: String,
+}
+
+/// `commitInfo` action of a commit.
+#[derive(Debug, Deserialize)]
+struct CommitInfo {
+    timestamp: Option<u64>,
+}
+
+/// One line of a commit file; actions other than these are ignored.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Action {
+    add: Option<Add>,
+    remove: Option<Remove>,
+    commit_info: Option<CommitInfo>,
+}
+
+/// Per-file statistics, limited to the `block_number` range.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Stats {
+    num_records: usize,
+    min_values: BlockStats,
+    max_values: BlockStats,
+}
+
+/// Column values recorded in [`Stats`].
+#[derive(Debug, Serialize, Deserialize)]
+struct BlockStats {
+    block_number: u64,
+}
+
+/// `commitInfo` action for an operation at `timestamp`.
+fn commit_info(timestamp: u64, operation: &str) -> serde_json::Value {
+    json!({"commitInfo": {
+        "timestamp": timestamp,
+        "operation": operation,
+        "isBlindAppend": true,
+        "engineInfo": concat!("erc8004-events/", env!("CARGO_PKG_VERSION")),
+    }})
+}
+
+/// The table schema in Delta's JSON notation.
+fn schema_string() -> serde_json::Value {
+    let mut fields: Vec<_> = DELTA_SCHEMA
+        .fields()
+        .iter()
+        .map(|f| {
+            let kind = match f.data_type() {
+                DataType::Int64 => "long",
+                DataType::Boolean => "boolean",
+                _ => "string",
+            };
+            json!({"name": f.name(), "type": kind, "nullable": f.is_nullable(), "metadata": {}})
+        })
+        .collect();
+    fields.push(json!({"name": "chain_id", "type": "long", "nullable": false, "metadata": {}}));
+    fields.push(json!({"name": "contract", "type": "string", "nullable": false, "metadata": {}}));
+    json!({"type": "struct", "fields": fields})
+}
+
+/// Write `actions` as commit `version`, or the first free version after it
+/// when `retry` is set.
+fn commit(root: &Path, mut version: u64, actions: &[serde_json::Value], retry: bool) -> Result<()> {
+    let mut body = String::new();
+    for action in actions {
+        body.push_str(&action.to_string());
+        body.push('\n');
+    }
+    let tmp = root
+        .join(LOG_DIR)
+        .join(format!(".{}.json.tmp", unique_id("commit")));
+    std::fs::write(&tmp, body).with_context(|| format!("writing {}", tmp.display()))?;
+    let res = loop {
+        match std::fs::hard_link(&tmp, commit_path(root, version)) {
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && retry => version += 1,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => break Ok(()),
+            res => break res,
+        }
+    };
+    let _ = std::fs::remove_file(&tmp);
+    res.with_context(|| format!("committing version {version} to {}", root.display()))?;
+    tracing::debug!(table = %root.display(), version, "delta commit");
+    Ok(())
+}
+
+/// Path of the commit file for `version`.
+fn commit_path(root: &Path, version: u64) -> PathBuf {
+    root.join(LOG_DIR).join(format!("{version:020}.json"))
+}
+
+/// Committed versions, in ascending order.
+fn versions(root: &Path) -> Result<Vec<u64>> {
+    let log = root.join(LOG_DIR);
+    if !log.exists() {
+        return Ok(Vec::new());
+    }
+    let mut versions: Vec<u64> = std::fs::read_dir(&log)
+        .with_context(|| format!("listing {}", log.display()))?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            name.to_str()?.strip_suffix(".json")?.parse().ok()
+        })
+        .collect();
+    versions.sort_unstable();
+    Ok(versions)
+}
+
+/// Write one data file atomically.
+fn write_file(path: &Path, batch: &RecordBatch) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("parquet.tmp");
+    let file =
+        std::fs::File::create(&tmp).with_context(|| format!("creating {}", tmp.display()))?;
+    let props = parquet::writer_properties(ParquetWriterOptions::default())?;
+    let mut writer = ArrowWriter::try_new(file, Arc::clone(&DELTA_SCHEMA), Some(props))?;
+    writer.write(batch)?;
+    writer.close()?;
+    std::fs::rename(&tmp, path).with_context(|| format!("renaming to {}", path.display()))
+}
+
+/// Convert a batch in [`event_schema`] to the table schema.
+fn to_delta(batch: &RecordBatch) -> Result<RecordBatch> {
+    let columns = batch
+        .columns()
+        .iter()
+        .map(|col| -> Result<ArrayRef> {
+            if let Some(a) = col.as_any().downcast_ref::<UInt64Array>() {
+                let values = a
+                    .iter()
+                    .map(|v| v.map(i64::try_from).transpose())
+                    .collect::<Result<Int64Array, _>>()
+                    .context("block number exceeds the Delta `long` range")?;
+                Ok(Arc::new(values))
+            } else if let Some(a) = col.as_any().downcast_ref::<UInt32Array>() {
+                Ok(Arc::new(
+                    a.iter().map(|v| v.map(i64::from)).collect::<Int64Array>(),
+                ))
+            } else {
+                Ok(Arc::clone(col))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(RecordBatch::try_new(Arc::clone(&DELTA_SCHEMA), columns)?)
+}
+
+/// Convert a batch read from a data file back to [`event_schema`].
+fn from_delta(batch: &RecordBatch) -> Result<RecordBatch> {
+    let schema = event_schema();
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|field| -> Result<ArrayRef> {
+            let Some(col) = batch.column_by_name(field.name()) else {
+                // Files written before a nullable column was added.
+                if field.is_nullable() {
+                    return Ok(new_null_array(field.data_type(), batch.num_rows()));
+                }
+                bail!("missing column `{}`", field.name());
+            };
+            let Some(a) = col.as_any().downcast_ref::<Int64Array>() else {
+                return Ok(Arc::clone(col));
+            };
+            let array: ArrayRef = match field.data_type() {
+                DataType::UInt64 => Arc::new(
+                    a.iter()
+                        .map(|v| v.map(u64::try_from).transpose())
+                        .collect::<Result<UInt64Array, _>>()?,
+                ),
+                _ => Arc::new(
+                    a.iter()
+                        .map(|v| v.map(u32::try_from).transpose())
+                        .collect::<Result<UInt32Array, _>>()?,
+                ),
+            };
+            Ok(array)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
+
+/// Lowest and highest `block_number` of a non-empty batch.
+fn block_range(batch: &RecordBatch) -> Option<(u64, u64)> {
+    let blocks = batch.column(0).as_any().downcast_ref::<UInt64Array>()?;
+    let min = blocks.values().iter().copied().min()?;
+    let max = blocks.values().iter().copied().max()?;
+    Some((min, max))
+}
+
+/// A UUID-formatted identifier, unique within and across processes.
+fn unique_id(salt: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    let digest = Sha256::new()
+        .chain_update(salt)
+        .chain_update(nanos.to_le_bytes())
+        .chain_update(std::process::id().to_le_bytes())
+        .chain_update(COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes())
+        .finalize();
+    let hex = alloy::hex::encode(digest.get(..16).unwrap_or_default());
+    format!(
+        "{}-{}-{}-{}-{}",
+        hex.get(..8).unwrap_or_default(),
+        hex.get(8..12).unwrap_or_default(),
+        hex.get(12..16).unwrap_or_default(),
+        hex.get(16..20).unwrap_or_default(),
+        hex.get(20..).unwrap_or_default()
+    )
+}
+
+/// Milliseconds since the Unix epoch.
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// Modification time of `path` in milliseconds since the Unix epoch.
+fn modified_ms(path: &Path) -> Result<u64> {
+    let modified = std::fs::metadata(path)?.modified()?;
+    Ok(modified
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{TempDir, logs_at};
+
+    fn batch(blocks: &[u64]) -> RecordBatch {
+        parquet::logs_to_batch(&logs_at(blocks)).unwrap().0
+    }
+
+    /// A clock that advances 10 ms on every reading.
+    fn tick() -> u64 {
+        static NOW: AtomicU64 = AtomicU64::new(1_000);
+        NOW.fetch_add(10, Ordering::Relaxed)
+    }
+
+    #[test]
+    fn test_delta_sink_appends_and_time_travels() {
+        let root = TempDir::new("delta");
+
+        let sink = DeltaSink::open_with_clock(&root, tick).unwrap();
+        assert!(is_table(&root));
+        sink.write(8453, "identity", &batch(&[10, 11])).unwrap();
+        let first = snapshot(&root, None).unwrap();
+        assert_eq!(first.version, 1);
+
+        // The first two commits are at 1000 and 1010 ms, the rest later.
+        let between = 1_015;
+        sink.write(8453, "identity", &batch(&[12])).unwrap();
+        sink.write(8453, "reputation", &batch(&[20])).unwrap();
+        sink.write(1, "identity", &batch(&[30])).unwrap();
+        DeltaSink::open(&root).unwrap();
+
+        assert_eq!(sink.high_water_mark(8453, "identity").unwrap(), Some(12));
+        assert_eq!(sink.high_water_mark(8453, "reputation").unwrap(), Some(20));
+        assert_eq!(sink.high_water_mark(10, "identity").unwrap(), None);
+
+        // Commits of another writer are picked up by the replayed log.
+        DeltaSink::open(&root)
+            .unwrap()
+            .write(8453, "identity", &batch(&[13]))
+            .unwrap();
+        assert_eq!(sink.high_water_mark(8453, "identity").unwrap(), Some(13));
+
+        let latest = read(&root, 8453, Some("identity"), None).unwrap();
+        assert_eq!(parquet::max_block_number(&latest), Some(13));
+        assert_eq!(latest.first().unwrap().schema(), event_schema());
+        assert_eq!(read(&root, 8453, None, None).unwrap().len(), 4);
+
+        let past = read(&root, 8453, None, Some(between)).unwrap();
+        assert_eq!(past.len(), 1);
+        assert_eq!(parquet::max_block_number(&past), Some(11));
+        assert!(snapshot(&root, Some(0)).is_err());
+    }
+
+    /// Write the table `scripts/check_delta_table.py` reads with other
+    /// Delta implementations, to `$DELTA_FIXTURE_DIR`.
+    #[test]
+    #[ignore = "run by the delta-readers CI job"]
+    fn test_delta_table_fixture() {
+        let root = PathBuf::from(std::env::var("DELTA_FIXTURE_DIR").unwrap());
+        let sink = DeltaSink::open(&root).unwrap();
+        sink.write(8453, "identity", &batch(&[10, 11])).unwrap();
+        sink.write(8453, "identity", &batch(&[12])).unwrap();
+        sink.write(1, "reputation", &batch(&[30])).unwrap();
+        assert_eq!(snapshot(&root, None).unwrap().version, 3);
+    }
+}
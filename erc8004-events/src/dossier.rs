@@ -0,0 +1,264 @@
+//! Agent dossiers: one agent's full history across every chain it exists on.
+//!
+//! [`build`] asks each chain's Identity Registry whether the agent ID exists
+//! (`ownerOf`, via the SDK), and for every chain where it does archives the
+//! agent's identity and reputation events with
+//! [`sync_agent`](crate::fetcher::sync_agent) and fetches its registration
+//! file.  The dossier directory holds one subdirectory per chain plus a
+//! `dossier.json` summary:
+//!
+//! ```text
+//! <out>/<agent_id>/dossier.json
+//! <out>/<agent_id>/<chain_id>/identity.parquet
+//! <out>/<agent_id>/<chain_id>/reputation.parquet
+//! <out>/<agent_id>/<chain_id>/registration.json
+//! ```
+//!
+//! Agent IDs are per-registry, so the same ID on two chains need not be the
+//! same agent; the summary lists each chain's owner and `agentURI` so that
+//! readers can tell.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use alloy::primitives::{Address, U256};
+use alloy::providers::Provider;
+use anyhow::{Context, Result, bail};
+use erc8004::Erc8004;
+use erc8004::networks::NetworkAddresses;
+use erc8004::types::RegistrationFile;
+use erc8004::uri::UriResolver;
+use serde::Serialize;
+
+use crate::cursor;
+use crate::fetcher::{self, SyncOptions, SyncTarget};
+use crate::retry;
+
+/// Deadline for fetching a registration file; a slow gateway must not stall
+/// the dossier.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Summary of a dossier, written to `dossier.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Dossier {
+    /// The agent ID, as a decimal string (`uint256`).
+    pub agent_id: String,
+    /// Unix timestamp (seconds) the dossier was built at.
+    pub built_at: u64,
+    /// Chains where the agent ID exists, in the order they were queried.
+    pub chains: Vec<ChainDossier>,
+    /// Chain IDs that could not be queried on any RPC.
+    pub unreachable: Vec<u64>,
+}
+
+/// The agent on one chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChainDossier {
+    /// EIP-155 chain ID.
+    pub chain_id: u64,
+    /// Human-readable chain name.
+    pub chain: String,
+    /// Current owner (`0x`-prefixed, lowercase).
+    pub owner: String,
+    /// Current `agentURI`.
+    pub uri: String,
+    /// Identity Registry events newly archived.
+    pub identity_events: usize,
+    /// Reputation Registry events newly archived.
+    pub reputation_events: usize,
+    /// Whether `registration.json` was fetched and written.
+    pub registration: bool,
+}
+
+/// The owner and `agentURI` of an agent on one chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Registration {
+    owner: Address,
+    uri: String,
+}
+
+/// Build the dossier of `agent_id` over `targets` in `out_dir/<agent_id>`
+/// and return its summary, which is also written to `dossier.json`.
+///
+/// Chains whose RPCs all fail are logged and listed as unreachable; event
+/// and registration file failures on a chain where the agent exists are
+/// logged and leave that chain's files incomplete.
+///
+/// # Errors
+///
+/// Returns an error if no chain could be queried, or on I/O failure.
+pub async fn build(
+    targets: &[SyncTarget],
+    agent_id: U256,
+    out_dir: &Path,
+    opts: &SyncOptions,
+    resolver: &dyn UriResolver,
+) -> Result<Dossier> {
+    let dir = out_dir.join(agent_id.to_string());
+    let mut dossier = Dossier {
+        agent_id: agent_id.to_string(),
+        built_at: cursor::unix_now(),
+        chains: Vec::new(),
+        unreachable: Vec::new(),
+    };
+    for target in targets {
+        let cid = target.chain.chain_id();
+        let reg = match locate(target, agent_id, opts).await {
+            Ok(Some(reg)) => reg,
+            Ok(None) => {
+                tracing::debug!(chain_id = cid, %agent_id, "agent not registered");
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!(chain_id = cid, error = %e, "chain unreachable");
+                dossier.unreachable.push(cid);
+                continue;
+            }
+        };
+        tracing::info!(chain_id = cid, %agent_id, owner = %reg.owner, "agent found");
+        let chain_dir = dir.join(cid.to_string());
+        std::fs::create_dir_all(&chain_dir)
+            .with_context(|| format!("creating {}", chain_dir.display()))?;
+        let (identity_events, reputation_events) =
+            match fetcher::sync_agent(target, &chain_dir, agent_id, opts).await {
+                Ok(counts) => counts,
+                Err(e) => {
+                    tracing::warn!(chain_id = cid, error = %e, "agent events not archived");
+                    (0, 0)
+                }
+            };
+        let registration =
+            fetch_registration(&reg.uri, resolver, &chain_dir.join("registration.json")).await?;
+        dossier.chains.push(ChainDossier {
+            chain_id: cid,
+            chain: target.chain.name.to_owned(),
+            owner: format!("{:#x}", reg.owner),
+            uri: reg.uri,
+            identity_events,
+            reputation_events,
+            registration,
+        });
+    }
+    if !targets.is_empty() && dossier.unreachable.len() == targets.len() {
+        bail!("no chain could be queried for agent {agent_id}");
+    }
+    std::fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    write_json(&dir.join("dossier.json"), &dossier)?;
+    Ok(dossier)
+}
+
+/// The agent's owner and URI on `target`'s chain, or `None` if the ID is
+/// not registered there, falling back through the target's RPCs.
+async fn locate(
+    target: &SyncTarget,
+    agent_id: U256,
+    opts: &SyncOptions,
+) -> Result<Option<Registration>> {
+    let cid = target.chain.chain_id();
+    let mut last_err = None;
+    for url in &target.rpcs {
+        let res = async {
+            let provider = fetcher::connect(target, url)?;
+            fetcher::verify_chain_id(&provider, cid, opts).await?;
+            tokio::time::timeout(
+                opts.request_timeout,
+                registration(&provider, target.chain.addresses(), agent_id),
+            )
+            .await
+            .context("ownerOf timed out")?
+        }
+        .await;
+        match res {
+            Ok(reg) => return Ok(reg),
+            Err(e) => {
+                tracing::debug!(chain_id = cid, rpc = %url, error = %e, "agent lookup failed");
+                last_err = Some(e);
+            }
+        }
+    }
+    match last_err {
+        Some(e) => Err(e),
+        None => bail!("no RPCs configured for chain {cid}"),
+    }
+}
+
+/// Query the Identity Registry at `addrs` for `agent_id`.
+async fn registration(
+    provider: &impl Provider,
+    addrs: NetworkAddresses,
+    agent_id: U256,
+) -> Result<Option<Registration>> {
+    let client = Erc8004::new(provider).with_addresses(addrs);
+    let identity = client.identity()?;
+    let Some(owner) = identity.try_owner_of(agent_id).await? else {
+        return Ok(None);
+    };
+    Ok(Some(Registration {
+        owner,
+        uri: identity.token_uri(agent_id).await?,
+    }))
+}
+
+/// Fetch the registration file at `uri` and write it to `path`; returns
+/// whether it was written.
+async fn fetch_registration(uri: &str, resolver: &dyn UriResolver, path: &Path) -> Result<bool> {
+    match tokio::time::timeout(FETCH_TIMEOUT, RegistrationFile::from_uri(uri, resolver)).await {
+        Ok(Ok(file)) => {
+            write_json(path, &file)?;
+            Ok(true)
+        }
+        Ok(Err(e)) => {
+            tracing::warn!(uri, error = %e, "registration file unavailable");
+            Ok(false)
+        }
+        Err(_) => {
+            tracing::warn!(uri, "registration file fetch timed out");
+            Ok(false)
+        }
+    }
+}
+
+/// Write `value` as pretty JSON to `path`, atomically (temp file + rename).
+fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let json = serde_json::to_string_pretty(value)?;
+    retry::io(path, retry::DEFAULT_IO_RETRIES, || {
+        std::fs::write(&tmp, json.as_bytes())
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("renaming {} → {}", tmp.display(), path.display()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Bytes, address};
+    use alloy::providers::ProviderBuilder;
+    use alloy::sol_types::SolValue;
+    use alloy::transports::mock::Asserter;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_registration_reads_owner_and_uri() -> Result<()> {
+        let owner = address!("00000000000000000000000000000000000000aa");
+        let asserter = Asserter::new();
+        // exists (ownerOf), ownerOf, tokenURI.
+        asserter.push_success(&Bytes::from(owner.abi_encode()));
+        asserter.push_success(&Bytes::from(owner.abi_encode()));
+        asserter.push_success(&Bytes::from("ipfs://agent".to_owned().abi_encode()));
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter);
+
+        let reg = registration(&provider, NetworkAddresses::MAINNET, U256::from(7)).await?;
+        assert_eq!(
+            reg,
+            Some(Registration {
+                owner,
+                uri: "ipfs://agent".to_owned(),
+            })
+        );
+        Ok(())
+    }
+}
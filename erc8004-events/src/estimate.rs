@@ -0,0 +1,251 @@
+//! Data-volume estimate ahead of a sync (`sync --estimate`).
+//!
+//! [`estimate_chain`] fetches a few evenly spaced sample windows from the
+//! range a sync would cover, measures their event density and log size, and
+//! extrapolates both to the whole range.  The sampled logs are encoded with
+//! the run's Parquet writer options, so the size estimate reflects the
+//! chosen codec; a small sample compresses worse than a full archive, so it
+//! tends to err high.  Activity that comes in bursts can put the event count
+//! off in either direction.  The estimate is a planning aid, not a bound.
+
+use std::path::Path;
+
+use ::parquet::arrow::ArrowWriter;
+use alloy::rpc::types::{Filter, Log};
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+
+use crate::cursor::Cursor;
+use crate::fetcher::{self, SyncOptions, SyncTarget};
+use crate::parquet;
+use crate::ratelimit::TokenBucket;
+
+/// Sample windows fetched per chain by default.
+pub const DEFAULT_SAMPLES: usize = 8;
+
+/// Blocks per sample window, unless the chain's `block_step` or
+/// `max_block_range` is smaller.
+pub const SAMPLE_BLOCKS: u64 = 1_000;
+
+/// Expected volume of syncing one chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SyncEstimate {
+    /// EIP-155 chain ID.
+    pub chain_id: u64,
+    /// First block the sync would fetch.
+    pub from_block: u64,
+    /// Last block the sync would fetch.
+    pub to_block: u64,
+    /// Blocks covered by the sample windows.
+    pub sampled_blocks: u64,
+    /// Events found in the sample windows.
+    pub sampled_events: u64,
+    /// Estimated events in the whole range.
+    pub events: u64,
+    /// Estimated size of the `eth_getLogs` responses, in JSON bytes.
+    pub download_bytes: u64,
+    /// Estimated size of the written Parquet data, in bytes.
+    pub parquet_bytes: u64,
+}
+
+impl SyncEstimate {
+    /// Number of blocks the sync would fetch.
+    #[must_use]
+    pub const fn blocks(&self) -> u64 {
+        (self.to_block + 1).saturating_sub(self.from_block)
+    }
+}
+
+/// Estimate the volume of syncing `target` with `opts` from `samples`
+/// windows; see the [module docs](self).
+///
+/// RPCs are tried in order until one answers every sample.  Nothing is
+/// written to `data_dir`; it is only read for the chain's cursor.
+///
+/// # Errors
+///
+/// Returns an error if every RPC fails.
+pub async fn estimate_chain(
+    target: &SyncTarget,
+    data_dir: &Path,
+    opts: &SyncOptions,
+    samples: usize,
+) -> Result<SyncEstimate> {
+    let cid = target.chain.chain_id();
+    let mut last_err = None;
+    for url in &target.rpcs {
+        match probe(target, data_dir, url, opts, samples).await {
+            Ok(estimate) => return Ok(estimate),
+            Err(e) => {
+                tracing::warn!(chain_id = cid, rpc = %url, error = %e, "estimate failed");
+                last_err = Some(e);
+            }
+        }
+    }
+    match last_err {
+        Some(e) => Err(e),
+        None => bail!("no RPCs configured for chain {cid}"),
+    }
+}
+
+/// [`estimate_chain`] against a single RPC.
+async fn probe(
+    target: &SyncTarget,
+    data_dir: &Path,
+    rpc_url: &str,
+    opts: &SyncOptions,
+    samples: usize,
+) -> Result<SyncEstimate> {
+    let chain = &target.chain;
+    let cid = chain.chain_id();
+    let provider = fetcher::connect(target, rpc_url)?;
+    fetcher::verify_chain_id(&provider, cid, opts).await?;
+    let latest = fetcher::block_number(&provider, opts)
+        .await?
+        .saturating_sub(opts.confirmations);
+    let cursor = Cursor::load(&data_dir.join(cid.to_string()))?;
+    let start = fetcher::start_block(chain, cursor.as_ref(), opts);
+
+    let size = target
+        .block_step
+        .or(target.max_block_range)
+        .map_or(SAMPLE_BLOCKS, |max| max.clamp(1, SAMPLE_BLOCKS));
+    let bucket = target
+        .rpcs_per_second
+        .or(opts.rpcs_per_second)
+        .and_then(TokenBucket::new);
+    let addrs = chain.addresses();
+    let mut logs = Vec::new();
+    let mut sampled_blocks = 0;
+    for (from, to) in sample_windows(start, latest, samples, size) {
+        if let Some(bucket) = &bucket {
+            bucket.acquire().await;
+        }
+        let filter = Filter::new()
+            .address(vec![addrs.identity, addrs.reputation])
+            .from_block(from)
+            .to_block(to);
+        let page = fetcher::get_logs(
+            &provider,
+            &[filter],
+            opts.request_timeout,
+            target.paginated_logs,
+        )
+        .await
+        .pop()
+        .context("eth_getLogs returned no result")?
+        .with_context(|| format!("sampling blocks {from}..={to}"))?;
+        logs.extend(page);
+        sampled_blocks += to - from + 1;
+    }
+
+    let (sampled_events, sample_json, sample_parquet) = measure(&logs, opts)?;
+    let blocks = (latest + 1).saturating_sub(start);
+    let estimate = SyncEstimate {
+        chain_id: cid,
+        from_block: start,
+        to_block: latest,
+        sampled_blocks,
+        sampled_events,
+        events: extrapolate(sampled_events, sampled_blocks, blocks),
+        download_bytes: extrapolate(sample_json, sampled_blocks, blocks),
+        parquet_bytes: extrapolate(sample_parquet, sampled_blocks, blocks),
+    };
+    tracing::debug!(chain_id = cid, rpc = rpc_url, ?estimate, "estimated");
+    Ok(estimate)
+}
+
+/// Number of archivable logs among `logs`, their JSON size and their size
+/// as a Parquet file written with `opts.parquet`.
+fn measure(logs: &[Log], opts: &SyncOptions) -> Result<(u64, u64, u64)> {
+    if logs.is_empty() {
+        return Ok((0, 0, 0));
+    }
+    let mut json = 0;
+    for log in logs {
+        json += serde_json::to_vec(log)?.len();
+    }
+    let (batch, rows) = parquet::logs_to_batch(logs)?;
+    let props = parquet::writer_properties(opts.parquet)?;
+    let mut buf = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buf, batch.schema(), Some(props))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok((
+        u64::try_from(rows)?,
+        u64::try_from(json)?,
+        u64::try_from(buf.len())?,
+    ))
+}
+
+/// Up to `count` windows of `size` blocks spread evenly over `from..=to`,
+/// or consecutive windows covering all of it when they would meet anyway.
+#[must_use]
+pub fn sample_windows(from: u64, to: u64, count: usize, size: u64) -> Vec<(u64, u64)> {
+    if from > to || count == 0 || size == 0 {
+        return Vec::new();
+    }
+    let span = to - from + 1;
+    let count = u64::try_from(count).unwrap_or(u64::MAX);
+    if span <= size.saturating_mul(count) {
+        let mut windows = Vec::new();
+        let mut block = from;
+        loop {
+            let end = block.saturating_add(size - 1).min(to);
+            windows.push((block, end));
+            if end == to {
+                return windows;
+            }
+            block = end + 1;
+        }
+    }
+    // `span > size * count`, so windows a `stride` apart never overlap and
+    // the last one ends before `to`.
+    let stride = span / count;
+    (0..count)
+        .map(|i| {
+            let start = from + i * stride;
+            (start, start + size - 1)
+        })
+        .collect()
+}
+
+/// Scale `sampled`, measured over `sampled_blocks`, to `total_blocks`.
+fn extrapolate(sampled: u64, sampled_blocks: u64, total_blocks: u64) -> u64 {
+    if sampled_blocks == 0 {
+        return 0;
+    }
+    let scaled = u128::from(sampled) * u128::from(total_blocks) / u128::from(sampled_blocks);
+    u64::try_from(scaled).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_windows_spread_over_range() {
+        assert_eq!(
+            sample_windows(1_000, 100_999, 4, 100),
+            [
+                (1_000, 1_099),
+                (26_000, 26_099),
+                (51_000, 51_099),
+                (76_000, 76_099)
+            ]
+        );
+        // A short range is covered completely.
+        assert_eq!(
+            sample_windows(10, 259, 4, 100),
+            [(10, 109), (110, 209), (210, 259)]
+        );
+        assert!(sample_windows(10, 9, 4, 100).is_empty());
+    }
+
+    #[test]
+    fn test_extrapolate_scales_density() {
+        assert_eq!(extrapolate(40, 4_000, 1_000_000), 10_000);
+        assert_eq!(extrapolate(40, 0, 1_000_000), 0);
+        assert_eq!(extrapolate(u64::MAX, 1, 2), u64::MAX);
+    }
+}
@@ -0,0 +1,94 @@
+//! Export of archived Parquet files for distribution.
+//!
+//! Copies every `<chain_id>/<contract>.parquet` from a data directory into an
+//! output directory and writes an `index.json` describing each file.  With
+//! content-addressed naming, files are stored as `<sha256>.parquet` so that
+//! consumers can cache them indefinitely.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::fetcher::discover_archived_chains;
+
+/// Contract file stems produced by the sync engine.
+pub const CONTRACTS: [&str; 2] = ["identity", "reputation"];
+
+/// One exported file as recorded in `index.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportEntry {
+    /// EIP-155 chain ID.
+    pub chain_id: u64,
+    /// Contract name (`identity` or `reputation`).
+    pub contract: String,
+    /// Path of the exported file relative to the output directory.
+    pub file: String,
+    /// Hex-encoded SHA-256 of the file content.
+    pub sha256: String,
+    /// File size in bytes.
+    pub bytes: u64,
+}
+
+/// Export all archived chains under `data_dir` into `out_dir`.
+///
+/// Files are named `<chain_id>/<contract>.parquet`, or `<sha256>.parquet`
+/// when `content_addressed` is set.  Each file is written to a temporary
+/// name and renamed into place after hashing.  Returns the entries written
+/// to `<out_dir>/index.json`.
+///
+/// # Errors
+///
+/// Returns an error on any I/O failure.
+pub fn export(
+    data_dir: &Path,
+    out_dir: &Path,
+    content_addressed: bool,
+) -> Result<Vec<ExportEntry>> {
+    std::fs::create_dir_all(out_dir).with_context(|| format!("creating {}", out_dir.display()))?;
+
+    let mut entries = Vec::new();
+    for (chain, dir) in discover_archived_chains(data_dir)? {
+        let cid = chain.chain_id();
+        for contract in CONTRACTS {
+            let src = dir.join(format!("{contract}.parquet"));
+            if !src.exists() {
+                continue;
+            }
+            let bytes =
+                std::fs::read(&src).with_context(|| format!("reading {}", src.display()))?;
+            let sha256 = alloy::hex::encode(Sha256::digest(&bytes));
+            let file = if content_addressed {
+                format!("{sha256}.parquet")
+            } else {
+                format!("{cid}/{contract}.parquet")
+            };
+
+            let dst = out_dir.join(&file);
+            if let Some(parent) = dst.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("creating {}", parent.display()))?;
+            }
+            let tmp = dst.with_extension("parquet.tmp");
+            std::fs::write(&tmp, &bytes).with_context(|| format!("writing {}", tmp.display()))?;
+            std::fs::rename(&tmp, &dst)
+                .with_context(|| format!("renaming {} → {}", tmp.display(), dst.display()))?;
+
+            tracing::info!(chain_id = cid, contract, file = %file, "exported");
+            entries.push(ExportEntry {
+                chain_id: cid,
+                contract: contract.to_owned(),
+                file,
+                sha256,
+                bytes: bytes.len() as u64,
+            });
+        }
+    }
+
+    let index = out_dir.join("index.json");
+    std::fs::write(&index, serde_json::to_string_pretty(&entries)?.as_bytes())
+        .with_context(|| format!("writing {}", index.display()))?;
+
+    Ok(entries)
+}
@@ -0,0 +1,139 @@
+//! Persistence of block ranges that could not be fetched.
+//!
+//! When a sync runs with `skip_unfetchable`, a range that still exceeds the
+//! RPC's limits at the minimum batch size is appended to `<dir>/gaps.json`
+//! instead of failing the chain, so it can be back-filled manually later.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::cursor::unix_now;
+
+/// A single skipped block range.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Gap {
+    /// Contract whose logs are missing (`0x`-prefixed hex).
+    pub address: String,
+    /// First skipped block (inclusive).
+    pub from_block: u64,
+    /// Last skipped block (inclusive).
+    pub to_block: u64,
+    /// The RPC error that caused the skip.
+    pub error: String,
+    /// Unix timestamp (seconds) at which the gap was recorded.
+    pub recorded_at: u64,
+}
+
+impl Gap {
+    /// Create a new gap record stamped with the current time.
+    #[must_use]
+    pub fn now(address: String, from_block: u64, to_block: u64, error: String) -> Self {
+        Self {
+            address,
+            from_block,
+            to_block,
+            error,
+            recorded_at: unix_now(),
+        }
+    }
+}
+
+/// Read all recorded gaps from `<dir>/gaps.json`.
+///
+/// Returns an empty vec if the file does not exist.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read or parsed.
+pub fn load(dir: &Path) -> Result<Vec<Gap>> {
+    let path = dir.join("gaps.json");
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data =
+        std::fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_str(&data).with_context(|| format!("parsing {}", path.display()))
+}
+
+/// Append a gap to `<dir>/gaps.json` atomically.
+///
+/// # Errors
+///
+/// Returns an error if the existing file cannot be parsed or the updated
+/// file cannot be written.
+pub fn append(dir: &Path, gap: Gap) -> Result<()> {
+    let mut gaps = load(dir)?;
+    gaps.push(gap);
+    save(dir, &gaps)
+}
+
+/// Forget the gaps of `address` at or after `block`, which a re-index from
+/// `block` fetches again; a gap that starts earlier is cut short.
+///
+/// Returns the number of gaps removed or shortened.
+///
+/// # Errors
+///
+/// Returns an error if the existing file cannot be parsed or the updated
+/// file cannot be written.
+pub fn truncate_from(dir: &Path, address: &str, block: u64) -> Result<usize> {
+    let mut gaps = load(dir)?;
+    let before = gaps.len();
+    gaps.retain(|g| !g.address.eq_ignore_ascii_case(address) || g.from_block < block);
+    let mut changed = before - gaps.len();
+    for gap in &mut gaps {
+        if gap.address.eq_ignore_ascii_case(address) && gap.to_block >= block {
+            gap.to_block = block - 1;
+            changed += 1;
+        }
+    }
+    if changed > 0 {
+        save(dir, &gaps)?;
+    }
+    Ok(changed)
+}
+
+/// Write `gaps` to `<dir>/gaps.json` atomically.
+fn save(dir: &Path, gaps: &[Gap]) -> Result<()> {
+    std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+
+    let path = dir.join("gaps.json");
+    let tmp = dir.join("gaps.json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(gaps)?.as_bytes())
+        .with_context(|| format!("writing {}", tmp.display()))?;
+    std::fs::rename(&tmp, &path)
+        .with_context(|| format!("renaming {} → {}", tmp.display(), path.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn test_truncate_from_drops_and_shortens_gaps() {
+        let dir = TempDir::new("gaps");
+        for (address, from, to) in [("0xaa", 10, 20), ("0xaa", 30, 40), ("0xbb", 30, 40)] {
+            append(
+                &dir,
+                Gap::now(address.to_owned(), from, to, "too large".to_owned()),
+            )
+            .unwrap();
+        }
+        assert_eq!(truncate_from(&dir, "0xAA", 15).unwrap(), 2);
+        let gaps: Vec<_> = load(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|g| (g.address, g.from_block, g.to_block))
+            .collect();
+        assert_eq!(
+            gaps,
+            [("0xaa".to_owned(), 10, 14), ("0xbb".to_owned(), 30, 40)]
+        );
+        assert_eq!(truncate_from(&dir, "0xaa", 15).unwrap(), 0);
+    }
+}
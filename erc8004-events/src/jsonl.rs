@@ -0,0 +1,169 @@
+//! Newline-delimited JSON archive files (`--sink-format jsonl`).
+//!
+//! Each line is one [`LogRow`].  Rows are only ever appended, one flush per
+//! `write`, so an interrupted run can at worst leave a partial last line;
+//! [`repair_tail`] cuts it off before the file is appended to again.  Since
+//! the fetch loop moves forward through the chain, the last line also holds
+//! the highest archived block, which [`last_block`] reads without scanning
+//! the whole file.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use arrow_array::RecordBatch;
+
+use crate::parquet::{self, LogRow};
+
+/// Bytes read from the end of the file when looking for the last line.
+const TAIL_CHUNK: u64 = 64 * 1024;
+
+/// Append every row of `batch` to `path` as one JSON object per line.
+///
+/// The rows are serialised first and written with a single append, so a
+/// failure leaves at most one partial line behind.
+///
+/// # Errors
+///
+/// Returns an error if the batch does not match the event schema or the
+/// file cannot be written.
+pub fn append(path: &Path, batch: &RecordBatch) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("creating {}", parent.display()))?;
+    }
+    let mut out = Vec::new();
+    for row in parquet::batch_to_rows(batch)? {
+        serde_json::to_writer(&mut out, &row)?;
+        out.push(b'\n');
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(&out))
+        .with_context(|| format!("appending {}", path.display()))
+}
+
+/// Truncate a partial last line left by an interrupted append.
+///
+/// Returns `true` if the file was truncated.  A missing file is not an
+/// error.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or truncated.
+pub fn repair_tail(path: &Path) -> Result<bool> {
+    let Some((mut file, len)) = open(path)? else {
+        return Ok(false);
+    };
+    let (start, tail) = read_tail(&mut file, len)?;
+    if tail.last().is_none_or(|&b| b == b'\n') {
+        return Ok(false);
+    }
+    let keep = match tail.iter().rposition(|&b| b == b'\n') {
+        Some(i) => start + i as u64 + 1,
+        // No newline in the last chunk: scan the whole file.
+        None => {
+            let mut all = Vec::new();
+            file.seek(SeekFrom::Start(0))?;
+            file.read_to_end(&mut all)?;
+            all.iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |i| i as u64 + 1)
+        }
+    };
+    OpenOptions::new()
+        .write(true)
+        .open(path)
+        .and_then(|f| f.set_len(keep))
+        .with_context(|| format!("truncating {}", path.display()))?;
+    Ok(true)
+}
+
+/// Block number of the last complete line, or `None` for a missing or
+/// empty file.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or its last line is not a
+/// valid row.
+pub fn last_block(path: &Path) -> Result<Option<u64>> {
+    let Some((mut file, len)) = open(path)? else {
+        return Ok(None);
+    };
+    let (_, tail) = read_tail(&mut file, len)?;
+    let Some(line) = tail
+        .split(|&b| b == b'\n')
+        .rev()
+        .find(|l| !l.iter().all(u8::is_ascii_whitespace))
+    else {
+        return Ok(None);
+    };
+    let row: LogRow = serde_json::from_slice(line)
+        .with_context(|| format!("parsing last line of {}", path.display()))?;
+    Ok(Some(row.block_number))
+}
+
+fn open(path: &Path) -> Result<Option<(File, u64)>> {
+    match File::open(path) {
+        Ok(f) => {
+            let len = f.metadata()?.len();
+            Ok(Some((f, len)))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("opening {}", path.display())),
+    }
+}
+
+/// Read the last [`TAIL_CHUNK`] bytes, returning their offset and content.
+fn read_tail(file: &mut File, len: u64) -> Result<(u64, Vec<u8>)> {
+    let start = len.saturating_sub(TAIL_CHUNK);
+    file.seek(SeekFrom::Start(start))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    Ok((start, tail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    fn line(block: u64) -> String {
+        let row = LogRow {
+            block_number: block,
+            tx_hash: "0x01".to_owned(),
+            tx_index: 0,
+            log_index: 0,
+            address: "0x02".to_owned(),
+            topic0: "0x03".to_owned(),
+            topic1: None,
+            topic2: None,
+            topic3: None,
+            data: "0x".to_owned(),
+            removed: false,
+            tx_nonce: None,
+            block_hash: None,
+        };
+        format!("{}\n", serde_json::to_string(&row).unwrap())
+    }
+
+    #[test]
+    fn test_last_block_and_repair_tail() {
+        let dir = TempDir::new("jsonl");
+        let path = dir.join("identity.jsonl");
+        assert_eq!(last_block(&path).unwrap(), None);
+        assert!(!repair_tail(&path).unwrap());
+
+        let complete = format!("{}{}", line(10), line(12));
+        std::fs::write(&path, format!("{complete}{{\"block_number\":1")).unwrap();
+        assert!(last_block(&path).is_err(), "partial line is not a row");
+
+        assert!(repair_tail(&path).unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), complete);
+        assert_eq!(last_block(&path).unwrap(), Some(12));
+        assert!(!repair_tail(&path).unwrap());
+    }
+}
@@ -0,0 +1,153 @@
+//! Kafka output through a Confluent-compatible REST Proxy
+//! (`--output kafka:<proxy>/topics/<topic>`, `--features kafka`).
+//!
+//! Every flush produces its new rows to the topic, one JSON
+//! [`LogRow`](crate::parquet::LogRow) per record keyed by
+//! `<chain_id>/<contract>`, so that a contract's events land on one
+//! partition in order.  Records go out in requests of at most
+//! [`RECORDS_PER_REQUEST`] with the REST Proxy v2 embedded-JSON format.
+//! Talking to the proxy over HTTP keeps a native Kafka client, and the C
+//! library it wraps, out of the build.
+
+use std::fmt;
+
+use alloy::transports::http::reqwest::{self, Url};
+use anyhow::{Context, Result, bail};
+use arrow_array::RecordBatch;
+use serde::{Deserialize, Serialize};
+
+use crate::output;
+use crate::parquet::{self, LogRow};
+
+/// Records sent per produce request.
+pub const RECORDS_PER_REQUEST: usize = 500;
+
+/// Content type of REST Proxy v2 produce requests with JSON values.
+const CONTENT_TYPE: &str = "application/vnd.kafka.json.v2+json";
+
+/// A REST Proxy topic endpoint receiving each flush's new rows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KafkaOutput {
+    /// `<proxy>/topics/<topic>`.
+    pub url: String,
+}
+
+/// Body of a produce request.
+#[derive(Serialize)]
+struct Produce<'a> {
+    records: Vec<Record<'a>>,
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    key: &'a str,
+    value: &'a LogRow,
+}
+
+/// Response of a produce request: one offset per record.
+#[derive(Deserialize)]
+struct Produced {
+    offsets: Vec<Offset>,
+}
+
+#[derive(Deserialize)]
+struct Offset {
+    error: Option<String>,
+}
+
+impl KafkaOutput {
+    /// Parse the part of `kafka:<proxy>/topics/<topic>` after `kafka:`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if it is not an `http(s)` URL ending in
+    /// `/topics/<topic>`.
+    pub fn parse(url: &str) -> Result<Self> {
+        let parsed = Url::parse(url).with_context(|| format!("output `kafka:{url}`"))?;
+        let topic = parsed
+            .path()
+            .rsplit_once("/topics/")
+            .map(|(_, topic)| topic);
+        if !matches!(parsed.scheme(), "http" | "https")
+            || topic.is_none_or(|t| t.is_empty() || t.contains('/'))
+        {
+            bail!("output `kafka:{url}`: expected `kafka:http(s)://<proxy>/topics/<topic>`");
+        }
+        Ok(Self {
+            url: url.to_owned(),
+        })
+    }
+
+    /// Produce the rows of `new` to the topic.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the batch does not match the event schema, a
+    /// request fails or the proxy reports an error for any record.
+    pub fn write(&self, chain_id: u64, contract: &str, new: &RecordBatch) -> Result<()> {
+        let rows = parquet::batch_to_rows(new)?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let key = format!("{chain_id}/{contract}");
+        output::block_on(async {
+            let client = output::http_client()?;
+            for chunk in rows.chunks(RECORDS_PER_REQUEST) {
+                let body = Produce {
+                    records: chunk
+                        .iter()
+                        .map(|row| Record {
+                            key: &key,
+                            value: row,
+                        })
+                        .collect(),
+                };
+                let response = client
+                    .post(&self.url)
+                    .header(reqwest::header::CONTENT_TYPE, CONTENT_TYPE)
+                    .body(serde_json::to_vec(&body)?)
+                    .send()
+                    .await
+                    .and_then(reqwest::Response::error_for_status)
+                    .with_context(|| format!("POST {}", self.url))?
+                    .bytes()
+                    .await
+                    .with_context(|| format!("reading the response of {}", self.url))?;
+                let produced: Produced = serde_json::from_slice(&response)
+                    .with_context(|| format!("parsing the response of {}", self.url))?;
+                if let Some(error) = produced.offsets.into_iter().find_map(|o| o.error) {
+                    bail!("POST {}: {error}", self.url);
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+impl fmt::Display for KafkaOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "kafka:{}", self.url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let out = KafkaOutput::parse("http://localhost:8082/topics/erc8004").unwrap();
+        assert_eq!(
+            out.to_string(),
+            "kafka:http://localhost:8082/topics/erc8004"
+        );
+        for bad in [
+            "localhost:8082/topics/erc8004",
+            "ftp://proxy/topics/erc8004",
+            "http://proxy/erc8004",
+            "http://proxy/topics/",
+        ] {
+            assert!(KafkaOutput::parse(bad).is_err(), "{bad}");
+        }
+    }
+}
@@ -3,11 +3,47 @@
 //! Fetches event logs from Identity and Reputation registries across all
 //! known ERC-8004 deployments and stores them as Parquet files.
 
+#[cfg(feature = "openapi")]
+pub mod api;
+pub mod auth;
+pub mod balancer;
+pub mod census;
 pub mod chains;
+pub mod checksum;
+pub mod concurrency;
 pub mod config;
 pub mod cursor;
+pub mod decode;
+#[cfg(feature = "delta")]
+pub mod delta;
+pub mod dossier;
+pub mod estimate;
+pub mod export;
 pub mod fetcher;
+pub mod gaps;
+pub mod jsonl;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod manifest;
+pub mod metadata;
+pub mod output;
 pub mod parquet;
+pub mod ratelimit;
+pub mod reader;
+#[cfg(feature = "lua")]
+pub mod replay;
+pub mod retry;
+pub mod rpc_metrics;
+#[cfg(feature = "s3")]
+pub mod s3;
+pub mod signatures;
+pub mod snapshot;
+pub mod stats;
+pub mod storage;
+pub mod telemetry;
+#[cfg(test)]
+mod testutil;
+pub mod verify;
 
 use clap as _;
 use tracing_subscriber as _;
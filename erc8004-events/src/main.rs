@@ -4,32 +4,90 @@
 //! erc8004-events sync --data-dir ./data
 //! erc8004-events sync --chain 8453 --rpc https://my-rpc.example.com
 //! erc8004-events sync --include-testnets
+//! erc8004-events discover-first-event --chain 1 --rpc https://eth.example.com
+//! erc8004-events sync-abi --chain 8453 --address 0x4200000000000000000000000000000000000006 --abi events.json --deployment-block 1 --name mytoken
+//! erc8004-events export --out-dir ./dist --content-addressed
+//! erc8004-events stats --json
+//! erc8004-events snapshot --output ./snapshot-20240101 --verify
+//! erc8004-events verify --chain 8453
+//! erc8004-events query --chain 8453 --as-of 2024-01-01
+//! erc8004-events census --chain 8453 --out agents.csv --format csv
+//! erc8004-events agent-dossier --agent-id 42 --out-dir ./dossiers
+//! erc8004-events metadata --chain 8453
+//! erc8004-events enrich-nonces --chain 8453 --rpc https://mainnet.base.org
+//! erc8004-events replay --chain 8453 --contract identity --script handler.lua
 //! erc8004-events list
 //! ```
 
-use std::path::PathBuf;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
 use std::time::Duration;
 
-use alloy as _;
+use alloy::primitives::{Address, U256};
+use alloy::providers::{Provider, ProviderBuilder};
 use anyhow::{Context, Result, bail};
-use arrow_array as _;
+use arrow_array::RecordBatch;
 use arrow_schema as _;
-use clap::{Parser, Subcommand};
-use erc8004 as _;
-use erc8004_events::{chains, config::Config, fetcher};
+use arrow_select as _;
+use clap::{Args, Parser, Subcommand};
+#[cfg(feature = "sign")]
+use ed25519_dalek as _;
+use erc8004::uri::CompositeUriResolver;
+use erc8004_events::census::{self, CensusFormat};
+use erc8004_events::chains::{self, Category, ChainConfig};
+use erc8004_events::checksum;
+use erc8004_events::config::{self, Config};
+use erc8004_events::cursor::{Cursor, CursorCompression};
+#[cfg(feature = "delta")]
+use erc8004_events::delta;
+use erc8004_events::estimate::{self, SyncEstimate};
+use erc8004_events::fetcher::{DefaultErrorClassifier, ErrorClassifier};
+use erc8004_events::manifest;
+use erc8004_events::metadata::ArchiveMetadata;
+use erc8004_events::output::{OutputBackend, SinkFormat};
+use erc8004_events::parquet::{
+    Compression, EthLogsFormat, EventOrder, LogRow, ParquetWriterOptions, PartitionBy, RowDiff,
+    batch_to_rows, diff, enrich_with_nonces, merge_contracts, partition_files, read_rotated,
+    rotated_files, write_eth_logs, write_merged,
+};
+use erc8004_events::storage::{ClickHouseConfig, StorageBackend, StorageConfig};
+use erc8004_events::{
+    decode, dossier, export, fetcher, retry, rpc_metrics, snapshot, stats, telemetry, verify,
+};
+use flate2 as _;
+use futures::{StreamExt, TryStreamExt};
+use jsonwebtoken as _;
+#[cfg(feature = "lua")]
+use mlua as _;
+#[cfg(feature = "otel")]
+use opentelemetry as _;
+#[cfg(feature = "otel")]
+use opentelemetry_otlp as _;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk as _;
 use parquet as _;
+#[cfg(feature = "rayon")]
+use rayon as _;
+use regex as _;
 use serde as _;
-use serde_json as _;
+use sha2 as _;
 use toml as _;
-use tracing as _;
+use tower as _;
+#[cfg(feature = "openapi")]
+use utoipa as _;
+use zstd as _;
 
 /// ERC-8004 raw on-chain event archiver.
 #[derive(Debug, Parser)]
 #[command(name = "erc8004-events", version, about)]
 struct Cli {
-    /// Path to config.toml (RPC pool configuration).
+    /// Path to config.toml (RPC pool configuration); `.gz` files are
+    /// decompressed, and `https://` URLs are fetched with the
+    /// `remote-config` feature.
     #[arg(long, default_value = "config.toml", global = true)]
-    config: PathBuf,
+    config: String,
 
     #[command(subcommand)]
     command: Command,
@@ -38,39 +96,696 @@ struct Cli {
 #[derive(Debug, Subcommand)]
 enum Command {
     /// Fetch new events from on-chain registries and write to Parquet.
-    Sync {
-        /// Output directory for chain data.
+    ///
+    /// Exits with 0 if every chain reached the head, 2 if every chain
+    /// synced but some stopped short of it (`--confirmations`, gaps,
+    /// bounded strategies), 1 if some chains failed and 3 if all failed.
+    Sync(SyncArgs),
+
+    /// Archive the logs of an arbitrary contract, filtered to the events of
+    /// a JSON ABI, to `<chain_id>/<name>.parquet` in the raw schema.
+    SyncAbi {
+        /// Directory to write chain data into.
         #[arg(long, default_value = "data")]
         data_dir: PathBuf,
 
-        /// Sync only a specific chain by its EIP-155 chain ID.
+        /// EIP-155 chain ID of the contract.
         #[arg(long)]
-        chain: Option<u64>,
+        chain: u64,
 
-        /// Override all configured RPCs with a single endpoint.
-        /// Only valid when `--chain` is also specified.
+        /// RPC to fetch logs from; defaults to the chain's configured RPCs.
         #[arg(long)]
         rpc: Option<String>,
 
-        /// Include testnet chains in the sync.
+        /// Address of the contract.
+        #[arg(long)]
+        address: Address,
+
+        /// JSON ABI (or Foundry/Hardhat artifact) whose events to archive.
+        #[arg(long)]
+        abi: PathBuf,
+
+        /// First block to fetch when the archive is empty.
+        #[arg(long)]
+        deployment_block: u64,
+
+        /// Archive file stem; must not be `identity` or `reputation`.
+        #[arg(long)]
+        name: String,
+
+        #[command(flatten)]
+        fetch: FetchArgs,
+    },
+
+    /// Binary-search the first block with a registry event after the
+    /// deployment block, print it, and record it in the chain's
+    /// `cursor.json` so that fresh syncs skip the empty blocks before it.
+    DiscoverFirstEvent {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// EIP-155 chain ID to search.
+        #[arg(long)]
+        chain: u64,
+
+        /// RPC to search with; defaults to the chain's configured RPCs.
+        #[arg(long)]
+        rpc: Option<String>,
+
+        /// Number of blocks after the deployment block to search.
+        #[arg(long, default_value_t = 10_000_000)]
+        search_range: u64,
+    },
+
+    /// Copy archived Parquet files into a distribution directory.
+    Export {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// Destination directory for exported files and `index.json`.
+        #[arg(long)]
+        out_dir: PathBuf,
+
+        /// Name files by the SHA-256 of their content (`<hash>.parquet`).
+        #[arg(long)]
+        content_addressed: bool,
+    },
+
+    /// Copy the cursors and archives (Parquet and JSONL files) into a new
+    /// directory, consistently even while a sync is running.
+    ///
+    /// Files are hard-linked when possible and copied otherwise; a sync of
+    /// the same data directory pauses before its next chain until the
+    /// snapshot is done.
+    Snapshot {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// Snapshot directory to create; must not exist.
+        #[arg(long)]
+        output: PathBuf,
+
+        /// Only snapshot this EIP-155 chain.
+        #[arg(long)]
+        chain: Option<u64>,
+
+        /// Decode every snapshotted Parquet file and fail on corruption.
+        #[arg(long)]
+        verify: bool,
+    },
+
+    /// Report event, transaction and block counts per archived contract.
+    Stats {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// Print the statistics as JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+
+        /// Rescan every file instead of reusing the counts cached in
+        /// `stats-cache.json` for unchanged files.
+        #[arg(long)]
+        no_cache: bool,
+    },
+
+    /// Print each chain's RPC ranking from `rpc_metrics.json`, in the
+    /// order the next sync tries them.
+    Status {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+    },
+
+    /// Compare the Parquet archives of two data directories row by row,
+    /// e.g. before and after a schema migration or an RPC change.
+    ///
+    /// Rows are matched on `(block_number, tx_index, log_index)`, whatever
+    /// their order.  Exits with an error if the archives differ.
+    Diff {
+        /// Data directory of the reference archive.
+        #[arg(long)]
+        before: PathBuf,
+
+        /// Data directory of the archive to check.
+        #[arg(long)]
+        after: PathBuf,
+
+        /// Only compare this chain; defaults to every chain archived in
+        /// either directory.
+        #[arg(long)]
+        chain: Option<u64>,
+
+        /// Output format: text (default) or json.
+        #[arg(long, default_value = "text")]
+        output: DiffOutput,
+
+        /// Differences listed per contract; the counts stay complete.
+        #[arg(long, default_value_t = 20)]
+        max_diff_rows: usize,
+    },
+
+    /// Check archived Parquet files for corruption and misfiled data.
+    ///
+    /// Exits with an error if any file fails verification.
+    Verify {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// Verify only a specific chain by its EIP-155 chain ID.
+        #[arg(long)]
+        chain: Option<u64>,
+    },
+
+    /// Write `SHA256SUMS` for every chain's archive files, or check it.
+    ///
+    /// The file lists `<chain_id>/<file>` paths relative to the data
+    /// directory and can also be checked with `sha256sum -c`.  `--sign` and
+    /// `--pubkey` need the `sign` feature.
+    Checksum {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// Check the existing `SHA256SUMS` instead of writing it; exits
+        /// with an error on any mismatch.
+        #[arg(long)]
+        verify: bool,
+
+        /// Also write a detached Ed25519 signature to `SHA256SUMS.sig`.
+        #[arg(long, requires = "key", conflicts_with = "verify")]
+        sign: bool,
+
+        /// Ed25519 private key (PKCS#8 PEM) for `--sign`.
+        #[arg(long)]
+        key: Option<PathBuf>,
+
+        /// With `--verify`, check `SHA256SUMS.sig` against this Ed25519
+        /// public key (PEM) before the file hashes.
+        #[arg(long, requires = "verify")]
+        pubkey: Option<PathBuf>,
+    },
+
+    /// Recompute the SHA-256 of every file listed in each chain's
+    /// `manifest.json` and report files that changed, disappeared or are
+    /// not listed.
+    ///
+    /// Exits with an error if any chain has a mismatch.
+    VerifyManifest {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+    },
+
+    /// Print a chain's archived events as a JSON array.
+    ///
+    /// Reads the Delta table when `--data-dir` holds one, otherwise the
+    /// chain's Parquet files.
+    Query {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// EIP-155 chain ID to query.
+        #[arg(long)]
+        chain: u64,
+
+        /// Only this contract (`identity` or `reputation`).
+        #[arg(long)]
+        contract: Option<String>,
+
+        /// Read the Delta table as it was at this UTC date or time
+        /// (`2024-01-01` or `2024-01-01T12:00:00Z`; needs the `delta`
+        /// feature).
+        #[arg(long, value_parser = parse_timestamp)]
+        as_of: Option<u64>,
+    },
+
+    /// Write a table of a chain's registered agents joined with their
+    /// registration files.
+    ///
+    /// Off-chain columns are filled from `data:` URIs only, unless `--fetch`
+    /// is set; agents whose file is unavailable keep null off-chain columns.
+    Census {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// EIP-155 chain ID of the census.
+        #[arg(long)]
+        chain: u64,
+
+        /// Output file.
+        #[arg(long)]
+        out: PathBuf,
+
+        /// Output format: parquet (default) or csv.
+        #[arg(long, default_value = "parquet")]
+        format: CensusFormat,
+
+        /// Fetch HTTP(S), IPFS and Arweave registration files through public
+        /// gateways.
+        #[arg(long)]
+        fetch: bool,
+    },
+
+    /// Collect one agent's identity and reputation events and registration
+    /// file from every chain where its ID is registered.
+    ///
+    /// Without `--chain`, each mainnet's Identity Registry (and testnet's,
+    /// with `--include-testnets`) is asked whether the ID exists.
+    AgentDossier {
+        /// Token ID of the agent in the Identity Registry.
+        #[arg(long)]
+        agent_id: U256,
+
+        /// Only look on this EIP-155 chain.
+        #[arg(long, conflicts_with = "include_testnets")]
+        chain: Option<u64>,
+
+        /// Also look on testnets.
         #[arg(long)]
         include_testnets: bool,
 
-        /// Number of chains to sync in parallel.
-        #[arg(long, default_value = "16")]
-        parallel: usize,
+        /// Directory the dossier is written to, under `<agent_id>/`.
+        #[arg(long, default_value = "dossiers")]
+        out_dir: PathBuf,
+
+        #[command(flatten)]
+        fetch: FetchArgs,
+    },
+
+    /// Print the provenance of a chain's archive (`metadata.json`): crate
+    /// version, run ID, time, user and RPC host of the last successful sync.
+    Metadata {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// EIP-155 chain ID.
+        #[arg(long)]
+        chain: u64,
+    },
+
+    /// Write a chain's identity and reputation events to one Parquet file,
+    /// in on-chain order, with a `contract` column naming the registry of
+    /// each event.
+    MergeContracts {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// EIP-155 chain ID to merge.
+        #[arg(long)]
+        chain: u64,
+
+        /// Output file.
+        #[arg(long)]
+        output: PathBuf,
+    },
+
+    /// Write a contract's archived events in the `eth_getLogs` JSON format,
+    /// for tools that consume raw RPC responses.
+    ///
+    /// Rotated files are included, oldest first.  Rows are streamed, so the
+    /// archive is never loaded into memory at once.
+    ExportEthLogs {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// EIP-155 chain ID to export.
+        #[arg(long)]
+        chain: u64,
+
+        /// Contract to export (`identity` or `reputation`).
+        #[arg(long)]
+        contract: String,
+
+        /// Output file.
+        #[arg(long)]
+        output: PathBuf,
 
-        /// Delay in milliseconds between consecutive RPC requests.
-        #[arg(long, default_value = "100")]
-        batch_delay: u64,
+        /// Output format: json (one array, default) or ndjson (one log per
+        /// line).
+        #[arg(long, default_value = "json")]
+        format: EthLogsFormat,
+    },
+
+    /// Write `<contract>.decoded.parquet` next to each raw archive, with the
+    /// event name and arguments decoded from the stored topics and data.
+    ///
+    /// Runs offline; logs of unknown events keep null decoded columns.
+    Decode {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// Decode only a specific chain by its EIP-155 chain ID.
+        #[arg(long)]
+        chain: Option<u64>,
+    },
+
+    /// Fill in the `tx_nonce` column of a chain's archived Parquet files
+    /// from an RPC.
+    ///
+    /// Each transaction with a missing nonce is looked up once; files
+    /// without missing nonces are not rewritten.
+    EnrichNonces {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// EIP-155 chain ID to enrich.
+        #[arg(long)]
+        chain: u64,
+
+        /// RPC to look transactions up from; defaults to the chain's first
+        /// configured RPC.
+        #[arg(long)]
+        rpc: Option<String>,
+
+        /// Parquet compression codec of the rewritten files: zstd
+        /// (default), lz4, snappy or none.  Overrides `compression` in
+        /// config.toml.
+        #[arg(long)]
+        compression: Option<Compression>,
+    },
+
+    /// Run a Lua script's `process(event)` function over a contract's
+    /// archived events, in on-chain order.
+    ///
+    /// Lines emitted with `output.write(key, value)` go to stdout as
+    /// `key<TAB>value`.
+    #[cfg(feature = "lua")]
+    Replay {
+        /// Directory containing synced chain data.
+        #[arg(long, default_value = "data")]
+        data_dir: PathBuf,
+
+        /// EIP-155 chain ID to replay.
+        #[arg(long)]
+        chain: u64,
+
+        /// Contract to replay (`identity` or `reputation`).
+        #[arg(long)]
+        contract: String,
+
+        /// Lua 5.4 script defining `process(event)`.
+        #[arg(long)]
+        script: PathBuf,
+    },
+
+    /// Write the OpenAPI 3.1 document of the planned read API.
+    #[cfg(feature = "openapi")]
+    GenerateOpenapi {
+        /// Destination file.
+        #[arg(long, default_value = "openapi.json")]
+        output: PathBuf,
     },
 
     /// List all known chain configurations.
     List,
 }
 
+#[derive(Debug, Args)]
+#[expect(clippy::struct_excessive_bools, reason = "independent CLI flags")]
+struct SyncArgs {
+    /// Output directory for chain data.
+    #[arg(long, default_value = "data")]
+    data_dir: PathBuf,
+
+    /// Sync only a specific chain by its EIP-155 chain ID.
+    #[arg(long)]
+    chain: Option<u64>,
+
+    /// Sync exactly the chains listed in a JSON file: an array of
+    /// `{"chain_id": 1, "rpcs": ["https://..."]}` objects (`rpcs` optional).
+    #[arg(long, conflicts_with_all = ["chain", "include_testnets"])]
+    targets_file: Option<PathBuf>,
+
+    /// Override all configured RPCs with a single endpoint.
+    /// Only valid when `--chain` is also specified.
+    #[arg(long)]
+    rpc: Option<String>,
+
+    /// Insert synced events into ClickHouse at this HTTP URL, overriding
+    /// the URL of a `clickhouse` `[storage]` section (or the whole section
+    /// if it configures another backend).
+    #[arg(long)]
+    clickhouse_url: Option<String>,
+
+    /// Rows per ClickHouse insert; blocks are never split across inserts.
+    #[arg(long, default_value = "100000", value_parser = clap::value_parser!(u64).range(1..))]
+    clickhouse_batch_rows: u64,
+
+    /// Hex-encoded JWT secret file for authenticating to `--rpc`.
+    #[arg(long, requires = "rpc")]
+    jwt_secret: Option<PathBuf>,
+
+    /// Include testnet chains in the sync.
+    #[arg(long)]
+    include_testnets: bool,
+
+    /// Sync only chains of this category: `l1`, `l2` (rollups) or
+    /// `sidechain`.
+    #[arg(long, conflicts_with_all = ["chain", "targets_file"])]
+    category: Option<Category>,
+
+    /// Number of chains to sync in parallel.
+    #[arg(long, default_value = "16")]
+    parallel: usize,
+
+    /// Treat `--parallel` as a maximum: start with 2 chains, add one per
+    /// chain that syncs without rate limits, and halve on a rate-limit spike.
+    #[arg(long)]
+    adaptive_concurrency: bool,
+
+    #[command(flatten)]
+    fetch: FetchArgs,
+
+    /// Periodically write unflushed events to `<contract>.partial.parquet`
+    /// so that long-running syncs can be inspected.
+    #[arg(long)]
+    write_in_progress: bool,
+
+    /// Record block ranges that exceed RPC limits even at the minimum
+    /// batch size to `gaps.json` and continue, instead of failing.
+    #[arg(long)]
+    skip_unfetchable: bool,
+
+    /// Also write each contract partitioned by time (`month`, `year` or
+    /// `none`) under `<chain_id>/<key>/<contract>.parquet`.
+    #[arg(long, default_value = "none")]
+    partition_by: PartitionBy,
+
+    /// Row order of the archive: `onchain` sorts by (block, log index);
+    /// `fetch` keeps RPC arrival order, skipping the sort.
+    #[arg(long, default_value = "onchain")]
+    order: EventOrder,
+
+    /// File format of the archive: `parquet`, `jsonl` to append one JSON
+    /// object per log to `<contract>.jsonl`, or `delta` to append to a
+    /// Delta Lake table at `--data-dir` (needs the `delta` feature).
+    #[arg(long, default_value = "parquet")]
+    sink_format: SinkFormat,
+
+    /// Fetch exactly this many blocks per `eth_getLogs` request instead of
+    /// adapting the range (overrides `block_step` in the config).
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    block_step: Option<u64>,
+
+    /// Also write each contract's logs in the pending block to
+    /// `<contract>.pending.parquet`, replaced on every run.
+    #[arg(long)]
+    include_pending: bool,
+
+    /// Do not drop fetched logs that are already archived (by block,
+    /// transaction index and log index).
+    #[arg(long)]
+    no_streaming_dedup: bool,
+
+    /// Send this many consecutive `eth_getLogs` windows per JSON-RPC batch
+    /// (one HTTP round-trip) for gateways that handle batches well.
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+    batch_requests: u64,
+
+    /// Spread `eth_getLogs` requests round-robin across all of a chain's
+    /// RPCs instead of falling back to the next one only on failure.  A
+    /// failing RPC is skipped for 30 seconds.
+    #[arg(long)]
+    load_balance: bool,
+
+    /// Try each chain's reliable RPCs with the fastest `eth_getLogs` calls
+    /// recorded in `rpc_metrics.json` first, instead of in configured order.
+    #[arg(long)]
+    adaptive_rpc: bool,
+
+    /// Skip chains whose cursor was written less than this long ago
+    /// (e.g. `30m`, `6h`, `1d`; bare numbers are seconds).
+    #[arg(long, value_parser = parse_duration)]
+    min_cursor_age: Option<Duration>,
+
+    /// Abandon a chain still syncing after this long and count it as
+    /// failed (e.g. `45m`, `2h`; `0` disables).  Flushed data and
+    /// checkpoints are kept; chains with a live strategy are exempt.
+    #[arg(long, default_value = "2h", value_parser = parse_duration)]
+    timeout_per_chain: Duration,
+
+    /// Print a JSON report per synced chain to stdout when done.
+    #[arg(long)]
+    json: bool,
+
+    /// Write a JSON summary of the run (crate version, start and end times,
+    /// and every chain's report) to this file when done, even if every
+    /// chain failed.
+    #[arg(long)]
+    report_file: Option<PathBuf>,
+
+    /// Write an empty `_SUCCESS` file to each chain directory once the chain
+    /// is archived up to the head; it is removed while the chain syncs and
+    /// stays absent after a run that stops short (`--confirmations`, gaps).
+    #[arg(long)]
+    success_marker: bool,
+
+    /// Write the cursor for chains without any events too, marking them as
+    /// checked up to the synced block so re-runs do not rescan them.
+    #[arg(long)]
+    always_write_cursor: bool,
+
+    /// Allow the cursor to move backwards; by default such a move is
+    /// refused with a warning.
+    #[arg(long)]
+    overwrite_cursor: bool,
+
+    /// Write each chain's cursor as `cursor.json` (none),
+    /// `cursor.json.zst` (zstd) or `cursor.json.gz` (gzip); any cursor is
+    /// read back.  By default an existing cursor keeps its encoding and a
+    /// new one is plain.
+    #[arg(long)]
+    cursor_compression: Option<CursorCompression>,
+
+    /// Sync even if an RPC's `eth_chainId` differs from the requested chain
+    /// (e.g. a fork that reports its own chain ID).
+    #[arg(long)]
+    allow_chain_mismatch: bool,
+
+    /// Verify each contract's existing files before syncing it and log any
+    /// problems (see the `verify` command).
+    #[arg(long)]
+    validate_on_read: bool,
+
+    /// Treat this block as the chain's deployment block (e.g. after a
+    /// contract redeploy). Requires `--chain`.
+    #[arg(long, requires = "chain")]
+    deployment_block_override: Option<u64>,
+
+    /// Re-index from this block: archived events before it are kept without
+    /// being fetched again, later ones are dropped and re-fetched. Runs
+    /// once: later syncs with the same value resume from the cursor.
+    /// Requires `--chain`.
+    #[arg(long, requires = "chain")]
+    since_block: Option<u64>,
+
+    /// Start chains without a cursor at block 0 instead of their
+    /// deployment block, to find pre-deployment events.  Asks for
+    /// confirmation unless `--yes` is set.
+    #[arg(long)]
+    start_from_genesis: bool,
+
+    /// Delete the chain's existing Parquet files and cursor before syncing.
+    /// Requires `--chain` and `--yes`.
+    #[arg(long, requires = "chain")]
+    reset: bool,
+
+    /// Before syncing, sample a few block windows per chain, print the
+    /// expected number of events and download and Parquet sizes, and ask
+    /// for confirmation (skipped with `--yes`).
+    #[arg(long)]
+    estimate: bool,
+
+    /// Confirm destructive operations such as `--reset`, and answer the
+    /// `--estimate` and `--start-from-genesis` prompts.
+    #[arg(long)]
+    yes: bool,
+}
+
+/// RPC and archive settings shared by `sync`, `sync-abi` and
+/// `agent-dossier`.
+#[derive(Debug, Args)]
+struct FetchArgs {
+    /// Default `eth_getLogs` requests per second per chain, enforced with
+    /// a token bucket (0 disables).  Overridden by `rpcs_per_second` in a
+    /// chain's config.toml section.
+    #[arg(long, default_value = "10")]
+    rpcs_per_second: f64,
+
+    /// Deprecated: use `--rpcs-per-second`.  Milliseconds between
+    /// `eth_getLogs` requests, converted to `1000 / ms` requests per
+    /// second (0 disables the limit).
+    #[arg(long, hide = true, conflicts_with = "rpcs_per_second")]
+    batch_delay: Option<u64>,
+
+    /// Stay this many blocks behind the chain head. Events in the most
+    /// recent blocks are archived on a later run once confirmed.
+    #[arg(long, default_value = "0")]
+    confirmations: u64,
+
+    /// Secondary outputs that receive every flush, comma-separated
+    /// (`parquet:<dir>`, `ndjson:<dir>`, `s3://<bucket>/<prefix>` with the
+    /// `s3` feature, `kafka:<proxy>/topics/<topic>` with the `kafka` feature).
+    #[arg(long, value_delimiter = ',')]
+    output: Vec<OutputBackend>,
+
+    /// Retry a failed Parquet or cursor write this many times, with a short
+    /// jittered backoff, before failing the chain (0 disables).
+    #[arg(long, default_value_t = retry::DEFAULT_IO_RETRIES)]
+    io_retries: u32,
+
+    /// Rotate each contract's Parquet file to `<contract>.NNN.parquet` before
+    /// it would grow past this many bytes.
+    #[arg(long)]
+    max_file_bytes: Option<u64>,
+
+    /// Read every Parquet file back after writing it and fail the flush,
+    /// before the cursor advances, unless it has the written row count.
+    #[arg(long)]
+    verify_writes: bool,
+
+    /// Parquet compression codec: zstd (default), lz4, snappy or none.
+    /// Overrides `compression` in config.toml.
+    #[arg(long)]
+    compression: Option<Compression>,
+}
+
+impl FetchArgs {
+    /// Sync options with these settings, the config's error classifiers
+    /// and compression default, and defaults for everything else.
+    fn options(self, config: &Config) -> Result<fetcher::SyncOptions> {
+        Ok(fetcher::SyncOptions {
+            rpcs_per_second: Some(rpcs_per_second(self.rpcs_per_second, self.batch_delay)),
+            confirmations: self.confirmations,
+            outputs: self.output,
+            error_classifiers: error_classifiers(config)?,
+            io_retries: self.io_retries,
+            parquet: ParquetWriterOptions {
+                max_file_bytes: self.max_file_bytes,
+                verify_writes: self.verify_writes,
+                compression: self.compression.or(config.compression).unwrap_or_default(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+}
+
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env()
@@ -80,77 +795,1395 @@ async fn main() -> Result<()> {
         .init();
 
     let cli = Cli::parse();
-    let config = Config::load(&cli.config)?;
+    let config = Config::load_from(&cli.config).await?;
+    let telemetry = telemetry::init()?;
+
+    // Shut telemetry down, flushing its last metrics, whether or not the
+    // command failed; the command's own error takes precedence.
+    let res = run(cli.command, &config).await;
+    let shutdown = telemetry.map_or(Ok(()), telemetry::Telemetry::shutdown);
+    let exit = res?;
+    shutdown?;
+    Ok(exit)
+}
 
-    match cli.command {
-        Command::Sync {
+/// Run one subcommand.
+#[expect(clippy::too_many_lines, reason = "one arm per subcommand")]
+async fn run(command: Command, config: &Config) -> Result<ExitCode> {
+    let mut exit = ExitCode::SUCCESS;
+    match command {
+        Command::Sync(args) => cmd_sync(args, config).await.map(|code| exit = code),
+        Command::Export {
+            data_dir,
+            out_dir,
+            content_addressed,
+        } => {
+            let entries = export::export(&data_dir, &out_dir, content_addressed)?;
+            tracing::info!(files = entries.len(), out_dir = %out_dir.display(), "export finished");
+            Ok(())
+        }
+        Command::SyncAbi {
             data_dir,
             chain,
             rpc,
+            address,
+            abi,
+            deployment_block,
+            name,
+            fetch,
+        } => {
+            let abi = std::fs::read_to_string(&abi)
+                .with_context(|| format!("reading {}", abi.display()))?;
+            let contract = fetcher::AbiContract::from_abi(address, &name, deployment_block, &abi)?;
+            let opts = fetch.options(config)?;
+            cmd_sync_abi(&data_dir, chain, rpc, &contract, &opts, config).await
+        }
+        Command::DiscoverFirstEvent {
+            data_dir,
+            chain,
+            rpc,
+            search_range,
+        } => cmd_discover_first_event(&data_dir, chain, rpc, search_range, config).await,
+        Command::Snapshot {
+            data_dir,
+            output,
+            chain,
+            verify,
+        } => {
+            let snapshot = snapshot::create(&data_dir, &output, chain, verify)?;
+            tracing::info!(
+                chains = snapshot.chains.len(),
+                files = snapshot.chains.iter().map(|c| c.files.len()).sum::<usize>(),
+                hard_linked = snapshot.hard_linked,
+                output = %output.display(),
+                "snapshot written"
+            );
+            Ok(())
+        }
+        Command::Stats {
+            data_dir,
+            json,
+            no_cache,
+        } => cmd_stats(&data_dir, json, no_cache),
+        Command::Status { data_dir } => cmd_status(&data_dir),
+        Command::Verify { data_dir, chain } => cmd_verify(&data_dir, chain),
+        Command::Diff {
+            before,
+            after,
+            chain,
+            output,
+            max_diff_rows,
+        } => cmd_diff(&before, &after, chain, output, max_diff_rows),
+        Command::Checksum {
+            data_dir,
+            verify,
+            sign,
+            key,
+            pubkey,
+        } => cmd_checksum(
+            &data_dir,
+            verify,
+            key.as_deref().filter(|_| sign),
+            pubkey.as_deref(),
+        ),
+        Command::VerifyManifest { data_dir } => cmd_verify_manifest(&data_dir),
+        Command::Query {
+            data_dir,
+            chain,
+            contract,
+            as_of,
+        } => cmd_query(&data_dir, chain, contract.as_deref(), as_of),
+        Command::Census {
+            data_dir,
+            chain,
+            out,
+            format,
+            fetch,
+        } => cmd_census(&data_dir, chain, &out, format, fetch).await,
+        Command::AgentDossier {
+            agent_id,
+            chain,
             include_testnets,
-            parallel,
-            batch_delay,
+            out_dir,
+            fetch,
         } => {
-            if rpc.is_some() && chain.is_none() {
-                bail!("--rpc requires --chain to be specified");
+            let opts = fetch.options(config)?;
+            cmd_agent_dossier(agent_id, chain, include_testnets, &out_dir, &opts, config).await
+        }
+        Command::Metadata { data_dir, chain } => cmd_metadata(&data_dir, chain),
+        Command::MergeContracts {
+            data_dir,
+            chain,
+            output,
+        } => cmd_merge_contracts(&data_dir, chain, &output),
+        Command::ExportEthLogs {
+            data_dir,
+            chain,
+            contract,
+            output,
+            format,
+        } => cmd_export_eth_logs(&data_dir, chain, &contract, &output, format),
+        Command::Decode { data_dir, chain } => cmd_decode(&data_dir, chain),
+        Command::EnrichNonces {
+            data_dir,
+            chain,
+            rpc,
+            compression,
+        } => cmd_enrich_nonces(&data_dir, chain, rpc, compression, config).await,
+        #[cfg(feature = "lua")]
+        Command::Replay {
+            data_dir,
+            chain,
+            contract,
+            script,
+        } => cmd_replay(&data_dir, chain, &contract, &script),
+        #[cfg(feature = "openapi")]
+        Command::GenerateOpenapi { output } => {
+            std::fs::write(&output, erc8004_events::api::openapi_json()?)
+                .with_context(|| format!("writing {}", output.display()))?;
+            tracing::info!(output = %output.display(), "OpenAPI document written");
+            Ok(())
+        }
+        Command::List => {
+            cmd_list(config);
+            Ok(())
+        }
+    }?;
+    Ok(exit)
+}
+
+/// Resolve target chains and RPCs, then run the sync; returns the
+/// [`SyncStatus`] exit code.
+async fn cmd_sync(args: SyncArgs, config: &Config) -> Result<ExitCode> {
+    if args.rpc.is_some() && args.chain.is_none() {
+        bail!("--rpc requires --chain to be specified");
+    }
+    if args.since_block.is_some()
+        && (args.sink_format != SinkFormat::Parquet || args.fetch.max_file_bytes.is_some())
+    {
+        bail!(
+            "--since-block cannot be combined with --sink-format jsonl or delta, \
+             or with --max-file-bytes"
+        );
+    }
+    if args.sink_format == SinkFormat::Delta
+        && (args.partition_by != PartitionBy::None
+            || args.fetch.max_file_bytes.is_some()
+            || args.output.iter().any(OutputBackend::needs_history))
+    {
+        bail!(
+            "--sink-format delta never rewrites files and cannot be combined with \
+             --partition-by, --max-file-bytes or parquet:/s3: outputs"
+        );
+    }
+    if args.sink_format == SinkFormat::Jsonl {
+        if args.partition_by != PartitionBy::None
+            || args.fetch.max_file_bytes.is_some()
+            || args.output.iter().any(OutputBackend::needs_history)
+        {
+            bail!(
+                "--sink-format jsonl does not keep the history in memory and cannot be \
+                 combined with --partition-by, --max-file-bytes or parquet:/s3: outputs"
+            );
+        }
+    }
+
+    // Resolve target chains and their RPCs: targets file > config.toml >
+    // built-in default.  Only RPCs from config.toml carry weights.
+    let from_config = |c: &'static ChainConfig| {
+        (
+            c,
+            config.rpcs_for_chain(c),
+            config.rpc_weights_for(c.chain_id()),
+        )
+    };
+    let selected: Vec<(&ChainConfig, Vec<String>, Vec<u32>)> =
+        if let Some(path) = &args.targets_file {
+            config::load_targets(path)?
+                .into_iter()
+                .map(|t| {
+                    let c = resolve_chain(t.chain_id)?;
+                    Ok(if t.rpcs.is_empty() {
+                        from_config(c)
+                    } else {
+                        (c, t.rpcs, Vec::new())
+                    })
+                })
+                .collect::<Result<_>>()
+                .with_context(|| format!("invalid targets file {}", path.display()))?
+        } else if let Some(id) = args.chain {
+            vec![from_config(resolve_chain(id)?)]
+        } else {
+            config
+                .all_chain_rpcs()
+                .into_iter()
+                .filter_map(|(cid, rpcs)| {
+                    let c = chains::by_chain_id(cid)?;
+                    ((args.include_testnets || !c.is_testnet)
+                        && args.category.is_none_or(|k| c.category == k))
+                    .then(|| (c, rpcs, config.rpc_weights_for(cid)))
+                })
+                .collect()
+        };
+
+    // A --rpc override replaces the resolved RPCs of every chain.
+    let mut targets: Vec<_> = selected
+        .into_iter()
+        .map(|(c, rpcs, rpc_weights)| {
+            let cid = c.chain_id();
+            let (rpcs, rpc_weights, jwt_secret) = match &args.rpc {
+                Some(url) => (vec![url.clone()], Vec::new(), args.jwt_secret.clone()),
+                None => (rpcs, rpc_weights, config.jwt_secret_for(cid)),
+            };
+            fetcher::SyncTarget {
+                chain: config.chain_with_addresses(c),
+                rpcs,
+                rpc_weights,
+                jwt_secret,
+                max_block_range: config.max_block_range_for(cid),
+                block_step: args.block_step.or_else(|| config.block_step_for(cid)),
+                log_result_cap: config.log_result_cap_for(cid),
+                rpcs_per_second: config.rpcs_per_second_for(cid),
+                paginated_logs: config.paginated_logs_for(cid),
+                strategies: config.strategies_for(cid),
             }
+        })
+        .collect();
+    for t in &targets {
+        fetcher::validate_strategies(&t.strategies)
+            .with_context(|| format!("invalid strategies for chain {}", t.chain.chain_id()))?;
+        if let Some(rate) = t.rpcs_per_second
+            && !(rate.is_finite() && rate > 0.0)
+        {
+            bail!(
+                "rpcs_per_second for chain {} must be positive, got {rate}",
+                t.chain.chain_id()
+            );
+        }
+    }
+
+    if let Some(block) = args.deployment_block_override {
+        for t in &mut targets {
+            t.chain.deployment_block = block;
+        }
+    }
 
-            // Resolve target chains.
-            let chains: Vec<_> = if let Some(id) = chain {
-                vec![chains::by_chain_id(id).with_context(|| format!("unknown chain ID {id}"))?]
+    if args.reset && !args.yes {
+        bail!("--reset permanently deletes archived data; pass --yes to confirm");
+    }
+
+    // `--reset` deletes every cursor before the sync, so none is fresh.
+    if let Some(min_age) = args.min_cursor_age
+        && !args.reset
+    {
+        let before = targets.len();
+        let mut fresh = Vec::new();
+        for t in &targets {
+            let dir = args.data_dir.join(t.chain.chain_id().to_string());
+            if Cursor::load(&dir)?.is_some_and(|c| c.age() < min_age) {
+                fresh.push(t.chain.chain_id());
+            }
+        }
+        targets.retain(|t| !fresh.contains(&t.chain.chain_id()));
+        tracing::info!(
+            skipped = before - targets.len(),
+            remaining = targets.len(),
+            min_age_secs = min_age.as_secs(),
+            "skipped chains with a fresh cursor"
+        );
+    }
+
+    let fetch = args.fetch.options(config)?;
+    let opts = fetcher::SyncOptions {
+        concurrency: args.parallel,
+        adaptive_concurrency: args.adaptive_concurrency,
+        timeout_per_chain: Some(args.timeout_per_chain).filter(|d| !d.is_zero()),
+        write_in_progress: args.write_in_progress,
+        skip_unfetchable: args.skip_unfetchable,
+        partition_by: args.partition_by,
+        order: args.order,
+        sink_format: args.sink_format,
+        include_pending: args.include_pending,
+        deduplicate_streaming: !args.no_streaming_dedup,
+        batch_requests: usize::try_from(args.batch_requests)?,
+        load_balance: args.load_balance,
+        adaptive_rpc: args.adaptive_rpc,
+        always_write_cursor: args.always_write_cursor,
+        overwrite_cursor: args.overwrite_cursor,
+        cursor_compression: args.cursor_compression,
+        since_block: args.since_block,
+        genesis_start: args.start_from_genesis,
+        success_marker: args.success_marker,
+        allow_chain_mismatch: args.allow_chain_mismatch,
+        validate_on_read: args.validate_on_read,
+        storage: storage_config(args.clickhouse_url.as_deref(), config)
+            .as_ref()
+            .map(StorageBackend::from_config)
+            .transpose()?,
+        storage_batch_rows: usize::try_from(args.clickhouse_batch_rows)?,
+        report_file: args.report_file,
+        ..fetch
+    };
+
+    if args.estimate {
+        // A reset chain is synced from its deployment block.
+        let estimate_opts = fetcher::SyncOptions {
+            since_block: if args.reset {
+                Some(0)
             } else {
-                chains::ALL
-                    .iter()
-                    .filter(|c| include_testnets || !c.is_testnet)
-                    .collect()
-            };
+                opts.since_block
+            },
+            ..opts.clone()
+        };
+        let estimates = estimate_all(&targets, &args.data_dir, &estimate_opts).await?;
+        print_estimates(&estimates);
+        if !args.yes && !confirm("Proceed with the sync?")? {
+            tracing::info!("sync cancelled");
+            return Ok(ExitCode::SUCCESS);
+        }
+    }
 
-            // Build per-chain RPC lists: CLI override > config.toml > built-in default.
-            let targets: Vec<_> = chains
-                .iter()
-                .map(|c| {
-                    let rpcs = rpc.as_ref().map_or_else(
-                        || config.rpcs_for(c.chain_id(), c.default_rpc),
-                        |url| vec![url.clone()],
-                    );
-                    (**c, rpcs)
-                })
-                .collect();
+    if args.start_from_genesis && !args.yes {
+        let fresh = print_genesis_ranges(&targets, &args.data_dir, args.reset)?;
+        if fresh > 0 && !confirm("Scan these chains from block 0?")? {
+            tracing::info!("sync cancelled");
+            return Ok(ExitCode::SUCCESS);
+        }
+    }
 
-            let opts = fetcher::SyncOptions {
-                batch_delay: Duration::from_millis(batch_delay),
-                concurrency: parallel,
-                ..Default::default()
+    if args.reset {
+        for t in &targets {
+            fetcher::reset_chain(&args.data_dir, t.chain.chain_id())?;
+        }
+    }
+
+    let reports = match fetcher::sync_all(targets, &args.data_dir, opts).await {
+        Ok(reports) => reports,
+        Err(e) if e.is::<fetcher::AllChainsFailed>() => {
+            tracing::error!(error = %e, "sync failed");
+            return Ok(SyncStatus::AllFailed.into());
+        }
+        Err(e) => return Err(e),
+    };
+    if args.json {
+        print_reports(&reports)?;
+    }
+    Ok(SyncStatus::of(&reports).into())
+}
+
+/// Outcome of a `sync` run, reported as its exit code so that schedulers
+/// can branch on it.  Errors that stop the run before any chain is synced
+/// exit with 1 as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncStatus {
+    /// Every chain reached the head (exit code 0).
+    AtHead,
+    /// Some chains failed (exit code 1).
+    SomeFailed,
+    /// Every chain synced, but some stopped short of the head (exit code 2).
+    BehindHead,
+    /// Every chain failed (exit code 3).
+    AllFailed,
+}
+
+impl SyncStatus {
+    /// Classify the chain reports of a run that did not fail as a whole.
+    fn of(reports: &[fetcher::ChainSyncReport]) -> Self {
+        if reports.iter().any(|r| r.error.is_some()) {
+            Self::SomeFailed
+        } else if reports.iter().all(|r| r.reached_head) {
+            Self::AtHead
+        } else {
+            Self::BehindHead
+        }
+    }
+}
+
+impl From<SyncStatus> for ExitCode {
+    fn from(status: SyncStatus) -> Self {
+        Self::from(match status {
+            SyncStatus::AtHead => 0,
+            SyncStatus::SomeFailed => 1,
+            SyncStatus::BehindHead => 2,
+            SyncStatus::AllFailed => 3,
+        })
+    }
+}
+
+/// Estimate every target's sync volume, `opts.concurrency` chains at a time.
+async fn estimate_all(
+    targets: &[fetcher::SyncTarget],
+    data_dir: &Path,
+    opts: &fetcher::SyncOptions,
+) -> Result<Vec<SyncEstimate>> {
+    futures::stream::iter(targets)
+        .map(|t| async move {
+            estimate::estimate_chain(t, data_dir, opts, estimate::DEFAULT_SAMPLES)
+                .await
+                .with_context(|| format!("estimating chain {}", t.chain.chain_id()))
+        })
+        .buffered(opts.concurrency.max(1))
+        .try_collect()
+        .await
+}
+
+/// Print sync estimates as a table with a total row.
+#[expect(clippy::print_stdout, reason = "--estimate output goes to stdout")]
+fn print_estimates(estimates: &[SyncEstimate]) {
+    println!(
+        "{:<12} {:>14} {:>12} {:>16} {:>16}",
+        "Chain ID", "Blocks", "Events", "Download (B)", "Parquet (B)",
+    );
+    println!("{}", "-".repeat(74));
+    for e in estimates {
+        println!(
+            "{:<12} {:>14} {:>12} {:>16} {:>16}",
+            e.chain_id,
+            e.blocks(),
+            e.events,
+            e.download_bytes,
+            e.parquet_bytes,
+        );
+    }
+    let total = |f: fn(&SyncEstimate) -> u64| estimates.iter().map(f).fold(0, u64::saturating_add);
+    println!(
+        "{:<12} {:>14} {:>12} {:>16} {:>16}",
+        "Total",
+        total(SyncEstimate::blocks),
+        total(|e| e.events),
+        total(|e| e.download_bytes),
+        total(|e| e.parquet_bytes),
+    );
+}
+
+/// List the extra blocks `--start-from-genesis` scans on each chain that
+/// has no cursor (or all of them with `--reset`), returning how many.
+#[expect(clippy::print_stdout, reason = "--start-from-genesis prompt")]
+fn print_genesis_ranges(
+    targets: &[fetcher::SyncTarget],
+    data_dir: &Path,
+    reset: bool,
+) -> Result<usize> {
+    let mut fresh = 0;
+    for t in targets {
+        let cid = t.chain.chain_id();
+        if !reset && Cursor::load(&data_dir.join(cid.to_string()))?.is_some() {
+            continue;
+        }
+        let deployment = t.chain.deployment_block;
+        println!(
+            "{cid:<12} {:<20} blocks 0..{deployment} before the deployment block, then up to the head",
+            t.chain.name,
+        );
+        fresh += 1;
+    }
+    Ok(fresh)
+}
+
+/// Ask a yes/no question on stderr; anything but `y` or `yes` (including
+/// end of input) is a no.
+#[expect(clippy::print_stderr, reason = "interactive prompt")]
+fn confirm(question: &str) -> Result<bool> {
+    eprint!("{question} [y/N] ");
+    std::io::Write::flush(&mut std::io::stderr())?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
+
+/// Config-driven error patterns first, then the built-in heuristics.
+fn error_classifiers(config: &Config) -> Result<Vec<Arc<dyn ErrorClassifier>>> {
+    let mut classifiers: Vec<Arc<dyn ErrorClassifier>> = Vec::new();
+    if let Some(custom) = config.error_classifier()? {
+        classifiers.push(Arc::new(custom));
+    }
+    classifiers.push(Arc::new(DefaultErrorClassifier));
+    Ok(classifiers)
+}
+
+/// The default `eth_getLogs` rate: `--rpcs-per-second`, or the rate
+/// equivalent to the deprecated `--batch-delay` if that was given.
+fn rpcs_per_second(rate: f64, batch_delay_ms: Option<u64>) -> f64 {
+    let Some(ms) = batch_delay_ms else {
+        return rate;
+    };
+    tracing::warn!(
+        batch_delay_ms = ms,
+        "--batch-delay is deprecated and will be removed; use --rpcs-per-second"
+    );
+    // A zero delay gives an infinite rate, which leaves requests unthrottled.
+    Duration::from_millis(ms).as_secs_f64().recip()
+}
+
+/// Parse a duration such as `90`, `90s`, `15m`, `6h` or `1d`.
+fn parse_duration(s: &str) -> Result<Duration> {
+    let (num, unit) = s
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or((s, ""), |i| s.split_at(i));
+    let n: u64 = num
+        .parse()
+        .with_context(|| format!("invalid duration `{s}`"))?;
+    let secs = match unit {
+        "" | "s" => n,
+        "m" => n.saturating_mul(60),
+        "h" => n.saturating_mul(3_600),
+        "d" => n.saturating_mul(86_400),
+        _ => bail!("invalid duration unit `{unit}` (expected s, m, h or d)"),
+    };
+    Ok(Duration::from_secs(secs))
+}
+
+/// Parse a UTC date (`2024-01-01`) or time (`2024-01-01T12:00:00Z`) into
+/// milliseconds since the Unix epoch.
+fn parse_timestamp(s: &str) -> Result<u64> {
+    let invalid = || format!("invalid time `{s}` (expected YYYY-MM-DD[THH:MM:SSZ])");
+    let (date, time) = s.split_once('T').unwrap_or((s, "00:00:00Z"));
+    let fields = |text: &str, sep: char| -> Result<Vec<u64>> {
+        text.split(sep)
+            .map(|n| n.parse().with_context(invalid))
+            .collect()
+    };
+    let (Ok([y, m, d]), Ok([hh, mm, ss])) = (
+        <[u64; 3]>::try_from(fields(date, '-')?),
+        <[u64; 3]>::try_from(fields(time.strip_suffix('Z').unwrap_or(time), ':')?),
+    ) else {
+        bail!(invalid());
+    };
+    if y < 1970 || !(1..=12).contains(&m) || !(1..=31).contains(&d) || hh > 23 || mm > 59 || ss > 59
+    {
+        bail!(invalid());
+    }
+    // Days from civil, after Howard Hinnant's date algorithms.
+    let y = if m <= 2 { y - 1 } else { y };
+    let (era, yoe) = (y / 400, y % 400);
+    let doy = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+    let days = era * 146_097 + yoe * 365 + yoe / 4 - yoe / 100 + doy - 719_468;
+    Ok(((days * 24 + hh) * 60 + mm) * 60_000 + ss * 1_000)
+}
+
+/// Look up a known chain by ID.
+fn resolve_chain(id: u64) -> Result<&'static ChainConfig> {
+    chains::by_chain_id(id).with_context(|| format!("unknown chain ID {id}"))
+}
+
+/// The `[storage]` configuration, with `--clickhouse-url` applied.
+fn storage_config(clickhouse_url: Option<&str>, config: &Config) -> Option<StorageConfig> {
+    let Some(url) = clickhouse_url else {
+        return config.storage.clone();
+    };
+    Some(StorageConfig::Clickhouse(match &config.storage {
+        Some(StorageConfig::Clickhouse(c)) => ClickHouseConfig {
+            url: url.to_owned(),
+            ..c.clone()
+        },
+        _ => ClickHouseConfig::new(url),
+    }))
+}
+
+/// Print a chain's archived events, optionally as of a past Delta version.
+#[expect(clippy::print_stdout, reason = "query results go to stdout")]
+fn cmd_query(
+    data_dir: &Path,
+    chain: u64,
+    contract: Option<&str>,
+    as_of: Option<u64>,
+) -> Result<()> {
+    let rows = read_events(data_dir, chain, contract, as_of)?;
+    println!("{}", serde_json::to_string_pretty(&rows)?);
+    Ok(())
+}
+
+/// Read a chain's archived events from the Delta table in `data_dir`, or
+/// from its Parquet files if there is no table.
+fn read_events(
+    data_dir: &Path,
+    chain: u64,
+    contract: Option<&str>,
+    as_of: Option<u64>,
+) -> Result<Vec<LogRow>> {
+    #[cfg(feature = "delta")]
+    if delta::is_table(data_dir) {
+        return rows_of(&delta::read(data_dir, chain, contract, as_of)?);
+    }
+    if as_of.is_some() {
+        bail!("--as-of needs a Delta table (sync with --sink-format delta)");
+    }
+    let all = ["identity", "reputation"];
+    let dir = data_dir.join(chain.to_string());
+    let mut batches = Vec::new();
+    for name in contract
+        .as_ref()
+        .map_or(all.as_slice(), std::slice::from_ref)
+    {
+        batches.extend(read_rotated(&dir.join(format!("{name}.parquet")))?);
+    }
+    rows_of(&batches)
+}
+
+/// The rows of `batches`, in order.
+fn rows_of(batches: &[RecordBatch]) -> Result<Vec<LogRow>> {
+    let mut rows = Vec::new();
+    for batch in batches {
+        rows.extend(batch_to_rows(batch)?);
+    }
+    Ok(rows)
+}
+
+/// Print a chain's `metadata.json` as pretty JSON.
+#[expect(clippy::print_stdout, reason = "metadata goes to stdout")]
+fn cmd_metadata(data_dir: &Path, chain: u64) -> Result<()> {
+    let dir = data_dir.join(chain.to_string());
+    let meta = ArchiveMetadata::load(&dir)?
+        .with_context(|| format!("no metadata.json in {}", dir.display()))?;
+    println!("{}", serde_json::to_string_pretty(&meta)?);
+    Ok(())
+}
+
+/// Merge a chain's identity and reputation archives into `output`.
+fn cmd_merge_contracts(data_dir: &Path, chain: u64, output: &Path) -> Result<()> {
+    let dir = data_dir.join(chain.to_string());
+    let mut contracts = Vec::new();
+    for name in ["identity", "reputation"] {
+        #[cfg(feature = "delta")]
+        if delta::is_table(data_dir) {
+            contracts.push((name, delta::read(data_dir, chain, Some(name), None)?));
+            continue;
+        }
+        contracts.push((name, read_rotated(&dir.join(format!("{name}.parquet")))?));
+    }
+    let merged = merge_contracts(&contracts)?;
+    write_merged(output, &merged)?;
+    tracing::info!(
+        chain_id = chain,
+        rows = merged.num_rows(),
+        output = %output.display(),
+        "contracts merged"
+    );
+    Ok(())
+}
+
+/// Export a contract's archived logs as `eth_getLogs` JSON to `output`.
+///
+/// The logs are written to `<output>.tmp` and renamed over `output` once
+/// complete, so a failed export leaves no truncated file behind.
+fn cmd_export_eth_logs(
+    data_dir: &Path,
+    chain: u64,
+    contract: &str,
+    output: &Path,
+    format: EthLogsFormat,
+) -> Result<()> {
+    if !export::CONTRACTS.contains(&contract) {
+        bail!(
+            "unknown contract {contract:?} (expected one of {})",
+            export::CONTRACTS.join(", ")
+        );
+    }
+    let base = data_dir
+        .join(chain.to_string())
+        .join(format!("{contract}.parquet"));
+    let mut paths = rotated_files(&base)?;
+    if base.exists() {
+        paths.push(base.clone());
+    }
+    if paths.is_empty() {
+        bail!("no archive at {}", base.display());
+    }
+    let mut tmp = output.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let logs = retry::io(output, retry::DEFAULT_IO_RETRIES, || {
+        let file =
+            std::fs::File::create(&tmp).with_context(|| format!("creating {}", tmp.display()))?;
+        let mut writer = std::io::BufWriter::new(file);
+        let logs = write_eth_logs(&paths, format, &mut writer)
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        writer
+            .into_inner()
+            .map_err(std::io::IntoInnerError::into_error)
+            .and_then(|file| file.sync_all())
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        std::fs::rename(&tmp, output)
+            .with_context(|| format!("renaming {} → {}", tmp.display(), output.display()))?;
+        Ok(logs)
+    })
+    .inspect_err(|_| {
+        std::fs::remove_file(&tmp).ok();
+    })?;
+    tracing::info!(chain_id = chain, contract, logs, output = %output.display(), "eth logs exported");
+    Ok(())
+}
+
+/// Write a decoded copy next to each registry archive of every archived
+/// chain, or only of `chain`.
+fn cmd_decode(data_dir: &Path, chain: Option<u64>) -> Result<()> {
+    let chains: Vec<_> = fetcher::discover_archived_chains(data_dir)?
+        .into_iter()
+        .filter(|(c, _)| chain.is_none_or(|id| c.chain_id() == id))
+        .collect();
+    if chains.is_empty() {
+        bail!("no archived chains to decode in {}", data_dir.display());
+    }
+    for (c, dir) in chains {
+        for contract in export::CONTRACTS {
+            let base = dir.join(format!("{contract}.parquet"));
+            if let Some(rows) = decode::decode_contract(&base)? {
+                tracing::info!(
+                    chain_id = c.chain_id(),
+                    contract,
+                    rows,
+                    output = %decode::decoded_path(&base).display(),
+                    "decoded"
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A single-pass sync target for `chain`, with `rpc` replacing the
+/// configured RPCs when given.
+fn chain_target(c: &ChainConfig, rpc: Option<String>, config: &Config) -> fetcher::SyncTarget {
+    let cid = c.chain_id();
+    let (rpcs, rpc_weights) = match rpc {
+        Some(url) => (vec![url], Vec::new()),
+        None => (config.rpcs_for_chain(c), config.rpc_weights_for(cid)),
+    };
+    fetcher::SyncTarget {
+        chain: config.chain_with_addresses(c),
+        rpcs,
+        rpc_weights,
+        jwt_secret: config.jwt_secret_for(cid),
+        max_block_range: config.max_block_range_for(cid),
+        block_step: config.block_step_for(cid),
+        log_result_cap: config.log_result_cap_for(cid),
+        rpcs_per_second: config.rpcs_per_second_for(cid),
+        paginated_logs: config.paginated_logs_for(cid),
+        strategies: Vec::new(),
+    }
+}
+
+/// Find a chain's first registry event, print it with the matching
+/// `chains.rs` edit, and record it in the cursor.
+#[expect(clippy::print_stdout, reason = "CLI output goes to stdout")]
+async fn cmd_discover_first_event(
+    data_dir: &Path,
+    chain: u64,
+    rpc: Option<String>,
+    search_range: u64,
+    config: &Config,
+) -> Result<()> {
+    let c = resolve_chain(chain)?;
+    let target = chain_target(c, rpc, config);
+    let opts = fetcher::SyncOptions {
+        error_classifiers: error_classifiers(config)?,
+        ..Default::default()
+    };
+    let from = c.deployment_block;
+    let to = from.saturating_add(search_range);
+    let mut last_err = None;
+    let mut found = None;
+    for url in &target.rpcs {
+        match fetcher::discover_first_event(&target, url, from, to, &opts).await {
+            Ok(block) => {
+                found = Some(block);
+                break;
+            }
+            Err(e) => {
+                tracing::warn!(chain_id = chain, rpc = %url, error = %e, "discovery failed");
+                last_err = Some(e);
+            }
+        }
+    }
+    let block = match (found, last_err) {
+        (Some(Some(block)), _) => block,
+        (Some(None), _) => {
+            println!(
+                "{} ({chain}): no registry events in blocks {from}..={to}",
+                c.name
+            );
+            return Ok(());
+        }
+        (None, Some(e)) => return Err(e),
+        (None, None) => bail!("no RPCs configured for chain {chain}"),
+    };
+
+    println!(
+        "{} ({chain}): first event at block {block}, {} blocks after the deployment block",
+        c.name,
+        block - from
+    );
+    println!(
+        "Suggested edit for {} in erc8004-events/src/chains.rs:\n    first_event_block: Some({}),",
+        c.name,
+        underscored(block)
+    );
+    Cursor::save_first_event_block(&data_dir.join(chain.to_string()), block)?;
+    tracing::info!(
+        chain_id = chain,
+        block,
+        "first event block recorded in cursor.json"
+    );
+    Ok(())
+}
+
+/// `n` with `_` between groups of three digits, as in a Rust literal.
+fn underscored(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() * 4 / 3);
+    for (i, d) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push('_');
+        }
+        out.push(d);
+    }
+    out
+}
+
+/// Archive an ABI-defined contract's events.
+async fn cmd_sync_abi(
+    data_dir: &Path,
+    chain: u64,
+    rpc: Option<String>,
+    contract: &fetcher::AbiContract,
+    opts: &fetcher::SyncOptions,
+    config: &Config,
+) -> Result<()> {
+    let target = chain_target(resolve_chain(chain)?, rpc, config);
+    let events = fetcher::sync_abi_contract(&target, data_dir, contract, opts).await?;
+    tracing::info!(
+        chain_id = chain,
+        contract = contract.name.as_str(),
+        address = %contract.address,
+        events = contract.events.len(),
+        new_events = events,
+        "ABI contract synced"
+    );
+    Ok(())
+}
+
+/// Fill in the transaction nonces of every Parquet file of a chain,
+/// including rotated and partition files.
+async fn cmd_enrich_nonces(
+    data_dir: &Path,
+    chain: u64,
+    rpc: Option<String>,
+    compression: Option<Compression>,
+    config: &Config,
+) -> Result<()> {
+    #[cfg(feature = "delta")]
+    if delta::is_table(data_dir) {
+        bail!("enrich-nonces rewrites Parquet files and cannot update a Delta table");
+    }
+    let url = match rpc {
+        Some(url) => url,
+        None => config
+            .rpcs_for_chain(resolve_chain(chain)?)
+            .into_iter()
+            .next()
+            .with_context(|| format!("no RPCs configured for chain {chain}"))?,
+    };
+    let provider = ProviderBuilder::new().connect_http(
+        url.parse()
+            .with_context(|| format!("invalid RPC URL: {url}"))?,
+    );
+    let actual = provider
+        .get_chain_id()
+        .await
+        .context("eth_chainId failed")?;
+    if actual != chain {
+        bail!("RPC reports chain ID {actual} but chain {chain} was requested");
+    }
+
+    let opts = ParquetWriterOptions {
+        compression: compression.or(config.compression).unwrap_or_default(),
+        ..Default::default()
+    };
+    snapshot::wait_while_locked(data_dir).await;
+    let _writes = snapshot::WriteLock::shared(data_dir).await?;
+    let dir = data_dir.join(chain.to_string());
+    for name in ["identity", "reputation"] {
+        let base = dir.join(format!("{name}.parquet"));
+        let files = rotated_files(&base)?
+            .into_iter()
+            .chain([base])
+            .chain(partition_files(&dir, name)?);
+        for path in files {
+            enrich_with_nonces(&path, &provider, opts).await?;
+        }
+    }
+    // The rewritten files no longer match the hashes in manifest.json.
+    if dir.exists() {
+        manifest::Manifest::refresh(&dir, resolve_chain(chain)?, opts.io_retries)?;
+    }
+    tracing::info!(chain_id = chain, "nonces enriched");
+    Ok(())
+}
+
+/// Run a Lua script over one contract's archived events.
+#[cfg(feature = "lua")]
+fn cmd_replay(data_dir: &Path, chain: u64, contract: &str, script: &Path) -> Result<()> {
+    if !matches!(contract, "identity" | "reputation") {
+        bail!("unknown contract {contract:?} (expected identity or reputation)");
+    }
+    let source =
+        std::fs::read_to_string(script).with_context(|| format!("reading {}", script.display()))?;
+    let mut rows = read_events(data_dir, chain, Some(contract), None)?;
+    rows.sort_by_key(|r| (r.block_number, r.tx_index, r.log_index));
+    let processed = erc8004_events::replay::replay(
+        &source,
+        &script.display().to_string(),
+        chain,
+        contract,
+        &rows,
+        &mut std::io::stdout().lock(),
+    )?;
+    tracing::info!(chain_id = chain, contract, processed, "replay finished");
+    Ok(())
+}
+
+/// Build the agent census of one chain and write it to `out`.
+async fn cmd_census(
+    data_dir: &Path,
+    chain: u64,
+    out: &Path,
+    format: CensusFormat,
+    fetch: bool,
+) -> Result<()> {
+    let batches = census::identity_batches(data_dir, chain)?;
+    let mut agents = census::onchain_agents(&batches)?;
+    let resolver = if fetch {
+        CompositeUriResolver::public_gateways()
+    } else {
+        CompositeUriResolver::empty()
+    };
+    let resolved = census::resolve(&mut agents, &resolver).await;
+    census::write(out, &agents, format, ParquetWriterOptions::default())?;
+    tracing::info!(
+        chain_id = chain,
+        agents = agents.len(),
+        resolved,
+        out = %out.display(),
+        "census written"
+    );
+    Ok(())
+}
+
+/// Build an agent's dossier and log where it was found.
+async fn cmd_agent_dossier(
+    agent_id: U256,
+    chain: Option<u64>,
+    include_testnets: bool,
+    out_dir: &Path,
+    opts: &fetcher::SyncOptions,
+    config: &Config,
+) -> Result<()> {
+    let targets: Vec<_> = match chain {
+        Some(id) => vec![chain_target(resolve_chain(id)?, None, config)],
+        None => chains::ALL
+            .iter()
+            .filter(|c| include_testnets || !c.is_testnet)
+            .map(|c| chain_target(c, None, config))
+            .collect(),
+    };
+    let resolver = CompositeUriResolver::public_gateways();
+    let dossier = dossier::build(&targets, agent_id, out_dir, opts, &resolver).await?;
+    if dossier.chains.is_empty() {
+        tracing::warn!(%agent_id, "agent not registered on any queried chain");
+    }
+    tracing::info!(
+        %agent_id,
+        chains = dossier.chains.len(),
+        unreachable = dossier.unreachable.len(),
+        out_dir = %out_dir.join(agent_id.to_string()).display(),
+        "dossier written"
+    );
+    Ok(())
+}
+
+/// Print sync reports as pretty JSON.
+#[expect(clippy::print_stdout, reason = "--json output goes to stdout")]
+fn print_reports(reports: &[fetcher::ChainSyncReport]) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(reports)?);
+    Ok(())
+}
+
+/// Print per-contract archive statistics as a table or JSON.
+#[expect(clippy::print_stdout, reason = "CLI stats command outputs to stdout")]
+fn cmd_stats(data_dir: &Path, json: bool, no_cache: bool) -> Result<()> {
+    let stats = stats::archive_stats(data_dir, !no_cache)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+    println!(
+        "{:<12} {:<20} {:<12} {:>10} {:>14} {:>10}",
+        "Chain ID", "Name", "Contract", "Events", "Transactions", "Blocks",
+    );
+    println!("{}", "-".repeat(83));
+    for s in &stats {
+        println!(
+            "{:<12} {:<20} {:<12} {:>10} {:>14} {:>10}",
+            s.chain_id, s.chain, s.contract, s.events, s.transactions, s.blocks,
+        );
+    }
+    Ok(())
+}
+
+/// Print each chain's RPCs in the order adaptive RPC ordering tries them.
+#[expect(clippy::print_stdout, reason = "CLI status command outputs to stdout")]
+fn cmd_status(data_dir: &Path) -> Result<()> {
+    let metrics = rpc_metrics::load(data_dir)?;
+    if metrics.is_empty() {
+        println!("No RPC metrics recorded in {}", data_dir.display());
+        return Ok(());
+    }
+    for (chain_id, rpcs) in &metrics {
+        let name = chains::by_chain_id(*chain_id).map_or("unknown", |c| c.name);
+        println!("{chain_id} ({name})");
+        for (i, m) in rpc_metrics::ranking(rpcs).into_iter().enumerate() {
+            let note = if m.is_reliable() {
+                ""
+            } else {
+                "  (unreliable)"
             };
+            println!(
+                "  {}. {:<40} {:>8} ms avg  {:>6} ok  {:>6} errors{note}",
+                i + 1,
+                m.url,
+                m.avg_latency_ms,
+                m.success_count,
+                m.error_count
+            );
+        }
+    }
+    Ok(())
+}
 
-            fetcher::sync_all(targets, &data_dir, opts).await
+/// Print a pass/fail line per archived file plus recommended actions, and
+/// fail if any file did not pass.
+#[expect(clippy::print_stdout, reason = "CLI verify command outputs to stdout")]
+fn cmd_verify(data_dir: &Path, chain: Option<u64>) -> Result<()> {
+    let results = verify::verify_archive(data_dir, chain)?;
+    let mut actions = BTreeSet::new();
+    for r in &results {
+        let path = r.path.strip_prefix(data_dir).unwrap_or(&r.path);
+        let status = if r.passed() { "PASS" } else { "FAIL" };
+        println!("{status}  {:<40} {:>10} rows", path.display(), r.rows);
+        for problem in &r.problems {
+            println!("      - {problem}");
+            actions.insert(problem.recommendation());
         }
-        Command::List => {
-            cmd_list(&config);
-            Ok(())
+    }
+
+    let failed = results.iter().filter(|r| !r.passed()).count();
+    let corrupted: u64 = results.iter().map(|r| r.corrupted_rows).sum();
+    println!(
+        "\n{} file(s) verified, {failed} failed, {corrupted} corrupted row(s)",
+        results.len()
+    );
+    if !actions.is_empty() {
+        println!("\nRecommended actions:");
+        for action in &actions {
+            println!("  - {action}");
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} file(s) failed verification");
+    }
+    Ok(())
+}
+
+/// Output format of `diff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffOutput {
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for DiffOutput {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => bail!("unknown output `{other}` (expected text or json)"),
+        }
+    }
+}
+
+/// Compare the Parquet archives of two data directories.
+#[expect(clippy::print_stdout, reason = "CLI diff outputs to stdout")]
+fn cmd_diff(
+    before: &Path,
+    after: &Path,
+    chain: Option<u64>,
+    output: DiffOutput,
+    max_rows: usize,
+) -> Result<()> {
+    #[cfg(feature = "delta")]
+    if delta::is_table(before) || delta::is_table(after) {
+        bail!("diff compares Parquet archives and cannot read Delta tables");
+    }
+    let chains: BTreeSet<u64> = match chain {
+        Some(id) => BTreeSet::from([id]),
+        None => fetcher::discover_archived_chains(before)?
+            .into_iter()
+            .chain(fetcher::discover_archived_chains(after)?)
+            .map(|(c, _)| c.chain_id())
+            .collect(),
+    };
+
+    let mut diffs = Vec::new();
+    for &chain_id in &chains {
+        for contract in ["identity", "reputation"] {
+            let file = Path::new(&chain_id.to_string()).join(format!("{contract}.parquet"));
+            let mut report = diff(
+                &read_rotated(&before.join(&file))?,
+                &read_rotated(&after.join(&file))?,
+            )
+            .with_context(|| format!("comparing {}", file.display()))?;
+            // Only the listing is capped; `is_empty` uses the full counts.
+            report.differences.truncate(max_rows);
+            diffs.push((chain_id, contract, report));
+        }
+    }
+
+    if output == DiffOutput::Json {
+        let json: Vec<_> = diffs
+            .iter()
+            .map(|(chain_id, contract, report)| {
+                serde_json::json!({ "chain_id": chain_id, "contract": contract, "report": report })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&json)?);
+    } else {
+        for (chain_id, contract, r) in &diffs {
+            let status = if r.is_empty() { "SAME" } else { "DIFF" };
+            println!(
+                "{status}  {chain_id:<10} {contract:<10} rows {} → {}  only before {}  only after {}  changed {}",
+                r.rows_before, r.rows_after, r.only_before, r.only_after, r.changed
+            );
+            for row_diff in &r.differences {
+                match row_diff {
+                    RowDiff::OnlyBefore { row } => println!(
+                        "      - block {} tx {} log {} only before",
+                        row.block_number, row.tx_index, row.log_index
+                    ),
+                    RowDiff::OnlyAfter { row } => println!(
+                        "      + block {} tx {} log {} only after",
+                        row.block_number, row.tx_index, row.log_index
+                    ),
+                    RowDiff::Changed { fields, after, .. } => println!(
+                        "      ~ block {} tx {} log {} changed: {}",
+                        after.block_number,
+                        after.tx_index,
+                        after.log_index,
+                        fields.join(", ")
+                    ),
+                }
+            }
+            let listed = r.only_before + r.only_after + r.changed;
+            if listed > r.differences.len() {
+                println!(
+                    "      … {} more (raise --max-diff-rows)",
+                    listed - r.differences.len()
+                );
+            }
+        }
+    }
+
+    let differing = diffs.iter().filter(|(_, _, r)| !r.is_empty()).count();
+    if differing > 0 {
+        bail!("{differing} contract archive(s) differ");
+    }
+    Ok(())
+}
+
+/// Write or check `SHA256SUMS`, signing it or checking its signature.
+#[expect(
+    clippy::print_stdout,
+    reason = "CLI checksum --verify outputs to stdout"
+)]
+fn cmd_checksum(
+    data_dir: &Path,
+    verify: bool,
+    key: Option<&Path>,
+    pubkey: Option<&Path>,
+) -> Result<()> {
+    if !verify {
+        // Fail before the sums file is replaced, not after.
+        if let Some(key) = key.filter(|_| !cfg!(feature = "sign")) {
+            bail!("signing with {} requires the `sign` feature", key.display());
         }
+        let files = checksum::write(data_dir)?;
+        if let Some(key) = key {
+            checksum::sign(data_dir, key)?;
+        }
+        tracing::info!(
+            files,
+            signed = key.is_some(),
+            "{} written",
+            checksum::SUMS_FILE
+        );
+        return Ok(());
+    }
+    if let Some(pubkey) = pubkey {
+        checksum::verify_signature(data_dir, pubkey)?;
+        println!("PASS  {} signature", checksum::SIG_FILE);
+    }
+    let mismatches = checksum::verify(data_dir)?;
+    for mismatch in &mismatches {
+        println!("FAIL  {mismatch}");
     }
+    if !mismatches.is_empty() {
+        bail!(
+            "{} file(s) do not match {}",
+            mismatches.len(),
+            checksum::SUMS_FILE
+        );
+    }
+    println!("PASS  {}", checksum::SUMS_FILE);
+    Ok(())
+}
+
+/// Check every chain's files against its `manifest.json`.
+#[expect(clippy::print_stdout, reason = "CLI verify-manifest outputs to stdout")]
+fn cmd_verify_manifest(data_dir: &Path) -> Result<()> {
+    let mut failed = 0;
+    let mut chains_checked = 0;
+    for (chain, dir) in fetcher::discover_archived_chains(data_dir)? {
+        let Some(mismatches) = manifest::verify(&dir)? else {
+            println!("SKIP  {:<12} no {}", chain.chain_id(), manifest::FILE_NAME);
+            continue;
+        };
+        chains_checked += 1;
+        let status = if mismatches.is_empty() {
+            "PASS"
+        } else {
+            "FAIL"
+        };
+        println!("{status}  {:<12} {}", chain.chain_id(), chain.name);
+        for mismatch in &mismatches {
+            println!("      - {mismatch}");
+        }
+        if !mismatches.is_empty() {
+            failed += 1;
+        }
+    }
+    println!("\n{chains_checked} manifest(s) verified, {failed} failed");
+    if failed > 0 {
+        bail!("{failed} chain(s) do not match their manifest");
+    }
+    Ok(())
 }
 
 /// Print all known chain configurations.
 #[expect(clippy::print_stdout, reason = "CLI list command outputs to stdout")]
 fn cmd_list(config: &Config) {
     println!(
-        "{:<12} {:<20} {:<8} {:<15} {:<6} RPCs",
-        "Chain ID", "Name", "Type", "Deploy Block", "Pool",
+        "{:<12} {:<20} {:<8} {:<8} {:<15} {:<6} RPCs",
+        "Chain ID", "Name", "Type", "Token", "Deploy Block", "Pool",
     );
-    println!("{}", "-".repeat(100));
+    println!("{}", "-".repeat(109));
     for c in chains::ALL {
         let kind = if c.is_testnet { "test" } else { "main" };
-        let rpcs = config.rpcs_for(c.chain_id(), c.default_rpc);
+        let rpcs = config.rpcs_for_chain(c);
         println!(
-            "{:<12} {:<20} {:<8} {:<15} {:<6} {}",
+            "{:<12} {:<20} {:<8} {:<8} {:<15} {:<6} {}",
             c.chain_id(),
             c.name,
             kind,
+            c.native_token_symbol,
             c.deployment_block,
             rpcs.len(),
             rpcs.first().map_or("", String::as_str),
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("15m").unwrap(), Duration::from_secs(900));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7_200));
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(86_400));
+        assert_eq!(parse_duration("0").unwrap(), Duration::ZERO);
+        assert_eq!(
+            parse_duration(&format!("{}d", u64::MAX)).unwrap(),
+            Duration::from_secs(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_parse_duration_rejects_invalid_input() {
+        for input in ["", "h", "-5m", "1.5h", "10w", "5 m", "1h30m"] {
+            assert!(parse_duration(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_batch_delay_maps_to_rate() {
+        let rate = |delay| rpcs_per_second(10.0, delay).to_bits();
+        assert_eq!(rate(None), 10.0_f64.to_bits());
+        assert_eq!(rate(Some(100)), 10.0_f64.to_bits());
+        assert_eq!(rate(Some(250)), 4.0_f64.to_bits());
+        assert!(rpcs_per_second(10.0, Some(0)).is_infinite());
+    }
+
+    #[test]
+    fn test_sync_status_exit_codes() {
+        let report = |reached_head, error: Option<&str>| fetcher::ChainSyncReport {
+            chain_id: 8453,
+            native_token_symbol: "ETH",
+            native_token_decimals: 18,
+            identity_events: 0,
+            reputation_events: 0,
+            identity_block_range: None,
+            reputation_block_range: None,
+            identity: fetcher::ContractOutcome::Incomplete,
+            reputation: fetcher::ContractOutcome::Incomplete,
+            rpc_used: String::new(),
+            duration: Duration::ZERO,
+            request_latency: None,
+            rpc_errors: 0,
+            removed_events: 0,
+            reached_head,
+            error: error.map(str::to_owned),
+        };
+        let (at_head, behind, failed) = (
+            report(true, None),
+            report(false, None),
+            report(false, Some("boom")),
+        );
+
+        assert_eq!(SyncStatus::of(&[]), SyncStatus::AtHead);
+        assert_eq!(SyncStatus::of(&[at_head.clone()]), SyncStatus::AtHead);
+        assert_eq!(
+            SyncStatus::of(&[at_head.clone(), behind.clone()]),
+            SyncStatus::BehindHead
+        );
+        assert_eq!(
+            SyncStatus::of(&[at_head, behind, failed]),
+            SyncStatus::SomeFailed
+        );
+
+        for (status, code) in [
+            (SyncStatus::AtHead, 0),
+            (SyncStatus::SomeFailed, 1),
+            (SyncStatus::BehindHead, 2),
+            (SyncStatus::AllFailed, 3),
+        ] {
+            assert_eq!(ExitCode::from(status), ExitCode::from(code));
+        }
+    }
+}
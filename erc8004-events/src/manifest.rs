@@ -0,0 +1,441 @@
+//! Per-chain file manifest.
+//!
+//! After each sync, `<chain_id>/manifest.json` lists the chain's Parquet
+//! archive files (rotated and live, including `sync-abi` contracts, not the
+//! partitioned copies) with their size, row count, block range and SHA-256,
+//! together with the cursor.
+//! External tools can discover and check an archive from it without
+//! listing directories or reading Parquet, and uploads can skip files whose
+//! hash did not change.  Row counts and block ranges come from the Parquet
+//! footers.  A file whose size and nanosecond modification time match the
+//! previous manifest keeps its recorded hash, so a sync only hashes the
+//! files it rewrote; [`verify`] recomputes every hash.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::chains::ChainConfig;
+use crate::cursor::Cursor;
+use crate::export::CONTRACTS;
+use crate::parquet::{self, rotated_files};
+use crate::retry;
+
+/// File name of the manifest inside a chain directory.
+pub const FILE_NAME: &str = "manifest.json";
+
+/// Version of the manifest layout, bumped on incompatible changes.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Contents of `manifest.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Layout version ([`SCHEMA_VERSION`] when written).
+    pub schema_version: u32,
+    /// EIP-155 chain ID.
+    pub chain_id: u64,
+    /// Human-readable chain name.
+    pub chain_name: String,
+    /// Archive files, each contract's rotated files before its live file.
+    pub files: Vec<ManifestFile>,
+    /// The chain's cursor when the manifest was written, if any.
+    pub cursor: Option<ManifestCursor>,
+}
+
+/// One archive file of a [`Manifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// File name relative to the chain directory.
+    pub name: String,
+    /// File size in bytes.
+    pub size_bytes: u64,
+    /// Number of rows.
+    pub rows: u64,
+    /// Lowest `block_number`, if the file has rows and footer statistics.
+    pub min_block: Option<u64>,
+    /// Highest `block_number`, if the file has rows and footer statistics.
+    pub max_block: Option<u64>,
+    /// Hex-encoded SHA-256 of the file content.
+    pub sha256: String,
+    /// Unix timestamp (seconds) at which the file was last written.
+    pub created_at: u64,
+    /// Modification time in nanoseconds since the Unix epoch, compared
+    /// with the file's to decide whether its hash can be reused: a file
+    /// rewritten within the same second at the same size still differs
+    /// here.  0 in manifests written before this field existed, which makes
+    /// their files rehash once.
+    #[serde(default)]
+    pub modified_ns: u64,
+}
+
+/// Sync progress recorded in a [`Manifest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestCursor {
+    /// Last fully synced block.
+    pub last_block: u64,
+    /// Unix timestamp (seconds) of the sync.
+    pub synced_at: u64,
+}
+
+impl Manifest {
+    /// Write `<dir>/manifest.json` for `chain` atomically, retrying a
+    /// failed write up to `retries` times (see [`retry`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn write(
+        dir: &Path,
+        chain: &ChainConfig,
+        files: Vec<ManifestFile>,
+        cursor: Option<&Cursor>,
+        retries: u32,
+    ) -> Result<()> {
+        let manifest = Self {
+            schema_version: SCHEMA_VERSION,
+            chain_id: chain.chain_id(),
+            chain_name: chain.name.to_owned(),
+            files,
+            cursor: cursor.map(|c| ManifestCursor {
+                last_block: c.last_block,
+                synced_at: c.synced_at,
+            }),
+        };
+        std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        let path = dir.join(FILE_NAME);
+        let tmp = dir.join(format!("{FILE_NAME}.tmp"));
+        let json = serde_json::to_string_pretty(&manifest)?;
+        retry::io(&path, retries, || {
+            std::fs::write(&tmp, json.as_bytes())
+                .with_context(|| format!("writing {}", tmp.display()))?;
+            std::fs::rename(&tmp, &path)
+                .with_context(|| format!("renaming {} → {}", tmp.display(), path.display()))
+        })
+    }
+
+    /// Read `<dir>/manifest.json`, or `None` if there is none.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = std::fs::read_to_string(&path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        serde_json::from_str(&data)
+            .map(Some)
+            .with_context(|| format!("parsing {}", path.display()))
+    }
+
+    /// Rewrite the manifest of `chain` in `dir` from the files on disk and
+    /// the current cursor, reusing hashes from the previous manifest; see
+    /// [`Manifest::write`] for `retries`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be read or the manifest cannot be
+    /// written.
+    pub fn refresh(dir: &Path, chain: &ChainConfig, retries: u32) -> Result<()> {
+        // A corrupt previous manifest only costs the reuse of its hashes.
+        let previous = Self::load(dir).ok().flatten();
+        let files = scan(dir, previous.as_ref())?;
+        Self::write(dir, chain, files, Cursor::load(dir)?.as_ref(), retries)
+    }
+}
+
+impl ManifestFile {
+    /// Describe the file at `path`, naming it `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not Parquet.
+    pub fn from_path(path: &Path, name: &str) -> Result<Self> {
+        let meta =
+            std::fs::metadata(path).with_context(|| format!("reading {}", path.display()))?;
+        let (rows, range) = parquet::footer_summary(path)?;
+        Ok(Self {
+            name: name.to_owned(),
+            size_bytes: meta.len(),
+            rows,
+            min_block: range.map(|(lo, _)| lo),
+            max_block: range.map(|(_, hi)| hi),
+            sha256: sha256_file(path)?,
+            created_at: modified_secs(&meta),
+            modified_ns: modified_ns(&meta),
+        })
+    }
+}
+
+/// Describe the archive files in `dir`, in [`Manifest::files`] order.
+///
+/// Entries of `previous` whose file still has the recorded size and
+/// modification time are reused without reading the file.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read.
+pub fn scan(dir: &Path, previous: Option<&Manifest>) -> Result<Vec<ManifestFile>> {
+    let mut files = Vec::new();
+    for (name, path) in archive_files(dir)? {
+        let meta =
+            std::fs::metadata(&path).with_context(|| format!("reading {}", path.display()))?;
+        let unchanged = previous.and_then(|m| {
+            m.files.iter().find(|f| {
+                f.name == name
+                    && f.size_bytes == meta.len()
+                    && f.modified_ns != 0
+                    && f.modified_ns == modified_ns(&meta)
+            })
+        });
+        files.push(match unchanged {
+            Some(f) => f.clone(),
+            None => ManifestFile::from_path(&path, &name)?,
+        });
+    }
+    Ok(files)
+}
+
+/// Names and paths of the existing archive files in `dir`: the registry
+/// contracts' first, then those of `sync-abi` contracts by name.
+pub(crate) fn archive_files(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut abi_contracts = BTreeSet::new();
+    if dir.is_dir() {
+        for entry in std::fs::read_dir(dir).with_context(|| format!("listing {}", dir.display()))? {
+            let name = entry?.file_name();
+            if let Some(stem) = archive_stem(&name.to_string_lossy())
+                && !CONTRACTS.contains(&stem)
+            {
+                abi_contracts.insert(stem.to_owned());
+            }
+        }
+    }
+    let mut files = Vec::new();
+    for contract in CONTRACTS
+        .iter()
+        .copied()
+        .chain(abi_contracts.iter().map(String::as_str))
+    {
+        let base = dir.join(format!("{contract}.parquet"));
+        for path in rotated_files(&base)?.into_iter().chain([base]) {
+            if let Some(name) = path.file_name()
+                && path.exists()
+            {
+                files.push((name.to_string_lossy().into_owned(), path));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// The contract name of an archive file name, `<name>.parquet` or rotated
+/// `<name>.NNN.parquet`; `None` for partial, pending and other files.
+fn archive_stem(file_name: &str) -> Option<&str> {
+    let stem = file_name.strip_suffix(".parquet")?;
+    let stem = match stem.rsplit_once('.') {
+        Some((name, index)) if !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()) => {
+            name
+        }
+        _ => stem,
+    };
+    (!stem.is_empty() && !stem.contains('.')).then_some(stem)
+}
+
+/// A difference between a chain's manifest and its files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// A listed file no longer exists.
+    Missing {
+        /// File name from the manifest.
+        name: String,
+    },
+    /// A listed file's content hash differs from the recorded one.
+    Hash {
+        /// File name from the manifest.
+        name: String,
+        /// SHA-256 recorded in the manifest.
+        expected: String,
+        /// SHA-256 of the file on disk.
+        actual: String,
+    },
+    /// An archive file is not listed in the manifest.
+    Unlisted {
+        /// File name in the chain directory.
+        name: String,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing { name } => write!(f, "{name}: listed but missing"),
+            Self::Hash {
+                name,
+                expected,
+                actual,
+            } => write!(f, "{name}: sha256 {actual}, manifest has {expected}"),
+            Self::Unlisted { name } => write!(f, "{name}: not in the manifest"),
+        }
+    }
+}
+
+/// Recompute the hash of every file in the manifest of `dir` and compare
+/// the listing with the archive files on disk.
+///
+/// Returns `None` if `dir` has no manifest.
+///
+/// # Errors
+///
+/// Returns an error if the manifest or a file cannot be read.
+pub fn verify(dir: &Path) -> Result<Option<Vec<Mismatch>>> {
+    let Some(manifest) = Manifest::load(dir)? else {
+        return Ok(None);
+    };
+    let mut mismatches = Vec::new();
+    for file in &manifest.files {
+        let path = dir.join(&file.name);
+        if !path.exists() {
+            mismatches.push(Mismatch::Missing {
+                name: file.name.clone(),
+            });
+            continue;
+        }
+        let actual = sha256_file(&path)?;
+        if actual != file.sha256 {
+            mismatches.push(Mismatch::Hash {
+                name: file.name.clone(),
+                expected: file.sha256.clone(),
+                actual,
+            });
+        }
+    }
+    for (name, _) in archive_files(dir)? {
+        if !manifest.files.iter().any(|f| f.name == name) {
+            mismatches.push(Mismatch::Unlisted { name });
+        }
+    }
+    Ok(Some(mismatches))
+}
+
+/// Hex-encoded SHA-256 of the file at `path`.
+pub(crate) fn sha256_file(path: &Path) -> Result<String> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).with_context(|| format!("hashing {}", path.display()))?;
+    Ok(alloy::hex::encode(hasher.finalize()))
+}
+
+/// Modification time of a file in Unix seconds, or 0 if unavailable.
+fn modified_secs(meta: &std::fs::Metadata) -> u64 {
+    modified_since_epoch(meta).map_or(0, |d| d.as_secs())
+}
+
+/// Modification time of a file in nanoseconds since the Unix epoch, or 0
+/// if unavailable.
+fn modified_ns(meta: &std::fs::Metadata) -> u64 {
+    modified_since_epoch(meta).map_or(0, |d| u64::try_from(d.as_nanos()).unwrap_or(u64::MAX))
+}
+
+/// Modification time of a file since the Unix epoch, if available.
+fn modified_since_epoch(meta: &std::fs::Metadata) -> Option<Duration> {
+    meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chains;
+    use crate::testutil::{TempDir, logs_at};
+
+    #[test]
+    fn test_refresh_and_verify() -> Result<()> {
+        let dir = TempDir::new("manifest");
+        let chain = chains::by_chain_id(8453).context("unknown chain")?;
+        let (batch, _) = parquet::logs_to_batch(&logs_at(&[10, 12, 15]))?;
+        parquet::write(&dir.join("identity.parquet"), &[batch])?;
+        Cursor::now(20).save(&dir)?;
+
+        assert_eq!(verify(&dir)?, None);
+        Manifest::refresh(&dir, chain, retry::DEFAULT_IO_RETRIES)?;
+        let manifest = Manifest::load(&dir)?.context("no manifest")?;
+        assert_eq!(manifest.chain_id, 8453);
+        assert_eq!(manifest.cursor.map(|c| c.last_block), Some(20));
+        let file = manifest.files.first().context("no files")?;
+        assert_eq!(manifest.files.len(), 1);
+        assert_eq!(file.name, "identity.parquet");
+        assert_eq!(file.rows, 3);
+        assert_eq!((file.min_block, file.max_block), (Some(10), Some(15)));
+        assert_eq!(verify(&dir)?, Some(Vec::new()));
+
+        let (batch, _) = parquet::logs_to_batch(&logs_at(&[30]))?;
+        parquet::write(&dir.join("reputation.parquet"), &[batch])?;
+        std::fs::write(dir.join("identity.parquet"), b"not parquet")?;
+        let mismatches = verify(&dir)?.context("no manifest")?;
+        assert!(matches!(
+            mismatches.as_slice(),
+            [Mismatch::Hash { .. }, Mismatch::Unlisted { name }] if name == "reputation.parquet"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_reuses_hash_only_for_the_same_mtime() -> Result<()> {
+        let dir = TempDir::new("manifest-mtime");
+        let (batch, _) = parquet::logs_to_batch(&logs_at(&[10]))?;
+        parquet::write(&dir.join("identity.parquet"), &[batch])?;
+        let file = scan(&dir, None)?.pop().context("no files")?;
+        let previous = |file: ManifestFile| Manifest {
+            schema_version: SCHEMA_VERSION,
+            chain_id: 8453,
+            chain_name: "Base".to_owned(),
+            files: vec![file],
+            cursor: None,
+        };
+
+        // Same size and nanosecond mtime: the recorded hash is trusted.
+        let stale = ManifestFile {
+            sha256: "stale".to_owned(),
+            ..file.clone()
+        };
+        let reused = scan(&dir, Some(&previous(stale.clone())))?;
+        assert_eq!(reused.first().map(|f| f.sha256.as_str()), Some("stale"));
+
+        // A rewrite within the same second only shows in the nanoseconds.
+        let rewritten = ManifestFile {
+            modified_ns: stale.modified_ns + 1,
+            ..stale
+        };
+        let rehashed = scan(&dir, Some(&previous(rewritten)))?;
+        assert_eq!(rehashed.first(), Some(&file));
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_files_include_abi_contracts() -> Result<()> {
+        let dir = TempDir::new("manifest-abi");
+        for name in [
+            "usdc.parquet",
+            "usdc.001.parquet",
+            "identity.parquet",
+            "identity.partial.parquet",
+            "reputation.pending.parquet",
+            "usdc.parquet.tmp",
+        ] {
+            std::fs::write(dir.join(name), b"")?;
+        }
+        let names: Vec<_> = archive_files(&dir)?.into_iter().map(|(n, _)| n).collect();
+        assert_eq!(
+            names,
+            ["identity.parquet", "usdc.001.parquet", "usdc.parquet"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,392 @@
+//! Additional output backends fed alongside the primary Parquet archive.
+//!
+//! The primary `<data_dir>/<chain_id>/<contract>.parquet` files (or an
+//! [`EventSink`] supplied by a library user) remain the source of truth
+//! for resumption.  Every configured [`OutputBackend`]
+//! receives the same data on each flush, all backends at once; a failing
+//! backend never stops the sync — its errors are collected by [`FanOut`]
+//! and reported as a [`MultiOutputError`] once the contract has been fully
+//! synced.
+//!
+//! Local Parquet and NDJSON backends are always built in.  The `s3`
+//! feature adds [`S3Output`](crate::s3::S3Output) and the `kafka` feature
+//! [`KafkaOutput`](crate::kafka::KafkaOutput); both speak plain HTTP through
+//! the client alloy already depends on, so neither pulls an SDK into the
+//! build.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+#[cfg(any(feature = "s3", feature = "kafka"))]
+use std::time::Duration;
+
+#[cfg(any(feature = "s3", feature = "kafka"))]
+use alloy::transports::http::reqwest;
+#[cfg(any(feature = "s3", feature = "kafka"))]
+use anyhow::Context;
+use anyhow::{Result, bail};
+use arrow_array::RecordBatch;
+
+use crate::jsonl;
+#[cfg(feature = "kafka")]
+use crate::kafka::KafkaOutput;
+use crate::parquet::{self, PartitionWriter};
+#[cfg(feature = "s3")]
+use crate::s3::S3Output;
+
+/// Timeout of one request of the HTTP outputs, long enough to upload a
+/// large Parquet file.
+#[cfg(any(feature = "s3", feature = "kafka"))]
+const HTTP_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// File format of the primary archive (`--sink-format`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SinkFormat {
+    /// `<contract>.parquet`, rewritten (or rotated) on every flush.
+    #[default]
+    Parquet,
+    /// `<contract>.jsonl`, one [`crate::parquet::LogRow`] per line, only
+    /// ever appended to.
+    Jsonl,
+    /// A Delta Lake table at the data directory root, written through
+    /// `delta::DeltaSink`; requires the `delta` feature.
+    Delta,
+}
+
+impl SinkFormat {
+    /// File extension of the contract files.
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Parquet | Self::Delta => "parquet",
+            Self::Jsonl => "jsonl",
+        }
+    }
+}
+
+impl FromStr for SinkFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "parquet" => Ok(Self::Parquet),
+            "jsonl" => Ok(Self::Jsonl),
+            "delta" if cfg!(feature = "delta") => Ok(Self::Delta),
+            "delta" => bail!("sink format `delta` requires the `delta` feature"),
+            _ => bail!("invalid sink format `{s}`; expected `parquet`, `jsonl` or `delta`"),
+        }
+    }
+}
+
+impl fmt::Display for SinkFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Parquet => "parquet",
+            Self::Jsonl => "jsonl",
+            Self::Delta => "delta",
+        })
+    }
+}
+
+/// A primary store for archived events supplied by a library user, taking
+/// the place of the built-in Parquet/JSONL files; see
+/// [`crate::fetcher::sync_chain_with_sink`].
+pub trait EventSink: fmt::Debug + Send + Sync {
+    /// Store the rows of one flush, built with
+    /// [`event_schema`](crate::parquet::event_schema).
+    ///
+    /// # Errors
+    ///
+    /// An error fails the chain's sync, like a failed Parquet write.
+    fn write(&self, chain_id: u64, contract: &str, batch: &RecordBatch) -> Result<()>;
+
+    /// Highest block already stored for a contract, for sinks that track
+    /// their own high-water mark (e.g. a database).  The sync resumes past
+    /// it when it is ahead of the cursor; `None` leaves resumption to the
+    /// cursor alone.
+    ///
+    /// # Errors
+    ///
+    /// An error fails the chain's sync.
+    fn high_water_mark(&self, _chain_id: u64, _contract: &str) -> Result<Option<u64>> {
+        Ok(None)
+    }
+}
+
+/// A secondary destination for archived events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputBackend {
+    /// Mirror the full Parquet files under another base directory.
+    Parquet(PathBuf),
+    /// Append new rows as newline-delimited JSON to
+    /// `<dir>/<chain_id>/<contract>.ndjson`.
+    ///
+    /// Only rows flushed while the backend is configured are written.
+    Ndjson(PathBuf),
+    /// Upload the full Parquet files to an S3 bucket.
+    #[cfg(feature = "s3")]
+    S3(S3Output),
+    /// Produce new rows to a Kafka topic through a REST Proxy.
+    ///
+    /// Only rows flushed while the backend is configured are produced.
+    #[cfg(feature = "kafka")]
+    Kafka(KafkaOutput),
+}
+
+impl FromStr for OutputBackend {
+    type Err = anyhow::Error;
+
+    /// Parse `parquet:<dir>`, `ndjson:<dir>`, `s3://<bucket>/<prefix>` or
+    /// `kafka:<proxy>/topics/<topic>`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            Some(("parquet", dir)) if !dir.is_empty() => Ok(Self::Parquet(dir.into())),
+            Some(("ndjson", dir)) if !dir.is_empty() => Ok(Self::Ndjson(dir.into())),
+            #[cfg(feature = "s3")]
+            Some(("s3", rest)) if rest.starts_with("//") => {
+                S3Output::parse(rest.trim_start_matches("//")).map(Self::S3)
+            }
+            #[cfg(feature = "kafka")]
+            Some(("kafka", url)) => KafkaOutput::parse(url).map(Self::Kafka),
+            Some(("s3", _)) if !cfg!(feature = "s3") => {
+                bail!("output `{s}` requires the `s3` feature")
+            }
+            Some(("kafka", _)) if !cfg!(feature = "kafka") => {
+                bail!("output `{s}` requires the `kafka` feature")
+            }
+            _ => bail!(
+                "invalid output `{s}`; expected `parquet:<dir>`, `ndjson:<dir>`, \
+                 `s3://<bucket>/<prefix>` or `kafka:<proxy>/topics/<topic>`"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for OutputBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parquet(dir) => write!(f, "parquet:{}", dir.display()),
+            Self::Ndjson(dir) => write!(f, "ndjson:{}", dir.display()),
+            #[cfg(feature = "s3")]
+            Self::S3(out) => out.fmt(f),
+            #[cfg(feature = "kafka")]
+            Self::Kafka(out) => out.fmt(f),
+        }
+    }
+}
+
+impl OutputBackend {
+    /// Whether the backend writes the complete contract history on every
+    /// flush, and so needs the primary archive to hold it.
+    #[must_use]
+    pub const fn needs_history(&self) -> bool {
+        match self {
+            Self::Parquet(_) => true,
+            #[cfg(feature = "s3")]
+            Self::S3(_) => true,
+            Self::Ndjson(_) => false,
+            #[cfg(feature = "kafka")]
+            Self::Kafka(_) => false,
+        }
+    }
+
+    /// Deliver one flush to this backend.
+    ///
+    /// `new` holds only the rows added by this flush; `all` is the complete
+    /// contract history as written to the primary archive.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the backend cannot be written.
+    pub fn write(
+        &self,
+        chain_id: u64,
+        contract: &str,
+        new: &RecordBatch,
+        all: &[RecordBatch],
+    ) -> Result<()> {
+        match self {
+            Self::Parquet(dir) => parquet::write(
+                &dir.join(chain_id.to_string())
+                    .join(format!("{contract}.parquet")),
+                all,
+            ),
+            Self::Ndjson(dir) => jsonl::append(
+                &dir.join(chain_id.to_string())
+                    .join(format!("{contract}.ndjson")),
+                new,
+            ),
+            #[cfg(feature = "s3")]
+            Self::S3(out) => out.write(chain_id, contract, all),
+            #[cfg(feature = "kafka")]
+            Self::Kafka(out) => out.write(chain_id, contract, new),
+        }
+    }
+}
+
+/// Run `fut` to completion on a current-thread runtime of its own.
+///
+/// Backends are written from [`FanOut::send`]'s scoped threads, outside
+/// the sync's runtime, so the HTTP outputs drive their requests here.
+#[cfg(any(feature = "s3", feature = "kafka"))]
+pub(crate) fn block_on<T>(fut: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("building the output runtime")?
+        .block_on(fut)
+}
+
+/// HTTP client of the S3 and Kafka outputs.
+#[cfg(any(feature = "s3", feature = "kafka"))]
+pub(crate) fn http_client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .context("building the output HTTP client")
+}
+
+/// Delivers one contract's flushes to every backend, collecting failures.
+#[derive(Debug)]
+pub struct FanOut<'a> {
+    backends: &'a [OutputBackend],
+    partitions: Option<PartitionWriter>,
+    /// Last block whose rows the backends already received.
+    delivered: Option<u64>,
+    chain_id: u64,
+    contract: &'a str,
+    failures: Vec<String>,
+}
+
+/// Error of [`FanOut::finish`]: the secondary writes that failed while a
+/// contract was synced.  The primary archive is complete regardless.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiOutputError {
+    /// Chain whose contract was synced.
+    pub chain_id: u64,
+    /// Contract whose flushes failed to reach some outputs.
+    pub contract: String,
+    /// One `<output>: <error>` entry per failed write.
+    pub failures: Vec<String>,
+}
+
+impl fmt::Display for MultiOutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "chain {} {}: {} output write(s) failed: {}",
+            self.chain_id,
+            self.contract,
+            self.failures.len(),
+            self.failures.join("; ")
+        )
+    }
+}
+
+impl std::error::Error for MultiOutputError {}
+
+impl<'a> FanOut<'a> {
+    /// Create a fan-out for one contract of one chain.
+    #[must_use]
+    pub const fn new(backends: &'a [OutputBackend], chain_id: u64, contract: &'a str) -> Self {
+        Self {
+            backends,
+            partitions: None,
+            delivered: None,
+            chain_id,
+            contract,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Deliver later flushes to `backends` instead.
+    pub const fn set_backends(&mut self, backends: &'a [OutputBackend]) {
+        self.backends = backends;
+    }
+
+    /// Also rewrite a partitioned copy of the contract on every flush.
+    #[must_use]
+    pub fn with_partitions(mut self, partitions: Option<PartitionWriter>) -> Self {
+        self.partitions = partitions;
+        self
+    }
+
+    /// Keep the rows up to block `delivered` from the backends, which
+    /// received them before a re-index; the partitions are still rewritten.
+    #[must_use]
+    pub const fn with_delivered(mut self, delivered: Option<u64>) -> Self {
+        self.delivered = delivered;
+        self
+    }
+
+    /// The rows of `new` the backends have not received yet, if any.
+    fn undelivered(&self, new: &RecordBatch) -> Result<Option<RecordBatch>> {
+        let Some(block) = self.delivered else {
+            return Ok(Some(new.clone()));
+        };
+        let (_, after) = parquet::split_batches(std::slice::from_ref(new), block + 1)?;
+        Ok(after.into_iter().next())
+    }
+
+    /// Send a flush to the partitions and every backend concurrently, one
+    /// thread each; failures are logged and remembered.
+    pub fn send(&mut self, new: &RecordBatch, all: &[RecordBatch]) {
+        let (chain_id, contract) = (self.chain_id, self.contract);
+        let (new, backends) = match self.undelivered(new) {
+            Ok(Some(new)) => (new, self.backends),
+            Ok(None) => (new.clone(), &[][..]),
+            Err(e) => {
+                tracing::warn!(chain_id, contract, error = %e, "filtering delivered rows failed");
+                self.failures
+                    .push(format!("filtering delivered rows: {e:#}"));
+                (new.clone(), &[][..])
+            }
+        };
+        let new = &new;
+        let results: Vec<(String, Result<()>)> = std::thread::scope(|scope| {
+            let mut handles = Vec::new();
+            if let Some(p) = &self.partitions {
+                handles.push(("partitions".to_owned(), scope.spawn(|| p.write(all))));
+            }
+            for backend in backends {
+                handles.push((
+                    backend.to_string(),
+                    scope.spawn(move || backend.write(chain_id, contract, new, all)),
+                ));
+            }
+            handles
+                .into_iter()
+                .map(|(output, handle)| {
+                    let res = handle
+                        .join()
+                        .unwrap_or_else(|_| Err(anyhow::anyhow!("output writer panicked")));
+                    (output, res)
+                })
+                .collect()
+        });
+        for (output, res) in results {
+            if let Err(e) = res {
+                tracing::warn!(chain_id, contract, output = %output, error = %e, "output write failed");
+                self.failures.push(format!("{output}: {e:#}"));
+            }
+        }
+    }
+
+    /// Report collected failures.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`MultiOutputError`] listing every failed write, if there
+    /// were any.
+    pub fn finish(self) -> Result<()> {
+        if self.failures.is_empty() {
+            return Ok(());
+        }
+        Err(MultiOutputError {
+            chain_id: self.chain_id,
+            contract: self.contract.to_owned(),
+            failures: self.failures,
+        }
+        .into())
+    }
+}
@@ -2,20 +2,72 @@
 //!
 //! Provides a single conversion path from alloy [`Log`]s directly to
 //! columnar [`RecordBatch`]es — no intermediate row struct needed.
+//!
+//! For read-side consumers, [`query`] applies an [`EventQuery`] filter and
+//! returns matching rows as [`LogRow`]s.
+//!
+//! # Compression
+//!
+//! Files are written with Zstd (level 3) unless
+//! [`ParquetWriterOptions::compression`] picks another [`Compression`]:
+//!
+//! - **Zstd** — the smallest files; the default for long-term archives.
+//! - **LZ4 raw** — the fastest to decompress, at a somewhat lower ratio;
+//!   suited to archives that are replayed or queried continuously.
+//! - **Snappy** — Parquet's traditional fast codec, readable by every
+//!   engine; similar speed to LZ4 with a slightly larger output.
+//! - **None** — uncompressed, for inspecting pages while debugging.
+//!
+//! The hex-encoded hash and data columns are high-entropy, so the gap
+//! between codecs is narrower than on typical text data.  Measure on your
+//! own archive with `cargo bench -p erc8004-events --bench compression`.
+//!
+//! Repetitive columns are covered by Parquet's own dictionary encoding (see
+//! [`DICTIONARY_COLUMNS`]).  A pre-trained Zstd dictionary
+//! ([`train_zstd_dictionary`]) cannot be applied to the archives: the
+//! format has no place to store a codec dictionary, so other readers could
+//! not decompress such pages.  `cargo bench -p erc8004-events --bench
+//! zstd_dictionary` measures what it would save on the raw column values
+//! of 1M rows.
 
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, LazyLock};
 
+use alloy::consensus::Transaction as _;
+use alloy::primitives::B256;
+use alloy::providers::Provider;
 use alloy::rpc::types::Log;
-use anyhow::{Context, Result};
-use arrow_array::{BooleanArray, RecordBatch, StringArray, UInt32Array, UInt64Array};
+use anyhow::{Context, Result, bail};
+use arrow_array::{
+    Array, BooleanArray, RecordBatch, StringArray, UInt32Array, UInt64Array, new_null_array,
+};
 use arrow_schema::{DataType, Field, Schema};
-use parquet::arrow::ArrowWriter;
-use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
-use parquet::file::properties::WriterProperties;
+use arrow_select::concat::concat_batches;
+use arrow_select::filter::filter_record_batch;
+use arrow_select::take::take_record_batch;
+use futures::{StreamExt, TryStreamExt};
+use parquet::arrow::arrow_reader::{
+    ArrowReaderMetadata, ArrowReaderOptions, ParquetRecordBatchReaderBuilder,
+};
+use parquet::arrow::{ArrowWriter, ProjectionMask};
+use parquet::file::metadata::RowGroupMetaData;
+use parquet::file::properties::{EnabledStatistics, WriterProperties};
+use parquet::file::statistics::Statistics;
+use parquet::schema::types::ColumnPath;
+use serde::{Deserialize, Serialize};
+
+use crate::ratelimit::TokenBucket;
+use crate::retry;
 
 /// Arrow schema mirroring the Ethereum `eth_getLogs` response structure.
-static EVENT_SCHEMA: LazyLock<Arc<Schema>> = LazyLock::new(|| {
+///
+/// `tx_nonce`, the sender's nonce of the emitting transaction, is not part
+/// of a log: it stays null until filled in by [`enrich_with_nonces`].
+/// `block_hash` is the hash of the block the log was archived from, for
+/// [`detect_reorg`].  Files written before either column existed are read
+/// with it null.
+pub(crate) static EVENT_SCHEMA: LazyLock<Arc<Schema>> = LazyLock::new(|| {
     Arc::new(Schema::new(vec![
         Field::new("block_number", DataType::UInt64, false),
         Field::new("tx_hash", DataType::Utf8, false),
@@ -28,18 +80,62 @@
         Field::new("topic3", DataType::Utf8, true),
         Field::new("data", DataType::Utf8, false),
         Field::new("removed", DataType::Boolean, false),
+        Field::new("tx_nonce", DataType::UInt64, true),
+        Field::new("block_hash", DataType::Utf8, true),
     ]))
 });
 
+/// [`EVENT_SCHEMA`] with a nullable `block_number`, for logs of the pending
+/// block (`<contract>.pending.parquet`).
+pub(crate) static PENDING_SCHEMA: LazyLock<Arc<Schema>> = LazyLock::new(|| {
+    let fields = EVENT_SCHEMA.fields().iter().map(|f| {
+        if f.name() == "block_number" {
+            Arc::new(f.as_ref().clone().with_nullable(true))
+        } else {
+            Arc::clone(f)
+        }
+    });
+    Arc::new(Schema::new(fields.collect::<Vec<_>>()))
+});
+
+/// The canonical Arrow schema of every archived Parquet file.
+///
+/// Use it to build compatible batches or validate external files.
+#[must_use]
+pub fn event_schema() -> Arc<Schema> {
+    Arc::clone(&EVENT_SCHEMA)
+}
+
 /// Convert RPC [`Log`]s directly into a columnar [`RecordBatch`].
 ///
-/// Skips logs that are missing required fields (block number, tx hash, etc.).
+/// Skips logs that are missing required fields (block number, tx hash, etc.)
+/// and repeats of a `(block_number, tx_index, log_index)` already seen in
+/// `logs`, which some load-balanced gateways return twice in one response.
 /// Returns the batch together with the number of valid rows.
 ///
 /// # Errors
 ///
 /// Returns an error if the Arrow `RecordBatch` construction fails.
 pub fn logs_to_batch(logs: &[Log]) -> Result<(RecordBatch, usize)> {
+    build_batch(logs, &EVENT_SCHEMA, false)
+}
+
+/// Convert the pending logs (those without a block number) among `logs`
+/// into a [`RecordBatch`] with a nullable `block_number` column.
+///
+/// Logs that already have a block number are skipped, as are logs missing
+/// any other required field.  Returns the batch and its number of rows.
+///
+/// # Errors
+///
+/// Returns an error if the Arrow `RecordBatch` construction fails.
+pub fn pending_logs_to_batch(logs: &[Log]) -> Result<(RecordBatch, usize)> {
+    build_batch(logs, &PENDING_SCHEMA, true)
+}
+
+/// Shared body of [`logs_to_batch`] and [`pending_logs_to_batch`]: keep the
+/// logs whose block number is missing iff `pending`.
+fn build_batch(logs: &[Log], schema: &Arc<Schema>, pending: bool) -> Result<(RecordBatch, usize)> {
     let cap = logs.len();
     let mut block_numbers = Vec::with_capacity(cap);
     let mut tx_hashes = Vec::with_capacity(cap);
@@ -52,16 +148,24 @@ pub fn logs_to_batch(logs: &[Log]) -> Result<(RecordBatch, usize)> {
     let mut topic3s: Vec<Option<String>> = Vec::with_capacity(cap);
     let mut datas = Vec::with_capacity(cap);
     let mut removeds = Vec::with_capacity(cap);
+    let mut block_hashes: Vec<Option<String>> = Vec::with_capacity(cap);
+    let mut seen = BTreeSet::new();
+    let mut duplicates = 0usize;
 
     for log in logs {
-        let (Some(bn), Some(th), Some(ti), Some(li)) = (
-            log.block_number,
-            log.transaction_hash,
-            log.transaction_index,
-            log.log_index,
-        ) else {
+        let bn = log.block_number;
+        let (Some(th), Some(ti), Some(li)) =
+            (log.transaction_hash, log.transaction_index, log.log_index)
+        else {
             continue;
         };
+        if bn.is_some() == pending {
+            continue;
+        }
+        if !seen.insert((bn, ti, li)) {
+            duplicates += 1;
+            continue;
+        }
 
         let topics = log.topics();
         let Some(t0) = topics.first() else {
@@ -85,11 +189,19 @@ pub fn logs_to_batch(logs: &[Log]) -> Result<(RecordBatch, usize)> {
         topic3s.push(topics.get(3).map(|t| format!("{t:#x}")));
         datas.push(format!("{}", log.data().data));
         removeds.push(log.removed);
+        block_hashes.push(log.block_hash.map(|h| format!("{h:#x}")));
+    }
+
+    if duplicates > 0 {
+        tracing::warn!(
+            duplicates,
+            "dropped logs returned more than once by the RPC"
+        );
     }
 
     let count = block_numbers.len();
     let batch = RecordBatch::try_new(
-        Arc::clone(&EVENT_SCHEMA),
+        Arc::clone(schema),
         vec![
             Arc::new(UInt64Array::from(block_numbers)),
             Arc::new(StringArray::from(tx_hashes)),
@@ -102,6 +214,8 @@ pub fn logs_to_batch(logs: &[Log]) -> Result<(RecordBatch, usize)> {
             Arc::new(StringArray::from(topic3s)),
             Arc::new(StringArray::from(datas)),
             Arc::new(BooleanArray::from(removeds)),
+            new_null_array(&DataType::UInt64, count),
+            Arc::new(StringArray::from(block_hashes)),
         ],
     )?;
 
@@ -140,40 +254,2467 @@ pub fn read(path: &Path) -> Result<Vec<RecordBatch>> {
         .with_context(|| format!("reading parquet header: {}", path.display()))?
         .build()
         .with_context(|| format!("building parquet reader: {}", path.display()))?
-        .collect::<Result<Vec<_>, _>>()
+        .map(|batch| upgrade(batch?))
+        .collect::<Result<Vec<_>>>()
         .with_context(|| format!("reading batches from {}", path.display()))
 }
 
+/// Columns appended to [`EVENT_SCHEMA`] after its first release, in order.
+const ADDED_COLUMNS: [(&str, DataType); 2] = [
+    ("tx_nonce", DataType::UInt64),
+    ("block_hash", DataType::Utf8),
+];
+
+/// Add the [`ADDED_COLUMNS`], all null, to a batch read from a file written
+/// before they existed.
+fn upgrade(batch: RecordBatch) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    let missing: Vec<_> = ADDED_COLUMNS
+        .iter()
+        .filter(|(name, _)| schema.column_with_name(name).is_none())
+        .collect();
+    if missing.is_empty() {
+        return Ok(batch);
+    }
+    let mut fields = schema.fields().to_vec();
+    let mut columns = batch.columns().to_vec();
+    for (name, data_type) in missing {
+        fields.push(Arc::new(Field::new(*name, data_type.clone(), true)));
+        columns.push(new_null_array(data_type, batch.num_rows()));
+    }
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone())),
+        columns,
+    )?)
+}
+
+/// Split batches into the rows before `block` and those at or after it,
+/// dropping batches that end up empty.
+///
+/// # Errors
+///
+/// Returns an error if a batch does not match the event schema.
+pub fn split_batches(
+    batches: &[RecordBatch],
+    block: u64,
+) -> Result<(Vec<RecordBatch>, Vec<RecordBatch>)> {
+    let (mut before, mut after) = (Vec::new(), Vec::new());
+    for batch in batches {
+        let blocks = column::<UInt64Array>(batch, "block_number")?;
+        let is_before: BooleanArray = blocks.values().iter().map(|&b| Some(b < block)).collect();
+        let is_after: BooleanArray = blocks.values().iter().map(|&b| Some(b >= block)).collect();
+        for (mask, out) in [(is_before, &mut before), (is_after, &mut after)] {
+            let part = filter_record_batch(batch, &mask)?;
+            if part.num_rows() > 0 {
+                out.push(part);
+            }
+        }
+    }
+    Ok((before, after))
+}
+
+/// A row group that [`read_with_recovery`] could not decode.
+#[derive(Debug, Clone)]
+pub struct RowGroupError {
+    /// File containing the row group.
+    pub path: PathBuf,
+    /// Zero-based row group index within the file.
+    pub row_group: usize,
+    /// Inclusive `block_number` range taken from the footer statistics, if
+    /// present.  This is the range that must be re-fetched.
+    pub block_range: Option<(u64, u64)>,
+    /// Number of rows in the row group according to the footer.
+    pub rows: u64,
+    /// The decode error.
+    pub error: String,
+}
+
+/// Read a Parquet file row group by row group, skipping any that fail to
+/// decode.
+///
+/// Unlike [`read`], a damaged row group (e.g. a truncated write after the
+/// disk filled up) does not fail the whole file: its rows are omitted and it
+/// is reported as a [`RowGroupError`].  Returns empty vecs if the file does
+/// not exist.
+///
+/// # Errors
+///
+/// Returns an error if the footer cannot be read or no row group is
+/// readable.
+pub fn read_with_recovery(path: &Path) -> Result<(Vec<RecordBatch>, Vec<RowGroupError>)> {
+    if !path.exists() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let (batches, errors, row_groups) = decode_row_groups(path)?;
+    if row_groups > 0 && errors.len() == row_groups {
+        bail!(
+            "no readable row groups in {} ({} corrupted)",
+            path.display(),
+            errors.len()
+        );
+    }
+    Ok((batches, errors))
+}
+
+/// Result of [`verify_integrity`] for a single Parquet file.
+#[derive(Debug, Clone, Default)]
+pub struct IntegrityReport {
+    /// Number of row groups in the footer.
+    pub row_groups: usize,
+    /// Rows decoded from the readable row groups.
+    pub rows: usize,
+    /// Row groups that failed to decode.
+    pub corrupted: Vec<RowGroupError>,
+    /// Inclusive `block_number` range of the readable rows.
+    pub block_range: Option<(u64, u64)>,
+    /// Distinct `address` values of the readable rows.
+    pub addresses: BTreeSet<String>,
+}
+
+impl IntegrityReport {
+    /// Rows lost to corrupted row groups, according to the footer.
+    #[must_use]
+    pub fn corrupted_rows(&self) -> u64 {
+        self.corrupted.iter().map(|e| e.rows).sum()
+    }
+}
+
+/// Decode every row group of `path` and summarise what it contains.
+///
+/// Corrupted row groups are reported rather than treated as an error, so
+/// the caller can decide how to surface them.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened, its footer cannot be read
+/// or a decoded batch does not match the event schema.
+pub fn verify_integrity(path: &Path) -> Result<IntegrityReport> {
+    let (batches, corrupted, row_groups) = decode_row_groups(path)?;
+    let mut report = IntegrityReport {
+        row_groups,
+        corrupted,
+        ..Default::default()
+    };
+    for batch in &batches {
+        report.rows += batch.num_rows();
+        let blocks = column::<UInt64Array>(batch, "block_number")?.values();
+        if let (Some(&lo), Some(&hi)) = (blocks.iter().min(), blocks.iter().max()) {
+            report.block_range = Some(
+                report
+                    .block_range
+                    .map_or((lo, hi), |(min, max)| (min.min(lo), max.max(hi))),
+            );
+        }
+        for address in column::<StringArray>(batch, "address")?.iter().flatten() {
+            if !report.addresses.contains(address) {
+                report.addresses.insert(address.to_owned());
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Decode `path` row group by row group.
+///
+/// Returns the readable batches, the failed row groups and the total number
+/// of row groups.
+fn decode_row_groups(path: &Path) -> Result<(Vec<RecordBatch>, Vec<RowGroupError>, usize)> {
+    let file = std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let metadata = ArrowReaderMetadata::load(&file, ArrowReaderOptions::default())
+        .with_context(|| format!("reading parquet footer: {}", path.display()))?;
+
+    let mut batches = Vec::new();
+    let mut errors = Vec::new();
+    let row_groups = metadata.metadata().row_groups();
+    for (row_group, meta) in row_groups.iter().enumerate() {
+        match read_row_group(&file, &metadata, row_group) {
+            Ok(b) => batches.extend(b),
+            Err(e) => errors.push(RowGroupError {
+                path: path.to_path_buf(),
+                row_group,
+                block_range: block_range(meta),
+                rows: u64::try_from(meta.num_rows()).unwrap_or(0),
+                error: format!("{e:#}"),
+            }),
+        }
+    }
+    Ok((batches, errors, row_groups.len()))
+}
+
+/// Decode a single row group using already-loaded footer metadata.
+fn read_row_group(
+    file: &std::fs::File,
+    metadata: &ArrowReaderMetadata,
+    row_group: usize,
+) -> Result<Vec<RecordBatch>> {
+    let reader =
+        ParquetRecordBatchReaderBuilder::new_with_metadata(file.try_clone()?, metadata.clone())
+            .with_row_groups(vec![row_group])
+            .build()?;
+    reader.map(|batch| upgrade(batch?)).collect()
+}
+
+/// Row count and `block_number` range of a Parquet file, read from its
+/// footer only.
+///
+/// The range is `None` for a file without rows or with a non-empty row
+/// group that has no statistics.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened or its footer cannot be
+/// read.
+pub fn footer_summary(path: &Path) -> Result<(u64, Option<(u64, u64)>)> {
+    let file = std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let metadata = ArrowReaderMetadata::load(&file, ArrowReaderOptions::default())
+        .with_context(|| format!("reading parquet footer: {}", path.display()))?;
+    let mut rows = 0;
+    let mut range: Option<(u64, u64)> = None;
+    let mut complete = true;
+    for row_group in metadata.metadata().row_groups() {
+        rows += u64::try_from(row_group.num_rows()).unwrap_or(0);
+        if row_group.num_rows() == 0 {
+            continue;
+        }
+        match block_range(row_group) {
+            Some((lo, hi)) => {
+                range = Some(range.map_or((lo, hi), |(min, max)| (min.min(lo), max.max(hi))));
+            }
+            None => complete = false,
+        }
+    }
+    Ok((rows, range.filter(|_| complete)))
+}
+
+/// The `block_number` min/max recorded in a row group's footer statistics.
+fn block_range(row_group: &RowGroupMetaData) -> Option<(u64, u64)> {
+    let column = row_group
+        .columns()
+        .iter()
+        .find(|c| c.column_path().string() == "block_number")?;
+    match column.statistics()? {
+        Statistics::Int64(s) => Some((
+            u64::try_from(*s.min_opt()?).ok()?,
+            u64::try_from(*s.max_opt()?).ok()?,
+        )),
+        _ => None,
+    }
+}
+
+/// Tuning knobs for [`write_with`] and [`RotatingWriter`].
+#[derive(Debug, Clone, Copy)]
+pub struct ParquetWriterOptions {
+    /// Encode columns in parallel on the rayon thread pool.
+    ///
+    /// Only effective with the `rayon` feature; defaults to `true` when the
+    /// feature is enabled.
+    pub parallel_encode: bool,
+    /// Rotate the live file to `<stem>.NNN.parquet` before it would grow
+    /// past this many bytes (see [`RotatingWriter`]).  [`write_with`]
+    /// itself always writes a single file.
+    pub max_file_bytes: Option<u64>,
+    /// Dictionary-encode only the repetitive columns listed in
+    /// [`DICTIONARY_COLUMNS`]; when `false`, no column is dictionary-encoded.
+    pub dictionary_encoding: bool,
+    /// Write page-level (rather than only column-chunk) min/max statistics,
+    /// enabling finer predicate pushdown in query engines.
+    pub page_statistics: bool,
+    /// Codec applied to every column.
+    pub compression: Compression,
+    /// After [`write_with`] renamed a file into place, read it back and fail
+    /// unless it decodes to as many rows as were written.  Catches silent
+    /// write corruption at the cost of a full read per write.
+    pub verify_writes: bool,
+    /// Retries of a failed write or rename (see [`retry`]); a sync uses
+    /// [`SyncOptions::io_retries`](crate::fetcher::SyncOptions::io_retries)
+    /// instead.
+    pub io_retries: u32,
+}
+
+impl Default for ParquetWriterOptions {
+    fn default() -> Self {
+        Self {
+            parallel_encode: cfg!(feature = "rayon"),
+            max_file_bytes: None,
+            dictionary_encoding: true,
+            page_statistics: true,
+            compression: Compression::Zstd,
+            verify_writes: false,
+            io_retries: retry::DEFAULT_IO_RETRIES,
+        }
+    }
+}
+
+/// Parquet compression codec (`--compression`, or `compression` in
+/// `config.toml`); see the [module docs](self#compression) for the
+/// tradeoffs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    /// Zstd at level 3.
+    #[default]
+    Zstd,
+    /// LZ4 without the Hadoop framing (`LZ4_RAW`).
+    #[serde(alias = "lz4")]
+    Lz4Raw,
+    /// Snappy.
+    Snappy,
+    /// Uncompressed.
+    None,
+}
+
+impl Compression {
+    /// The codec as configured on the Parquet writer.
+    fn codec(self) -> Result<parquet::basic::Compression> {
+        Ok(match self {
+            Self::Zstd => parquet::basic::Compression::ZSTD(
+                parquet::basic::ZstdLevel::try_new(3).context("invalid zstd level")?,
+            ),
+            Self::Lz4Raw => parquet::basic::Compression::LZ4_RAW,
+            Self::Snappy => parquet::basic::Compression::SNAPPY,
+            Self::None => parquet::basic::Compression::UNCOMPRESSED,
+        })
+    }
+}
+
+impl std::str::FromStr for Compression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "zstd" => Ok(Self::Zstd),
+            "lz4" | "lz4_raw" => Ok(Self::Lz4Raw),
+            "snappy" => Ok(Self::Snappy),
+            "none" => Ok(Self::None),
+            _ => bail!("invalid compression `{s}`; expected zstd, lz4, snappy or none"),
+        }
+    }
+}
+
+/// Columns with few distinct values per file (a handful of contract
+/// addresses and event signatures) that compress well with a dictionary.
+/// Unique-per-row columns such as `tx_hash` and `data` gain nothing from it.
+pub const DICTIONARY_COLUMNS: [&str; 2] = ["address", "topic0"];
+
+/// Build the Parquet writer properties for `opts`.
+pub(crate) fn writer_properties(opts: ParquetWriterOptions) -> Result<WriterProperties> {
+    let statistics = if opts.page_statistics {
+        EnabledStatistics::Page
+    } else {
+        EnabledStatistics::Chunk
+    };
+    let mut builder = WriterProperties::builder()
+        .set_compression(opts.compression.codec()?)
+        .set_statistics_enabled(statistics)
+        .set_dictionary_enabled(false);
+    if opts.dictionary_encoding {
+        for col in DICTIONARY_COLUMNS {
+            builder = builder.set_column_dictionary_enabled(ColumnPath::from(col), true);
+        }
+    }
+    Ok(builder.build())
+}
+
+/// Maximum size of a dictionary from [`train_zstd_dictionary`].
+pub const ZSTD_DICTIONARY_MAX_BYTES: usize = 16 * 1024;
+
+/// Train a Zstd dictionary on the string `columns` of `batches`, one
+/// sample per row (the row's values concatenated).
+///
+/// The archives themselves never use it: Parquet has no field for a codec
+/// dictionary and the `parquet` crate no per-column codec options, so a
+/// page compressed with one could not be read back.  It exists for the
+/// `zstd_dictionary` benchmark, which measures what it would save.
+///
+/// # Errors
+///
+/// Returns an error if a column is missing or not a string column, or if
+/// Zstd rejects the samples (too few or too small to train on).
+pub fn train_zstd_dictionary(batches: &[RecordBatch], columns: &[&str]) -> Result<Vec<u8>> {
+    let mut samples = Vec::new();
+    for batch in batches {
+        let arrays = columns
+            .iter()
+            .map(|name| column::<StringArray>(batch, name))
+            .collect::<Result<Vec<_>>>()?;
+        for row in 0..batch.num_rows() {
+            let mut sample = Vec::new();
+            for array in arrays.iter().filter(|a| a.is_valid(row)) {
+                sample.extend_from_slice(array.value(row).as_bytes());
+            }
+            samples.push(sample);
+        }
+    }
+    zstd::dict::from_samples(&samples, ZSTD_DICTIONARY_MAX_BYTES)
+        .context("training Zstd dictionary")
+}
+
 /// Write record batches to a Parquet file using Zstd compression.
 ///
-/// Uses atomic write (temp file + rename) to prevent corruption.
+/// Uses atomic write (temp file + rename) to prevent corruption; transient
+/// I/O errors are retried (see [`retry`]).
 ///
 /// # Errors
 ///
 /// Returns an error on I/O failure or if the Parquet writer rejects the data.
 pub fn write(path: &Path, batches: &[RecordBatch]) -> Result<()> {
+    write_with(path, batches, ParquetWriterOptions::default())
+}
+
+/// Like [`write`], with explicit [`ParquetWriterOptions`].
+///
+/// # Errors
+///
+/// Returns an error on I/O failure or if the Parquet writer rejects the
+/// data, and with [`ParquetWriterOptions::verify_writes`] if the file does
+/// not read back with the written row count.
+pub fn write_with(path: &Path, batches: &[RecordBatch], opts: ParquetWriterOptions) -> Result<()> {
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)?;
     }
 
     let tmp = path.with_extension("parquet.tmp");
-    let file =
-        std::fs::File::create(&tmp).with_context(|| format!("creating {}", tmp.display()))?;
+    let props = writer_properties(opts)?;
+    retry::io(path, opts.io_retries, || {
+        let file =
+            std::fs::File::create(&tmp).with_context(|| format!("creating {}", tmp.display()))?;
+
+        #[cfg(feature = "rayon")]
+        if opts.parallel_encode {
+            parallel::write(file, batches, props.clone())?;
+            return rename_into_place(&tmp, path);
+        }
+        let mut writer =
+            ArrowWriter::try_new(file, Arc::clone(&EVENT_SCHEMA), Some(props.clone()))?;
+        for batch in batches {
+            writer.write(batch)?;
+        }
+        writer.close()?;
 
-    let props = WriterProperties::builder()
-        .set_compression(parquet::basic::Compression::ZSTD(
-            parquet::basic::ZstdLevel::try_new(3).context("invalid zstd level")?,
-        ))
-        .build();
+        rename_into_place(&tmp, path)
+    })?;
+    if opts.verify_writes {
+        verify_written(path, batches.iter().map(RecordBatch::num_rows).sum())?;
+    }
+    Ok(())
+}
 
-    let mut writer = ArrowWriter::try_new(file, Arc::clone(&EVENT_SCHEMA), Some(props))?;
+/// Encode `batches` as one in-memory Parquet file with the default
+/// [`ParquetWriterOptions`], for outputs that upload it.
+///
+/// # Errors
+///
+/// Returns an error if the batches do not match the event schema.
+#[cfg(feature = "s3")]
+pub(crate) fn to_bytes(batches: &[RecordBatch]) -> Result<Vec<u8>> {
+    let props = writer_properties(ParquetWriterOptions::default())?;
+    let mut writer = ArrowWriter::try_new(Vec::new(), Arc::clone(&EVENT_SCHEMA), Some(props))?;
     for batch in batches {
         writer.write(batch)?;
     }
-    writer.close()?;
-
-    std::fs::rename(&tmp, path)
-        .with_context(|| format!("renaming {} → {}", tmp.display(), path.display()))?;
+    Ok(writer.into_inner()?)
+}
 
+/// Read back a file written by [`write_with`] and check its row count.
+fn verify_written(path: &Path, expected: usize) -> Result<()> {
+    let rows: usize = read(path)
+        .with_context(|| format!("verifying {}", path.display()))?
+        .iter()
+        .map(RecordBatch::num_rows)
+        .sum();
+    if rows != expected {
+        bail!(
+            "verifying {}: wrote {expected} rows but read back {rows}",
+            path.display()
+        );
+    }
+    tracing::debug!(path = %path.display(), rows, "verified written file");
     Ok(())
 }
+
+/// Atomically replace `path` with a batch built by
+/// [`pending_logs_to_batch`].
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write_pending(path: &Path, batch: &RecordBatch) -> Result<()> {
+    write_single(path, Arc::clone(&PENDING_SCHEMA), batch)
+}
+
+/// Atomically replace `path` with a batch built by [`merge_contracts`].
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn write_merged(path: &Path, batch: &RecordBatch) -> Result<()> {
+    write_single(path, batch.schema(), batch)
+}
+
+/// Atomically replace `path` with the batches built by
+/// [`decode_batch`](crate::decode::decode_batch), written one at a time
+/// with `schema`.  `batches` is called again if a write is retried.
+///
+/// Returns the number of rows written.
+///
+/// # Errors
+///
+/// Returns an error if a batch fails or the file cannot be written.
+pub fn write_decoded<I>(
+    path: &Path,
+    schema: &Arc<Schema>,
+    mut batches: impl FnMut() -> Result<I>,
+) -> Result<usize>
+where
+    I: Iterator<Item = Result<RecordBatch>>,
+{
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("parquet.tmp");
+    let opts = ParquetWriterOptions::default();
+    let props = writer_properties(opts)?;
+    retry::io(path, opts.io_retries, || {
+        let file =
+            std::fs::File::create(&tmp).with_context(|| format!("creating {}", tmp.display()))?;
+        let mut writer = ArrowWriter::try_new(file, Arc::clone(schema), Some(props.clone()))?;
+        let mut rows = 0;
+        for batch in batches()? {
+            let batch = batch?;
+            rows += batch.num_rows();
+            writer.write(&batch)?;
+        }
+        writer.close()?;
+        rename_into_place(&tmp, path)?;
+        Ok(rows)
+    })
+}
+
+/// Atomically replace `path` with `batch`, written with `schema`.
+fn write_single(path: &Path, schema: Arc<Schema>, batch: &RecordBatch) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("parquet.tmp");
+    let opts = ParquetWriterOptions::default();
+    let props = writer_properties(opts)?;
+    retry::io(path, opts.io_retries, || {
+        let file =
+            std::fs::File::create(&tmp).with_context(|| format!("creating {}", tmp.display()))?;
+        let mut writer = ArrowWriter::try_new(file, Arc::clone(&schema), Some(props.clone()))?;
+        writer.write(batch)?;
+        writer.close()?;
+        rename_into_place(&tmp, path)
+    })
+}
+
+/// Path of the `index`-th rotated file next to `base` (`identity.001.parquet`).
+#[must_use]
+pub fn rotated_path(base: &Path, index: u32) -> PathBuf {
+    base.with_extension(format!("{index:03}.parquet"))
+}
+
+/// Rotated files next to `base`, oldest first.
+///
+/// # Errors
+///
+/// Returns an error if a rotated file exists but its metadata cannot be read.
+pub fn rotated_files(base: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for index in 1.. {
+        let path = rotated_path(base, index);
+        if !path
+            .try_exists()
+            .with_context(|| format!("checking {}", path.display()))?
+        {
+            break;
+        }
+        files.push(path);
+    }
+    Ok(files)
+}
+
+/// Read every rotated file followed by the live file at `base`.
+///
+/// [`max_block_number`] over the result covers the whole history.
+///
+/// # Errors
+///
+/// Returns an error if any file cannot be read.
+pub fn read_rotated(base: &Path) -> Result<Vec<RecordBatch>> {
+    let mut batches = Vec::new();
+    for path in rotated_files(base)? {
+        batches.extend(read(&path)?);
+    }
+    batches.extend(read(base)?);
+    Ok(batches)
+}
+
+/// Stream the batches of `paths` in order, one at a time, upgraded like
+/// [`read`]'s.  Each file is opened when the previous one is exhausted.
+pub fn read_each(paths: &[PathBuf]) -> impl Iterator<Item = Result<RecordBatch>> + '_ {
+    paths.iter().flat_map(|path| {
+        let reader = std::fs::File::open(path)
+            .with_context(|| format!("opening {}", path.display()))
+            .and_then(|file| {
+                ParquetRecordBatchReaderBuilder::try_new(file)
+                    .with_context(|| format!("reading parquet header: {}", path.display()))?
+                    .build()
+                    .with_context(|| format!("building parquet reader: {}", path.display()))
+            });
+        let (batches, error) = match reader {
+            Ok(reader) => (Some(reader), None),
+            Err(e) => (None, Some(Err(e))),
+        };
+        batches
+            .into_iter()
+            .flatten()
+            .map(move |batch| {
+                batch
+                    .map_err(anyhow::Error::from)
+                    .and_then(upgrade)
+                    .with_context(|| format!("reading batches from {}", path.display()))
+            })
+            .chain(error)
+    })
+}
+
+/// Read the rows with `block_number >= from` from every rotated file and
+/// the live file at `base`.
+///
+/// Files and row groups whose footer statistics end below `from` are
+/// skipped without being decoded, so reading the tail of a long history
+/// stays cheap.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read or does not match the event
+/// schema.
+pub fn read_rotated_from(base: &Path, from: u64) -> Result<Vec<RecordBatch>> {
+    let q = EventQuery::new().from_block(from);
+    let mut batches = Vec::new();
+    for path in rotated_files(base)?.into_iter().chain([base.to_path_buf()]) {
+        if !path.exists() {
+            continue;
+        }
+        let file =
+            std::fs::File::open(&path).with_context(|| format!("opening {}", path.display()))?;
+        let metadata = ArrowReaderMetadata::load(&file, ArrowReaderOptions::default())
+            .with_context(|| format!("reading parquet footer: {}", path.display()))?;
+        let row_groups: Vec<usize> = metadata
+            .metadata()
+            .row_groups()
+            .iter()
+            .enumerate()
+            .filter(|(_, rg)| block_range(rg).is_none_or(|(_, hi)| hi >= from))
+            .map(|(i, _)| i)
+            .collect();
+        if row_groups.is_empty() {
+            continue;
+        }
+        let reader = ParquetRecordBatchReaderBuilder::new_with_metadata(file, metadata)
+            .with_row_groups(row_groups)
+            .build()
+            .with_context(|| format!("building parquet reader: {}", path.display()))?;
+        for batch in reader {
+            let batch = upgrade(batch.with_context(|| format!("reading {}", path.display()))?)?;
+            batches.push(
+                filter_record_batch(&batch, &q.mask(&batch)?)
+                    .with_context(|| format!("filtering {}", path.display()))?,
+            );
+        }
+    }
+    Ok(batches)
+}
+
+/// The partition files of `contract` in the chain directory `dir`
+/// (`{dir}/{key}/{contract}.parquet`, as written by [`PartitionWriter`]),
+/// sorted by partition key.
+///
+/// # Errors
+///
+/// Returns an error if `dir` exists but cannot be listed.
+pub fn partition_files(dir: &Path, contract: &str) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+    for entry in std::fs::read_dir(dir).with_context(|| format!("listing {}", dir.display()))? {
+        let entry = entry.with_context(|| format!("listing {}", dir.display()))?;
+        let path = entry.path().join(format!("{contract}.parquet"));
+        if entry.file_type()?.is_dir() && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Appends batches to a live Parquet file, rotating it once it would exceed
+/// a size limit.
+///
+/// On rotation the live file is renamed to the next [`rotated_path`] and a
+/// fresh live file is started, so no file grows much past `max_bytes`.
+#[derive(Debug)]
+pub struct RotatingWriter {
+    /// Path of the live file (e.g. `data/1/identity.parquet`).
+    pub base_path: PathBuf,
+    /// Size limit for a single file.
+    pub max_bytes: u64,
+    /// Number of rotated files so far; the next rotation uses
+    /// `current_index + 1`.
+    pub current_index: u32,
+    current: Vec<RecordBatch>,
+    opts: ParquetWriterOptions,
+}
+
+impl RotatingWriter {
+    /// Open the live file at `base_path`, loading its contents and counting
+    /// existing rotated files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the live file or rotated files cannot be read.
+    pub fn open(base_path: &Path, max_bytes: u64, opts: ParquetWriterOptions) -> Result<Self> {
+        let current_index =
+            u32::try_from(rotated_files(base_path)?.len()).context("too many rotated files")?;
+        Ok(Self {
+            base_path: base_path.to_path_buf(),
+            max_bytes,
+            current_index,
+            current: read(base_path)?,
+            opts,
+        })
+    }
+
+    /// Append `batch` to the live file, rotating first if the file would
+    /// exceed [`max_bytes`](Self::max_bytes).
+    ///
+    /// The new size is estimated from the batch's in-memory size, which
+    /// over-estimates the compressed on-disk size.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if rotating or writing fails.
+    pub fn write_batch(&mut self, batch: &RecordBatch) -> Result<()> {
+        let live = match std::fs::metadata(&self.base_path) {
+            Ok(m) => m.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => {
+                return Err(e).with_context(|| format!("stat {}", self.base_path.display()));
+            }
+        };
+        let added = u64::try_from(batch.get_array_memory_size()).unwrap_or(u64::MAX);
+        if live > 0 && live.saturating_add(added) > self.max_bytes {
+            self.rotate()?;
+        }
+        self.current.push(batch.clone());
+        write_with(&self.base_path, &self.current, self.opts)
+    }
+
+    /// Move the live file to the next rotated path and start a new one.
+    fn rotate(&mut self) -> Result<()> {
+        let next = self.current_index + 1;
+        let target = rotated_path(&self.base_path, next);
+        std::fs::rename(&self.base_path, &target).with_context(|| {
+            format!(
+                "rotating {} → {}",
+                self.base_path.display(),
+                target.display()
+            )
+        })?;
+        tracing::info!(from = %self.base_path.display(), to = %target.display(), "rotated parquet file");
+        self.current_index = next;
+        self.current.clear();
+        Ok(())
+    }
+}
+
+/// Atomically move a finished temp file to its final path.
+fn rename_into_place(tmp: &Path, path: &Path) -> Result<()> {
+    std::fs::rename(tmp, path)
+        .with_context(|| format!("renaming {} → {}", tmp.display(), path.display()))
+}
+
+/// Column-parallel encoding using the low-level [`ArrowColumnWriter`] API.
+///
+/// The rows are cut into row groups of at most
+/// [`WriterProperties::max_row_group_size`] rows, as [`ArrowWriter`] does.
+/// Within each row group every column is encoded independently on the
+/// rayon pool, then the finished chunks are appended to the file in schema
+/// order.
+///
+/// [`ArrowColumnWriter`]: parquet::arrow::arrow_writer::ArrowColumnWriter
+#[cfg(feature = "rayon")]
+mod parallel {
+    use std::fs::File;
+    use std::sync::Arc;
+
+    use anyhow::Result;
+    use arrow_array::RecordBatch;
+    use parquet::arrow::arrow_writer::{compute_leaves, get_column_writers};
+    use parquet::arrow::{ArrowSchemaConverter, add_encoded_arrow_schema_to_metadata};
+    use parquet::errors::ParquetError;
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use rayon::prelude::*;
+
+    use super::EVENT_SCHEMA;
+
+    /// Encode `batches` column-by-column in parallel and write them to `file`.
+    pub(super) fn write(
+        file: File,
+        batches: &[RecordBatch],
+        props: WriterProperties,
+    ) -> Result<()> {
+        let schema = &**EVENT_SCHEMA;
+        let parquet_schema = ArrowSchemaConverter::new().convert(schema)?;
+        let mut props = props;
+        add_encoded_arrow_schema_to_metadata(schema, &mut props);
+        let props = Arc::new(props);
+
+        let mut writer =
+            SerializedFileWriter::new(file, parquet_schema.root_schema_ptr(), Arc::clone(&props))?;
+        for group in row_groups(batches, props.max_row_group_size()) {
+            // The event schema is flat, so there is exactly one leaf writer per field.
+            let writers = get_column_writers(&parquet_schema, &props, &EVENT_SCHEMA)?;
+            let chunks = writers
+                .into_par_iter()
+                .zip(schema.fields().to_vec())
+                .enumerate()
+                .map(|(i, (mut writer, field))| {
+                    for batch in &group {
+                        for leaf in compute_leaves(&field, batch.column(i))? {
+                            writer.write(&leaf)?;
+                        }
+                    }
+                    writer.close()
+                })
+                .collect::<Result<Vec<_>, ParquetError>>()?;
+
+            let mut row_group = writer.next_row_group()?;
+            for chunk in chunks {
+                chunk.append_to_row_group(&mut row_group)?;
+            }
+            row_group.close()?;
+        }
+        writer.close()?;
+        Ok(())
+    }
+
+    /// Cut `batches` into consecutive groups of at most `max_rows` rows,
+    /// slicing batches that straddle a group boundary.
+    pub(super) fn row_groups(batches: &[RecordBatch], max_rows: usize) -> Vec<Vec<RecordBatch>> {
+        let max_rows = max_rows.max(1);
+        let mut groups = Vec::new();
+        let mut group: Vec<RecordBatch> = Vec::new();
+        let mut rows = 0;
+        for batch in batches {
+            let mut offset = 0;
+            while offset < batch.num_rows() {
+                let len = (max_rows - rows).min(batch.num_rows() - offset);
+                group.push(batch.slice(offset, len));
+                offset += len;
+                rows += len;
+                if rows == max_rows {
+                    groups.push(std::mem::take(&mut group));
+                    rows = 0;
+                }
+            }
+        }
+        if !group.is_empty() {
+            groups.push(group);
+        }
+        groups
+    }
+}
+
+/// Row order of archived events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EventOrder {
+    /// Globally sorted by `(block_number, log_index)`, i.e. on-chain order.
+    #[default]
+    OnChain,
+    /// Arrival order: whatever each `eth_getLogs` window returned,
+    /// concatenated.  Cheaper, but not guaranteed to be sorted.
+    Fetch,
+}
+
+impl std::str::FromStr for EventOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "onchain" => Ok(Self::OnChain),
+            "fetch" => Ok(Self::Fetch),
+            _ => anyhow::bail!("invalid event order `{s}`; expected onchain or fetch"),
+        }
+    }
+}
+
+/// The `(block_number, log_index)` of every row, in row order.
+fn onchain_keys(batches: &[RecordBatch]) -> Result<Vec<(u64, u32)>> {
+    let mut keys = Vec::new();
+    for batch in batches {
+        let blocks = column::<UInt64Array>(batch, "block_number")?.values();
+        let logs = column::<UInt32Array>(batch, "log_index")?.values();
+        keys.extend(blocks.iter().copied().zip(logs.iter().copied()));
+    }
+    Ok(keys)
+}
+
+/// `(block_number, tx_index, log_index)` of every row in `batches`.
+///
+/// # Errors
+///
+/// Returns an error if a batch does not match the event schema.
+pub(crate) fn log_keys(batches: &[RecordBatch]) -> Result<BTreeSet<(u64, u64, u64)>> {
+    let mut keys = BTreeSet::new();
+    for batch in batches {
+        let blocks = column::<UInt64Array>(batch, "block_number")?.values();
+        let txs = column::<UInt32Array>(batch, "tx_index")?.values();
+        let logs = column::<UInt32Array>(batch, "log_index")?.values();
+        for ((&block, &tx), &log) in blocks.iter().zip(txs.iter()).zip(logs.iter()) {
+            keys.insert((block, u64::from(tx), u64::from(log)));
+        }
+    }
+    Ok(keys)
+}
+
+/// Whether the rows of `batches` are in on-chain order.
+///
+/// # Errors
+///
+/// Returns an error if a batch does not match the event schema.
+pub fn is_onchain_sorted(batches: &[RecordBatch]) -> Result<bool> {
+    Ok(onchain_keys(batches)?.is_sorted())
+}
+
+/// Concatenate `batches` and sort the rows by `(block_number, log_index)`.
+///
+/// # Errors
+///
+/// Returns an error if a batch does not match the event schema.
+pub fn sort_onchain(batches: &[RecordBatch]) -> Result<RecordBatch> {
+    take_onchain(&concat_batches(&EVENT_SCHEMA, batches)?)
+}
+
+/// The rows of `batch`, which has at least the event schema's key columns,
+/// sorted by `(block_number, log_index)`.
+fn take_onchain(batch: &RecordBatch) -> Result<RecordBatch> {
+    let mut rows: Vec<_> = onchain_keys(std::slice::from_ref(batch))?
+        .into_iter()
+        .zip(0_u32..)
+        .collect();
+    rows.sort_unstable();
+    let indices = UInt32Array::from_iter_values(rows.into_iter().map(|(_, i)| i));
+    Ok(take_record_batch(batch, &indices)?)
+}
+
+/// Append a non-null `column` holding `value` in every row of `batch`.
+///
+/// # Errors
+///
+/// Returns an error if `batch` already has a column named `column`.
+pub fn tag_batch(batch: &RecordBatch, column: &str, value: &str) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    if schema.column_with_name(column).is_some() {
+        bail!("batch already has a `{column}` column");
+    }
+    let mut fields = schema.fields().to_vec();
+    fields.push(Arc::new(Field::new(column, DataType::Utf8, false)));
+    let mut columns = batch.columns().to_vec();
+    columns.push(Arc::new(StringArray::from_iter_values(
+        std::iter::repeat_n(value, batch.num_rows()),
+    )));
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone())),
+        columns,
+    )?)
+}
+
+/// Merge the batches of several contracts into one batch in on-chain order,
+/// with a `contract` column naming the contract of each row.
+///
+/// `contracts` pairs each contract name (`identity`, `reputation`) with its
+/// batches.
+///
+/// # Errors
+///
+/// Returns an error if a batch does not match the event schema.
+pub fn merge_contracts(contracts: &[(&str, Vec<RecordBatch>)]) -> Result<RecordBatch> {
+    let schema = tag_batch(&RecordBatch::new_empty(event_schema()), "contract", "")?.schema();
+    let mut tagged = Vec::new();
+    for (name, batches) in contracts {
+        for batch in batches {
+            tagged.push(tag_batch(batch, "contract", name)?);
+        }
+    }
+    take_onchain(&concat_batches(&schema, &tagged)?)
+}
+
+/// Directory layout for partitioned output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PartitionBy {
+    /// Single file per contract (no partitions).
+    #[default]
+    None,
+    /// One `YYYY-MM` directory per calendar month.
+    Month,
+    /// One `YYYY` directory per calendar year.
+    Year,
+}
+
+impl std::str::FromStr for PartitionBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Self::None),
+            "month" => Ok(Self::Month),
+            "year" => Ok(Self::Year),
+            _ => anyhow::bail!("invalid partition scheme `{s}`; expected month, year or none"),
+        }
+    }
+}
+
+impl PartitionBy {
+    /// Build a [`PartitionWriter`] for one contract of one chain, or `None`
+    /// when partitioning is disabled.
+    #[must_use]
+    pub fn writer(self, base_dir: &Path, chain_id: u64, contract: &str) -> Option<PartitionWriter> {
+        let key: Box<dyn Fn(u64) -> String + Send + Sync> = match self {
+            Self::None => return None,
+            Self::Month => Box::new(move |block| default_partition_key(block, chain_id)),
+            Self::Year => Box::new(move |block| {
+                let mut key = default_partition_key(block, chain_id);
+                key.truncate(4);
+                key
+            }),
+        };
+        Some(PartitionWriter::new(base_dir, contract, key))
+    }
+}
+
+/// Writes a contract's rows into `{base_dir}/{key}/{contract}.parquet`,
+/// where `key` is derived from each row's block number.
+pub struct PartitionWriter {
+    /// Directory that holds the partition subdirectories.
+    pub base_dir: PathBuf,
+    /// Contract file stem (`identity` or `reputation`).
+    pub contract: String,
+    /// Maps a block number to its partition key (e.g. `2026-01`).
+    pub block_to_key: Box<dyn Fn(u64) -> String + Send + Sync>,
+}
+
+impl std::fmt::Debug for PartitionWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PartitionWriter")
+            .field("base_dir", &self.base_dir)
+            .field("contract", &self.contract)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartitionWriter {
+    /// Create a partition writer with a custom key function.
+    #[must_use]
+    pub fn new(
+        base_dir: &Path,
+        contract: &str,
+        block_to_key: Box<dyn Fn(u64) -> String + Send + Sync>,
+    ) -> Self {
+        Self {
+            base_dir: base_dir.to_path_buf(),
+            contract: contract.to_owned(),
+            block_to_key,
+        }
+    }
+
+    /// Split `batches` by partition key and rewrite every partition file.
+    ///
+    /// `batches` must be the contract's full history so that each partition
+    /// file is complete.  Returns the number of partitions written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on I/O failure or if a batch does not match the
+    /// event schema.
+    pub fn write(&self, batches: &[RecordBatch]) -> Result<usize> {
+        let mut parts: BTreeMap<String, Vec<RecordBatch>> = BTreeMap::new();
+        for batch in batches {
+            let blocks = column::<UInt64Array>(batch, "block_number")?;
+            let mut rows: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+            for (i, bn) in blocks.values().iter().enumerate() {
+                let i = u32::try_from(i).context("batch exceeds u32::MAX rows")?;
+                rows.entry((self.block_to_key)(*bn)).or_default().push(i);
+            }
+            for (key, idx) in rows {
+                let part = take_record_batch(batch, &UInt32Array::from(idx))?;
+                parts.entry(key).or_default().push(part);
+            }
+        }
+
+        for (key, part) in &parts {
+            let path = self
+                .base_dir
+                .join(key)
+                .join(format!("{}.parquet", self.contract));
+            write(&path, part)?;
+        }
+        Ok(parts.len())
+    }
+}
+
+/// Approximate block-time anchors: `(chain_id, block, unix_ts, block_time_ms)`.
+///
+/// Only chains with fixed (or near-fixed) block times are listed, so that
+/// timestamps can be estimated without RPC calls.  Ethereum drifts slightly
+/// because of missed slots.
+const BLOCK_TIME_ANCHORS: &[(u64, u64, u64, u64)] = &[
+    (1, 15_537_394, 1_663_224_179, 12_000),
+    (10, 105_235_063, 1_686_068_903, 2_000),
+    (8453, 0, 1_686_789_347, 2_000),
+    (84532, 0, 1_695_768_288, 2_000),
+    (11_155_420, 0, 1_691_802_540, 2_000),
+];
+
+/// Estimate the `YYYY-MM` month of `block` on `chain_id` without RPC calls.
+///
+/// Uses [`BLOCK_TIME_ANCHORS`]; chains without an anchor map to `unknown`.
+#[must_use]
+pub fn default_partition_key(block: u64, chain_id: u64) -> String {
+    let Some(&(_, anchor, ts, ms)) = BLOCK_TIME_ANCHORS.iter().find(|a| a.0 == chain_id) else {
+        return "unknown".to_owned();
+    };
+    let ts = if block >= anchor {
+        ts.saturating_add((block - anchor).saturating_mul(ms) / 1000)
+    } else {
+        ts.saturating_sub((anchor - block).saturating_mul(ms) / 1000)
+    };
+    let (year, month) = year_month(ts);
+    format!("{year:04}-{month:02}")
+}
+
+/// Convert a Unix timestamp to a UTC `(year, month)` pair.
+///
+/// Days-to-civil conversion from Howard Hinnant's `chrono`-compatible
+/// date algorithms, restricted to non-negative timestamps.
+const fn year_month(ts: u64) -> (u64, u64) {
+    let z = ts / 86_400 + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month)
+}
+
+/// A single decoded row of the event schema.
+///
+/// Hex fields are stored exactly as written to Parquet (`0x`-prefixed,
+/// lowercase).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LogRow {
+    /// Block in which the event was emitted.
+    pub block_number: u64,
+    /// Transaction hash.
+    pub tx_hash: String,
+    /// Transaction position in the block.
+    pub tx_index: u32,
+    /// Log position in the block.
+    pub log_index: u32,
+    /// Emitting contract address.
+    pub address: String,
+    /// Event signature hash.
+    pub topic0: String,
+    /// First indexed parameter.
+    pub topic1: Option<String>,
+    /// Second indexed parameter.
+    pub topic2: Option<String>,
+    /// Third indexed parameter.
+    pub topic3: Option<String>,
+    /// ABI-encoded non-indexed parameters.
+    pub data: String,
+    /// Whether the log was removed due to a chain reorg.
+    pub removed: bool,
+    /// Sender's nonce of the transaction, once filled in by
+    /// [`enrich_with_nonces`].
+    #[serde(default)]
+    pub tx_nonce: Option<u64>,
+    /// Hash of the block the log was archived from.
+    #[serde(default)]
+    pub block_hash: Option<String>,
+}
+
+/// Multi-column filter for [`query`].
+///
+/// All set conditions must match (logical AND).  Within a list field
+/// (`addresses`, `topic0`, `topic1`) any entry may match (logical OR); an
+/// empty list matches everything.  Hex comparisons are case-insensitive.
+///
+/// ```rust,no_run
+/// # fn example() -> anyhow::Result<()> {
+/// use erc8004_events::parquet::{self, EventQuery};
+///
+/// let q = EventQuery::new()
+///     .from_block(41_663_783)
+///     .topic0("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef")
+///     .limit(1000);
+/// let rows = parquet::query("data/8453/identity.parquet".as_ref(), &q)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EventQuery {
+    /// Inclusive lower block bound.
+    pub from_block: Option<u64>,
+    /// Inclusive upper block bound.
+    pub to_block: Option<u64>,
+    /// Emitting contract addresses to accept.
+    pub addresses: Vec<String>,
+    /// Event signature hashes to accept.
+    pub topic0: Vec<String>,
+    /// First indexed parameter values to accept.
+    pub topic1: Vec<String>,
+    /// Accept only logs with this `removed` flag.
+    pub removed: Option<bool>,
+    /// Maximum number of rows to return.
+    pub limit: Option<usize>,
+}
+
+impl EventQuery {
+    /// Create an empty query that matches every row.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the inclusive lower block bound.
+    #[must_use]
+    pub const fn from_block(mut self, block: u64) -> Self {
+        self.from_block = Some(block);
+        self
+    }
+
+    /// Set the inclusive upper block bound.
+    #[must_use]
+    pub const fn to_block(mut self, block: u64) -> Self {
+        self.to_block = Some(block);
+        self
+    }
+
+    /// Add an accepted emitting contract address.
+    #[must_use]
+    pub fn address(mut self, address: impl Into<String>) -> Self {
+        self.addresses.push(address.into());
+        self
+    }
+
+    /// Add an accepted event signature hash.
+    #[must_use]
+    pub fn topic0(mut self, topic: impl Into<String>) -> Self {
+        self.topic0.push(topic.into());
+        self
+    }
+
+    /// Add an accepted first indexed parameter value.
+    #[must_use]
+    pub fn topic1(mut self, topic: impl Into<String>) -> Self {
+        self.topic1.push(topic.into());
+        self
+    }
+
+    /// Accept only logs whose `removed` flag equals `removed`.
+    #[must_use]
+    pub const fn removed(mut self, removed: bool) -> Self {
+        self.removed = Some(removed);
+        self
+    }
+
+    /// Cap the number of returned rows.
+    #[must_use]
+    pub const fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Build the boolean selection mask for one batch.
+    fn mask(&self, batch: &RecordBatch) -> Result<BooleanArray> {
+        let blocks = column::<UInt64Array>(batch, "block_number")?;
+        let addresses = column::<StringArray>(batch, "address")?;
+        let topic0 = column::<StringArray>(batch, "topic0")?;
+        let topic1 = column::<StringArray>(batch, "topic1")?;
+        let removed = column::<BooleanArray>(batch, "removed")?;
+
+        let mask: Vec<bool> = (0..batch.num_rows())
+            .map(|i| {
+                let bn = blocks.value(i);
+                self.from_block.is_none_or(|f| bn >= f)
+                    && self.to_block.is_none_or(|t| bn <= t)
+                    && any_of(&self.addresses, Some(addresses.value(i)))
+                    && any_of(&self.topic0, Some(topic0.value(i)))
+                    && any_of(&self.topic1, topic1.is_valid(i).then(|| topic1.value(i)))
+                    && self.removed.is_none_or(|r| removed.value(i) == r)
+            })
+            .collect();
+        Ok(BooleanArray::from(mask))
+    }
+}
+
+/// `true` if `wanted` is empty or contains `value` (case-insensitive).
+fn any_of(wanted: &[String], value: Option<&str>) -> bool {
+    wanted.is_empty() || value.is_some_and(|v| wanted.iter().any(|w| w.eq_ignore_ascii_case(v)))
+}
+
+/// Look up a column by name and downcast it to its concrete array type.
+pub(crate) fn column<'a, T: Array + 'static>(batch: &'a RecordBatch, name: &str) -> Result<&'a T> {
+    batch
+        .column_by_name(name)
+        .and_then(|c| c.as_any().downcast_ref::<T>())
+        .with_context(|| format!("missing or mistyped column `{name}`"))
+}
+
+/// Convert a record batch in [`event_schema`] layout into rows.
+///
+/// # Errors
+///
+/// Returns an error if a required column is missing or has the wrong type.
+pub fn batch_to_rows(batch: &RecordBatch) -> Result<Vec<LogRow>> {
+    let block_numbers = column::<UInt64Array>(batch, "block_number")?;
+    let tx_hashes = column::<StringArray>(batch, "tx_hash")?;
+    let tx_indices = column::<UInt32Array>(batch, "tx_index")?;
+    let log_indices = column::<UInt32Array>(batch, "log_index")?;
+    let addresses = column::<StringArray>(batch, "address")?;
+    let topic0s = column::<StringArray>(batch, "topic0")?;
+    let topic1s = column::<StringArray>(batch, "topic1")?;
+    let topic2s = column::<StringArray>(batch, "topic2")?;
+    let topic3s = column::<StringArray>(batch, "topic3")?;
+    let datas = column::<StringArray>(batch, "data")?;
+    let removeds = column::<BooleanArray>(batch, "removed")?;
+    let nonces = batch
+        .column_by_name("tx_nonce")
+        .and_then(|c| c.as_any().downcast_ref::<UInt64Array>());
+    let block_hashes = batch
+        .column_by_name("block_hash")
+        .and_then(|c| c.as_any().downcast_ref::<StringArray>());
+
+    let opt = |col: &StringArray, i: usize| col.is_valid(i).then(|| col.value(i).to_owned());
+    Ok((0..batch.num_rows())
+        .map(|i| LogRow {
+            block_number: block_numbers.value(i),
+            tx_hash: tx_hashes.value(i).to_owned(),
+            tx_index: tx_indices.value(i),
+            log_index: log_indices.value(i),
+            address: addresses.value(i).to_owned(),
+            topic0: topic0s.value(i).to_owned(),
+            topic1: opt(topic1s, i),
+            topic2: opt(topic2s, i),
+            topic3: opt(topic3s, i),
+            data: datas.value(i).to_owned(),
+            removed: removeds.value(i),
+            tx_nonce: nonces.and_then(|c| c.is_valid(i).then(|| c.value(i))),
+            block_hash: block_hashes.and_then(|c| opt(c, i)),
+        })
+        .collect())
+}
+
+/// Differences between two archives of the same contract, found by
+/// [`diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DiffReport {
+    /// Rows in the first archive.
+    pub rows_before: usize,
+    /// Rows in the second archive.
+    pub rows_after: usize,
+    /// Rows whose key is only in the first archive.
+    pub only_before: usize,
+    /// Rows whose key is only in the second archive.
+    pub only_after: usize,
+    /// Rows whose key is in both archives with different values.
+    pub changed: usize,
+    /// Every difference, in `(block_number, tx_index, log_index)` order.
+    /// Callers may truncate it; the counts above stay complete.
+    pub differences: Vec<RowDiff>,
+}
+
+impl DiffReport {
+    /// Whether both archives hold the same rows, judged by the counts so
+    /// that a truncated [`differences`](Self::differences) list does not
+    /// hide any.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.rows_before == self.rows_after
+            && self.only_before + self.only_after + self.changed == 0
+    }
+}
+
+/// One difference listed in a [`DiffReport`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RowDiff {
+    /// A row only in the first archive.
+    OnlyBefore {
+        /// The row.
+        row: LogRow,
+    },
+    /// A row only in the second archive.
+    OnlyAfter {
+        /// The row.
+        row: LogRow,
+    },
+    /// A row whose key is in both archives with different values.
+    Changed {
+        /// The columns that differ.
+        fields: Vec<&'static str>,
+        /// The row in the first archive.
+        before: LogRow,
+        /// The row in the second archive.
+        after: LogRow,
+    },
+}
+
+/// Compare two archives of the same contract row by row, matching rows on
+/// `(block_number, tx_index, log_index)` regardless of their order.
+///
+/// A key stored more than once on one side is compared by its last row;
+/// the duplicates only show in the row counts.  A `block_hash` that is
+/// null on one side is not a difference: archives written before the
+/// column existed would otherwise differ in every row.
+///
+/// # Errors
+///
+/// Returns an error if a batch does not match [`event_schema`].
+pub fn diff(a: &[RecordBatch], b: &[RecordBatch]) -> Result<DiffReport> {
+    let keyed = |batches: &[RecordBatch]| -> Result<(usize, BTreeMap<(u64, u32, u32), LogRow>)> {
+        let mut rows = 0;
+        let mut map = BTreeMap::new();
+        for batch in batches {
+            for row in batch_to_rows(batch)? {
+                rows += 1;
+                map.insert((row.block_number, row.tx_index, row.log_index), row);
+            }
+        }
+        Ok((rows, map))
+    };
+    let (rows_before, mut before) = keyed(a)?;
+    let (rows_after, after) = keyed(b)?;
+
+    let mut report = DiffReport {
+        rows_before,
+        rows_after,
+        ..DiffReport::default()
+    };
+    let mut differences = BTreeMap::new();
+    for (key, row) in after {
+        match before.remove(&key) {
+            None => {
+                report.only_after += 1;
+                differences.insert(key, RowDiff::OnlyAfter { row });
+            }
+            Some(old) => {
+                let fields = changed_fields(&old, &row);
+                if !fields.is_empty() {
+                    report.changed += 1;
+                    differences.insert(
+                        key,
+                        RowDiff::Changed {
+                            fields,
+                            before: old,
+                            after: row,
+                        },
+                    );
+                }
+            }
+        }
+    }
+    report.only_before = before.len();
+    differences.extend(
+        before
+            .into_iter()
+            .map(|(key, row)| (key, RowDiff::OnlyBefore { row })),
+    );
+    report.differences = differences.into_values().collect();
+    Ok(report)
+}
+
+/// Names of the columns in which `a` and `b` differ, ignoring a null
+/// `block_hash`.
+fn changed_fields(a: &LogRow, b: &LogRow) -> Vec<&'static str> {
+    [
+        ("tx_hash", a.tx_hash != b.tx_hash),
+        ("address", a.address != b.address),
+        ("topic0", a.topic0 != b.topic0),
+        ("topic1", a.topic1 != b.topic1),
+        ("topic2", a.topic2 != b.topic2),
+        ("topic3", a.topic3 != b.topic3),
+        ("data", a.data != b.data),
+        ("removed", a.removed != b.removed),
+        ("tx_nonce", a.tx_nonce != b.tx_nonce),
+        (
+            "block_hash",
+            a.block_hash.is_some() && b.block_hash.is_some() && a.block_hash != b.block_hash,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, differs)| differs.then_some(name))
+    .collect()
+}
+
+/// Transactions looked up concurrently by [`enrich_with_nonces`].
+const NONCE_CONCURRENCY: usize = 16;
+
+/// Fill in the null `tx_nonce` cells of the Parquet file at `path` from
+/// `provider` and rewrite the file with `opts`.
+///
+/// Each distinct transaction with a missing nonce is looked up once with
+/// `eth_getTransactionByHash`.  Transactions unknown to the RPC keep a null
+/// nonce, so a later run against another RPC can fill them in.  The file
+/// is left untouched when no nonce is missing.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or written, does not match
+/// the event schema, or a lookup fails.
+pub async fn enrich_with_nonces<P: Provider>(
+    path: &Path,
+    provider: &P,
+    opts: ParquetWriterOptions,
+) -> Result<()> {
+    let batches = read(path)?;
+    let mut missing = BTreeSet::new();
+    for batch in &batches {
+        let hashes = column::<StringArray>(batch, "tx_hash")?;
+        let nonces = column::<UInt64Array>(batch, "tx_nonce")?;
+        missing.extend(
+            (0..batch.num_rows())
+                .filter(|&i| nonces.is_null(i))
+                .map(|i| hashes.value(i).to_owned()),
+        );
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let lookups: Vec<Option<(String, u64)>> = futures::stream::iter(&missing)
+        .map(|hash| async move {
+            let tx_hash: B256 = hash
+                .parse()
+                .with_context(|| format!("invalid transaction hash {hash}"))?;
+            let tx = provider
+                .get_transaction_by_hash(tx_hash)
+                .await
+                .with_context(|| format!("eth_getTransactionByHash {hash}"))?;
+            anyhow::Ok(tx.map(|tx| (hash.clone(), tx.nonce())))
+        })
+        .buffer_unordered(NONCE_CONCURRENCY)
+        .try_collect()
+        .await?;
+    let found: BTreeMap<String, u64> = lookups.into_iter().flatten().collect();
+
+    let enriched = batches
+        .iter()
+        .map(|batch| {
+            let hashes = column::<StringArray>(batch, "tx_hash")?;
+            let nonces = column::<UInt64Array>(batch, "tx_nonce")?;
+            let filled: UInt64Array = (0..batch.num_rows())
+                .map(|i| {
+                    if nonces.is_valid(i) {
+                        Some(nonces.value(i))
+                    } else {
+                        found.get(hashes.value(i)).copied()
+                    }
+                })
+                .collect();
+            let schema = batch.schema();
+            let (idx, _) = schema
+                .column_with_name("tx_nonce")
+                .context("missing column `tx_nonce`")?;
+            let mut columns = batch.columns().to_vec();
+            if let Some(col) = columns.get_mut(idx) {
+                *col = Arc::new(filled);
+            }
+            Ok(RecordBatch::try_new(schema, columns)?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    write_with(path, &enriched, opts)?;
+    tracing::info!(
+        path = %path.display(),
+        transactions = missing.len(),
+        filled = found.len(),
+        "filled in transaction nonces"
+    );
+    Ok(())
+}
+
+/// Blocks looked up concurrently by [`detect_reorg`].
+const REORG_CONCURRENCY: usize = 16;
+
+/// How far below the newest archived block [`detect_reorg`] looks.  Blocks
+/// deeper than this are final on every supported chain, and leaving them
+/// out keeps the check to a handful of requests per sync.
+pub const REORG_DEPTH: u64 = 256;
+
+/// Compare the archived `block_hash` of the blocks in the Parquet file at
+/// `path` with the current canonical hashes from `provider`, and return the
+/// blocks that have been reorged since they were archived, sorted.
+///
+/// Only blocks within [`REORG_DEPTH`] of the file's newest block are
+/// checked, and only the `block_number` and `block_hash` columns of the row
+/// groups that reach them are decoded.  Rows without a hash (written before
+/// the column existed) are skipped, so an old archive reports nothing.  A
+/// block the RPC no longer knows counts as reorged.  Each lookup first
+/// takes a token from `bucket`, if given.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or does not match the event
+/// schema, or a lookup fails.
+pub async fn detect_reorg<P: Provider>(
+    path: &Path,
+    provider: &P,
+    bucket: Option<&TokenBucket>,
+) -> Result<Vec<u64>> {
+    let recent = recent_block_hashes(path)?;
+    let reorged: Vec<Option<u64>> = futures::stream::iter(&recent)
+        .map(|(&number, hash)| async move {
+            if let Some(bucket) = bucket {
+                bucket.acquire().await;
+            }
+            let block = provider
+                .get_block_by_number(number.into())
+                .await
+                .with_context(|| format!("eth_getBlockByNumber {number}"))?;
+            let canonical = block.map(|b| format!("{:#x}", b.header.hash));
+            anyhow::Ok((canonical.as_ref() != Some(hash)).then_some(number))
+        })
+        .buffer_unordered(REORG_CONCURRENCY)
+        .try_collect()
+        .await?;
+    let mut reorged: Vec<u64> = reorged.into_iter().flatten().collect();
+    reorged.sort_unstable();
+    Ok(reorged)
+}
+
+/// The archived `block_hash` of every block within [`REORG_DEPTH`] of the
+/// newest hashed block of the Parquet file at `path`.
+///
+/// Row groups whose footer statistics end below the window are skipped.
+fn recent_block_hashes(path: &Path) -> Result<BTreeMap<u64, String>> {
+    let file = std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let metadata = ArrowReaderMetadata::load(&file, ArrowReaderOptions::default())
+        .with_context(|| format!("reading parquet footer: {}", path.display()))?;
+    let mut archived = BTreeMap::new();
+    if metadata.schema().column_with_name("block_hash").is_none() {
+        return Ok(archived);
+    }
+    let row_groups = metadata.metadata().row_groups();
+    let floor = row_groups
+        .iter()
+        .filter_map(block_range)
+        .map(|(_, hi)| hi)
+        .max()
+        .map_or(0, |newest| newest.saturating_sub(REORG_DEPTH));
+    let recent: Vec<usize> = row_groups
+        .iter()
+        .enumerate()
+        .filter(|(_, rg)| block_range(rg).is_none_or(|(_, hi)| hi >= floor))
+        .map(|(i, _)| i)
+        .collect();
+    let mask = ProjectionMask::columns(metadata.parquet_schema(), ["block_number", "block_hash"]);
+    let reader = ParquetRecordBatchReaderBuilder::new_with_metadata(file, metadata)
+        .with_row_groups(recent)
+        .with_projection(mask)
+        .build()
+        .with_context(|| format!("building parquet reader: {}", path.display()))?;
+    for batch in reader {
+        let batch = batch.with_context(|| format!("reading {}", path.display()))?;
+        let blocks = column::<UInt64Array>(&batch, "block_number")?;
+        let hashes = column::<StringArray>(&batch, "block_hash")?;
+        archived.extend(
+            (0..batch.num_rows())
+                .filter(|&i| hashes.is_valid(i))
+                .map(|i| (blocks.value(i), hashes.value(i).to_owned())),
+        );
+    }
+    let Some(&newest) = archived.keys().next_back() else {
+        return Ok(archived);
+    };
+    Ok(archived.split_off(&newest.saturating_sub(REORG_DEPTH)))
+}
+
+/// Read a Parquet file and return the rows matching `q`.
+///
+/// Batches are read and filtered one at a time with a boolean mask, and no
+/// further batches are read once `q.limit` rows have been collected.  Returns an empty vec if the
+/// file does not exist.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or does not match the
+/// event schema.
+pub fn query(path: &Path, q: &EventQuery) -> Result<Vec<LogRow>> {
+    let mut rows = Vec::new();
+    if !path.exists() {
+        return Ok(rows);
+    }
+    let file = std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .with_context(|| format!("reading parquet header: {}", path.display()))?
+        .build()
+        .with_context(|| format!("building parquet reader: {}", path.display()))?;
+    for batch in reader {
+        let batch = upgrade(batch.with_context(|| format!("reading {}", path.display()))?)?;
+        let filtered = filter_record_batch(&batch, &q.mask(&batch)?)
+            .with_context(|| format!("filtering {}", path.display()))?;
+        rows.extend(batch_to_rows(&filtered)?);
+        if q.limit.is_some_and(|n| rows.len() >= n) {
+            break;
+        }
+    }
+    if let Some(n) = q.limit {
+        rows.truncate(n);
+    }
+    Ok(rows)
+}
+
+impl LogRow {
+    /// Convert the row back into the [`Log`] an `eth_getLogs` call returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a hex column does not parse.
+    pub fn to_rpc_log(&self) -> Result<Log> {
+        let hash = |s: &str| {
+            s.parse::<B256>()
+                .with_context(|| format!("invalid hash `{s}`"))
+        };
+        let topics = [
+            Some(&self.topic0),
+            self.topic1.as_ref(),
+            self.topic2.as_ref(),
+            self.topic3.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .map(hash)
+        .collect::<Result<Vec<_>>>()?;
+        Ok(Log {
+            inner: alloy::primitives::Log {
+                address: self
+                    .address
+                    .parse()
+                    .with_context(|| format!("invalid address `{}`", self.address))?,
+                data: alloy::primitives::LogData::new_unchecked(
+                    topics,
+                    self.data
+                        .parse()
+                        .with_context(|| format!("invalid data `{}`", self.data))?,
+                ),
+            },
+            block_hash: self.block_hash.as_deref().map(hash).transpose()?,
+            block_number: Some(self.block_number),
+            block_timestamp: None,
+            transaction_hash: Some(hash(&self.tx_hash)?),
+            transaction_index: Some(u64::from(self.tx_index)),
+            log_index: Some(u64::from(self.log_index)),
+            removed: self.removed,
+        })
+    }
+}
+
+/// Layout of [`write_eth_logs`] output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EthLogsFormat {
+    /// A single JSON array, like an `eth_getLogs` `result`.
+    #[default]
+    Json,
+    /// One log object per line.
+    Ndjson,
+}
+
+impl std::str::FromStr for EthLogsFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "json" => Ok(Self::Json),
+            "ndjson" => Ok(Self::Ndjson),
+            other => bail!("unknown format `{other}` (expected json or ndjson)"),
+        }
+    }
+}
+
+/// Write every row of the Parquet file at `path` to `writer` as a JSON
+/// array of `eth_getLogs` log objects.
+///
+/// Returns the number of logs written.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, a row does not convert or
+/// the writer fails.
+pub fn to_eth_logs_json(path: &Path, writer: &mut dyn std::io::Write) -> Result<usize> {
+    write_eth_logs(
+        std::slice::from_ref(&path.to_path_buf()),
+        EthLogsFormat::Json,
+        writer,
+    )
+}
+
+/// Write the rows of `paths`, in order, to `writer` as `eth_getLogs` log
+/// objects (see [`LogRow::to_rpc_log`]).
+///
+/// Files are streamed batch by batch, so memory use does not grow with the
+/// archive.  Returns the number of logs written.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read, a row does not convert or
+/// the writer fails.
+pub fn write_eth_logs(
+    paths: &[PathBuf],
+    format: EthLogsFormat,
+    writer: &mut dyn std::io::Write,
+) -> Result<usize> {
+    let mut count = 0;
+    if format == EthLogsFormat::Json {
+        writer.write_all(b"[")?;
+    }
+    for path in paths {
+        let file =
+            std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+        let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+            .with_context(|| format!("reading parquet header: {}", path.display()))?
+            .build()
+            .with_context(|| format!("building parquet reader: {}", path.display()))?;
+        for batch in reader {
+            let batch = upgrade(batch.with_context(|| format!("reading {}", path.display()))?)?;
+            for row in batch_to_rows(&batch)? {
+                if format == EthLogsFormat::Json && count > 0 {
+                    writer.write_all(b",")?;
+                }
+                serde_json::to_writer(&mut *writer, &row.to_rpc_log()?)?;
+                if format == EthLogsFormat::Ndjson {
+                    writer.write_all(b"\n")?;
+                }
+                count += 1;
+            }
+        }
+    }
+    if format == EthLogsFormat::Json {
+        writer.write_all(b"]")?;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use parquet::basic::Encoding;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    use super::*;
+    use crate::testutil::{self, TempDir};
+
+    fn sample_batch() -> RecordBatch {
+        let n = 64;
+        let text =
+            |f: &dyn Fn(usize) -> String| StringArray::from((0..n).map(f).collect::<Vec<_>>());
+        let opt = |v: Option<&str>| StringArray::from(vec![v; n]);
+        RecordBatch::try_new(
+            event_schema(),
+            vec![
+                Arc::new(UInt64Array::from((0..n as u64).collect::<Vec<_>>())),
+                Arc::new(text(&|i| format!("0x{i:064x}"))),
+                Arc::new(UInt32Array::from(vec![0; n])),
+                Arc::new(UInt32Array::from(vec![0; n])),
+                Arc::new(text(&|_| {
+                    "0x8004a169fb4a3325136eb29fa0ceb6d2e539a432".to_owned()
+                })),
+                Arc::new(text(&|i| format!("0x{:064x}", i % 2))),
+                Arc::new(opt(None)),
+                Arc::new(opt(None)),
+                Arc::new(opt(None)),
+                Arc::new(text(&|i| format!("0x{i:x}"))),
+                Arc::new(BooleanArray::from(vec![false; n])),
+                Arc::new(UInt64Array::from(vec![None::<u64>; n])),
+                Arc::new(opt(None)),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_split_batches() {
+        assert_eq!(split_batches(&[], 10).unwrap().0.len(), 0);
+        let batches = [sample_batch(), sample_batch()];
+
+        let (before, after) = split_batches(&batches, 40).unwrap();
+        let rows = |b: &[RecordBatch]| b.iter().map(RecordBatch::num_rows).sum::<usize>();
+        assert_eq!(rows(&before), 80);
+        assert_eq!(rows(&after), 48);
+        assert_eq!(max_block_number(&before), Some(39));
+
+        let (before, after) = split_batches(&batches, 0).unwrap();
+        assert!(before.is_empty());
+        assert_eq!(rows(&after), 128);
+    }
+
+    #[test]
+    fn test_dictionary_encoding_only_on_repetitive_columns() {
+        let dir = TempDir::new("dict");
+        let path = dir.join("identity.parquet");
+        let opts = ParquetWriterOptions {
+            parallel_encode: false,
+            ..Default::default()
+        };
+        write_with(&path, &[sample_batch()], opts).unwrap();
+
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let row_group = reader.metadata().row_group(0);
+        for col in row_group.columns() {
+            let name = col.column_path().string();
+            let dict = col
+                .encodings()
+                .any(|e| matches!(e, Encoding::RLE_DICTIONARY | Encoding::PLAIN_DICTIONARY));
+            assert_eq!(
+                dict,
+                DICTIONARY_COLUMNS.contains(&name.as_str()),
+                "unexpected dictionary encoding state for `{name}`"
+            );
+        }
+    }
+
+    #[test]
+    fn test_dictionary_encoding_shrinks_files() {
+        let dir = TempDir::new("dict-size");
+        let size = |dictionary_encoding| {
+            let path = dir.join(format!("{dictionary_encoding}.parquet"));
+            // Uncompressed, so the codec does not hide the difference.
+            let opts = ParquetWriterOptions {
+                compression: Compression::None,
+                dictionary_encoding,
+                parallel_encode: false,
+                ..Default::default()
+            };
+            write_with(&path, &[sample_batch(), sample_batch()], opts).unwrap();
+            std::fs::metadata(&path).unwrap().len()
+        };
+        let (with, without) = (size(true), size(false));
+        // 128 plain copies of the 42-byte address and 66-byte topic0
+        // against one dictionary entry each.
+        assert!(
+            with + 10_000 < without,
+            "{with} bytes with dictionaries, {without} without"
+        );
+    }
+
+    #[test]
+    fn test_verify_writes_checks_row_count() {
+        let dir = TempDir::new("verify");
+        let path = dir.join("identity.parquet");
+        let opts = ParquetWriterOptions {
+            verify_writes: true,
+            ..Default::default()
+        };
+        let batch = sample_batch();
+        write_with(&path, &[batch.clone()], opts).unwrap();
+
+        let err = verify_written(&path, batch.num_rows() + 1).unwrap_err();
+        assert!(err.to_string().contains("read back"));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_row_groups_respect_max_rows() {
+        let batch = sample_batch();
+        let groups = parallel::row_groups(&[batch.clone(), batch.clone(), batch], 50);
+        let sizes: Vec<usize> = groups
+            .iter()
+            .map(|g| g.iter().map(RecordBatch::num_rows).sum())
+            .collect();
+        assert_eq!(sizes, [50, 50, 50, 42]);
+    }
+
+    #[test]
+    fn test_write_with_each_compression() {
+        for (name, codec) in [
+            (
+                "zstd",
+                parquet::basic::Compression::ZSTD(Default::default()),
+            ),
+            ("lz4", parquet::basic::Compression::LZ4_RAW),
+            ("snappy", parquet::basic::Compression::SNAPPY),
+            ("none", parquet::basic::Compression::UNCOMPRESSED),
+        ] {
+            let dir = TempDir::new(&format!("codec-{name}"));
+            let path = dir.join("identity.parquet");
+            let opts = ParquetWriterOptions {
+                parallel_encode: false,
+                compression: name.parse().unwrap(),
+                ..Default::default()
+            };
+            write_with(&path, &[sample_batch()], opts).unwrap();
+
+            let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+            let column = reader.metadata().row_group(0).column(0);
+            assert_eq!(
+                std::mem::discriminant(&column.compression()),
+                std::mem::discriminant(&codec),
+                "{name}"
+            );
+            assert_eq!(max_block_number(&read(&path).unwrap()), Some(63));
+        }
+        assert!("gzip".parse::<Compression>().is_err());
+    }
+
+    #[test]
+    fn test_eth_logs_json_round_trips_logs() {
+        use alloy::primitives::{Address, Bytes, LogData};
+
+        let log = |block: u64, topics: Vec<B256>| {
+            testutil::log(block)
+                .address(Address::repeat_byte(0x80))
+                .data(LogData::new_unchecked(
+                    topics,
+                    Bytes::from_static(b"\x01\x02"),
+                ))
+                .block_hash(B256::repeat_byte(0xbb))
+                .tx_hash(B256::repeat_byte(0xaa))
+                .tx_index(3)
+                .log_index(7)
+                .build()
+        };
+        let logs = vec![
+            log(10, vec![B256::ZERO]),
+            log(
+                11,
+                vec![B256::ZERO, B256::repeat_byte(1), B256::repeat_byte(2)],
+            ),
+        ];
+        let dir = TempDir::new("eth-logs");
+        let path = dir.join("identity.parquet");
+        write(&path, &[logs_to_batch(&logs).unwrap().0]).unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(to_eth_logs_json(&path, &mut out).unwrap(), 2);
+        assert_eq!(serde_json::from_slice::<Vec<Log>>(&out).unwrap(), logs);
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json[1]["topics"].as_array().unwrap().len(), 3);
+        assert_eq!(json[0]["blockNumber"], "0xa");
+
+        let mut out = Vec::new();
+        let paths = [path.clone(), path.clone()];
+        assert_eq!(
+            write_eth_logs(&paths, EthLogsFormat::Ndjson, &mut out).unwrap(),
+            4
+        );
+        let lines: Vec<Log> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines.get(3), logs.get(1));
+    }
+
+    #[test]
+    fn test_diff_matches_rows_by_key_in_any_order() {
+        use alloy::primitives::{Bytes, LogData};
+
+        let log = |block: u64, data: &'static [u8]| {
+            testutil::log(block)
+                .data(LogData::new_unchecked(
+                    vec![B256::ZERO],
+                    Bytes::from_static(data),
+                ))
+                .build()
+        };
+        let (before, _) = logs_to_batch(&[log(10, b""), log(11, b""), log(12, b"")]).unwrap();
+        let (after, _) = logs_to_batch(&[log(12, b""), log(11, b"\x01"), log(13, b"")]).unwrap();
+
+        assert!(
+            diff(&[before.clone()], &[before.clone()])
+                .unwrap()
+                .is_empty()
+        );
+
+        let report = diff(&[before], &[after]).unwrap();
+        assert_eq!(
+            (
+                report.rows_before,
+                report.rows_after,
+                report.only_before,
+                report.only_after,
+                report.changed
+            ),
+            (3, 3, 1, 1, 1)
+        );
+        let summary: Vec<_> = report
+            .differences
+            .iter()
+            .map(|d| match d {
+                RowDiff::OnlyBefore { row } => (row.block_number, "only_before", Vec::new()),
+                RowDiff::OnlyAfter { row } => (row.block_number, "only_after", Vec::new()),
+                RowDiff::Changed { fields, after, .. } => {
+                    (after.block_number, "changed", fields.clone())
+                }
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (10, "only_before", Vec::new()),
+                (11, "changed", vec!["data"]),
+                (13, "only_after", Vec::new()),
+            ]
+        );
+
+        let mut truncated = report;
+        truncated.differences.clear();
+        assert!(!truncated.is_empty());
+
+        // Rows archived before `block_hash` existed match their re-synced
+        // copies; two different hashes do not.
+        let hashed = |hash: u8| {
+            let log = testutil::log(10)
+                .block_hash(B256::repeat_byte(hash))
+                .build();
+            logs_to_batch(&[log]).unwrap().0
+        };
+        let (unhashed, _) = logs_to_batch(&[testutil::log(10).build()]).unwrap();
+        assert!(diff(&[unhashed], &[hashed(1)]).unwrap().is_empty());
+        let report = diff(&[hashed(1)], &[hashed(2)]).unwrap();
+        assert!(matches!(
+            report.differences.as_slice(),
+            [RowDiff::Changed { fields, .. }] if fields == &["block_hash"]
+        ));
+    }
+
+    #[test]
+    fn test_logs_to_batch_drops_intra_window_duplicates() {
+        let log = |block: u64, log_index: u64| testutil::log(block).log_index(log_index).build();
+        let response = [log(10, 0), log(10, 1), log(10, 0), log(11, 0), log(10, 1)];
+
+        let (batch, n) = logs_to_batch(&response).unwrap();
+        assert_eq!(n, 3);
+        assert_eq!(
+            onchain_keys(&[batch]).unwrap(),
+            vec![(10, 0), (10, 1), (11, 0)]
+        );
+    }
+
+    #[test]
+    fn test_pending_logs_go_to_nullable_schema() {
+        let log = |block: Option<u64>, log_index: u64| Log {
+            block_number: block,
+            ..testutil::log(0).log_index(log_index).build()
+        };
+        let logs = [log(Some(10), 0), log(None, 1), log(None, 2)];
+
+        let (confirmed, n) = logs_to_batch(&logs).unwrap();
+        assert_eq!(n, 1);
+        assert_eq!(confirmed.schema(), event_schema());
+
+        let (pending, n) = pending_logs_to_batch(&logs).unwrap();
+        assert_eq!(n, 2);
+        assert_eq!(pending.column(0).null_count(), 2);
+
+        let dir = TempDir::new("pending");
+        let path = dir.join("identity.parquet");
+        write_pending(&path, &pending).unwrap();
+        let batches = read(&path).unwrap();
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 2);
+    }
+
+    #[test]
+    fn test_files_without_nonces_read_as_null() {
+        let batch = sample_batch();
+        let old_schema = Arc::new(Schema::new(
+            event_schema()
+                .fields()
+                .iter()
+                .filter(|f| ADDED_COLUMNS.iter().all(|(name, _)| f.name() != name))
+                .cloned()
+                .collect::<Vec<_>>(),
+        ));
+        let old = batch
+            .project(&(0..old_schema.fields().len()).collect::<Vec<_>>())
+            .unwrap();
+        let dir = TempDir::new("no-nonce");
+        let path = dir.join("identity.parquet");
+        let mut writer =
+            ArrowWriter::try_new(std::fs::File::create(&path).unwrap(), old_schema, None).unwrap();
+        writer.write(&old).unwrap();
+        writer.close().unwrap();
+
+        let batches = read(&path).unwrap();
+        let read_back = batches.first().unwrap();
+        assert_eq!(read_back.schema().fields(), event_schema().fields());
+        let rows = batch_to_rows(read_back).unwrap();
+        assert!(
+            rows.iter()
+                .all(|r| r.tx_nonce.is_none() && r.block_hash.is_none())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detect_reorg_compares_recent_block_hashes() {
+        use alloy::providers::ProviderBuilder;
+        use alloy::transports::mock::Asserter;
+
+        let hash = |n: u8| B256::repeat_byte(n);
+        let block = |number: u64, hash: B256| {
+            serde_json::json!({
+                "hash": hash,
+                "parentHash": B256::ZERO,
+                "sha3Uncles": B256::ZERO,
+                "miner": alloy::primitives::Address::ZERO,
+                "stateRoot": B256::ZERO,
+                "transactionsRoot": B256::ZERO,
+                "receiptsRoot": B256::ZERO,
+                "logsBloom": alloy::primitives::Bloom::ZERO,
+                "difficulty": "0x0",
+                "number": format!("{number:#x}"),
+                "gasLimit": "0x0",
+                "gasUsed": "0x0",
+                "timestamp": "0x0",
+                "extraData": "0x",
+                "mixHash": B256::ZERO,
+                "nonce": "0x0000000000000000",
+                "uncles": [],
+                "transactions": [],
+            })
+        };
+        let logs: Vec<Log> = [(10, 1), (300, 2), (400, 3)]
+            .into_iter()
+            .map(|(number, h)| testutil::log(number).block_hash(hash(h)).build())
+            .collect();
+        let (batch, _) = logs_to_batch(&logs).unwrap();
+        let rows = batch_to_rows(&batch).unwrap();
+        assert_eq!(
+            rows.first().and_then(|r| r.block_hash.clone()),
+            Some(format!("{:#x}", hash(1)))
+        );
+        let dir = TempDir::new("reorg");
+        let path = dir.join("identity.parquet");
+        write(&path, &[batch]).unwrap();
+
+        // Block 10 is deeper than REORG_DEPTH below 400 and not looked up;
+        // 300 is unchanged and 400 was replaced.
+        let asserter = Asserter::new();
+        asserter.push_success(&block(300, hash(2)));
+        asserter.push_success(&block(400, hash(9)));
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter);
+        assert_eq!(detect_reorg(&path, &provider, None).await.unwrap(), [400]);
+    }
+
+    #[tokio::test]
+    async fn test_enrich_with_nonces_fills_missing() {
+        use alloy::providers::ProviderBuilder;
+        use alloy::transports::mock::Asserter;
+
+        let tx = B256::with_last_byte(1);
+        let (batch, _) = logs_to_batch(&[
+            testutil::log(10).tx_hash(tx).build(),
+            testutil::log(10).tx_hash(tx).log_index(1).build(),
+        ])
+        .unwrap();
+        let dir = TempDir::new("nonces");
+        let path = dir.join("identity.parquet");
+        write(&path, &[batch]).unwrap();
+        let opts = ParquetWriterOptions {
+            parallel_encode: false,
+            compression: Compression::Snappy,
+            ..Default::default()
+        };
+
+        // Both logs share one transaction, which is looked up once.
+        let asserter = Asserter::new();
+        asserter.push_success(&serde_json::json!({
+            "hash": format!("{tx:#x}"),
+            "blockHash": null,
+            "blockNumber": null,
+            "transactionIndex": null,
+            "from": "0x0000000000000000000000000000000000000001",
+            "to": "0x0000000000000000000000000000000000000002",
+            "type": "0x0",
+            "chainId": "0x1",
+            "nonce": "0x7",
+            "gas": "0x5208",
+            "gasPrice": "0x1",
+            "value": "0x0",
+            "input": "0x",
+            "v": "0x25",
+            "r": "0x1",
+            "s": "0x1",
+        }));
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        enrich_with_nonces(&path, &provider, opts).await.unwrap();
+
+        let nonces: Vec<_> = read(&path)
+            .unwrap()
+            .iter()
+            .flat_map(|b| batch_to_rows(b).unwrap())
+            .map(|r| r.tx_nonce)
+            .collect();
+        assert_eq!(nonces, [Some(7), Some(7)]);
+        // The rewrite keeps the caller's writer options.
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(
+            reader.metadata().row_group(0).column(0).compression(),
+            parquet::basic::Compression::SNAPPY
+        );
+
+        // With every nonce filled in, nothing is looked up: the asserter
+        // has no responses left.
+        enrich_with_nonces(&path, &provider, opts).await.unwrap();
+    }
+
+    #[test]
+    fn test_read_rotated_from_skips_older_rows() {
+        let dir = TempDir::new("read-from");
+        let base = dir.join("identity.parquet");
+        let (old, _) = logs_to_batch(&testutil::logs_at(&[5, 8])).unwrap();
+        write(&rotated_path(&base, 1), &[old]).unwrap();
+        let (live, _) = logs_to_batch(&testutil::logs_at(&[10, 12, 15])).unwrap();
+        write(&base, &[live]).unwrap();
+
+        let blocks = |from| {
+            read_rotated_from(&base, from)
+                .unwrap()
+                .iter()
+                .flat_map(|b| batch_to_rows(b).unwrap())
+                .map(|r| r.block_number)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(blocks(0), [5, 8, 10, 12, 15]);
+        assert_eq!(blocks(8), [8, 10, 12, 15]);
+        assert_eq!(blocks(12), [12, 15]);
+        assert!(blocks(16).is_empty());
+        assert!(
+            read_rotated_from(&dir.join("missing.parquet"), 0)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_partition_files_lists_contract_files() {
+        let dir = TempDir::new("partition-files");
+        assert!(
+            partition_files(&dir.join("missing"), "identity")
+                .unwrap()
+                .is_empty()
+        );
+        for key in ["2026-02", "2026-01"] {
+            write(&dir.join(key).join("identity.parquet"), &[]).unwrap();
+        }
+        write(&dir.join("2026-03").join("reputation.parquet"), &[]).unwrap();
+        write(&dir.join("identity.parquet"), &[]).unwrap();
+
+        assert_eq!(
+            partition_files(&dir, "identity").unwrap(),
+            [
+                dir.join("2026-01").join("identity.parquet"),
+                dir.join("2026-02").join("identity.parquet"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sort_onchain_orders_by_block_then_log_index() {
+        let batch = sample_batch();
+        let reversed =
+            take_record_batch(&batch, &UInt32Array::from_iter_values((0..64_u32).rev())).unwrap();
+        assert!(is_onchain_sorted(std::slice::from_ref(&batch)).unwrap());
+        assert!(!is_onchain_sorted(&[batch.clone(), reversed.clone()]).unwrap());
+
+        let sorted = sort_onchain(&[reversed, batch]).unwrap();
+        assert_eq!(sorted.num_rows(), 128);
+        assert!(is_onchain_sorted(&[sorted]).unwrap());
+    }
+
+    #[test]
+    fn test_merge_contracts_tags_and_sorts() {
+        let batch = sample_batch();
+        let evens = take_record_batch(
+            &batch,
+            &UInt32Array::from_iter_values((0..64_u32).rev().step_by(2)),
+        )
+        .unwrap();
+        let odds = take_record_batch(
+            &batch,
+            &UInt32Array::from_iter_values((1..64_u32).step_by(2)),
+        )
+        .unwrap();
+        let merged =
+            merge_contracts(&[("identity", vec![evens]), ("reputation", vec![odds])]).unwrap();
+        assert_eq!(merged.num_rows(), 64);
+        assert!(is_onchain_sorted(std::slice::from_ref(&merged)).unwrap());
+        let contracts = column::<StringArray>(&merged, "contract").unwrap();
+        assert_eq!(contracts.value(0), "identity");
+        assert_eq!(contracts.value(1), "reputation");
+        assert_eq!(contracts.null_count(), 0);
+
+        assert!(tag_batch(&merged, "contract", "identity").is_err());
+    }
+
+    #[test]
+    fn test_read_with_recovery_skips_corrupted_row_group() {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let dir = TempDir::new("recovery");
+        let path = dir.join("identity.parquet");
+        let mut writer =
+            ArrowWriter::try_new(std::fs::File::create(&path).unwrap(), event_schema(), None)
+                .unwrap();
+        for _ in 0..2 {
+            writer.write(&sample_batch()).unwrap();
+            writer.flush().unwrap();
+        }
+        writer.close().unwrap();
+
+        // Overwrite the first data page header of row group 0's `tx_hash`.
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let offset = reader.metadata().row_group(0).column(1).data_page_offset();
+        let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.seek(SeekFrom::Start(u64::try_from(offset).unwrap()))
+            .unwrap();
+        file.write_all(&[0xFF; 32]).unwrap();
+        drop(file);
+
+        assert!(read(&path).is_err());
+        let (batches, errors) = read_with_recovery(&path).unwrap();
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 64);
+        assert_eq!(errors.len(), 1);
+        let error = errors.first().unwrap();
+        assert_eq!(error.row_group, 0);
+        assert_eq!(error.block_range, Some((0, 63)));
+    }
+
+    #[test]
+    fn test_train_zstd_dictionary() {
+        use alloy::primitives::{Address, Bytes, LogData};
+
+        let logs: Vec<_> = (0..2_000u64)
+            .map(|n| {
+                let topic0 = B256::with_last_byte(u8::try_from(n % 3).unwrap());
+                testutil::log(n)
+                    .address(Address::with_last_byte(u8::try_from(n % 2).unwrap()))
+                    .data(LogData::new_unchecked(vec![topic0], Bytes::new()))
+                    .build()
+            })
+            .collect();
+        let (batch, _) = logs_to_batch(&logs).unwrap();
+        let dictionary = train_zstd_dictionary(&[batch.clone()], &DICTIONARY_COLUMNS).unwrap();
+        assert!(!dictionary.is_empty());
+        assert!(dictionary.len() <= ZSTD_DICTIONARY_MAX_BYTES);
+        assert!(train_zstd_dictionary(&[batch], &["block_number"]).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+//! Per-chain request rate limiting.
+//!
+//! A [`TokenBucket`] holds up to `capacity` tokens and refills at
+//! `refill_rate` tokens per second.  Every `eth_getLogs` call takes one
+//! token, waiting for the next refill when the bucket is empty.  Unlike a
+//! fixed sleep between requests, time spent waiting on slow responses is
+//! credited back, so a chain runs at its provider's limit whatever the
+//! response latency, and a short burst after an idle period is allowed up
+//! to the bucket's capacity.
+
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Token-bucket rate limiter; see the [module docs](self).
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// Maximum number of stored tokens (the burst size).
+    capacity: u32,
+    /// Tokens added per second.
+    refill_rate: f64,
+    /// Available tokens and the time they were last topped up.
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    /// A full bucket allowing `rate` requests per second, with bursts of up
+    /// to one second's worth of requests (at least one).
+    ///
+    /// Returns `None`, for unthrottled requests, if `rate` is not a
+    /// positive, finite number: a rate of 0 disables the limit, as
+    /// `--rpcs-per-second 0` does.
+    #[must_use]
+    pub fn new(rate: f64) -> Option<Self> {
+        if !(rate.is_finite() && rate > 0.0) {
+            return None;
+        }
+        let capacity = Self::burst(rate);
+        Some(Self {
+            capacity,
+            refill_rate: rate,
+            state: Mutex::new((f64::from(capacity), Instant::now())),
+        })
+    }
+
+    /// Maximum number of stored tokens.
+    #[must_use]
+    pub const fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Tokens added per second.
+    #[must_use]
+    pub const fn refill_rate(&self) -> f64 {
+        self.refill_rate
+    }
+
+    /// Take one token, waiting until one is available.
+    pub async fn acquire(&self) {
+        while let Some(wait) = self.try_acquire(Instant::now()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Top the bucket up to `now` and take a token, or return how long to
+    /// wait for the next one.
+    fn try_acquire(&self, now: Instant) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let (tokens, last) = &mut *state;
+        let elapsed = now.saturating_duration_since(*last).as_secs_f64();
+        *tokens = elapsed
+            .mul_add(self.refill_rate, *tokens)
+            .min(f64::from(self.capacity));
+        *last = now;
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - *tokens) / self.refill_rate))
+        }
+    }
+
+    /// One second's worth of tokens, rounded up, within `1..=u32::MAX`.
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "clamped to the u32 range first"
+    )]
+    fn burst(rate: f64) -> u32 {
+        rate.ceil().clamp(1.0, f64::from(u32::MAX)) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_bursts_then_paces() {
+        let bucket = TokenBucket::new(4.0).unwrap();
+        assert_eq!(bucket.capacity(), 4);
+        let t0 = Instant::now();
+        for _ in 0..4 {
+            assert_eq!(bucket.try_acquire(t0), None);
+        }
+        // Empty: the next token arrives after 1 / 4 s.
+        assert_eq!(bucket.try_acquire(t0), Some(Duration::from_millis(250)));
+        assert_eq!(bucket.try_acquire(t0 + Duration::from_millis(250)), None);
+
+        // An idle period refills at most `capacity` tokens.
+        let later = t0 + Duration::from_secs(60);
+        for _ in 0..4 {
+            assert_eq!(bucket.try_acquire(later), None);
+        }
+        assert!(bucket.try_acquire(later).is_some());
+    }
+
+    #[test]
+    fn test_fractional_rate_has_capacity_one() {
+        let bucket = TokenBucket::new(0.5).unwrap();
+        assert_eq!(bucket.capacity(), 1);
+        let t0 = Instant::now();
+        assert_eq!(bucket.try_acquire(t0), None);
+        assert_eq!(bucket.try_acquire(t0), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_zero_or_invalid_rate_is_unthrottled() {
+        for rate in [0.0, -1.0, f64::INFINITY, f64::NAN] {
+            assert!(TokenBucket::new(rate).is_none(), "rate {rate}");
+        }
+    }
+}
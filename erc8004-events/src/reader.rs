@@ -0,0 +1,97 @@
+//! Read-side access to a synced archive.
+//!
+//! [`ArchiveReader`] returns each contract's history as a single
+//! concatenated [`RecordBatch`] whose schema is guaranteed to be exactly the
+//! event schema (see [`ArchiveReader::schema`]).  This makes the output
+//! suitable for zero-copy ingestion by DataFrame libraries that speak Arrow
+//! (Polars, `DataFusion`, pyarrow) without this crate depending on them.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use arrow_array::RecordBatch;
+use arrow_schema::Schema;
+use arrow_select::concat::concat_batches;
+
+use crate::chains::ChainConfig;
+use crate::fetcher::discover_archived_chains;
+use crate::parquet;
+
+/// Reader over a data directory produced by the sync engine.
+#[derive(Debug, Clone)]
+pub struct ArchiveReader {
+    data_dir: PathBuf,
+}
+
+impl ArchiveReader {
+    /// Create a reader rooted at `data_dir`.
+    #[must_use]
+    pub fn new(data_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            data_dir: data_dir.into(),
+        }
+    }
+
+    /// The data directory this reader is rooted at.
+    #[must_use]
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// The Arrow schema of every batch returned by this reader.
+    ///
+    /// Batches from [`read`](Self::read) and [`read_all`](Self::read_all)
+    /// always carry exactly this schema, even when empty.
+    #[must_use]
+    pub fn schema(&self) -> Arc<Schema> {
+        parquet::event_schema()
+    }
+
+    /// All known chains present in the archive, sorted by chain ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data directory cannot be listed.
+    pub fn chains(&self) -> Result<Vec<(ChainConfig, PathBuf)>> {
+        discover_archived_chains(&self.data_dir)
+    }
+
+    /// Read one contract (`identity` or `reputation`) of one chain as a
+    /// single concatenated batch, including rotated `<contract>.NNN.parquet`
+    /// files.
+    ///
+    /// Returns an empty batch if the file does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or does not match the
+    /// event schema.
+    pub fn read(&self, chain_id: u64, contract: &str) -> Result<RecordBatch> {
+        let path = self
+            .data_dir
+            .join(chain_id.to_string())
+            .join(format!("{contract}.parquet"));
+        self.concat(&parquet::read_rotated(&path)?)
+            .with_context(|| format!("concatenating {}", path.display()))
+    }
+
+    /// Read one contract across every archived chain as a single batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any file cannot be read or does not match the
+    /// event schema.
+    pub fn read_all(&self, contract: &str) -> Result<RecordBatch> {
+        let mut batches = Vec::new();
+        for (chain, _) in self.chains()? {
+            batches.push(self.read(chain.chain_id(), contract)?);
+        }
+        self.concat(&batches)
+    }
+
+    /// Concatenate batches under the canonical schema.
+    fn concat(&self, batches: &[RecordBatch]) -> Result<RecordBatch> {
+        Ok(concat_batches(&self.schema(), batches)?)
+    }
+}
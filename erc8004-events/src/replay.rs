@@ -0,0 +1,192 @@
+//! Lua event processors (`--features lua`).
+//!
+//! [`replay`] runs a Lua 5.4 script over archived events, so one-off
+//! extractions ("every agent URI ever set", "feedback per client") need no
+//! Rust code.  The script defines a global `process(event)` function, which
+//! is called once per [`LogRow`] in the order given.  `event` is a table
+//! with the row's columns (`block_number`, `tx_hash`, `tx_index`,
+//! `log_index`, `address`, `topic0` … `topic3`, `data`, `removed`), plus:
+//!
+//! - `event.event` — the event name (`"URIUpdated"`, …), nil for unknown
+//!   `topic0`s;
+//! - `event.args` — the decoded event arguments by their Solidity names,
+//!   all as strings (numbers in decimal, addresses and bytes as lowercase
+//!   `0x` hex); nil if the log does not decode.  Indexed `string`
+//!   arguments are only stored as hashes and are left out.
+//!
+//! The script also sees:
+//!
+//! - `chain_id` and `contract` — the replayed chain and contract;
+//! - `output.write(key, value)` — writes `key\tvalue` as one line of the
+//!   output;
+//! - `log.info(msg)` — logs `msg` through `tracing`.
+//!
+//! ```lua
+//! function process(event)
+//!   if event.event == "URIUpdated" then
+//!     output.write(event.args.agentId, event.args.newURI)
+//!   end
+//! end
+//! ```
+
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use mlua::{Function, Lua, Table};
+
+use crate::decode::decode_args;
+use crate::parquet::LogRow;
+use crate::signatures;
+
+/// Run `script` over `rows`, writing `output.write` lines to `out`, and
+/// return the number of events processed.  `name` identifies the script in
+/// Lua error messages.
+///
+/// # Errors
+///
+/// Returns an error if the script does not compile, does not define a
+/// global `process` function, raises an error, or `out` cannot be written.
+pub fn replay(
+    script: &str,
+    name: &str,
+    chain_id: u64,
+    contract: &str,
+    rows: &[LogRow],
+    out: &mut dyn Write,
+) -> Result<usize> {
+    let lua = Lua::new();
+    let globals = lua.globals();
+    globals.set("chain_id", chain_id)?;
+    globals.set("contract", contract)?;
+    let log = lua.create_table()?;
+    log.set(
+        "info",
+        lua.create_function(|_, msg: String| {
+            tracing::info!(target: "erc8004_events::replay::script", "{msg}");
+            Ok(())
+        })?,
+    )?;
+    globals.set("log", log)?;
+
+    lua.load(script)
+        .set_name(name)
+        .exec()
+        .with_context(|| format!("loading {name}"))?;
+    let process: Function = globals
+        .get::<Option<Function>>("process")?
+        .with_context(|| format!("{name} does not define a process(event) function"))?;
+
+    let processed = lua.scope(|scope| {
+        let output = lua.create_table()?;
+        output.set(
+            "write",
+            scope.create_function_mut(|_, (key, value): (String, String)| {
+                writeln!(out, "{key}\t{value}").map_err(mlua::Error::external)
+            })?,
+        )?;
+        lua.globals().set("output", output)?;
+        for row in rows {
+            process.call::<()>(event_table(&lua, row)?)?;
+        }
+        Ok(rows.len())
+    })?;
+    out.flush()?;
+    Ok(processed)
+}
+
+/// The `event` table passed to `process`.
+fn event_table(lua: &Lua, row: &LogRow) -> mlua::Result<Table> {
+    let t = lua.create_table()?;
+    t.set("block_number", row.block_number)?;
+    t.set("tx_hash", row.tx_hash.as_str())?;
+    t.set("tx_index", row.tx_index)?;
+    t.set("log_index", row.log_index)?;
+    t.set("address", row.address.as_str())?;
+    t.set("topic0", row.topic0.as_str())?;
+    t.set("topic1", row.topic1.as_deref())?;
+    t.set("topic2", row.topic2.as_deref())?;
+    t.set("topic3", row.topic3.as_deref())?;
+    t.set("data", row.data.as_str())?;
+    t.set("removed", row.removed)?;
+    t.set(
+        "event",
+        signatures::lookup_hex(&row.topic0).map(signatures::EventKind::name),
+    )?;
+    if let Some(args) = decode_args(row) {
+        t.set("args", lua.create_table_from(args)?)?;
+    }
+    Ok(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Address, U256};
+    use alloy::sol_types::SolEvent;
+    use erc8004::contracts::IdentityRegistry;
+
+    use super::*;
+
+    fn uri_updated(agent_id: u64, uri: &str) -> LogRow {
+        let event = IdentityRegistry::URIUpdated {
+            agentId: U256::from(agent_id),
+            newURI: uri.to_owned(),
+            updatedBy: Address::ZERO,
+        };
+        let data = event.encode_log_data();
+        let topic = |i: usize| data.topics().get(i).map(ToString::to_string);
+        LogRow {
+            block_number: 100 + agent_id,
+            tx_hash: format!("0x{agent_id:064x}"),
+            tx_index: 0,
+            log_index: 0,
+            address: format!("{:#x}", Address::ZERO),
+            topic0: topic(0).unwrap(),
+            topic1: topic(1),
+            topic2: topic(2),
+            topic3: topic(3),
+            data: format!("0x{}", alloy::hex::encode(&data.data)),
+            removed: false,
+            tx_nonce: None,
+            block_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_replay_writes_uris() -> Result<()> {
+        let script = r#"
+            function process(event)
+              if event.event == "URIUpdated" then
+                output.write(event.args.agentId, event.args.newURI)
+              end
+              output.write("block", tostring(event.block_number))
+            end
+            log.info("replaying " .. contract .. " of chain " .. chain_id)
+        "#;
+        let rows = [uri_updated(1, "ipfs://a"), uri_updated(2, "ipfs://b")];
+        let mut out = Vec::new();
+        let n = replay(script, "test.lua", 8453, "identity", &rows, &mut out)?;
+        assert_eq!(n, 2);
+        assert_eq!(
+            String::from_utf8(out)?,
+            "1\tipfs://a\nblock\t101\n2\tipfs://b\nblock\t102\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_requires_process() {
+        let mut out = Vec::new();
+        assert!(replay("x = 1", "test.lua", 1, "identity", &[], &mut out).is_err());
+        assert!(
+            replay(
+                "function process(",
+                "test.lua",
+                1,
+                "identity",
+                &[],
+                &mut out
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Retries of transient filesystem errors.
+//!
+//! On networked storage (NFS, cloud-backed volumes) a write or the final
+//! `rename` of an atomic write can fail with errors such as `EBUSY` or a
+//! stale handle that succeed when repeated a moment later.  [`io`] re-runs
+//! such an operation up to a given number of more times with a short
+//! jittered backoff before giving up.  Errors that cannot be transient
+//! (missing files, permissions, invalid input) and errors without an
+//! underlying [`std::io::Error`] fail immediately.
+//!
+//! A sync retries as often as
+//! [`SyncOptions::io_retries`](crate::fetcher::SyncOptions::io_retries)
+//! says (`sync --io-retries`); other writers use [`DEFAULT_IO_RETRIES`].
+//!
+//! The writers are synchronous but run on the async runtime's worker
+//! threads too.  There the backoff first hands the worker's other tasks to
+//! another thread ([`tokio::task::block_in_place`]), so a retry does not
+//! stall them.
+
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+/// Retries of a failed write or rename unless configured otherwise.
+pub const DEFAULT_IO_RETRIES: u32 = 3;
+
+/// Backoff before the first retry; doubled on each further one.
+const BASE_DELAY: Duration = Duration::from_millis(50);
+
+/// Run `op`, retrying it up to `retries` times on transient I/O errors
+/// (0 disables); see the [module docs](self).  `path` is only used for
+/// logging.
+///
+/// # Errors
+///
+/// Returns the error of the last attempt.
+pub fn io<T>(path: &Path, retries: u32, mut op: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < retries && is_transient(&e) => {
+                attempt += 1;
+                let delay = backoff(attempt);
+                tracing::warn!(
+                    path = %path.display(),
+                    attempt,
+                    retries,
+                    delay_ms = delay.as_millis(),
+                    error = %e,
+                    "retrying file write"
+                );
+                wait(delay);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Block the thread for `delay`.  On a multi-threaded runtime the
+/// worker's other tasks move elsewhere and a runtime timer does the
+/// waiting.
+fn wait(delay: Duration) {
+    match Handle::try_current() {
+        Ok(rt) if rt.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| rt.block_on(tokio::time::sleep(delay)));
+        }
+        _ => sleep(delay),
+    }
+}
+
+/// Sleep outside a multi-threaded runtime.
+#[expect(
+    clippy::disallowed_methods,
+    reason = "no runtime, or a current-thread one that has no other worker to hand tasks to"
+)]
+fn sleep(delay: Duration) {
+    std::thread::sleep(delay);
+}
+
+/// Whether `e` was caused by an I/O error that may go away on its own.
+fn is_transient(e: &anyhow::Error) -> bool {
+    let io = e.chain().find_map(|c| c.downcast_ref::<std::io::Error>());
+    io.is_some_and(|io| {
+        !matches!(
+            io.kind(),
+            ErrorKind::NotFound
+                | ErrorKind::PermissionDenied
+                | ErrorKind::InvalidInput
+                | ErrorKind::InvalidData
+                | ErrorKind::ReadOnlyFilesystem
+                | ErrorKind::Unsupported
+        )
+    })
+}
+
+/// Delay before retry `attempt` (1-based): [`BASE_DELAY`] doubled per
+/// attempt, plus up to as much again of jitter so that concurrent writers
+/// do not retry in lockstep.
+fn backoff(attempt: u32) -> Duration {
+    let base = BASE_DELAY.saturating_mul(1 << attempt.saturating_sub(1).min(6));
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    let jitter = base.mul_f64(f64::from(nanos) / 1e9);
+    base + jitter
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn test_rename_retried_after_transient_failure() -> Result<()> {
+        let dir = TempDir::new("retry");
+        let (tmp, path) = (dir.join("file.tmp"), dir.join("file"));
+        std::fs::write(&tmp, b"data")?;
+        // A non-empty directory in the way makes the rename fail with a
+        // real I/O error, until another process clears it.
+        std::fs::create_dir_all(path.join("busy"))?;
+
+        let mut attempts = 0;
+        io(&path, 1, || {
+            attempts += 1;
+            let renamed = std::fs::rename(&tmp, &path).context("renaming file.tmp → file");
+            if renamed.is_err() {
+                std::fs::remove_dir_all(&path)?;
+            }
+            renamed
+        })?;
+        assert_eq!(attempts, 2);
+        assert_eq!(std::fs::read(&path)?, b"data");
+
+        // Without retries the first failure is final.
+        let mut attempts = 0;
+        let res: Result<()> = io(&path, 0, || {
+            attempts += 1;
+            Err(std::io::Error::from(ErrorKind::ResourceBusy).into())
+        });
+        assert!(res.is_err());
+        assert_eq!(attempts, 1);
+
+        // Permanent errors are not retried.
+        let mut attempts = 0;
+        let res: Result<()> = io(&path, 3, || {
+            attempts += 1;
+            Err(std::io::Error::from(ErrorKind::PermissionDenied).into())
+        });
+        assert!(res.is_err());
+        assert_eq!(attempts, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_retry_on_multi_threaded_runtime() {
+        let dir = TempDir::new("retry-runtime");
+        let path = dir.join("file");
+        let mut attempts = 0;
+        io(&path, 2, || {
+            attempts += 1;
+            if attempts < 3 {
+                return Err(std::io::Error::from(ErrorKind::ResourceBusy).into());
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(attempts, 3);
+    }
+}
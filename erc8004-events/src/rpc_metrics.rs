@@ -0,0 +1,198 @@
+//! Per-RPC sync statistics in `<data_dir>/rpc_metrics.json`.
+//!
+//! Every attempt to sync a chain from an RPC is [`record`]ed: a success
+//! updates the RPC's average `eth_getLogs` latency, a failure its error
+//! count.  The latency is per call, so an RPC that ran a long backfill is
+//! not ranked behind one that only caught up a few blocks.  Before the
+//! next sync, [`rank`] moves the chain's fastest reliable RPCs to the
+//! front, so a slow primary is not tried first on every run once a faster
+//! fallback has proven itself.
+//!
+//! RPCs are stored by host and port only, as in
+//! [`ArchiveMetadata`](crate::metadata::ArchiveMetadata), so API keys in
+//! URLs are not written to the data directory.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::metadata::rpc_host;
+use crate::retry;
+
+/// Name of the metrics file in the data directory.
+pub const FILE_NAME: &str = "rpc_metrics.json";
+
+/// Serialises [`record`] calls from concurrently syncing chains.
+static METRICS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Sync statistics of one RPC of a chain.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcMetrics {
+    /// Host and port of the RPC URL.
+    pub url: String,
+    /// Syncs that completed from this RPC.
+    pub success_count: u64,
+    /// Syncs that failed on this RPC.
+    pub error_count: u64,
+    /// Average wall time of an `eth_getLogs` call in the successful syncs,
+    /// in milliseconds.
+    pub avg_latency_ms: u64,
+}
+
+impl RpcMetrics {
+    /// Whether the RPC failed on fewer than one in ten successful syncs;
+    /// only such RPCs are moved ahead by [`rank`].
+    #[must_use]
+    pub const fn is_reliable(&self) -> bool {
+        self.error_count.saturating_mul(10) < self.success_count
+    }
+
+    /// Count one sync attempt: `Some` with its average `eth_getLogs`
+    /// latency if it succeeded.
+    fn record(&mut self, latency: Option<Duration>) {
+        let Some(latency) = latency else {
+            self.error_count += 1;
+            return;
+        };
+        let n = u128::from(self.success_count);
+        let total = u128::from(self.avg_latency_ms) * n + latency.as_millis();
+        self.avg_latency_ms = u64::try_from(total / (n + 1)).unwrap_or(u64::MAX);
+        self.success_count += 1;
+    }
+}
+
+/// Read `<data_dir>/rpc_metrics.json`, keyed by chain ID.
+///
+/// Returns an empty map if the file does not exist.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read or parsed.
+pub fn load(data_dir: &Path) -> Result<BTreeMap<u64, Vec<RpcMetrics>>> {
+    let path = data_dir.join(FILE_NAME);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let data =
+        std::fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_str(&data).with_context(|| format!("parsing {}", path.display()))
+}
+
+/// Record a sync of `chain_id` from `rpc_url` in `<data_dir>/rpc_metrics.json`
+/// atomically: `latency` is the sync's average `eth_getLogs` latency, or
+/// `None` if it failed.
+///
+/// Safe to call from concurrently syncing chains: updates are serialised so
+/// none is lost.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or written.
+pub fn record(
+    data_dir: &Path,
+    chain_id: u64,
+    rpc_url: &str,
+    latency: Option<Duration>,
+) -> Result<()> {
+    let _guard = METRICS_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let mut all = load(data_dir)?;
+    let chain = all.entry(chain_id).or_default();
+    let url = rpc_host(rpc_url);
+    match chain.iter_mut().find(|m| m.url == url) {
+        Some(m) => m.record(latency),
+        None => {
+            let mut m = RpcMetrics {
+                url,
+                ..RpcMetrics::default()
+            };
+            m.record(latency);
+            chain.push(m);
+        }
+    }
+    chain.sort_by(|a, b| a.url.cmp(&b.url));
+
+    std::fs::create_dir_all(data_dir)
+        .with_context(|| format!("creating {}", data_dir.display()))?;
+    let path = data_dir.join(FILE_NAME);
+    let tmp = data_dir.join(format!("{FILE_NAME}.tmp"));
+    let json = serde_json::to_string_pretty(&all)?;
+    retry::io(&path, retry::DEFAULT_IO_RETRIES, || {
+        std::fs::write(&tmp, json.as_bytes())
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        std::fs::rename(&tmp, &path)
+            .with_context(|| format!("renaming {} → {}", tmp.display(), path.display()))
+    })
+}
+
+/// Order `rpcs` for the next sync: reliable RPCs with recorded syncs first,
+/// fastest first, then the rest in their given order.
+#[must_use]
+pub fn rank<'a>(rpcs: &[&'a str], metrics: &[RpcMetrics]) -> Vec<&'a str> {
+    let mut ranked = rpcs.to_vec();
+    ranked.sort_by_cached_key(|url| {
+        let host = rpc_host(url);
+        metrics
+            .iter()
+            .find(|m| m.url == host && m.is_reliable())
+            .map_or((1, 0), |m| (0, m.avg_latency_ms))
+    });
+    ranked
+}
+
+/// A chain's recorded RPCs in the order [`rank`] prefers them: reliable
+/// RPCs by average `eth_getLogs` latency, then unreliable ones.
+#[must_use]
+pub fn ranking(metrics: &[RpcMetrics]) -> Vec<&RpcMetrics> {
+    let mut ranked: Vec<_> = metrics.iter().collect();
+    ranked.sort_by_key(|m| (!m.is_reliable(), m.avg_latency_ms));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn test_record_and_rank() {
+        let dir = TempDir::new("rpc-metrics");
+        let (slow, fast) = ("https://slow.example/KEY", "https://fast.example/KEY");
+
+        for ms in [900, 1_100] {
+            record(&dir, 8453, slow, Some(Duration::from_millis(ms))).unwrap();
+        }
+        record(&dir, 8453, fast, Some(Duration::from_millis(100))).unwrap();
+        let all = load(&dir).unwrap();
+        let metrics = all.get(&8453).unwrap();
+        assert_eq!(
+            metrics.iter().find(|m| m.url == "slow.example"),
+            Some(&RpcMetrics {
+                url: "slow.example".to_owned(),
+                success_count: 2,
+                error_count: 0,
+                avg_latency_ms: 1_000,
+            })
+        );
+        let unknown = "https://new.example";
+        assert_eq!(rank(&[unknown, slow, fast], metrics), [fast, slow, unknown]);
+
+        // One error in a single success is too many: the fast RPC drops
+        // behind the slow one.
+        record(&dir, 8453, fast, None).unwrap();
+        let all = load(&dir).unwrap();
+        let metrics = all.get(&8453).unwrap();
+        assert_eq!(rank(&[fast, slow], metrics), [slow, fast]);
+        assert_eq!(
+            ranking(metrics)
+                .iter()
+                .map(|m| m.url.as_str())
+                .collect::<Vec<_>>(),
+            ["slow.example", "fast.example"]
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//! Amazon S3 output (`--output s3://<bucket>/<prefix>`, `--features s3`).
+//!
+//! Every flush uploads the contract's complete Parquet file to
+//! `<prefix>/<chain_id>/<contract>.parquet`, like a `parquet:` mirror, with
+//! one `PUT` signed with AWS Signature Version 4.  Credentials are read
+//! from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and, for temporary
+//! credentials, `AWS_SESSION_TOKEN` on each upload; the region from
+//! `AWS_REGION` (default `us-east-1`).  `AWS_ENDPOINT_URL` points the
+//! output at an S3-compatible store such as MinIO or R2, addressed
+//! path-style as `<endpoint>/<bucket>/<key>`.
+//!
+//! A single `PUT` accepts objects up to 5 GiB; rotate larger archives with
+//! `--max-file-bytes`.
+
+use std::fmt;
+
+use alloy::transports::http::reqwest::{self, Url};
+use anyhow::{Context, Result, bail};
+use arrow_array::RecordBatch;
+use sha2::{Digest, Sha256};
+
+use crate::{cursor, output, parquet};
+
+/// Region used when `AWS_REGION` is not set.
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// An S3 bucket and key prefix receiving full Parquet files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct S3Output {
+    /// Bucket name.
+    pub bucket: String,
+    /// Key prefix without leading or trailing `/`; may be empty.
+    pub prefix: String,
+}
+
+impl S3Output {
+    /// Parse `<bucket>[/<prefix>]`, the part of `s3://<bucket>/<prefix>`
+    /// after the scheme.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bucket name is empty.
+    pub fn parse(location: &str) -> Result<Self> {
+        let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+        if bucket.is_empty() {
+            bail!("output `s3://{location}`: missing bucket name");
+        }
+        Ok(Self {
+            bucket: bucket.to_owned(),
+            prefix: prefix.trim_matches('/').to_owned(),
+        })
+    }
+
+    /// Object key of a contract's file.
+    fn key(&self, chain_id: u64, contract: &str) -> String {
+        let file = format!("{chain_id}/{contract}.parquet");
+        if self.prefix.is_empty() {
+            file
+        } else {
+            format!("{}/{file}", self.prefix)
+        }
+    }
+
+    /// Upload `all` as the contract's Parquet object.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if credentials are missing, the batches cannot be
+    /// encoded or the upload fails.
+    pub fn write(&self, chain_id: u64, contract: &str, all: &[RecordBatch]) -> Result<()> {
+        let credentials = Credentials::from_env()?;
+        let region = std::env::var("AWS_REGION").unwrap_or_else(|_| DEFAULT_REGION.to_owned());
+        let key = self.key(chain_id, contract);
+        let url = match std::env::var("AWS_ENDPOINT_URL") {
+            Ok(endpoint) => format!(
+                "{}/{}/{}",
+                endpoint.trim_end_matches('/'),
+                self.bucket,
+                encode_path(&key)
+            ),
+            Err(_) => format!(
+                "https://{}.s3.{region}.amazonaws.com/{}",
+                self.bucket,
+                encode_path(&key)
+            ),
+        };
+        let url = Url::parse(&url).with_context(|| format!("invalid S3 URL {url}"))?;
+        let body = parquet::to_bytes(all)?;
+        let headers = sign_put(&url, &body, &credentials, &region, cursor::unix_now());
+        output::block_on(async {
+            let mut req = output::http_client()?.put(url.clone()).body(body);
+            for (name, value) in headers {
+                req = req.header(name, value);
+            }
+            req.send()
+                .await
+                .and_then(reqwest::Response::error_for_status)
+                .with_context(|| format!("PUT s3://{}/{key}", self.bucket))?;
+            Ok(())
+        })
+    }
+}
+
+impl fmt::Display for S3Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s3://{}/{}", self.bucket, self.prefix)
+    }
+}
+
+/// Access key, secret and optional session token.
+struct Credentials {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+impl Credentials {
+    fn from_env() -> Result<Self> {
+        let var = |name: &str| {
+            std::env::var(name).with_context(|| format!("S3 output needs {name} to be set"))
+        };
+        Ok(Self {
+            access_key: var("AWS_ACCESS_KEY_ID")?,
+            secret_key: var("AWS_SECRET_ACCESS_KEY")?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
+}
+
+/// Headers of a Signature Version 4 signed `PUT` of `body` to `url` at
+/// Unix time `now`, `Authorization` included.
+fn sign_put(
+    url: &Url,
+    body: &[u8],
+    credentials: &Credentials,
+    region: &str,
+    now: u64,
+) -> Vec<(&'static str, String)> {
+    let amz_date = amz_date(now);
+    let date = amz_date.get(..8).unwrap_or_default();
+    let payload_hash = alloy::hex::encode(Sha256::digest(body));
+    let host = match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+        None => url.host_str().unwrap_or_default().to_owned(),
+    };
+
+    // Canonical headers must be sorted by name.
+    let mut headers = vec![
+        ("host", host),
+        ("x-amz-content-sha256", payload_hash.clone()),
+        ("x-amz-date", amz_date.clone()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+        .collect();
+    let canonical_request = format!(
+        "PUT\n{}\n\n{canonical_headers}\n{signed_headers}\n{payload_hash}",
+        url.path()
+    );
+
+    let scope = format!("{date}/{region}/s3/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        alloy::hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let key = signing_key(&credentials.secret_key, date, region, "s3");
+    let signature = alloy::hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+    headers.push((
+        "authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, \
+             Signature={signature}",
+            credentials.access_key
+        ),
+    ));
+    // reqwest sets `Host` from the URL itself.
+    headers.retain(|(name, _)| *name != "host");
+    headers
+}
+
+/// Signature Version 4 signing key for `date` (`YYYYMMDD`).
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> [u8; 32] {
+    let k_date = hmac_sha256(format!("AWS4{secret}").as_bytes(), date.as_bytes());
+    let k_region = hmac_sha256(&k_date, region.as_bytes());
+    let k_service = hmac_sha256(&k_region, service.as_bytes());
+    hmac_sha256(&k_service, b"aws4_request")
+}
+
+/// HMAC-SHA256 (RFC 2104) of `message` under `key`.
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK: usize = 64;
+    let hashed;
+    let key = if key.len() > BLOCK {
+        hashed = Sha256::digest(key);
+        hashed.as_slice()
+    } else {
+        key
+    };
+    let mut block = [0u8; BLOCK];
+    for (b, k) in block.iter_mut().zip(key) {
+        *b = *k;
+    }
+    let inner = Sha256::new()
+        .chain_update(block.map(|b| b ^ 0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(block.map(|b| b ^ 0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+/// `YYYYMMDD'T'HHMMSS'Z'` of Unix time `secs`.
+fn amz_date(secs: u64) -> String {
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Days to civil date, after Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+/// URI-encode an object key, keeping `/` between its segments.
+fn encode_path(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    for byte in key.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~' | b'/') {
+            out.push(char::from(byte));
+        } else {
+            out.push('%');
+            out.push_str(&alloy::hex::encode_upper([byte]));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_key() {
+        let out = S3Output::parse("archive/erc8004/events/").unwrap();
+        assert_eq!(out.bucket, "archive");
+        assert_eq!(
+            out.key(8453, "identity"),
+            "erc8004/events/8453/identity.parquet"
+        );
+        assert_eq!(out.to_string(), "s3://archive/erc8004/events");
+        let bare = S3Output::parse("archive").unwrap();
+        assert_eq!(bare.key(1, "reputation"), "1/reputation.parquet");
+        assert!(S3Output::parse("/prefix").is_err());
+    }
+
+    #[test]
+    fn test_signature_v4_primitives() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            alloy::hex::encode(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        // AWS documentation example of deriving a signing key.
+        assert_eq!(
+            alloy::hex::encode(signing_key(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "20120215",
+                "us-east-1",
+                "iam"
+            )),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+        assert_eq!(amz_date(0), "19700101T000000Z");
+        assert_eq!(amz_date(1_329_264_000 + 3_723), "20120215T010203Z");
+        assert_eq!(encode_path("a b/c+d.parquet"), "a%20b/c%2Bd.parquet");
+    }
+
+    #[test]
+    fn test_sign_put_headers() {
+        let credentials = Credentials {
+            access_key: "AKID".to_owned(),
+            secret_key: "secret".to_owned(),
+            session_token: Some("token".to_owned()),
+        };
+        let url = Url::parse("http://localhost:9000/bucket/1/identity.parquet").unwrap();
+        let headers = sign_put(&url, b"", &credentials, "us-east-1", 0);
+        let names: Vec<_> = headers.iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            names,
+            [
+                "x-amz-content-sha256",
+                "x-amz-date",
+                "x-amz-security-token",
+                "authorization"
+            ]
+        );
+        let authorization = &headers.last().unwrap().1;
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKID/19700101/us-east-1/s3/aws4_request, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token, \
+             Signature="
+        ));
+    }
+}
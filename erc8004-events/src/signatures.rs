@@ -0,0 +1,136 @@
+//! Classification of archived logs by `topic0`.
+//!
+//! [`lookup`] maps an event signature hash to the registry event it
+//! identifies.  The table is built once from the SDK's contract bindings and
+//! shared by every caller, so classifying a log is a single map lookup
+//! rather than a string comparison against each known signature.
+
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use alloy::primitives::B256;
+use alloy::sol_types::SolEvent;
+use erc8004::contracts::{IdentityRegistry, ReputationRegistry};
+
+/// An event emitted by the Identity or Reputation Registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventKind {
+    /// `IdentityRegistry.Registered`.
+    Registered,
+    /// `IdentityRegistry.URIUpdated`.
+    UriUpdated,
+    /// `IdentityRegistry.MetadataSet`.
+    MetadataSet,
+    /// ERC-721 `Transfer` (mints have `topic1` = zero address).
+    Transfer,
+    /// ERC-721 `Approval`.
+    Approval,
+    /// ERC-721 `ApprovalForAll`.
+    ApprovalForAll,
+    /// `ReputationRegistry.NewFeedback`.
+    NewFeedback,
+    /// `ReputationRegistry.FeedbackRevoked`.
+    FeedbackRevoked,
+    /// `ReputationRegistry.ResponseAppended`.
+    ResponseAppended,
+}
+
+impl EventKind {
+    /// Every known event.
+    pub const ALL: [Self; 9] = [
+        Self::Registered,
+        Self::UriUpdated,
+        Self::MetadataSet,
+        Self::Transfer,
+        Self::Approval,
+        Self::ApprovalForAll,
+        Self::NewFeedback,
+        Self::FeedbackRevoked,
+        Self::ResponseAppended,
+    ];
+
+    /// The event's `topic0` (keccak-256 of its signature).
+    #[must_use]
+    pub const fn signature_hash(self) -> B256 {
+        match self {
+            Self::Registered => IdentityRegistry::Registered::SIGNATURE_HASH,
+            Self::UriUpdated => IdentityRegistry::URIUpdated::SIGNATURE_HASH,
+            Self::MetadataSet => IdentityRegistry::MetadataSet::SIGNATURE_HASH,
+            Self::Transfer => IdentityRegistry::Transfer::SIGNATURE_HASH,
+            Self::Approval => IdentityRegistry::Approval::SIGNATURE_HASH,
+            Self::ApprovalForAll => IdentityRegistry::ApprovalForAll::SIGNATURE_HASH,
+            Self::NewFeedback => ReputationRegistry::NewFeedback::SIGNATURE_HASH,
+            Self::FeedbackRevoked => ReputationRegistry::FeedbackRevoked::SIGNATURE_HASH,
+            Self::ResponseAppended => ReputationRegistry::ResponseAppended::SIGNATURE_HASH,
+        }
+    }
+
+    /// The Solidity event name.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Registered => "Registered",
+            Self::UriUpdated => "URIUpdated",
+            Self::MetadataSet => "MetadataSet",
+            Self::Transfer => "Transfer",
+            Self::Approval => "Approval",
+            Self::ApprovalForAll => "ApprovalForAll",
+            Self::NewFeedback => "NewFeedback",
+            Self::FeedbackRevoked => "FeedbackRevoked",
+            Self::ResponseAppended => "ResponseAppended",
+        }
+    }
+
+    /// The archive file the event is stored in (`identity` or `reputation`).
+    #[must_use]
+    pub const fn contract(self) -> &'static str {
+        match self {
+            Self::NewFeedback | Self::FeedbackRevoked | Self::ResponseAppended => "reputation",
+            _ => "identity",
+        }
+    }
+}
+
+/// `topic0` → event, built on first use.
+static BY_TOPIC0: LazyLock<BTreeMap<B256, EventKind>> = LazyLock::new(|| {
+    EventKind::ALL
+        .into_iter()
+        .map(|kind| (kind.signature_hash(), kind))
+        .collect()
+});
+
+/// Identify the event with the given `topic0`.
+#[must_use]
+pub fn lookup(topic0: &B256) -> Option<EventKind> {
+    BY_TOPIC0.get(topic0).copied()
+}
+
+/// Identify the event with the given `0x`-prefixed hex `topic0`, as stored
+/// in the archive's `topic0` column.
+#[must_use]
+pub fn lookup_hex(topic0: &str) -> Option<EventKind> {
+    lookup(&topic0.parse().ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_hashes_are_distinct() {
+        assert_eq!(BY_TOPIC0.len(), EventKind::ALL.len());
+        for kind in EventKind::ALL {
+            assert_eq!(lookup(&kind.signature_hash()), Some(kind));
+        }
+    }
+
+    #[test]
+    fn test_lookup_hex_matches_archived_topic0() {
+        assert_eq!(
+            lookup_hex("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"),
+            Some(EventKind::Transfer)
+        );
+        assert_eq!(lookup_hex(&format!("{:#x}", B256::ZERO)), None);
+        assert_eq!(lookup_hex("not a topic"), None);
+    }
+}
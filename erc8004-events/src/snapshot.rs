@@ -0,0 +1,532 @@
+//! Point-in-time copies of an archive (`snapshot`).
+//!
+//! [`create`] holds `<data_dir>/snapshot.lock` while it runs; [`sync_all`]
+//! and the other writers wait for the lock to go away before they start
+//! each chain, so a looping sync pauses between chains instead of racing
+//! the copy.  The lock records the PID and start time of its snapshot, and
+//! a lock older than [`STALE_LOCK_AFTER`], left by a crashed snapshot, is
+//! ignored by the writers and replaced by the next snapshot.
+//!
+//! Chains already syncing are paused by `<data_dir>/write.lock`: every
+//! flush, repair and rewrite of an archive holds a shared [`WriteLock`] on
+//! it, and [`create`] holds it exclusively while staging.  A flush that
+//! rotates `identity.parquet` to `identity.001.parquet` therefore either
+//! finishes before the snapshot lists the files or waits until it is done.
+//! Cursors are copied before the archives, so a snapshot's archives are
+//! never behind its cursors.
+//!
+//! Besides cursors and archives (Parquet files including rotated files and
+//! partition subdirectories, and `--sink-format jsonl` files), each chain's
+//! `gaps.json`, `metadata.json` and `manifest.json` are snapshotted.
+//!
+//! Files that are only ever replaced, never modified in place, are
+//! hard-linked when the output is on the same filesystem (instant and free
+//! of extra disk space) and copied otherwise.  JSONL archives grow by
+//! appends, which a hard link would let into the snapshot, so they are
+//! always copied.  Either way files are staged in `<output>.partial` and
+//! the staging directory is renamed to `<output>` once complete, so a
+//! snapshot directory is never half-written.
+//!
+//! [`sync_all`]: crate::fetcher::sync_all
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::cursor::{self, Cursor, CursorCompression};
+use crate::fetcher::discover_archived_chains;
+use crate::{manifest, metadata, parquet};
+
+/// Lock file held in the data directory while a snapshot is taken.
+pub const LOCK_FILE: &str = "snapshot.lock";
+
+/// Lock file in the data directory whose [`WriteLock`] pauses writes while
+/// a snapshot is staged.
+pub const WRITE_LOCK_FILE: &str = "write.lock";
+
+/// Age after which a [`LOCK_FILE`] is taken to be left by a crashed
+/// snapshot.
+pub const STALE_LOCK_AFTER: Duration = Duration::from_secs(2 * 60 * 60);
+
+/// How often a paused sync checks whether the snapshot lock or the write
+/// lock is free.
+const LOCK_POLL: Duration = Duration::from_millis(500);
+
+/// Contents of `snapshot.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    /// Unix timestamp (seconds) the snapshot was taken at.
+    pub created_at: u64,
+    /// Whether the files that may be linked were hard-linked rather than
+    /// copied; JSONL archives are always copied.
+    pub hard_linked: bool,
+    /// Snapshotted chains, by chain ID.
+    pub chains: Vec<SnapshotChain>,
+}
+
+/// One chain of a [`Snapshot`].
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotChain {
+    /// EIP-155 chain ID.
+    pub chain_id: u64,
+    /// The chain's cursor at snapshot time, if it had one.
+    pub cursor: Option<Cursor>,
+    /// Snapshotted files, relative to the chain directory.
+    pub files: Vec<String>,
+}
+
+/// Contents of a [`LOCK_FILE`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LockOwner {
+    /// PID of the snapshotting process.
+    pid: u32,
+    /// Unix timestamp (seconds) the lock was taken at.
+    created_at: u64,
+}
+
+/// The snapshot lock of a data directory, released on drop.
+#[derive(Debug)]
+pub struct SnapshotLock {
+    path: PathBuf,
+}
+
+impl SnapshotLock {
+    /// Take the lock of `data_dir`, replacing a stale one (see
+    /// [`STALE_LOCK_AFTER`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if another snapshot holds the lock or the lock file
+    /// cannot be created.
+    pub fn acquire(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(LOCK_FILE);
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists && is_stale(&path) => {
+                tracing::warn!(lock = %path.display(), "replacing stale snapshot lock");
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("removing {}", path.display()))?;
+                return Self::acquire(data_dir);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => bail!(
+                "{} exists: another snapshot is running ({})",
+                path.display(),
+                std::fs::read_to_string(&path).unwrap_or_default().trim()
+            ),
+            Err(e) => return Err(e).with_context(|| format!("creating {}", path.display())),
+        };
+        // Removed again on drop if the owner cannot be written.
+        let lock = Self { path };
+        let owner = LockOwner {
+            pid: std::process::id(),
+            created_at: cursor::unix_now(),
+        };
+        file.write_all(serde_json::to_string(&owner)?.as_bytes())
+            .with_context(|| format!("writing {}", lock.path.display()))?;
+        Ok(lock)
+    }
+}
+
+impl Drop for SnapshotLock {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::warn!(path = %self.path.display(), error = %e, "removing snapshot lock failed");
+        }
+    }
+}
+
+/// Whether the lock file at `path` is older than [`STALE_LOCK_AFTER`], by
+/// its recorded start time or, if that cannot be read, its modification
+/// time.
+fn is_stale(path: &Path) -> bool {
+    let recorded = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<LockOwner>(&s).ok())
+        .map(|owner| Duration::from_secs(cursor::unix_now().saturating_sub(owner.created_at)));
+    let age = recorded.or_else(|| {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        SystemTime::now().duration_since(modified).ok()
+    });
+    age.is_some_and(|age| age > STALE_LOCK_AFTER)
+}
+
+/// Wait while a snapshot of `data_dir` is being taken; a stale lock (see
+/// [`STALE_LOCK_AFTER`]) is ignored.
+pub async fn wait_while_locked(data_dir: &Path) {
+    let path = data_dir.join(LOCK_FILE);
+    if !path.exists() {
+        return;
+    }
+    if is_stale(&path) {
+        tracing::warn!(lock = %path.display(), "ignoring stale snapshot lock");
+        return;
+    }
+    tracing::info!(lock = %path.display(), "snapshot in progress, pausing sync");
+    while path.exists() {
+        if is_stale(&path) {
+            tracing::warn!(lock = %path.display(), "ignoring stale snapshot lock");
+            return;
+        }
+        tokio::time::sleep(LOCK_POLL).await;
+    }
+    tracing::info!("snapshot finished, resuming sync");
+}
+
+/// A lock on `<data_dir>/write.lock`, released on drop.
+///
+/// Every write to the archives of a data directory holds it shared, and
+/// [`create`] holds it exclusively while staging, so a snapshot waits for
+/// writes in flight and writes started during a snapshot wait for it.
+/// The operating system releases the lock of a crashed process.
+#[derive(Debug)]
+pub struct WriteLock {
+    _file: File,
+}
+
+impl WriteLock {
+    /// Take the lock of `data_dir` shared, polling every [`LOCK_POLL`]
+    /// while a snapshot holds it so that the async runtime is not blocked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock file cannot be created or locked.
+    pub async fn shared(data_dir: &Path) -> Result<Self> {
+        let (file, path) = Self::open(data_dir)?;
+        loop {
+            match file.try_lock_shared() {
+                Ok(()) => return Ok(Self { _file: file }),
+                Err(TryLockError::WouldBlock) => tokio::time::sleep(LOCK_POLL).await,
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("locking {}", path.display()));
+                }
+            }
+        }
+    }
+
+    /// Take the lock of `data_dir` exclusively, blocking while writes hold
+    /// it.
+    fn exclusive(data_dir: &Path) -> Result<Self> {
+        let (file, path) = Self::open(data_dir)?;
+        file.lock()
+            .with_context(|| format!("locking {}", path.display()))?;
+        Ok(Self { _file: file })
+    }
+
+    /// Open `<data_dir>/write.lock`, creating it if needed.
+    fn open(data_dir: &Path) -> Result<(File, PathBuf)> {
+        std::fs::create_dir_all(data_dir)
+            .with_context(|| format!("creating {}", data_dir.display()))?;
+        let path = data_dir.join(WRITE_LOCK_FILE);
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("opening {}", path.display()))?;
+        Ok((file, path))
+    }
+}
+
+/// Snapshot the cursors, Parquet files and JSON metadata of `data_dir`
+/// (only `chain`, if given) into the new directory `output`, and write
+/// `snapshot.json`.
+///
+/// With `verify`, every snapshotted Parquet file is decoded with
+/// [`parquet::verify_integrity`] before the snapshot is published, and a
+/// corrupted file fails the snapshot.
+///
+/// # Errors
+///
+/// Returns an error if `output` already exists, the lock is held, a file
+/// cannot be linked or copied, or verification fails.
+pub fn create(
+    data_dir: &Path,
+    output: &Path,
+    chain: Option<u64>,
+    verify: bool,
+) -> Result<Snapshot> {
+    if output.exists() {
+        bail!("{} already exists", output.display());
+    }
+    let _lock = SnapshotLock::acquire(data_dir)?;
+    let staging = staging_path(output);
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)
+            .with_context(|| format!("removing stale {}", staging.display()))?;
+    }
+
+    // Writes in flight finish before staging and writes started meanwhile
+    // wait for it; the staged links are verified without holding them up.
+    let res = WriteLock::exclusive(data_dir)
+        .and_then(|_writes| stage(data_dir, &staging, chain))
+        .and_then(|snapshot| {
+            if verify {
+                verify_staged(data_dir, &staging, &snapshot)?;
+            }
+            Ok(snapshot)
+        });
+    let snapshot = match res {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            std::fs::remove_dir_all(&staging).ok();
+            return Err(e);
+        }
+    };
+    std::fs::rename(&staging, output)
+        .with_context(|| format!("renaming {} → {}", staging.display(), output.display()))?;
+    Ok(snapshot)
+}
+
+/// `<output>.partial`.
+fn staging_path(output: &Path) -> PathBuf {
+    let mut staging = output.as_os_str().to_owned();
+    staging.push(".partial");
+    PathBuf::from(staging)
+}
+
+/// Link or copy every snapshotted file into `staging` and write
+/// `snapshot.json` there; run under the exclusive [`WriteLock`].
+fn stage(data_dir: &Path, staging: &Path, chain: Option<u64>) -> Result<Snapshot> {
+    let mut snapshot = Snapshot {
+        created_at: cursor::unix_now(),
+        hard_linked: true,
+        chains: Vec::new(),
+    };
+    for (config, dir) in discover_archived_chains(data_dir)? {
+        let cid = config.chain_id();
+        if chain.is_some_and(|c| c != cid) {
+            continue;
+        }
+        let out = staging.join(cid.to_string());
+        std::fs::create_dir_all(&out).with_context(|| format!("creating {}", out.display()))?;
+
+        // Cursors first: the archives copied after them can only be ahead.
+        let mut files: Vec<String> = CursorCompression::ALL
+            .into_iter()
+            .map(|c| c.path(&dir))
+            .chain(
+                ["gaps.json", metadata::FILE_NAME, manifest::FILE_NAME].map(|name| dir.join(name)),
+            )
+            .filter(|p| p.exists())
+            .filter_map(|p| Some(p.file_name()?.to_string_lossy().into_owned()))
+            .collect();
+        files.extend(archive_files(&dir)?);
+        for name in &files {
+            let (src, dst) = (dir.join(name), out.join(name));
+            if let Some(parent) = dst.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("creating {}", parent.display()))?;
+            }
+            if name.ends_with(".jsonl") {
+                copy(&src, &dst)?;
+            } else {
+                snapshot.hard_linked &= link_or_copy(&src, &dst)?;
+            }
+        }
+        let cursor = Cursor::load(&out)?;
+        tracing::info!(chain_id = cid, files = files.len(), "chain snapshotted");
+        snapshot.chains.push(SnapshotChain {
+            chain_id: cid,
+            cursor,
+            files,
+        });
+    }
+    if let Some(c) = chain.filter(|_| snapshot.chains.is_empty()) {
+        bail!("no archive for chain {c} in {}", data_dir.display());
+    }
+    std::fs::create_dir_all(staging).with_context(|| format!("creating {}", staging.display()))?;
+    let path = staging.join("snapshot.json");
+    std::fs::write(&path, serde_json::to_string_pretty(&snapshot)?)
+        .with_context(|| format!("writing {}", path.display()))?;
+    Ok(snapshot)
+}
+
+/// Decode every staged Parquet file with [`parquet::verify_integrity`]
+/// and fail on a corrupted one.
+fn verify_staged(data_dir: &Path, staging: &Path, snapshot: &Snapshot) -> Result<()> {
+    for chain in &snapshot.chains {
+        let cid = chain.chain_id.to_string();
+        for name in chain.files.iter().filter(|n| n.ends_with(".parquet")) {
+            let path = staging.join(&cid).join(name);
+            let report = parquet::verify_integrity(&path)
+                .with_context(|| format!("verifying {}", path.display()))?;
+            if !report.corrupted.is_empty() {
+                bail!(
+                    "{}: {} corrupted row groups ({} rows)",
+                    data_dir.join(&cid).join(name).display(),
+                    report.corrupted.len(),
+                    report.corrupted_rows()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Paths of the finished Parquet and JSONL files in a chain directory and
+/// its partition subdirectories, relative to it and sorted.
+fn archive_files(dir: &Path) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(rel) = dirs.pop() {
+        let path = dir.join(&rel);
+        for entry in
+            std::fs::read_dir(&path).with_context(|| format!("listing {}", path.display()))?
+        {
+            let entry = entry.with_context(|| format!("listing {}", path.display()))?;
+            let name = rel.join(entry.file_name());
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(name);
+            } else if file_type.is_file()
+                && name
+                    .extension()
+                    .is_some_and(|e| e == "parquet" || e == "jsonl")
+            {
+                names.push(name.to_string_lossy().into_owned());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Hard-link `src` to `dst`, copying it if they are on different
+/// filesystems (or the filesystem has no hard links).  Returns whether the
+/// file was linked.
+fn link_or_copy(src: &Path, dst: &Path) -> Result<bool> {
+    match std::fs::hard_link(src, dst) {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            tracing::debug!(src = %src.display(), error = %e, "hard link failed, copying");
+            copy(src, dst)?;
+            Ok(false)
+        }
+    }
+}
+
+/// Copy `src` to `dst`.
+fn copy(src: &Path, dst: &Path) -> Result<()> {
+    std::fs::copy(src, dst)
+        .with_context(|| format!("copying {} → {}", src.display(), dst.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn test_snapshot_links_cursor_and_archives() {
+        let root = TempDir::new("snapshot");
+        let data_dir = root.join("data");
+        let chain_dir = data_dir.join("8453");
+        Cursor::now(100).save(&chain_dir).unwrap();
+        for name in [
+            "identity.parquet",
+            "identity.001.parquet",
+            "2026-01/identity.parquet",
+        ] {
+            let path = chain_dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            parquet::write(&path, &[]).unwrap();
+        }
+        for name in ["gaps.json", "metadata.json", "manifest.json"] {
+            std::fs::write(chain_dir.join(name), b"{}").unwrap();
+        }
+        std::fs::write(chain_dir.join("identity.parquet.tmp"), b"torn").unwrap();
+        std::fs::write(chain_dir.join("reputation.jsonl"), b"{}\n").unwrap();
+
+        let output = root.join("snapshot");
+        let snapshot = create(&data_dir, &output, None, true).unwrap();
+        let chain = snapshot.chains.first().unwrap();
+        assert_eq!(
+            chain.files,
+            [
+                "cursor.json",
+                "gaps.json",
+                "metadata.json",
+                "manifest.json",
+                "2026-01/identity.parquet",
+                "identity.001.parquet",
+                "identity.parquet",
+                "reputation.jsonl"
+            ]
+        );
+        assert_eq!(chain.cursor.as_ref().map(|c| c.last_block), Some(100));
+        assert!(output.join("snapshot.json").exists());
+        assert!(output.join("8453/2026-01/identity.parquet").exists());
+        assert!(output.join("8453/manifest.json").exists());
+        assert!(!output.join("8453/identity.parquet.tmp").exists());
+
+        // An append to the live JSONL archive does not reach the snapshot.
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(chain_dir.join("reputation.jsonl"))
+            .and_then(|mut f| f.write_all(b"{}\n"))
+            .unwrap();
+        assert_eq!(
+            std::fs::read(output.join("8453/reputation.jsonl")).unwrap(),
+            b"{}\n"
+        );
+        assert!(!data_dir.join(LOCK_FILE).exists());
+
+        // Never overwrites, and refuses while another snapshot holds the lock.
+        assert!(create(&data_dir, &output, None, false).is_err());
+        let lock = SnapshotLock::acquire(&data_dir).unwrap();
+        assert!(create(&data_dir, &root.join("other"), None, false).is_err());
+        drop(lock);
+        assert!(!root.join("other").exists());
+    }
+
+    #[tokio::test]
+    async fn test_stale_lock_is_ignored_and_replaced() {
+        let data_dir = TempDir::new("snapshot-stale-lock");
+        let path = data_dir.join(LOCK_FILE);
+        let owner = LockOwner {
+            pid: 1,
+            created_at: cursor::unix_now() - STALE_LOCK_AFTER.as_secs() - 1,
+        };
+        std::fs::write(&path, serde_json::to_string(&owner).unwrap()).unwrap();
+
+        tokio::time::timeout(LOCK_POLL, wait_while_locked(&data_dir))
+            .await
+            .unwrap();
+        let lock = SnapshotLock::acquire(&data_dir).unwrap();
+        let owner: LockOwner =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(owner.pid, std::process::id());
+        // A fresh lock is neither ignored nor replaced.
+        assert!(SnapshotLock::acquire(&data_dir).is_err());
+        assert!(
+            tokio::time::timeout(LOCK_POLL, wait_while_locked(&data_dir))
+                .await
+                .is_err()
+        );
+        drop(lock);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_shared_write_lock_waits_for_snapshot() {
+        let data_dir = TempDir::new("snapshot-write-lock");
+        let exclusive = WriteLock::exclusive(&data_dir).unwrap();
+        assert!(
+            tokio::time::timeout(LOCK_POLL, WriteLock::shared(&data_dir))
+                .await
+                .is_err()
+        );
+        drop(exclusive);
+        let _shared = tokio::time::timeout(LOCK_POLL, WriteLock::shared(&data_dir))
+            .await
+            .unwrap()
+            .unwrap();
+        // Shared holders do not exclude each other.
+        WriteLock::shared(&data_dir).await.unwrap();
+    }
+}
@@ -0,0 +1,326 @@
+//! Summary statistics over a synced archive.
+//!
+//! For each archived contract, [`archive_stats`] counts events together with
+//! the distinct transactions and blocks they span ("X events across Y
+//! transactions in Z blocks").  Files are streamed batch by batch with only
+//! the `block_number` and `tx_hash` columns decoded, so memory grows with
+//! the number of distinct values rather than with the file size.
+//!
+//! Counts are kept per file and summed per contract: a block (and so a
+//! transaction) never spans two files of a contract, because files are only
+//! rotated between appended fetch windows.  The per-file counts are cached
+//! in `<data_dir>/stats-cache.json`, keyed by each file's size and
+//! modification time, so that repeated runs only rescan files that changed.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use arrow_array::{StringArray, UInt64Array};
+use parquet::arrow::ProjectionMask;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use serde::{Deserialize, Serialize};
+
+use crate::export::CONTRACTS;
+use crate::fetcher::discover_archived_chains;
+use crate::parquet::{column, rotated_files};
+use crate::retry;
+
+/// Name of the per-file stats cache in the data directory.
+pub const CACHE_FILE: &str = "stats-cache.json";
+
+/// Event, transaction and block counts for one contract of one chain.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContractStats {
+    /// EIP-155 chain ID.
+    pub chain_id: u64,
+    /// Human-readable chain name.
+    pub chain: &'static str,
+    /// Contract name (`identity` or `reputation`).
+    pub contract: &'static str,
+    /// Number of archived events (rows).
+    pub events: usize,
+    /// Number of distinct `tx_hash` values.
+    pub transactions: usize,
+    /// Number of distinct `block_number` values.
+    pub blocks: usize,
+    /// Lowest archived block, if any.
+    pub min_block: Option<u64>,
+    /// Highest archived block, if any.
+    pub max_block: Option<u64>,
+}
+
+/// Counts of one Parquet file, cached under its path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStats {
+    /// File size in bytes when scanned.
+    size: u64,
+    /// Modification time (nanoseconds since the Unix epoch) when scanned.
+    modified: u128,
+    events: usize,
+    transactions: usize,
+    blocks: usize,
+    min_block: Option<u64>,
+    max_block: Option<u64>,
+}
+
+/// Compute [`ContractStats`] for every archived contract under `data_dir`,
+/// sorted by chain ID.  Contracts without any Parquet file are skipped.
+///
+/// With `use_cache`, files whose size and modification time match
+/// `stats-cache.json` are not rescanned; the cache is rewritten either way,
+/// dropping entries of files that no longer exist.  A cache that cannot be
+/// written (e.g. in a read-only data directory) is logged and skipped.
+///
+/// # Errors
+///
+/// Returns an error if the data directory cannot be listed or a file
+/// cannot be read.
+pub fn archive_stats(data_dir: &Path, use_cache: bool) -> Result<Vec<ContractStats>> {
+    let cache_path = data_dir.join(CACHE_FILE);
+    let cached = if use_cache {
+        load_cache(&cache_path)
+    } else {
+        BTreeMap::new()
+    };
+    let mut cache = BTreeMap::new();
+    let (mut hits, mut scans) = (0usize, 0usize);
+    let mut stats = Vec::new();
+    for (chain, dir) in discover_archived_chains(data_dir)? {
+        for contract in CONTRACTS {
+            let base = dir.join(format!("{contract}.parquet"));
+            let mut files = rotated_files(&base)?;
+            if base.exists() {
+                files.push(base);
+            }
+            if files.is_empty() {
+                continue;
+            }
+
+            let mut total = ContractStats {
+                chain_id: chain.chain_id(),
+                chain: chain.name,
+                contract,
+                events: 0,
+                transactions: 0,
+                blocks: 0,
+                min_block: None,
+                max_block: None,
+            };
+            for path in &files {
+                let key = path
+                    .strip_prefix(data_dir)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .into_owned();
+                let (size, modified) = file_version(path)?;
+                let file = match cached.get(&key) {
+                    Some(&f) if f.size == size && f.modified == modified => {
+                        hits += 1;
+                        f
+                    }
+                    _ => {
+                        scans += 1;
+                        let mut counter = Counter::default();
+                        counter
+                            .scan(path)
+                            .with_context(|| format!("computing stats for {}", path.display()))?;
+                        counter.finish(size, modified)
+                    }
+                };
+                total.events += file.events;
+                total.transactions += file.transactions;
+                total.blocks += file.blocks;
+                total.min_block = min_some(total.min_block, file.min_block);
+                total.max_block = total.max_block.max(file.max_block);
+                cache.insert(key, file);
+            }
+            stats.push(total);
+        }
+    }
+    tracing::debug!(cached = hits, scanned = scans, "archive stats computed");
+    if let Err(e) = save_cache(&cache_path, &cache) {
+        tracing::warn!(path = %cache_path.display(), error = %e, "writing stats cache failed");
+    }
+    Ok(stats)
+}
+
+/// The smaller of two optional blocks, ignoring `None`.
+fn min_some(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Size and modification time (nanoseconds since the epoch) of `path`.
+fn file_version(path: &Path) -> Result<(u64, u128)> {
+    let meta = std::fs::metadata(path).with_context(|| format!("reading {}", path.display()))?;
+    let modified = meta
+        .modified()
+        .with_context(|| format!("reading mtime of {}", path.display()))?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    Ok((meta.len(), modified))
+}
+
+/// Read the stats cache; a missing or unreadable cache is empty.
+fn load_cache(path: &Path) -> BTreeMap<String, FileStats> {
+    let Ok(data) = std::fs::read(path) else {
+        return BTreeMap::new();
+    };
+    serde_json::from_slice(&data).unwrap_or_else(|e| {
+        tracing::warn!(path = %path.display(), error = %e, "ignoring corrupted stats cache");
+        BTreeMap::new()
+    })
+}
+
+/// Write the stats cache atomically (temp file + rename).
+fn save_cache(path: &Path, cache: &BTreeMap<String, FileStats>) -> Result<()> {
+    if cache.is_empty() && !path.exists() {
+        return Ok(());
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let json = serde_json::to_string(cache)?;
+    retry::io(path, retry::DEFAULT_IO_RETRIES, || {
+        std::fs::write(&tmp, json.as_bytes())
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("renaming {} → {}", tmp.display(), path.display()))
+    })
+}
+
+/// Running row count and distinct-value sets of one file.
+#[derive(Default)]
+struct Counter {
+    events: usize,
+    tx_hashes: BTreeSet<String>,
+    blocks: BTreeSet<u64>,
+}
+
+impl Counter {
+    /// Stream the `block_number` and `tx_hash` columns of one file.
+    fn scan(&mut self, path: &Path) -> Result<()> {
+        let file = std::fs::File::open(path)?;
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+        let mask = ProjectionMask::columns(builder.parquet_schema(), ["block_number", "tx_hash"]);
+        for batch in builder.with_projection(mask).build()? {
+            let batch = batch?;
+            self.events = self.events.saturating_add(batch.num_rows());
+            self.blocks.extend(
+                column::<UInt64Array>(&batch, "block_number")?
+                    .values()
+                    .iter()
+                    .copied(),
+            );
+            for tx in column::<StringArray>(&batch, "tx_hash")?.iter().flatten() {
+                if !self.tx_hashes.contains(tx) {
+                    self.tx_hashes.insert(tx.to_owned());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The cache entry of a scanned file of `size` bytes modified at
+    /// `modified`.
+    fn finish(self, size: u64, modified: u128) -> FileStats {
+        FileStats {
+            size,
+            modified,
+            events: self.events,
+            transactions: self.tx_hashes.len(),
+            blocks: self.blocks.len(),
+            min_block: self.blocks.first().copied(),
+            max_block: self.blocks.last().copied(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::B256;
+
+    use super::*;
+    use crate::parquet::{self, logs_to_batch};
+    use crate::testutil::{TempDir, log};
+
+    fn write_logs(path: &Path, blocks: &[(u64, u8)]) {
+        let logs: Vec<_> = (0..)
+            .zip(blocks)
+            .map(|(i, &(block, tx))| {
+                log(block)
+                    .tx_hash(B256::repeat_byte(tx))
+                    .log_index(i)
+                    .build()
+            })
+            .collect();
+        let (batch, _) = logs_to_batch(&logs).unwrap();
+        parquet::write(path, &[batch]).unwrap();
+    }
+
+    #[test]
+    fn test_stats_cache_reused_until_file_changes() {
+        let data_dir = TempDir::new("stats-cache");
+        let dir = data_dir.join("8453");
+        std::fs::create_dir_all(&dir).unwrap();
+        let identity = dir.join("identity.parquet");
+        write_logs(&identity, &[(10, 1), (10, 1), (12, 2)]);
+
+        let stats = archive_stats(&data_dir, true).unwrap();
+        let s = stats.first().unwrap();
+        assert_eq!(
+            (s.events, s.transactions, s.blocks, s.min_block, s.max_block),
+            (3, 2, 2, Some(10), Some(12))
+        );
+        let cache = load_cache(&data_dir.join(CACHE_FILE));
+        assert_eq!(cache.len(), 1);
+
+        // A tampered entry with the file's version is trusted...
+        let key = Path::new("8453").join("identity.parquet");
+        let mut tampered = cache.clone();
+        if let Some(f) = tampered.get_mut(&*key.to_string_lossy()) {
+            f.events = 99;
+        }
+        save_cache(&data_dir.join(CACHE_FILE), &tampered).unwrap();
+        assert_eq!(
+            archive_stats(&data_dir, true)
+                .unwrap()
+                .first()
+                .unwrap()
+                .events,
+            99
+        );
+        // ...but not with --no-cache, nor once the file changes.
+        assert_eq!(
+            archive_stats(&data_dir, false)
+                .unwrap()
+                .first()
+                .unwrap()
+                .events,
+            3
+        );
+        save_cache(&data_dir.join(CACHE_FILE), &tampered).unwrap();
+        write_logs(&identity, &[(10, 1), (12, 2), (15, 3), (16, 4)]);
+        let s = archive_stats(&data_dir, true).unwrap();
+        assert_eq!(s.first().unwrap().events, 4);
+        assert_eq!(s.first().unwrap().max_block, Some(16));
+    }
+
+    #[test]
+    fn test_stats_returned_when_cache_cannot_be_written() {
+        let data_dir = TempDir::new("stats-cache-unwritable");
+        let dir = data_dir.join("8453");
+        std::fs::create_dir_all(&dir).unwrap();
+        write_logs(&dir.join("identity.parquet"), &[(10, 1)]);
+        // A directory in the cache's place makes the rename fail.
+        std::fs::create_dir_all(data_dir.join(CACHE_FILE).join("blocker")).unwrap();
+
+        let stats = archive_stats(&data_dir, true).unwrap();
+        assert_eq!(stats.first().unwrap().events, 1);
+    }
+}
@@ -0,0 +1,975 @@
+//! Remote storage for the archived Parquet files.
+//!
+//! Configured by the `[storage]` table in `config.toml`.  After a chain has
+//! synced successfully, its contract files are uploaded to
+//! `<url>/<chain_id>/<file>`:
+//!
+//! ```toml
+//! [storage]
+//! backend = "webdav"
+//! url = "https://nas.example.com/remote.php/dav/files/me/erc8004"
+//! username = "me"
+//! password = "app-password"
+//! ```
+//!
+//! The live `<contract>.parquet` file is uploaded on every sync; rotated
+//! `<contract>.NNN.parquet` files never change and are only uploaded if the
+//! server does not have them yet.
+//!
+//! The `clickhouse` backend inserts the archived rows into a ClickHouse
+//! table through its HTTP interface instead, for analytics without DuckDB
+//! or Spark:
+//!
+//! ```toml
+//! [storage]
+//! backend = "clickhouse"
+//! url = "http://localhost:8123"
+//! database = "default"
+//! table = "erc8004_events"
+//! username = "default"
+//! password = "secret"
+//! ```
+//!
+//! The table is created if missing.  Each sync reads only the archived rows
+//! from [`REORG_DEPTH`] blocks below the highest block already in the table
+//! for that chain and contract.  Rows above that block are inserted; blocks
+//! within the depth whose row count or `block_hash` differ from the archive
+//! (replaced by a reorg since they were inserted) are deleted and inserted
+//! again.  Deletes use lightweight `DELETE FROM`, available since
+//! ClickHouse 23.3.  Values reach the queries as HTTP query parameters
+//! (`{contract:String}` with `param_contract=`), never spliced into the
+//! SQL, and the database and table names are quoted as identifiers.
+//!
+//! Uploads read the Parquet archive, so storage requires the default
+//! `parquet` sink format.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+use alloy::transports::http::reqwest::header::{CONTENT_TYPE, IF_MATCH, IF_NONE_MATCH};
+use alloy::transports::http::reqwest::{self, Method, StatusCode, Url};
+use anyhow::{Context, Result, bail};
+use arrow_array::RecordBatch;
+use serde::{Deserialize, Serialize};
+
+use crate::export::CONTRACTS;
+use crate::parquet::{self, LogRow, REORG_DEPTH};
+
+/// The `[storage]` section of `config.toml`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum StorageConfig {
+    /// Upload to a WebDAV server (Nextcloud, ownCloud, Seafile, NAS devices).
+    Webdav(WebDavConfig),
+    /// Insert rows into a ClickHouse table over HTTP.
+    Clickhouse(ClickHouseConfig),
+}
+
+/// Connection settings for a WebDAV server.
+#[derive(Clone, Deserialize)]
+pub struct WebDavConfig {
+    /// Base collection URL; files go to `<url>/<chain_id>/<file>`.
+    pub url: String,
+    /// HTTP basic-auth user name.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// HTTP basic-auth password.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Accept invalid TLS certificates (self-signed NAS certificates).
+    #[serde(default)]
+    pub tls_insecure: bool,
+}
+
+impl fmt::Debug for WebDavConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebDavConfig")
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("tls_insecure", &self.tls_insecure)
+            .finish()
+    }
+}
+
+/// Connection settings for a ClickHouse server.
+#[derive(Clone, Deserialize)]
+pub struct ClickHouseConfig {
+    /// HTTP interface URL (`http://localhost:8123`).
+    pub url: String,
+    /// Database of the table.
+    #[serde(default = "default_clickhouse_database")]
+    pub database: String,
+    /// Table the events are inserted into.
+    #[serde(default = "default_clickhouse_table")]
+    pub table: String,
+    /// User name.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Password.
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+impl ClickHouseConfig {
+    /// Settings for `url` with the default database and table and no
+    /// credentials.
+    #[must_use]
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            database: default_clickhouse_database(),
+            table: default_clickhouse_table(),
+            username: None,
+            password: None,
+        }
+    }
+}
+
+fn default_clickhouse_database() -> String {
+    "default".to_owned()
+}
+
+fn default_clickhouse_table() -> String {
+    "erc8004_events".to_owned()
+}
+
+impl fmt::Debug for ClickHouseConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClickHouseConfig")
+            .field("url", &self.url)
+            .field("database", &self.database)
+            .field("table", &self.table)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/// A configured upload destination.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum StorageBackend {
+    /// See [`WebDavBackend`].
+    WebDav(WebDavBackend),
+    /// See [`ClickHouseBackend`].
+    ClickHouse(ClickHouseBackend),
+}
+
+impl StorageBackend {
+    /// Build the backend described by `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be built or the
+    /// configuration is invalid.
+    pub fn from_config(config: &StorageConfig) -> Result<Self> {
+        match config {
+            StorageConfig::Webdav(c) => Ok(Self::WebDav(WebDavBackend::new(c)?)),
+            StorageConfig::Clickhouse(c) => Ok(Self::ClickHouse(ClickHouseBackend::new(c)?)),
+        }
+    }
+
+    /// Upload every contract file of `chain_dir` for chain `chain_id`.
+    ///
+    /// Returns the number of files uploaded, or of rows inserted for
+    /// ClickHouse, which sends at most about `batch_rows` rows per insert.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be read or uploaded.
+    pub async fn upload_chain(
+        &self,
+        chain_dir: &Path,
+        chain_id: u64,
+        batch_rows: usize,
+    ) -> Result<usize> {
+        match self {
+            Self::WebDav(b) => b.upload_chain(chain_dir, chain_id).await,
+            Self::ClickHouse(b) => b.upload_chain(chain_dir, chain_id, batch_rows).await,
+        }
+    }
+}
+
+/// Uploads Parquet files with WebDAV `PROPFIND`, `MKCOL` and `PUT`.
+///
+/// Every `PUT` is conditional on the state seen by the preceding
+/// `PROPFIND` (`If-Match: <etag>`, or `If-None-Match: *` for new files), so
+/// a concurrent writer to the same file makes the upload fail instead of
+/// being silently overwritten.
+#[derive(Clone)]
+pub struct WebDavBackend {
+    client: reqwest::Client,
+    base_url: String,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl fmt::Debug for WebDavBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebDavBackend")
+            .field("base_url", &self.base_url)
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Server-side state of a file as reported by `PROPFIND`.
+enum Remote {
+    Missing,
+    Present { etag: Option<String> },
+}
+
+/// `PROPFIND` body requesting only the entity tag.
+const PROPFIND_ETAG: &str =
+    r#"<?xml version="1.0"?><d:propfind xmlns:d="DAV:"><d:prop><d:getetag/></d:prop></d:propfind>"#;
+
+impl WebDavBackend {
+    /// Create a backend from its configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be built.
+    pub fn new(config: &WebDavConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .danger_accept_invalid_certs(config.tls_insecure)
+            .build()
+            .context("building WebDAV client")?;
+        Ok(Self {
+            client,
+            base_url: config.url.trim_end_matches('/').to_owned(),
+            username: config.username.clone(),
+            password: config.password.clone(),
+        })
+    }
+
+    /// Upload every contract file of `chain_dir` for chain `chain_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be read or uploaded.
+    pub async fn upload_chain(&self, chain_dir: &Path, chain_id: u64) -> Result<usize> {
+        let collection = format!("{}/{chain_id}", self.base_url);
+        let mut created = false;
+        let mut uploaded = 0;
+        for contract in CONTRACTS {
+            let live = chain_dir.join(format!("{contract}.parquet"));
+            let mut files: Vec<_> = parquet::rotated_files(&live)?
+                .into_iter()
+                .map(|p| (p, false))
+                .collect();
+            if live.exists() {
+                files.push((live, true));
+            }
+            for (path, is_live) in files {
+                let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                let url = format!("{collection}/{name}");
+                let remote = self.stat(&url).await?;
+                if !is_live && matches!(remote, Remote::Present { .. }) {
+                    continue;
+                }
+                if !created {
+                    self.mkcol(&collection).await?;
+                    created = true;
+                }
+                let body =
+                    std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+                self.put(&url, body, &remote).await?;
+                tracing::info!(chain_id, file = name, url = %url, "uploaded to WebDAV");
+                uploaded += 1;
+            }
+        }
+        Ok(uploaded)
+    }
+
+    fn request(&self, method: Method, url: &str) -> reqwest::RequestBuilder {
+        let req = self.client.request(method, url);
+        match &self.username {
+            Some(user) => req.basic_auth(user, self.password.as_deref()),
+            None => req,
+        }
+    }
+
+    /// `PROPFIND` (depth 0) a file to learn whether it exists and its ETag.
+    async fn stat(&self, url: &str) -> Result<Remote> {
+        let resp = self
+            .request(Method::from_bytes(b"PROPFIND")?, url)
+            .header("Depth", "0")
+            .header(CONTENT_TYPE, "application/xml")
+            .body(PROPFIND_ETAG)
+            .send()
+            .await
+            .with_context(|| format!("PROPFIND {url}"))?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(Remote::Missing);
+        }
+        let body = resp
+            .error_for_status()
+            .with_context(|| format!("PROPFIND {url}"))?
+            .text()
+            .await?;
+        Ok(Remote::Present {
+            etag: parse_etag(&body),
+        })
+    }
+
+    /// Create a collection; an existing one (`405`) is not an error.
+    async fn mkcol(&self, url: &str) -> Result<()> {
+        let resp = self
+            .request(Method::from_bytes(b"MKCOL")?, url)
+            .send()
+            .await
+            .with_context(|| format!("MKCOL {url}"))?;
+        if resp.status() != StatusCode::METHOD_NOT_ALLOWED {
+            resp.error_for_status()
+                .with_context(|| format!("MKCOL {url}"))?;
+        }
+        Ok(())
+    }
+
+    /// Upload a file, conditional on the `remote` state seen before.
+    async fn put(&self, url: &str, body: Vec<u8>, remote: &Remote) -> Result<()> {
+        let req = self.request(Method::PUT, url).body(body);
+        let req = match remote {
+            Remote::Missing => req.header(IF_NONE_MATCH, "*"),
+            Remote::Present { etag: Some(etag) } => req.header(IF_MATCH, etag),
+            Remote::Present { etag: None } => req,
+        };
+        let resp = req.send().await.with_context(|| format!("PUT {url}"))?;
+        if resp.status() == StatusCode::PRECONDITION_FAILED {
+            bail!("PUT {url}: file was modified concurrently by another writer");
+        }
+        resp.error_for_status()
+            .with_context(|| format!("PUT {url}"))?;
+        Ok(())
+    }
+}
+
+/// Inserts archived rows into a ClickHouse `MergeTree` table with
+/// `INSERT … FORMAT JSONEachRow` over the HTTP interface.
+///
+/// The table has the event columns plus `chain_id` and `contract`, and is
+/// ordered by `(chain_id, block_number, log_index)`.  Inserts never split a
+/// block, so a failed upload leaves whole blocks behind and the next sync
+/// resumes after the last inserted one; blocks deleted for a reorg but not
+/// inserted again show up as differing on the next sync.
+#[derive(Clone)]
+pub struct ClickHouseBackend {
+    client: reqwest::Client,
+    url: String,
+    table: String,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl fmt::Debug for ClickHouseBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClickHouseBackend")
+            .field("url", &self.url)
+            .field("table", &self.table)
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+/// One row of the ClickHouse table.
+#[derive(Serialize)]
+struct InsertRow<'a> {
+    chain_id: u64,
+    contract: &'a str,
+    #[serde(flatten)]
+    row: &'a LogRow,
+}
+
+impl ClickHouseBackend {
+    /// Create a backend from its configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database or table name is empty or the
+    /// HTTP client cannot be built.
+    pub fn new(config: &ClickHouseConfig) -> Result<Self> {
+        for name in [&config.database, &config.table] {
+            if name.is_empty() {
+                bail!("empty ClickHouse identifier");
+            }
+        }
+        let client = reqwest::Client::builder()
+            .build()
+            .context("building ClickHouse client")?;
+        Ok(Self {
+            client,
+            url: config.url.trim_end_matches('/').to_owned(),
+            table: format!(
+                "{}.{}",
+                quote_identifier(&config.database),
+                quote_identifier(&config.table)
+            ),
+            username: config.username.clone(),
+            password: config.password.clone(),
+        })
+    }
+
+    /// Bring the table up to date with the archive in `chain_dir`, at most
+    /// about `batch_rows` rows per insert, and return the number of rows
+    /// inserted.  Creates the table if needed.
+    ///
+    /// Per contract, rows above the table's highest block are inserted and
+    /// blocks up to [`REORG_DEPTH`] below it that differ from the archive
+    /// are replaced; see the [module docs](self).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be read or a query fails.
+    pub async fn upload_chain(
+        &self,
+        chain_dir: &Path,
+        chain_id: u64,
+        batch_rows: usize,
+    ) -> Result<usize> {
+        self.execute(create_table_sql(&self.table), &[]).await?;
+        let mut inserted = 0;
+        for contract in CONTRACTS {
+            let remote_max = self.max_block(chain_id, contract).await?;
+            let floor = remote_max.map_or(0, |max| max.saturating_sub(REORG_DEPTH));
+            let batches =
+                parquet::read_rotated_from(&chain_dir.join(format!("{contract}.parquet")), floor)?;
+            if batches.is_empty() && remote_max.is_none() {
+                continue;
+            }
+            let rows = parquet::batch_to_rows(&parquet::sort_onchain(&batches)?)?;
+
+            let mut stale = BTreeSet::new();
+            if let Some(max) = remote_max {
+                let local = block_summaries(rows.iter().take_while(|r| r.block_number <= max));
+                let remote = self.block_summaries(chain_id, contract, floor).await?;
+                stale.extend(
+                    local
+                        .keys()
+                        .chain(remote.keys())
+                        .copied()
+                        .filter(|b| local.get(b) != remote.get(b)),
+                );
+            }
+            if !stale.is_empty() {
+                tracing::warn!(
+                    chain_id,
+                    contract,
+                    blocks = stale.len(),
+                    first = ?stale.first(),
+                    "replacing ClickHouse rows that differ from the archive"
+                );
+                self.delete_blocks(chain_id, contract, &stale).await?;
+            }
+
+            let rows: Vec<_> = rows
+                .into_iter()
+                .filter(|r| {
+                    remote_max.is_none_or(|max| r.block_number > max)
+                        || stale.contains(&r.block_number)
+                })
+                .collect();
+            for chunk in block_aligned_chunks(&rows, batch_rows) {
+                self.insert_rows(chain_id, contract, chunk).await?;
+                inserted += chunk.len();
+            }
+            tracing::info!(
+                chain_id,
+                contract,
+                rows = rows.len(),
+                "inserted into ClickHouse"
+            );
+        }
+        Ok(inserted)
+    }
+
+    /// Insert the rows of `batch` as events of `contract` on `chain_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the batch does not match the event schema or the
+    /// insert fails.
+    pub async fn insert(&self, chain_id: u64, contract: &str, batch: &RecordBatch) -> Result<()> {
+        self.insert_rows(chain_id, contract, &parquet::batch_to_rows(batch)?)
+            .await
+    }
+
+    async fn insert_rows(&self, chain_id: u64, contract: &str, rows: &[LogRow]) -> Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let mut body = format!("INSERT INTO {} FORMAT JSONEachRow\n", self.table);
+        body.push_str(&to_json_each_row(chain_id, contract, rows)?);
+        self.execute(body, &[]).await.map(drop)
+    }
+
+    /// Highest block of `contract` on `chain_id` in the table, if any.
+    async fn max_block(&self, chain_id: u64, contract: &str) -> Result<Option<u64>> {
+        let out = self
+            .execute(
+                format!(
+                    "SELECT count(), max(block_number) FROM {} \
+                     WHERE {CONTRACT_FILTER} FORMAT TabSeparated",
+                    self.table
+                ),
+                &contract_params(chain_id, contract),
+            )
+            .await?;
+        let mut fields = out.split_whitespace().map(str::parse::<u64>);
+        match (fields.next(), fields.next()) {
+            (Some(Ok(0)), _) => Ok(None),
+            (Some(Ok(_)), Some(Ok(max))) => Ok(Some(max)),
+            _ => bail!("unexpected ClickHouse response: {out:?}"),
+        }
+    }
+
+    /// Row count and block hash of each block of `contract` on `chain_id`
+    /// in the table from block `from` on.
+    async fn block_summaries(
+        &self,
+        chain_id: u64,
+        contract: &str,
+        from: u64,
+    ) -> Result<BTreeMap<u64, BlockSummary>> {
+        let mut params = contract_params(chain_id, contract);
+        params.push(("from", from.to_string()));
+        let out = self
+            .execute(
+                format!(
+                    "SELECT block_number, count(), any(block_hash) FROM {} \
+                     WHERE {CONTRACT_FILTER} AND block_number >= {{from:UInt64}} \
+                     GROUP BY block_number FORMAT TabSeparated",
+                    self.table
+                ),
+                &params,
+            )
+            .await?;
+        out.lines()
+            .map(|line| {
+                let mut fields = line.split('\t');
+                match (fields.next(), fields.next(), fields.next()) {
+                    (Some(block), Some(rows), Some(hash)) => Ok((
+                        block.parse()?,
+                        BlockSummary {
+                            rows: rows.parse()?,
+                            block_hash: (hash != "\\N").then(|| hash.to_owned()),
+                        },
+                    )),
+                    _ => bail!("unexpected ClickHouse response line: {line:?}"),
+                }
+            })
+            .collect()
+    }
+
+    /// Delete the rows of `blocks` of `contract` on `chain_id`.
+    async fn delete_blocks(
+        &self,
+        chain_id: u64,
+        contract: &str,
+        blocks: &BTreeSet<u64>,
+    ) -> Result<()> {
+        let list = blocks
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut params = contract_params(chain_id, contract);
+        params.push(("blocks", format!("[{list}]")));
+        self.execute(
+            format!(
+                "DELETE FROM {} WHERE {CONTRACT_FILTER} \
+                 AND block_number IN {{blocks:Array(UInt64)}}",
+                self.table
+            ),
+            &params,
+        )
+        .await
+        .map(drop)
+    }
+
+    /// Run `sql` (followed by any data) with the query parameters `params`
+    /// and return the response body.
+    async fn execute(&self, sql: String, params: &[(&str, String)]) -> Result<String> {
+        let mut url = Url::parse(&self.url).with_context(|| format!("invalid URL {}", self.url))?;
+        for (name, value) in params {
+            url.query_pairs_mut()
+                .append_pair(&format!("param_{name}"), value);
+        }
+        let req = self.client.post(url).body(sql);
+        let req = match &self.username {
+            Some(user) => req.basic_auth(user, self.password.as_deref()),
+            None => req,
+        };
+        let resp = req
+            .send()
+            .await
+            .with_context(|| format!("POST {}", self.url))?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        if !status.is_success() {
+            bail!("ClickHouse returned {status}: {}", text.trim());
+        }
+        Ok(text)
+    }
+}
+
+/// `WHERE` condition selecting one contract of one chain, with the
+/// parameters of [`contract_params`].
+const CONTRACT_FILTER: &str = "chain_id = {chain_id:UInt64} AND contract = {contract:String}";
+
+/// Query parameters of [`CONTRACT_FILTER`].
+fn contract_params(chain_id: u64, contract: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("chain_id", chain_id.to_string()),
+        ("contract", contract.to_owned()),
+    ]
+}
+
+/// Quote `name` as a ClickHouse identifier.
+fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`"))
+}
+
+/// `CREATE TABLE IF NOT EXISTS` statement of the events table.
+fn create_table_sql(table: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {table} (\
+         chain_id UInt64, \
+         contract LowCardinality(String), \
+         block_number UInt64, \
+         tx_hash String, \
+         tx_index UInt32, \
+         log_index UInt32, \
+         address LowCardinality(String), \
+         topic0 LowCardinality(String), \
+         topic1 Nullable(String), \
+         topic2 Nullable(String), \
+         topic3 Nullable(String), \
+         data String, \
+         removed Bool, \
+         tx_nonce Nullable(UInt64), \
+         block_hash Nullable(String)\
+         ) ENGINE = MergeTree ORDER BY (chain_id, block_number, log_index)"
+    )
+}
+
+/// What [`ClickHouseBackend::upload_chain`] compares per block to find
+/// rows replaced by a reorg.
+#[derive(Debug, PartialEq, Eq)]
+struct BlockSummary {
+    rows: u64,
+    block_hash: Option<String>,
+}
+
+/// Summaries of the blocks of `rows`, taking the first non-null hash of
+/// each block as ClickHouse's `any` does.
+fn block_summaries<'a>(rows: impl Iterator<Item = &'a LogRow>) -> BTreeMap<u64, BlockSummary> {
+    let mut blocks = BTreeMap::<u64, BlockSummary>::new();
+    for row in rows {
+        let block = blocks.entry(row.block_number).or_insert(BlockSummary {
+            rows: 0,
+            block_hash: None,
+        });
+        block.rows += 1;
+        if block.block_hash.is_none() {
+            block.block_hash.clone_from(&row.block_hash);
+        }
+    }
+    blocks
+}
+
+/// `rows` as `JSONEachRow` lines with `chain_id` and `contract` added.
+fn to_json_each_row(chain_id: u64, contract: &str, rows: &[LogRow]) -> Result<String> {
+    let mut out = String::new();
+    for row in rows {
+        out.push_str(&serde_json::to_string(&InsertRow {
+            chain_id,
+            contract,
+            row,
+        })?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Split `rows`, sorted by block, into runs of about `size` rows without
+/// splitting a block; a block larger than `size` forms its own run.
+fn block_aligned_chunks(rows: &[LogRow], size: usize) -> Vec<&[LogRow]> {
+    let mut chunks = Vec::new();
+    let mut rest = rows;
+    let mut len = 0;
+    for block in rows.chunk_by(|a, b| a.block_number == b.block_number) {
+        if len > 0 && len + block.len() > size {
+            let (chunk, tail) = rest.split_at(len);
+            chunks.push(chunk);
+            rest = tail;
+            len = 0;
+        }
+        len += block.len();
+    }
+    if !rest.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+/// Extract the first `getetag` value from a `PROPFIND` multistatus body,
+/// whatever namespace prefix the server uses.
+fn parse_etag(xml: &str) -> Option<String> {
+    let rest = xml.get(xml.find("getetag")?..)?;
+    let open_end = rest.find('>')?;
+    if rest.get(..open_end)?.ends_with('/') {
+        return None;
+    }
+    let value = rest.get(open_end + 1..)?;
+    let value = value.get(..value.find('<')?)?.trim();
+    (!value.is_empty()).then(|| value.replace("&quot;", "\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    use alloy::primitives::B256;
+
+    use super::*;
+    use crate::testutil::{TempDir, log};
+
+    #[test]
+    fn test_parse_etag_handles_prefixes_and_entities() {
+        let nextcloud = r#"<d:multistatus xmlns:d="DAV:"><d:response><d:propstat><d:prop><d:getetag>&quot;5f1a&quot;</d:getetag></d:prop></d:propstat></d:response></d:multistatus>"#;
+        assert_eq!(parse_etag(nextcloud).as_deref(), Some("\"5f1a\""));
+
+        let apache = r#"<D:multistatus xmlns:D="DAV:"><D:response><D:propstat><D:prop><lp1:getetag xmlns:lp1="DAV:">"abc-123"</lp1:getetag></D:prop></D:propstat></D:response></D:multistatus>"#;
+        assert_eq!(parse_etag(apache).as_deref(), Some("\"abc-123\""));
+    }
+
+    #[test]
+    fn test_parse_etag_missing_or_empty() {
+        assert_eq!(parse_etag("<d:prop><d:getetag/></d:prop>"), None);
+        assert_eq!(parse_etag("<d:prop><d:getetag></d:getetag></d:prop>"), None);
+        assert_eq!(parse_etag("<d:prop></d:prop>"), None);
+    }
+
+    #[test]
+    fn test_storage_config_from_toml() {
+        let config: crate::config::Config = toml::from_str(
+            r#"
+            [storage]
+            backend = "webdav"
+            url = "https://nas.local/dav/"
+            username = "me"
+            password = "secret"
+            "#,
+        )
+        .unwrap();
+        let Some(StorageConfig::Webdav(webdav)) = config.storage else {
+            panic!("expected WebDAV storage");
+        };
+        assert_eq!(webdav.url, "https://nas.local/dav/");
+        assert!(!webdav.tls_insecure);
+        assert!(!format!("{webdav:?}").contains("secret"));
+    }
+
+    #[test]
+    fn test_clickhouse_config_from_toml() {
+        let config: crate::config::Config = toml::from_str(
+            r#"
+            [storage]
+            backend = "clickhouse"
+            url = "http://localhost:8123/"
+            password = "secret"
+            "#,
+        )
+        .unwrap();
+        let Some(StorageConfig::Clickhouse(clickhouse)) = config.storage else {
+            panic!("expected ClickHouse storage");
+        };
+        assert_eq!(clickhouse.database, "default");
+        assert_eq!(clickhouse.table, "erc8004_events");
+        assert!(!format!("{clickhouse:?}").contains("secret"));
+
+        let backend = ClickHouseBackend::new(&clickhouse).unwrap();
+        assert_eq!(backend.table, "`default`.`erc8004_events`");
+        assert!(
+            create_table_sql(&backend.table)
+                .contains("ORDER BY (chain_id, block_number, log_index)")
+        );
+        let odd = ClickHouseConfig {
+            table: "events`; DROP TABLE x".to_owned(),
+            ..clickhouse.clone()
+        };
+        assert_eq!(
+            ClickHouseBackend::new(&odd).unwrap().table,
+            "`default`.`events\\`; DROP TABLE x`"
+        );
+        let empty = ClickHouseConfig {
+            database: String::new(),
+            ..clickhouse
+        };
+        assert!(ClickHouseBackend::new(&empty).is_err());
+    }
+
+    fn row(block_number: u64) -> LogRow {
+        LogRow {
+            block_number,
+            tx_hash: format!("0x{block_number:064x}"),
+            tx_index: 0,
+            log_index: 0,
+            address: "0x8004a169fb4a3325136eb29fa0ceb6d2e539a432".to_owned(),
+            topic0: format!("0x{:064x}", 1),
+            topic1: None,
+            topic2: None,
+            topic3: None,
+            data: "0x".to_owned(),
+            removed: false,
+            tx_nonce: None,
+            block_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_json_each_row_adds_chain_and_contract() {
+        let out = to_json_each_row(8453, "identity", &[row(1), row(2)]).unwrap();
+        let lines: Vec<serde_json::Value> = out
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        let first = lines.first().unwrap();
+        assert_eq!(first.get("chain_id"), Some(&serde_json::json!(8453)));
+        assert_eq!(first.get("contract"), Some(&serde_json::json!("identity")));
+        assert_eq!(first.get("block_number"), Some(&serde_json::json!(1)));
+        assert_eq!(first.get("tx_nonce"), Some(&serde_json::Value::Null));
+    }
+
+    #[test]
+    fn test_block_aligned_chunks_keep_blocks_whole() {
+        let rows: Vec<_> = [1, 1, 2, 3, 3, 3, 4].into_iter().map(row).collect();
+        let sizes = |size| {
+            block_aligned_chunks(&rows, size)
+                .iter()
+                .map(|c| c.len())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sizes(3), [3, 3, 1]);
+        assert_eq!(sizes(1), [2, 1, 3, 1]);
+        assert_eq!(sizes(100), [7]);
+        assert!(block_aligned_chunks(&[], 3).is_empty());
+    }
+
+    /// Serve `responses` in order, one per HTTP request, from a thread
+    /// standing in for ClickHouse; returns its URL and a handle yielding
+    /// the request targets and bodies.
+    fn mock_clickhouse(
+        responses: Vec<String>,
+    ) -> (String, std::thread::JoinHandle<Vec<(String, String)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut conn: Option<BufReader<TcpStream>> = None;
+            let mut bodies = Vec::new();
+            for response in responses {
+                let body = loop {
+                    let reader =
+                        conn.get_or_insert_with(|| BufReader::new(listener.accept().unwrap().0));
+                    if let Some(body) = read_request(reader) {
+                        break body;
+                    }
+                    // The client closed the connection: wait for the next.
+                    conn = None;
+                };
+                bodies.push(body);
+                let stream = conn.as_mut().unwrap().get_mut();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{response}",
+                    response.len()
+                )
+                .unwrap();
+            }
+            bodies
+        });
+        (url, server)
+    }
+
+    /// Read one HTTP request and return its target and body, or `None` if
+    /// the connection was closed.
+    fn read_request(reader: &mut impl BufRead) -> Option<(String, String)> {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).ok()? == 0 {
+            return None;
+        }
+        let target = request_line.split(' ').nth(1)?.to_owned();
+        let mut len = 0;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            if line == "\r\n" {
+                break;
+            }
+            if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                len = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body).ok()?;
+        Some((target, String::from_utf8(body).unwrap()))
+    }
+
+    #[tokio::test]
+    async fn test_clickhouse_upload_inserts_new_and_reorged_blocks() {
+        let dir = TempDir::new("clickhouse-upload");
+        let hash = B256::with_last_byte;
+        let (batch, _) = parquet::logs_to_batch(&[
+            log(10).block_hash(hash(1)).build(),
+            log(20).block_hash(hash(2)).build(),
+            log(30).block_hash(hash(3)).build(),
+        ])
+        .unwrap();
+        parquet::write(&dir.join("identity.parquet"), &[batch]).unwrap();
+
+        // The table has blocks 10 and 20, but block 20 under the hash a
+        // reorg has since replaced; it has no reputation rows.
+        let (url, server) = mock_clickhouse(vec![
+            String::new(),
+            "2\t20\n".to_owned(),
+            format!("10\t1\t{:#x}\n20\t1\t{:#x}\n", hash(1), hash(9)),
+            String::new(),
+            String::new(),
+            "0\t0\n".to_owned(),
+        ]);
+        let backend = ClickHouseBackend::new(&ClickHouseConfig::new(url)).unwrap();
+        assert_eq!(backend.upload_chain(&dir, 8453, 100).await.unwrap(), 2);
+
+        let requests = server.join().unwrap();
+        let [create, max, summaries, delete, insert, _] = requests.as_slice() else {
+            panic!("unexpected requests: {requests:?}");
+        };
+        assert!(create.1.starts_with("CREATE TABLE IF NOT EXISTS"));
+        assert!(max.1.contains("contract = {contract:String}"));
+        assert_eq!(max.0, "/?param_chain_id=8453&param_contract=identity");
+        assert!(summaries.1.contains("block_number >= {from:UInt64}"));
+        assert!(summaries.0.ends_with("&param_from=0"));
+        assert!(
+            delete
+                .1
+                .ends_with("AND block_number IN {blocks:Array(UInt64)}")
+        );
+        assert!(delete.0.ends_with("&param_blocks=%5B20%5D"));
+        let inserted: Vec<_> = insert
+            .1
+            .lines()
+            .skip(1)
+            .map(|l| serde_json::from_str::<LogRow>(l).unwrap().block_number)
+            .collect();
+        assert_eq!(inserted, [20, 30]);
+    }
+}
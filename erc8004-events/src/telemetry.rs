@@ -0,0 +1,207 @@
+//! OpenTelemetry metrics (`--features otel`).
+//!
+//! With the feature enabled and `OTEL_EXPORTER_OTLP_ENDPOINT` set, [`init`]
+//! exports these instruments over OTLP/HTTP:
+//!
+//! - `rpc_call_duration_ms` — histogram of `eth_getLogs` latency, labelled
+//!   with `chain_id`, `method` and `success`.
+//! - `events_fetched_total` — counter of archived events, labelled with
+//!   `chain_id` and `contract`.
+//! - `batch_size_blocks` — histogram of requested block windows, labelled
+//!   with `chain_id`.
+//! - `rpc_fallback_count` — counter of fallbacks to the next RPC, labelled
+//!   with `chain_id`.
+//! - `removed_events_total` — counter of archived logs flagged `removed`
+//!   (dropped by a reorg), labelled with `chain_id` and `contract`.
+//!
+//! The W3C trace-context propagator is registered as well, so that future
+//! RPC spans carry `traceparent` headers; no endpoint accepts them yet and
+//! none are sent.  Without the feature every recording function is a no-op.
+
+#![cfg_attr(
+    not(feature = "otel"),
+    expect(
+        clippy::missing_const_for_fn,
+        reason = "the recording functions only do work with the otel feature"
+    )
+)]
+
+use std::time::Duration;
+
+use anyhow::Result;
+
+#[cfg(feature = "otel")]
+mod otel {
+    use std::sync::LazyLock;
+
+    use opentelemetry::global;
+    use opentelemetry::metrics::{Counter, Histogram};
+
+    /// Instruments recorded by the sync.
+    pub(super) struct Instruments {
+        pub(super) rpc_call_duration: Histogram<f64>,
+        pub(super) events_fetched: Counter<u64>,
+        pub(super) batch_size: Histogram<u64>,
+        pub(super) rpc_fallback: Counter<u64>,
+        pub(super) removed_events: Counter<u64>,
+    }
+
+    /// Created from the global meter on first use, i.e. after [`super::init`]
+    /// registered the provider.
+    pub(super) static INSTRUMENTS: LazyLock<Instruments> = LazyLock::new(|| {
+        let meter = global::meter("erc8004-events");
+        Instruments {
+            rpc_call_duration: meter
+                .f64_histogram("rpc_call_duration_ms")
+                .with_unit("ms")
+                .with_description("Duration of RPC calls")
+                .build(),
+            events_fetched: meter
+                .u64_counter("events_fetched_total")
+                .with_description("Events written to the archive")
+                .build(),
+            batch_size: meter
+                .u64_histogram("batch_size_blocks")
+                .with_description("Blocks requested per eth_getLogs call")
+                .build(),
+            rpc_fallback: meter
+                .u64_counter("rpc_fallback_count")
+                .with_description("Fallbacks to the next RPC of a chain")
+                .build(),
+            removed_events: meter
+                .u64_counter("removed_events_total")
+                .with_description("Archived logs flagged removed by a reorg")
+                .build(),
+        }
+    });
+}
+
+/// Exporter handle returned by [`init`]; call [`Telemetry::shutdown`]
+/// before exiting so the last metrics are flushed.
+#[derive(Debug)]
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    provider: opentelemetry_sdk::metrics::SdkMeterProvider,
+}
+
+impl Telemetry {
+    /// Flush pending metrics and stop the exporter.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the final export fails.
+    pub fn shutdown(self) -> Result<()> {
+        #[cfg(feature = "otel")]
+        self.provider.shutdown()?;
+        Ok(())
+    }
+}
+
+/// Start the OTLP metrics exporter if `OTEL_EXPORTER_OTLP_ENDPOINT` is set.
+///
+/// Returns `None` when the variable is unset or the `otel` feature is
+/// disabled.
+///
+/// # Errors
+///
+/// Returns an error if the exporter cannot be built.
+pub fn init() -> Result<Option<Telemetry>> {
+    if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_none() {
+        return Ok(None);
+    }
+    #[cfg(feature = "otel")]
+    {
+        use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+
+        let exporter = opentelemetry_otlp::MetricExporter::builder()
+            .with_http()
+            .build()?;
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter).build())
+            .build();
+        opentelemetry::global::set_meter_provider(provider.clone());
+        opentelemetry::global::set_text_map_propagator(
+            opentelemetry_sdk::propagation::TraceContextPropagator::new(),
+        );
+        tracing::info!("exporting OpenTelemetry metrics");
+        Ok(Some(Telemetry { provider }))
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        tracing::warn!("OTEL_EXPORTER_OTLP_ENDPOINT is set but the otel feature is disabled");
+        Ok(None)
+    }
+}
+
+/// Record one RPC call.
+pub fn rpc_call(chain_id: u64, method: &'static str, success: bool, elapsed: Duration) {
+    #[cfg(feature = "otel")]
+    otel::INSTRUMENTS.rpc_call_duration.record(
+        elapsed.as_secs_f64() * 1000.0,
+        &[
+            opentelemetry::KeyValue::new("chain_id", chain_id.to_string()),
+            opentelemetry::KeyValue::new("method", method),
+            opentelemetry::KeyValue::new("success", success),
+        ],
+    );
+    #[cfg(not(feature = "otel"))]
+    let _ = (chain_id, method, success, elapsed);
+}
+
+/// Record events written to a contract's archive.
+pub fn events_fetched(chain_id: u64, contract: &str, events: usize) {
+    #[cfg(feature = "otel")]
+    otel::INSTRUMENTS.events_fetched.add(
+        events as u64,
+        &[
+            opentelemetry::KeyValue::new("chain_id", chain_id.to_string()),
+            opentelemetry::KeyValue::new("contract", contract.to_owned()),
+        ],
+    );
+    #[cfg(not(feature = "otel"))]
+    let _ = (chain_id, contract, events);
+}
+
+/// Record archived logs flagged `removed`.
+pub fn removed_events(chain_id: u64, contract: &str, events: usize) {
+    #[cfg(feature = "otel")]
+    if events > 0 {
+        otel::INSTRUMENTS.removed_events.add(
+            events as u64,
+            &[
+                opentelemetry::KeyValue::new("chain_id", chain_id.to_string()),
+                opentelemetry::KeyValue::new("contract", contract.to_owned()),
+            ],
+        );
+    }
+    #[cfg(not(feature = "otel"))]
+    let _ = (chain_id, contract, events);
+}
+
+/// Record the block window of one `eth_getLogs` call.
+pub fn batch_size(chain_id: u64, blocks: u64) {
+    #[cfg(feature = "otel")]
+    otel::INSTRUMENTS.batch_size.record(
+        blocks,
+        &[opentelemetry::KeyValue::new(
+            "chain_id",
+            chain_id.to_string(),
+        )],
+    );
+    #[cfg(not(feature = "otel"))]
+    let _ = (chain_id, blocks);
+}
+
+/// Record a fallback to the next RPC of a chain.
+pub fn rpc_fallback(chain_id: u64) {
+    #[cfg(feature = "otel")]
+    otel::INSTRUMENTS.rpc_fallback.add(
+        1,
+        &[opentelemetry::KeyValue::new(
+            "chain_id",
+            chain_id.to_string(),
+        )],
+    );
+    #[cfg(not(feature = "otel"))]
+    let _ = chain_id;
+}
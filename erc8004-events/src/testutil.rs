@@ -0,0 +1,117 @@
+//! Fixtures shared by the unit tests.
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use alloy::primitives::{Address, B256, Bytes, LogData};
+use alloy::rpc::types::Log;
+
+/// A fresh directory `erc8004-events-<name>-<pid>` under the system temp
+/// directory, removed again when dropped.
+///
+/// Leftovers of an earlier, aborted run with the same PID are removed
+/// first.
+#[derive(Debug)]
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create the directory; `name` must be unique among the tests.
+    pub(crate) fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("erc8004-events-{name}-{}", std::process::id()));
+        std::fs::remove_dir_all(&path).ok();
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+/// Start a [`Log`] at `block` that [`logs_to_batch`] accepts: emitted by
+/// the zero address with a zero `topic0` and no data, in a transaction
+/// with a zero hash, at index 0 in the block and the transaction.
+///
+/// [`logs_to_batch`]: crate::parquet::logs_to_batch
+pub(crate) fn log(block: u64) -> LogBuilder {
+    LogBuilder(Log {
+        inner: alloy::primitives::Log {
+            address: Address::ZERO,
+            data: LogData::new_unchecked(vec![B256::ZERO], Bytes::new()),
+        },
+        block_number: Some(block),
+        transaction_hash: Some(B256::ZERO),
+        transaction_index: Some(0),
+        log_index: Some(0),
+        ..Default::default()
+    })
+}
+
+/// One default [`log`] per block.
+pub(crate) fn logs_at(blocks: &[u64]) -> Vec<Log> {
+    blocks.iter().map(|&b| log(b).build()).collect()
+}
+
+/// Builder returned by [`log`].
+#[derive(Debug, Clone)]
+pub(crate) struct LogBuilder(Log);
+
+impl LogBuilder {
+    /// Set the emitting contract.
+    #[must_use]
+    pub(crate) const fn address(mut self, address: Address) -> Self {
+        self.0.inner.address = address;
+        self
+    }
+
+    /// Set the topics and data.
+    #[must_use]
+    pub(crate) fn data(mut self, data: LogData) -> Self {
+        self.0.inner.data = data;
+        self
+    }
+
+    /// Set the transaction hash.
+    #[must_use]
+    pub(crate) const fn tx_hash(mut self, hash: B256) -> Self {
+        self.0.transaction_hash = Some(hash);
+        self
+    }
+
+    /// Set the transaction's index in the block.
+    #[must_use]
+    pub(crate) const fn tx_index(mut self, index: u64) -> Self {
+        self.0.transaction_index = Some(index);
+        self
+    }
+
+    /// Set the log's index in the block.
+    #[must_use]
+    pub(crate) const fn log_index(mut self, index: u64) -> Self {
+        self.0.log_index = Some(index);
+        self
+    }
+
+    /// Set the block hash.
+    #[must_use]
+    pub(crate) const fn block_hash(mut self, hash: B256) -> Self {
+        self.0.block_hash = Some(hash);
+        self
+    }
+
+    /// Finish the log.
+    pub(crate) const fn build(self) -> Log {
+        self.0
+    }
+}
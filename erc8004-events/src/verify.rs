@@ -0,0 +1,375 @@
+//! Integrity verification of a synced archive.
+//!
+//! [`verify_archive`] decodes every primary Parquet file (including rotated
+//! `<contract>.NNN.parquet` files) and checks it against the chain metadata
+//! and the cursor, catching disk corruption and misfiled data that would
+//! otherwise only surface when the archive is consumed.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use alloy::primitives::Address;
+use anyhow::Result;
+
+use crate::chains::ChainConfig;
+use crate::cursor::Cursor;
+use crate::export::CONTRACTS;
+use crate::fetcher::discover_archived_chains;
+use crate::parquet::{self, IntegrityReport};
+
+/// A single failed check on an archived file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Problem {
+    /// The file or its footer could not be read at all.
+    Unreadable(String),
+    /// Some row groups failed to decode.
+    CorruptedRowGroups {
+        /// Number of corrupted row groups.
+        count: usize,
+        /// Rows in those row groups according to the footer.
+        rows: u64,
+    },
+    /// The file holds blocks past the cursor (and any checkpoint).
+    AheadOfCursor {
+        /// Highest archived block.
+        max_block: u64,
+        /// Highest block the cursor accounts for.
+        last_block: u64,
+    },
+    /// The file holds blocks before the contract was deployed.
+    BeforeDeployment {
+        /// Lowest archived block.
+        min_block: u64,
+        /// The chain's deployment block.
+        deployment_block: u64,
+    },
+    /// Rows come from more than one contract address.
+    MixedAddresses(Vec<String>),
+    /// Rows come from a different contract than the file is named after.
+    WrongAddress {
+        /// Address found in the file.
+        found: String,
+        /// Address of the registry the file belongs to.
+        expected: String,
+    },
+}
+
+impl Problem {
+    /// A short suggestion for repairing the problem.
+    #[must_use]
+    pub const fn recommendation(&self) -> &'static str {
+        match self {
+            Self::Unreadable(_) => {
+                "restore the file from a backup or re-sync the chain with --reset"
+            }
+            Self::CorruptedRowGroups { .. } => {
+                "run sync: corrupted row groups are dropped and their block range re-fetched"
+            }
+            Self::AheadOfCursor { .. } => "run sync to advance the cursor past the interrupted run",
+            Self::BeforeDeployment { .. } | Self::MixedAddresses(_) | Self::WrongAddress { .. } => {
+                "check the chain configuration, then re-sync the chain with --reset"
+            }
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreadable(e) => write!(f, "unreadable: {e}"),
+            Self::CorruptedRowGroups { count, rows } => {
+                write!(f, "{count} corrupted row group(s) ({rows} rows)")
+            }
+            Self::AheadOfCursor {
+                max_block,
+                last_block,
+            } => write!(f, "max block {max_block} is past cursor block {last_block}"),
+            Self::BeforeDeployment {
+                min_block,
+                deployment_block,
+            } => write!(
+                f,
+                "min block {min_block} is before deployment block {deployment_block}"
+            ),
+            Self::MixedAddresses(addrs) => {
+                write!(
+                    f,
+                    "rows from {} addresses: {}",
+                    addrs.len(),
+                    addrs.join(", ")
+                )
+            }
+            Self::WrongAddress { found, expected } => {
+                write!(f, "rows from {found}, expected {expected}")
+            }
+        }
+    }
+}
+
+/// Verification outcome for one Parquet file.
+#[derive(Debug, Clone)]
+pub struct FileVerification {
+    /// EIP-155 chain ID.
+    pub chain_id: u64,
+    /// Contract name (`identity` or `reputation`).
+    pub contract: String,
+    /// Path of the verified file.
+    pub path: PathBuf,
+    /// Rows decoded from readable row groups.
+    pub rows: usize,
+    /// Rows lost to corrupted row groups.
+    pub corrupted_rows: u64,
+    /// Failed checks; empty if the file passed.
+    pub problems: Vec<Problem>,
+}
+
+impl FileVerification {
+    /// Whether every check passed.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Verify every archived contract under `data_dir`, optionally restricted
+/// to one chain.
+///
+/// # Errors
+///
+/// Returns an error if the data directory cannot be listed or a cursor
+/// cannot be read.  Problems with individual files are reported in the
+/// result instead.
+pub fn verify_archive(data_dir: &Path, chain_id: Option<u64>) -> Result<Vec<FileVerification>> {
+    let mut results = Vec::new();
+    for (chain, dir) in discover_archived_chains(data_dir)? {
+        if chain_id.is_some_and(|id| id != chain.chain_id()) {
+            continue;
+        }
+        let cursor = Cursor::load(&dir)?;
+        for contract in CONTRACTS {
+            let last_block = cursor.as_ref().map(|c| {
+                c.checkpoints
+                    .get(contract)
+                    .map_or(c.last_block, |&b| b.max(c.last_block))
+            });
+            results.extend(verify_contract(&dir, &chain, contract, last_block)?);
+        }
+    }
+    Ok(results)
+}
+
+/// Verify the rotated and live files of one contract in `chain_dir`.
+///
+/// `last_block` is the highest block the cursor accounts for; the
+/// past-cursor check is skipped when it is `None`.
+///
+/// # Errors
+///
+/// Returns an error if the rotated files cannot be listed.
+pub fn verify_contract(
+    chain_dir: &Path,
+    chain: &ChainConfig,
+    contract: &str,
+    last_block: Option<u64>,
+) -> Result<Vec<FileVerification>> {
+    let base = chain_dir.join(format!("{contract}.parquet"));
+    let mut files = parquet::rotated_files(&base)?;
+    if base.exists() {
+        files.push(base);
+    }
+    let addrs = chain.addresses();
+    let expected = if contract == "identity" {
+        addrs.identity
+    } else {
+        addrs.reputation
+    };
+    Ok(files
+        .into_iter()
+        .map(|path| {
+            let (rows, corrupted_rows, problems) = match parquet::verify_integrity(&path) {
+                Ok(report) => (
+                    report.rows,
+                    report.corrupted_rows(),
+                    check(&report, chain, expected, last_block),
+                ),
+                Err(e) => (0, 0, vec![Problem::Unreadable(format!("{e:#}"))]),
+            };
+            FileVerification {
+                chain_id: chain.chain_id(),
+                contract: contract.to_owned(),
+                path,
+                rows,
+                corrupted_rows,
+                problems,
+            }
+        })
+        .collect())
+}
+
+/// Run the content checks on a decoded file.
+fn check(
+    report: &IntegrityReport,
+    chain: &ChainConfig,
+    expected: Address,
+    last_block: Option<u64>,
+) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    if !report.corrupted.is_empty() {
+        problems.push(Problem::CorruptedRowGroups {
+            count: report.corrupted.len(),
+            rows: report.corrupted_rows(),
+        });
+    }
+    if let Some((min_block, max_block)) = report.block_range {
+        if let Some(last_block) = last_block
+            && max_block > last_block
+        {
+            problems.push(Problem::AheadOfCursor {
+                max_block,
+                last_block,
+            });
+        }
+        if min_block < chain.deployment_block {
+            problems.push(Problem::BeforeDeployment {
+                min_block,
+                deployment_block: chain.deployment_block,
+            });
+        }
+    }
+    let expected = format!("{expected:#x}");
+    match report.addresses.iter().collect::<Vec<_>>().as_slice() {
+        [] => {}
+        [found] if found.eq_ignore_ascii_case(&expected) => {}
+        [found] => problems.push(Problem::WrongAddress {
+            found: (*found).clone(),
+            expected,
+        }),
+        many => problems.push(Problem::MixedAddresses(
+            many.iter().map(|a| (*a).clone()).collect(),
+        )),
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::RecordBatch;
+
+    use super::*;
+    use crate::chains;
+    use crate::testutil::{TempDir, log};
+
+    fn batch(blocks: &[u64], address: Address) -> RecordBatch {
+        let logs: Vec<_> = blocks
+            .iter()
+            .map(|&b| log(b).address(address).build())
+            .collect();
+        parquet::logs_to_batch(&logs).unwrap().0
+    }
+
+    fn fixture(name: &str) -> (TempDir, &'static ChainConfig) {
+        let chain = chains::by_chain_id(8453).unwrap();
+        (TempDir::new(&format!("verify-{name}")), chain)
+    }
+
+    #[test]
+    fn test_verify_contract_passes_clean_file() {
+        let (dir, chain) = fixture("clean");
+        let start = chain.deployment_block;
+        parquet::write(
+            &dir.join("identity.parquet"),
+            &[batch(&[start, start + 10], chain.addresses().identity)],
+        )
+        .unwrap();
+
+        let results = verify_contract(&dir, chain, "identity", Some(start + 10)).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results.iter().all(FileVerification::passed));
+    }
+
+    #[test]
+    fn test_verify_contract_reports_content_problems() {
+        let (dir, chain) = fixture("problems");
+        let start = chain.deployment_block;
+        let (a, b) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        parquet::write(
+            &dir.join("identity.parquet"),
+            &[batch(&[start - 1], a), batch(&[start + 100], b)],
+        )
+        .unwrap();
+
+        let results = verify_contract(&dir, chain, "identity", Some(start + 50)).unwrap();
+        let problems = &results.first().unwrap().problems;
+        assert!(problems.contains(&Problem::AheadOfCursor {
+            max_block: start + 100,
+            last_block: start + 50,
+        }));
+        assert!(problems.contains(&Problem::BeforeDeployment {
+            min_block: start - 1,
+            deployment_block: start,
+        }));
+        assert!(problems.contains(&Problem::MixedAddresses(vec![
+            format!("{a:#x}"),
+            format!("{b:#x}"),
+        ])));
+    }
+
+    #[test]
+    fn test_verify_contract_reports_unreadable_file() {
+        let (dir, chain) = fixture("unreadable");
+        std::fs::write(dir.join("reputation.parquet"), b"not parquet").unwrap();
+
+        let results = verify_contract(&dir, chain, "reputation", None).unwrap();
+        assert!(matches!(
+            results.first().unwrap().problems.as_slice(),
+            [Problem::Unreadable(_)]
+        ));
+    }
+
+    /// The fixture archive checked in CI: a clean chain next to one with a
+    /// misfiled contract file.
+    #[test]
+    fn test_verify_archive_fixture() {
+        let (dir, base) = fixture("archive");
+        let start = base.deployment_block;
+        let chain_dir = dir.join(base.chain_id().to_string());
+        Cursor::now(start + 10).save(&chain_dir).unwrap();
+        for (contract, address) in [
+            ("identity", base.addresses().identity),
+            ("reputation", base.addresses().reputation),
+        ] {
+            parquet::write(
+                &chain_dir.join(format!("{contract}.parquet")),
+                &[batch(&[start, start + 10], address)],
+            )
+            .unwrap();
+        }
+        let results = verify_archive(&dir, None).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(FileVerification::passed));
+
+        let eth = chains::by_chain_id(1).unwrap();
+        let eth_dir = dir.join("1");
+        Cursor::now(eth.deployment_block).save(&eth_dir).unwrap();
+        parquet::write(
+            &eth_dir.join("identity.parquet"),
+            &[batch(&[eth.deployment_block], eth.addresses().reputation)],
+        )
+        .unwrap();
+        let results = verify_archive(&dir, None).unwrap();
+        let failed: Vec<_> = results.iter().filter(|r| !r.passed()).collect();
+        let [failed] = failed.as_slice() else {
+            panic!("expected one failed file, got {failed:?}");
+        };
+        assert_eq!(failed.chain_id, 1);
+        assert!(matches!(
+            failed.problems.as_slice(),
+            [Problem::WrongAddress { .. }]
+        ));
+        assert_eq!(
+            verify_archive(&dir, Some(base.chain_id())).unwrap().len(),
+            2
+        );
+    }
+}
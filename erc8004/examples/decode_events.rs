@@ -0,0 +1,68 @@
+#![expect(
+    clippy::print_stdout,
+    reason = "example demonstrates output via stdout"
+)]
+//! Fetch and decode ERC-8004 registry events into typed values.
+//!
+//! Usage:
+//!   cargo run --example `decode_events`
+//!
+//! Fetches the first blocks of Identity and Reputation Registry logs on
+//! Ethereum mainnet and prints each recognised event.
+
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::Filter;
+use base64 as _;
+use erc8004::Network;
+use erc8004::events::{self, Erc8004Event, IdentityEvent, ReputationEvent};
+use serde as _;
+use serde_json as _;
+use thiserror as _;
+
+/// Block in which the registries were deployed on Ethereum mainnet.
+const DEPLOYMENT_BLOCK: u64 = 24_339_871;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let provider = ProviderBuilder::new().connect_http("https://eth.llamarpc.com".parse()?);
+
+    let addrs = Network::EthereumMainnet.addresses();
+    let filter = Filter::new()
+        .address(vec![addrs.identity, addrs.reputation])
+        .from_block(DEPLOYMENT_BLOCK)
+        .to_block(DEPLOYMENT_BLOCK + 2_000);
+    let logs = provider.get_logs(&filter).await?;
+    println!("Fetched {} log(s)", logs.len());
+
+    let decoded = events::decode_logs(&logs);
+    for (i, event) in &decoded {
+        let block = logs
+            .get(*i)
+            .and_then(|l| l.block_number)
+            .unwrap_or_default();
+        match event {
+            Erc8004Event::Identity(IdentityEvent::Registered(e)) => {
+                println!(
+                    "[{block}] agent #{} registered by {}: {}",
+                    e.agentId, e.owner, e.agentURI
+                );
+            }
+            Erc8004Event::Identity(IdentityEvent::URIUpdated(e)) => {
+                println!("[{block}] agent #{} URI -> {}", e.agentId, e.newURI);
+            }
+            Erc8004Event::Reputation(ReputationEvent::NewFeedback(e)) => {
+                println!(
+                    "[{block}] feedback #{} for agent #{} from {}",
+                    e.feedbackIndex, e.agentId, e.clientAddress
+                );
+            }
+            other => println!("[{block}] {other:?}"),
+        }
+    }
+    println!(
+        "Decoded {} event(s), skipped {}",
+        decoded.len(),
+        logs.len() - decoded.len()
+    );
+    Ok(())
+}
@@ -8,9 +8,11 @@
 //!   cargo run --example `multi_network`
 //!
 //! This example queries the Identity Registry version on both Ethereum mainnet
-//! and Base mainnet, showing how the same SDK types work across chains.
+//! and Base mainnet, showing how the same SDK types work across chains, then
+//! shares one cloned client between parallel tasks.
 
-use alloy::providers::ProviderBuilder;
+use alloy::{primitives::U256, providers::ProviderBuilder};
+use base64 as _;
 use erc8004::{Erc8004, Network};
 use serde as _;
 use serde_json as _;
@@ -38,5 +40,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         );
     }
 
+    // Clients are `Clone`, so each task can own a handle to the same provider.
+    let provider = ProviderBuilder::new().connect_http("https://mainnet.base.org".parse()?);
+    let client = Erc8004::new(provider).with_network(Network::BaseMainnet);
+    let tasks: Vec<_> = (1..=3_u64)
+        .map(|id| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                let owner = client.identity()?.owner_of(U256::from(id)).await?;
+                Ok::<_, erc8004::Erc8004Error>((id, owner))
+            })
+        })
+        .collect();
+    for task in tasks {
+        let (id, owner) = task.await??;
+        println!("[Base] agent {id} owner={owner}");
+    }
+
     Ok(())
 }
@@ -11,6 +11,7 @@
 //! reads basic identity information for a given agent ID.
 
 use alloy::{primitives::U256, providers::ProviderBuilder};
+use base64 as _;
 use erc8004::{Erc8004, Network};
 use serde as _;
 use serde_json as _;
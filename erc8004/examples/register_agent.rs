@@ -13,6 +13,7 @@
 use alloy::{
     network::EthereumWallet, providers::ProviderBuilder, signers::local::PrivateKeySigner,
 };
+use base64 as _;
 use erc8004::{Erc8004, Network};
 use serde as _;
 use serde_json as _;
@@ -11,6 +11,7 @@
 //! file that an agent publishes at its `agentURI`. No RPC connection is needed.
 
 use alloy as _;
+use base64 as _;
 use erc8004::types::{RegistrationFile, ServiceEndpoint};
 use serde as _;
 use serde_json as _;
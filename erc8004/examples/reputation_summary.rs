@@ -10,6 +10,7 @@
 //! Connects to Ethereum mainnet and reads reputation data for a given agent.
 
 use alloy::{primitives::U256, providers::ProviderBuilder};
+use base64 as _;
 use erc8004::{Erc8004, Network};
 use serde as _;
 use serde_json as _;
@@ -0,0 +1,61 @@
+#![expect(
+    clippy::print_stdout,
+    reason = "example demonstrates output via stdout"
+)]
+//! Query validation status from an ERC-8004 Validation Registry.
+//!
+//! Usage:
+//!   `VALIDATION_REGISTRY=0x... cargo run --example validation_status`
+//!
+//! The Validation Registry has no canonical deployment yet, so its address
+//! must be supplied explicitly. The example lists the validation requests
+//! for agent #1 and prints the status of each, followed by the summary.
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::ProviderBuilder,
+};
+use base64 as _;
+use erc8004::{Erc8004, Network};
+use serde as _;
+use serde_json as _;
+use thiserror as _;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let registry: Address = std::env::var("VALIDATION_REGISTRY")?.parse()?;
+    let provider = ProviderBuilder::new().connect_http("https://sepolia.base.org".parse()?);
+
+    let client = Erc8004::new(provider)
+        .with_network(Network::BaseSepolia)
+        .with_validation_address(registry);
+    let validation = client.validation()?;
+
+    let version = validation.get_version().await?;
+    println!("Validation Registry version: {version}");
+
+    // Every validation request is keyed by its request hash.
+    let agent_id = U256::from(1);
+    let hashes = validation.get_agent_validations(agent_id).await?;
+    println!(
+        "Agent #{agent_id} has {} validation request(s)",
+        hashes.len()
+    );
+
+    for hash in &hashes {
+        let status = validation.get_validation_status(*hash).await?;
+        println!(
+            "  {hash}: validator={}, response={}, tag={:?}, updated={}",
+            status.validator_address, status.response, status.tag, status.last_update,
+        );
+    }
+
+    // Aggregate across all validators and tags.
+    let summary = validation.get_summary(agent_id, &[], "").await?;
+    println!(
+        "Summary: count={}, avg_response={}",
+        summary.count, summary.avg_response,
+    );
+
+    Ok(())
+}
@@ -16,6 +16,9 @@
 //! # }
 //! ```
 
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use alloy::{primitives::Address, providers::Provider};
 
 use crate::{
@@ -23,6 +26,7 @@
     identity::Identity,
     networks::{Network, NetworkAddresses},
     reputation::Reputation,
+    types::HealthStatus,
     validation::Validation,
 };
 
@@ -33,6 +37,10 @@
 /// signer configuration the user has already set up via
 /// [`ProviderBuilder`](alloy::providers::ProviderBuilder).
 ///
+/// The client is `Clone` whenever the provider is (alloy's HTTP providers
+/// are cheap to clone), so each spawned task can own a copy; use
+/// [`into_arc`](Self::into_arc) for providers that are not.
+///
 /// # Examples
 ///
 /// ```rust,no_run
@@ -157,6 +165,49 @@ pub fn validation(&self) -> Result<Validation<&P>> {
         Ok(Validation::new(&self.provider, address))
     }
 
+    /// Measure the round-trip time of an `eth_blockNumber` request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails.
+    pub async fn ping(&self) -> Result<Duration> {
+        let started = Instant::now();
+        self.provider.get_block_number().await?;
+        Ok(started.elapsed())
+    }
+
+    /// Check the provider and the configured registries.
+    ///
+    /// Reads the block number (timed as in [`ping`](Self::ping)) and chain
+    /// ID, then calls `getVersion()` on the Identity and Reputation
+    /// Registries.  A registry that is unconfigured or fails the call is
+    /// reported as inaccessible rather than as an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the block number or chain ID cannot be read.
+    pub async fn health_check(&self) -> Result<HealthStatus> {
+        let started = Instant::now();
+        let block_number = self.provider.get_block_number().await?;
+        let latency = started.elapsed();
+        let chain_id = self.provider.get_chain_id().await?;
+        let identity_accessible = match self.identity() {
+            Ok(identity) => identity.get_version().await.is_ok(),
+            Err(_) => false,
+        };
+        let reputation_accessible = match self.reputation() {
+            Ok(reputation) => reputation.get_version().await.is_ok(),
+            Err(_) => false,
+        };
+        Ok(HealthStatus {
+            block_number,
+            chain_id,
+            latency_ms: u64::try_from(latency.as_millis()).unwrap_or(u64::MAX),
+            identity_accessible,
+            reputation_accessible,
+        })
+    }
+
     /// Get a reference to the underlying alloy provider.
     #[must_use]
     pub const fn provider(&self) -> &P {
@@ -169,6 +220,12 @@ pub fn into_provider(self) -> P {
         self.provider
     }
 
+    /// Wrap this client in an [`Arc`] for sharing across tasks.
+    #[must_use]
+    pub fn into_arc(self) -> Arc<Self> {
+        Arc::new(self)
+    }
+
     /// Get the configured Identity Registry address, if any.
     #[must_use]
     pub const fn identity_address(&self) -> Option<Address> {
@@ -259,4 +316,43 @@ fn test_with_addresses_struct() {
         assert_eq!(client.identity_address(), Some(addrs.identity));
         assert_eq!(client.reputation_address(), Some(addrs.reputation));
     }
+
+    #[tokio::test]
+    async fn test_health_check_reports_registries() {
+        use alloy::primitives::{Bytes, U64};
+        use alloy::sol_types::SolValue;
+        use alloy::transports::mock::Asserter;
+
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        let client = Erc8004::new(provider).with_network(Network::BaseMainnet);
+
+        asserter.push_success(&U64::from(1_234));
+        asserter.push_success(&U64::from(8453));
+        asserter.push_success(&Bytes::from("1.0.0".to_owned().abi_encode()));
+        asserter.push_failure_msg("execution reverted");
+        let status = client.health_check().await.unwrap();
+        assert_eq!(status.block_number, 1_234);
+        assert_eq!(status.chain_id, 8453);
+        assert!(status.identity_accessible);
+        assert!(!status.reputation_accessible);
+
+        asserter.push_success(&U64::from(1_235));
+        assert!(client.ping().await.is_ok());
+        asserter.push_failure_msg("connection refused");
+        assert!(client.ping().await.is_err());
+    }
+
+    #[test]
+    fn test_clone_keeps_addresses() {
+        let provider = ProviderBuilder::new().connect_http("https://localhost:1".parse().unwrap());
+        let client = Erc8004::new(provider).with_network(Network::BaseMainnet);
+        let cloned = client.clone();
+        assert_eq!(cloned.identity_address(), client.identity_address());
+        assert_eq!(cloned.reputation_address(), client.reputation_address());
+        assert!(cloned.identity().is_ok());
+
+        let shared = cloned.into_arc();
+        assert_eq!(shared.identity_address(), client.identity_address());
+    }
 }
@@ -15,7 +15,7 @@
     ///
     /// Deployed on Ethereum, Base, Polygon, Arbitrum, Celo mainnet at
     /// `0x8004A169FB4a3325136EB29fA0ceB6D2e539a432`.
-    #[sol(rpc)]
+    #[sol(rpc, all_derives)]
     contract IdentityRegistry {
         struct MetadataEntry {
             string metadataKey;
@@ -61,6 +61,13 @@ struct MetadataEntry {
         function safeTransferFrom(address from, address to, uint256 tokenId, bytes data) external;
         function supportsInterface(bytes4 interfaceId) external view returns (bool);
 
+        // ERC-721 Enumerable (optional — check `supportsInterface` first)
+        function totalSupply() external view returns (uint256);
+        function tokenOfOwnerByIndex(address owner, uint256 index) external view returns (uint256);
+
+        // ERC-6093 errors
+        error ERC721NonexistentToken(uint256 tokenId);
+
         // EIP-712
         function eip712Domain() external view returns (bytes1 fields, string name, string version, uint256 chainId, address verifyingContract, bytes32 salt, uint256[] extensions);
     }
@@ -72,7 +79,7 @@ struct MetadataEntry {
     /// Deployed on Ethereum, Base, Polygon, Arbitrum, Celo mainnet at
     /// `0x8004BAa17C55a88189AE136b182e5fdA19dE9b63`.
     #[allow(clippy::too_many_arguments, reason = "sol! macro mirrors on-chain ABI")]
-    #[sol(rpc)]
+    #[sol(rpc, all_derives)]
     contract ReputationRegistry {
         event NewFeedback(uint256 indexed agentId, address indexed clientAddress, uint64 feedbackIndex, int128 value, uint8 valueDecimals, string indexed indexedTag1, string tag1, string tag2, string endpoint, string feedbackURI, bytes32 feedbackHash);
         event FeedbackRevoked(uint256 indexed agentId, address indexed clientAddress, uint64 indexed feedbackIndex);
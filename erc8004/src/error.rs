@@ -4,6 +4,7 @@
 
 /// The primary error type for all ERC-8004 SDK operations.
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum Erc8004Error {
     /// A contract interaction failed.
     #[error("contract error: {0}")]
@@ -45,6 +46,36 @@ pub enum Erc8004Error {
         source: alloy::hex::FromHexError,
     },
 
+    /// The chain ID is not a known ERC-8004 deployment.
+    #[error("unknown chain ID: {chain_id}")]
+    UnknownChain {
+        /// The unrecognized EIP-155 chain ID.
+        chain_id: u64,
+    },
+
+    /// The registry does not implement an optional interface (ERC-165).
+    #[error("registry does not support {interface}")]
+    InterfaceNotSupported {
+        /// Name of the missing interface.
+        interface: &'static str,
+    },
+
+    /// No resolver handles the URI's scheme.
+    #[error("unsupported URI: {uri}")]
+    UnsupportedUri {
+        /// The URI that could not be dispatched.
+        uri: String,
+    },
+
+    /// Fetching or decoding a URI failed.
+    #[error("failed to resolve {uri}: {reason}")]
+    UriResolution {
+        /// The URI being resolved.
+        uri: String,
+        /// Human-readable failure reason.
+        reason: String,
+    },
+
     /// A registration transaction succeeded but emitted no `Registered` event.
     #[error("transaction receipt contained no Registered event")]
     MissingRegisteredEvent,
@@ -103,6 +134,35 @@ fn test_identity_registry_mismatch_display() {
         );
     }
 
+    #[test]
+    fn test_unknown_chain_display() {
+        let err = Erc8004Error::UnknownChain { chain_id: 42 };
+        assert_eq!(err.to_string(), "unknown chain ID: 42");
+    }
+
+    #[test]
+    fn test_interface_not_supported_display() {
+        let err = Erc8004Error::InterfaceNotSupported {
+            interface: "ERC721Enumerable",
+        };
+        assert_eq!(
+            err.to_string(),
+            "registry does not support ERC721Enumerable"
+        );
+    }
+
+    #[test]
+    fn test_uri_resolution_display() {
+        let err = Erc8004Error::UriResolution {
+            uri: "ipfs://bafy".to_owned(),
+            reason: "HTTP status 404".to_owned(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "failed to resolve ipfs://bafy: HTTP status 404"
+        );
+    }
+
     #[test]
     fn test_error_is_send_sync() {
         fn assert_send_sync<T: Send + Sync>() {}
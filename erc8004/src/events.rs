@@ -0,0 +1,184 @@
+//! Typed decoding of registry event logs.
+//!
+//! [`Erc8004Event::from_log`] identifies a log by its `topic0` and decodes
+//! it with the ABI from [`contracts`](crate::contracts), so indexers can
+//! work with typed events instead of raw topics and data.
+//!
+//! ```rust,no_run
+//! use alloy::providers::{Provider, ProviderBuilder};
+//! use alloy::rpc::types::Filter;
+//! use erc8004::Network;
+//! use erc8004::events::{Erc8004Event, IdentityEvent};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let provider = ProviderBuilder::new().connect_http("https://eth.llamarpc.com".parse()?);
+//! let filter = Filter::new()
+//!     .address(Network::EthereumMainnet.addresses().identity)
+//!     .from_block(24_339_871)
+//!     .to_block(24_340_871);
+//! let logs = provider.get_logs(&filter).await?;
+//!
+//! for (i, event) in erc8004::events::decode_logs(&logs) {
+//!     if let Erc8004Event::Identity(IdentityEvent::Registered(e)) = event {
+//!         println!("log {i}: agent {} registered by {}", e.agentId, e.owner);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use alloy::primitives::B256;
+use alloy::rpc::types::Log;
+use alloy::sol_types::{SolEvent, SolEventInterface};
+
+use crate::contracts::{IdentityRegistry, ReputationRegistry};
+
+/// An event emitted by the Identity Registry.
+pub type IdentityEvent = IdentityRegistry::IdentityRegistryEvents;
+
+/// An event emitted by the Reputation Registry.
+pub type ReputationEvent = ReputationRegistry::ReputationRegistryEvents;
+
+/// `topic0` of `IdentityRegistry.Registered`.
+pub const REGISTERED_SELECTOR: B256 = IdentityRegistry::Registered::SIGNATURE_HASH;
+/// `topic0` of `IdentityRegistry.URIUpdated`.
+pub const URI_UPDATED_SELECTOR: B256 = IdentityRegistry::URIUpdated::SIGNATURE_HASH;
+/// `topic0` of `IdentityRegistry.MetadataSet`.
+pub const METADATA_SET_SELECTOR: B256 = IdentityRegistry::MetadataSet::SIGNATURE_HASH;
+/// `topic0` of the ERC-721 `Transfer` event.
+pub const TRANSFER_SELECTOR: B256 = IdentityRegistry::Transfer::SIGNATURE_HASH;
+/// `topic0` of the ERC-721 `Approval` event.
+pub const APPROVAL_SELECTOR: B256 = IdentityRegistry::Approval::SIGNATURE_HASH;
+/// `topic0` of the ERC-721 `ApprovalForAll` event.
+pub const APPROVAL_FOR_ALL_SELECTOR: B256 = IdentityRegistry::ApprovalForAll::SIGNATURE_HASH;
+/// `topic0` of `ReputationRegistry.NewFeedback`.
+pub const NEW_FEEDBACK_SELECTOR: B256 = ReputationRegistry::NewFeedback::SIGNATURE_HASH;
+/// `topic0` of `ReputationRegistry.FeedbackRevoked`.
+pub const FEEDBACK_REVOKED_SELECTOR: B256 = ReputationRegistry::FeedbackRevoked::SIGNATURE_HASH;
+/// `topic0` of `ReputationRegistry.ResponseAppended`.
+pub const RESPONSE_APPENDED_SELECTOR: B256 = ReputationRegistry::ResponseAppended::SIGNATURE_HASH;
+
+/// A decoded Identity or Reputation Registry event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Erc8004Event {
+    /// An Identity Registry event (including ERC-721 events).
+    Identity(IdentityEvent),
+    /// A Reputation Registry event.
+    Reputation(ReputationEvent),
+}
+
+impl Erc8004Event {
+    /// Decode a log emitted by one of the registries.
+    ///
+    /// Returns `None` if `topic0` is not a known registry event or the
+    /// topics and data do not match its ABI.  The emitting address is not
+    /// checked; filter logs by [`Network::addresses`](crate::Network::addresses)
+    /// first if that matters.
+    #[must_use]
+    pub fn from_log(log: &Log) -> Option<Self> {
+        let topic0 = log.topic0()?;
+        let (topics, data) = (log.topics(), &log.data().data);
+        if IdentityEvent::SELECTORS.contains(&topic0.0) {
+            IdentityEvent::decode_raw_log(topics, data)
+                .ok()
+                .map(Self::Identity)
+        } else if ReputationEvent::SELECTORS.contains(&topic0.0) {
+            ReputationEvent::decode_raw_log(topics, data)
+                .ok()
+                .map(Self::Reputation)
+        } else {
+            None
+        }
+    }
+}
+
+/// Decode every recognised registry event in `logs`.
+///
+/// Returns `(index into logs, event)` pairs in input order; logs that
+/// [`Erc8004Event::from_log`] does not recognise are skipped.
+#[must_use]
+pub fn decode_logs(logs: &[Log]) -> Vec<(usize, Erc8004Event)> {
+    logs.iter()
+        .enumerate()
+        .filter_map(|(i, log)| Erc8004Event::from_log(log).map(|event| (i, event)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Address, LogData, U256, address};
+
+    use super::*;
+
+    fn rpc_log(data: LogData) -> Log {
+        Log {
+            inner: alloy::primitives::Log {
+                address: Address::ZERO,
+                data,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_from_log_decodes_identity_event() {
+        let owner = address!("0x0000000000000000000000000000000000000001");
+        let event = IdentityRegistry::Registered {
+            agentId: U256::from(7),
+            agentURI: "ipfs://agent".to_owned(),
+            owner,
+        };
+        let log = rpc_log(event.encode_log_data());
+        assert_eq!(log.topic0(), Some(&REGISTERED_SELECTOR));
+
+        let Some(Erc8004Event::Identity(IdentityEvent::Registered(decoded))) =
+            Erc8004Event::from_log(&log)
+        else {
+            unreachable!("expected a Registered event");
+        };
+        assert_eq!(decoded.agentId, U256::from(7));
+        assert_eq!(decoded.agentURI, "ipfs://agent");
+        assert_eq!(decoded.owner, owner);
+    }
+
+    #[test]
+    fn test_decode_logs_skips_unknown_and_keeps_indices() {
+        let revoked = ReputationRegistry::FeedbackRevoked {
+            agentId: U256::from(1),
+            clientAddress: Address::ZERO,
+            feedbackIndex: 3,
+        };
+        let logs = vec![
+            rpc_log(LogData::new_unchecked(vec![B256::ZERO], Default::default())),
+            rpc_log(revoked.encode_log_data()),
+            rpc_log(LogData::default()),
+        ];
+
+        let decoded = decode_logs(&logs);
+        assert_eq!(decoded.len(), 1);
+        let (index, event) = decoded.first().unwrap();
+        assert_eq!(*index, 1);
+        assert_eq!(
+            *event,
+            Erc8004Event::Reputation(ReputationEvent::FeedbackRevoked(revoked))
+        );
+    }
+
+    #[test]
+    fn test_selectors_are_distinct() {
+        let selectors = [
+            REGISTERED_SELECTOR,
+            URI_UPDATED_SELECTOR,
+            METADATA_SET_SELECTOR,
+            TRANSFER_SELECTOR,
+            APPROVAL_SELECTOR,
+            APPROVAL_FOR_ALL_SELECTOR,
+            NEW_FEEDBACK_SELECTOR,
+            FEEDBACK_REVOKED_SELECTOR,
+            RESPONSE_APPENDED_SELECTOR,
+        ];
+        let unique: std::collections::BTreeSet<_> = selectors.iter().collect();
+        assert_eq!(unique.len(), selectors.len());
+    }
+}
@@ -8,7 +8,7 @@
 //! `IdentityRegistryUpgradeable` contract.
 
 use alloy::{
-    primitives::{Address, Bytes, U256},
+    primitives::{Address, Bytes, FixedBytes, U256},
     providers::Provider,
 };
 
@@ -17,10 +17,13 @@
     error::{Erc8004Error, Result},
 };
 
+/// ERC-165 interface ID of `ERC721Enumerable`.
+const ERC721_ENUMERABLE: FixedBytes<4> = FixedBytes([0x78, 0x0e, 0x9d, 0x63]);
+
 /// A handle to the Identity Registry contract bound to a specific provider.
 ///
 /// Created via [`Erc8004::identity()`](crate::Erc8004::identity).
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Identity<P> {
     address: Address,
     provider: P,
@@ -201,6 +204,75 @@ pub async fn owner_of(&self, agent_id: U256) -> Result<Address> {
         Ok(contract.ownerOf(agent_id).call().await?)
     }
 
+    /// Get the owner address of an agent, or `None` if it does not exist.
+    ///
+    /// Calls `ownerOf` once and maps the `ERC721NonexistentToken` revert to
+    /// `Ok(None)`, so callers need not inspect revert data themselves.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails for any other reason.
+    pub async fn try_owner_of(&self, agent_id: U256) -> Result<Option<Address>> {
+        let contract = IdentityRegistry::new(self.address, &self.provider);
+        match contract.ownerOf(agent_id).call().await {
+            Ok(owner) => Ok(Some(owner)),
+            Err(e)
+                if e.as_decoded_error::<IdentityRegistry::ERC721NonexistentToken>()
+                    .is_some() =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Check whether an agent exists.
+    ///
+    /// Calls `ownerOf` and maps the `ERC721NonexistentToken` revert to
+    /// `Ok(false)`; see [`try_owner_of`](Self::try_owner_of).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the RPC call fails for any other reason.
+    pub async fn exists(&self, agent_id: U256) -> Result<bool> {
+        Ok(self.try_owner_of(agent_id).await?.is_some())
+    }
+
+    /// Get the total number of registered agents (ERC-721 Enumerable
+    /// `totalSupply`).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Erc8004Error::InterfaceNotSupported`] if the registry does
+    /// not implement `ERC721Enumerable`, or an error if the RPC call fails.
+    pub async fn total_supply(&self) -> Result<U256> {
+        self.require_enumerable().await?;
+        let contract = IdentityRegistry::new(self.address, &self.provider);
+        Ok(contract.totalSupply().call().await?)
+    }
+
+    /// List every agent ID owned by `owner`.
+    ///
+    /// Pages through `tokenOfOwnerByIndex` from `0` to `balanceOf(owner)`,
+    /// one call per agent.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Erc8004Error::InterfaceNotSupported`] if the registry does
+    /// not implement `ERC721Enumerable`, or an error if an RPC call fails.
+    pub async fn agents_owned_by(&self, owner: Address) -> Result<Vec<U256>> {
+        self.require_enumerable().await?;
+        let contract = IdentityRegistry::new(self.address, &self.provider);
+        let balance = contract.balanceOf(owner).call().await?;
+        let mut ids = Vec::new();
+        let mut index = U256::ZERO;
+        while index < balance {
+            ids.push(contract.tokenOfOwnerByIndex(owner, index).call().await?);
+            index += U256::from(1);
+        }
+        Ok(ids)
+    }
+
     /// Get the agent wallet address for an agent.
     ///
     /// Returns [`Address::ZERO`] if the wallet has not been set.
@@ -256,6 +328,18 @@ pub async fn eip712_domain(&self) -> Result<IdentityRegistry::eip712DomainReturn
         Ok(contract.eip712Domain().call().await?)
     }
 
+    /// Fail unless the registry advertises `ERC721Enumerable` via ERC-165.
+    async fn require_enumerable(&self) -> Result<()> {
+        let contract = IdentityRegistry::new(self.address, &self.provider);
+        if contract.supportsInterface(ERC721_ENUMERABLE).call().await? {
+            Ok(())
+        } else {
+            Err(Erc8004Error::InterfaceNotSupported {
+                interface: "ERC721Enumerable",
+            })
+        }
+    }
+
     /// Parse `agentId` from a transaction receipt's `Registered` event.
     fn parse_agent_id(receipt: &alloy::rpc::types::TransactionReceipt) -> Result<U256> {
         receipt
@@ -41,15 +41,20 @@
 //! - **[`Validation`](validation::Validation)** — Validation Registry
 //!   operations: request/respond to validation, query status.
 //! - **[`Network`]** — Pre-configured network addresses for known deployments.
+//! - **[`events`]** — Typed decoding of registry event logs.
 //! - **[`types`]** — Off-chain JSON types (registration files, feedback, etc.).
+//! - **[`uri`]** — Resolvers that fetch `agentURI` content (HTTP, IPFS,
+//!   Arweave, `data:`).
 
 pub mod client;
 pub mod contracts;
 pub mod error;
+pub mod events;
 pub mod identity;
 pub mod networks;
 pub mod reputation;
 pub mod types;
+pub mod uri;
 pub mod validation;
 
 // Re-export primary public API at crate root.
@@ -4,10 +4,15 @@
 //! so mainnet chains share the same addresses and testnet chains share
 //! the same addresses.
 
+use std::iter::FusedIterator;
+use std::slice;
+
 use alloy::primitives::{Address, address};
 
+use crate::error::Erc8004Error;
+
 /// Known contract addresses for a specific network deployment.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct NetworkAddresses {
     /// The Identity Registry (ERC-721) contract address.
     pub identity: Address,
@@ -15,6 +20,20 @@ pub struct NetworkAddresses {
     pub reputation: Address,
 }
 
+impl NetworkAddresses {
+    /// Addresses shared by every mainnet deployment.
+    pub const MAINNET: Self = Self {
+        identity: MAINNET_IDENTITY,
+        reputation: MAINNET_REPUTATION,
+    };
+
+    /// Addresses shared by every testnet deployment.
+    pub const TESTNET: Self = Self {
+        identity: TESTNET_IDENTITY,
+        reputation: TESTNET_REPUTATION,
+    };
+}
+
 /// Pre-defined network configurations for ERC-8004 deployments.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[non_exhaustive]
@@ -91,6 +110,13 @@ pub enum Network {
 
 impl Network {
     /// Returns the known contract addresses for this network.
+    ///
+    /// These are the shared CREATE2 addresses ([`NetworkAddresses::MAINNET`]
+    /// or [`NetworkAddresses::TESTNET`]); no variant has addresses of its
+    /// own.  A chain whose registries were deployed elsewhere (a different
+    /// deployer nonce or salt) must not be added as a variant that returns
+    /// them; configure its real addresses with
+    /// [`Erc8004::with_addresses`](crate::Erc8004::with_addresses) instead.
     #[must_use]
     pub const fn addresses(self) -> NetworkAddresses {
         match self {
@@ -109,10 +135,7 @@ pub const fn addresses(self) -> NetworkAddresses {
             | Self::LineaMainnet
             | Self::MantleMainnet
             | Self::MegaEthMainnet
-            | Self::OptimismMainnet => NetworkAddresses {
-                identity: MAINNET_IDENTITY,
-                reputation: MAINNET_REPUTATION,
-            },
+            | Self::OptimismMainnet => NetworkAddresses::MAINNET,
             Self::EthereumSepolia
             | Self::BaseSepolia
             | Self::PolygonAmoy
@@ -126,10 +149,7 @@ pub const fn addresses(self) -> NetworkAddresses {
             | Self::LineaSepolia
             | Self::MantleSepolia
             | Self::MegaEthTestnet
-            | Self::OptimismSepolia => NetworkAddresses {
-                identity: TESTNET_IDENTITY,
-                reputation: TESTNET_REPUTATION,
-            },
+            | Self::OptimismSepolia => NetworkAddresses::TESTNET,
         }
     }
 
@@ -204,6 +224,12 @@ pub const fn chain_id(self) -> u64 {
         Self::OptimismSepolia,
     ];
 
+    /// Iterate over all known networks, in [`Network::ALL`] order.
+    #[must_use]
+    pub fn iter() -> NetworkIter {
+        NetworkIter(Self::ALL.iter())
+    }
+
     /// Look up a [`Network`] by its EIP-155 chain ID.
     ///
     /// Returns [`None`] if the chain ID is not a known ERC-8004 deployment.
@@ -219,6 +245,52 @@ pub fn agent_registry_prefix(self) -> String {
     }
 }
 
+impl From<Network> for u64 {
+    fn from(network: Network) -> Self {
+        network.chain_id()
+    }
+}
+
+impl TryFrom<u64> for Network {
+    type Error = Erc8004Error;
+
+    fn try_from(chain_id: u64) -> Result<Self, Self::Error> {
+        Self::from_chain_id(chain_id).ok_or(Erc8004Error::UnknownChain { chain_id })
+    }
+}
+
+impl From<Network> for NetworkAddresses {
+    fn from(network: Network) -> Self {
+        network.addresses()
+    }
+}
+
+/// Iterator over all known networks, returned by [`Network::iter`].
+#[derive(Debug, Clone)]
+pub struct NetworkIter(slice::Iter<'static, Network>);
+
+impl Iterator for NetworkIter {
+    type Item = Network;
+
+    fn next(&mut self) -> Option<Network> {
+        self.0.next().copied()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for NetworkIter {
+    fn next_back(&mut self) -> Option<Network> {
+        self.0.next_back().copied()
+    }
+}
+
+impl ExactSizeIterator for NetworkIter {}
+
+impl FusedIterator for NetworkIter {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -248,11 +320,43 @@ fn test_chain_id_round_trip() {
         }
     }
 
+    #[test]
+    fn test_addresses_are_the_shared_pairs() {
+        for &network in Network::ALL {
+            let addrs = network.addresses();
+            assert!(
+                addrs == NetworkAddresses::MAINNET || addrs == NetworkAddresses::TESTNET,
+                "{network:?} has addresses of its own"
+            );
+        }
+        assert_ne!(NetworkAddresses::MAINNET, NetworkAddresses::TESTNET);
+    }
+
     #[test]
     fn test_from_chain_id_unknown_returns_none() {
         assert_eq!(Network::from_chain_id(999_999_999), None);
     }
 
+    #[test]
+    fn test_iter_matches_all() {
+        assert_eq!(Network::iter().len(), Network::ALL.len());
+        assert!(Network::iter().eq(Network::ALL.iter().copied()));
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(u64::from(Network::BaseMainnet), 8453);
+        assert_eq!(Network::try_from(8453_u64).unwrap(), Network::BaseMainnet);
+        assert!(matches!(
+            Network::try_from(999_999_999_u64),
+            Err(Erc8004Error::UnknownChain {
+                chain_id: 999_999_999
+            })
+        ));
+        let addrs = NetworkAddresses::from(Network::EthereumMainnet);
+        assert_eq!(addrs.identity, MAINNET_IDENTITY);
+    }
+
     #[test]
     fn test_create2_mainnet_addresses_are_consistent() {
         let reference = Network::EthereumMainnet.addresses();
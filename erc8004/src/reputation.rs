@@ -22,7 +22,7 @@
 /// A handle to the Reputation Registry contract bound to a specific provider.
 ///
 /// Created via [`Erc8004::reputation()`](crate::Erc8004::reputation).
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Reputation<P> {
     address: Address,
     provider: P,
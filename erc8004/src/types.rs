@@ -4,8 +4,12 @@
 //! specification (agent registration files, feedback files, etc.) and provide
 //! ergonomic wrappers around on-chain primitives.
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::uri::{UriResolver, decode_data_uri};
+
 /// Deserialize a `u64` from either a JSON number or a JSON string.
 fn deserialize_u64_or_string<'de, D>(deserializer: D) -> Result<u64, D::Error>
 where
@@ -23,6 +27,51 @@ enum StringOrNum {
     }
 }
 
+/// (De)serialize an `i128` as a decimal JSON string, so that values beyond
+/// 2^53 survive JavaScript consumers; plain numbers are accepted on input.
+mod i128_string {
+    use std::fmt;
+
+    use serde::de::{self, Visitor};
+    use serde::{Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(value: &i128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<i128, D::Error> {
+        struct I128Visitor;
+
+        impl Visitor<'_> for I128Visitor {
+            type Value = i128;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an integer or a decimal string")
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<i128, E> {
+                Ok(v.into())
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<i128, E> {
+                Ok(v.into())
+            }
+
+            fn visit_i128<E: de::Error>(self, v: i128) -> Result<i128, E> {
+                Ok(v)
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<i128, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(I128Visitor)
+    }
+}
+
 /// The top-level agent registration file resolved by `agentURI`.
 ///
 /// See: <https://eips.ethereum.org/EIPS/eip-8004#agent-uri-and-agent-registration-file>
@@ -135,9 +184,13 @@ pub struct FeedbackInput {
 }
 
 /// A single feedback entry as returned by `readFeedback`.
-#[derive(Debug, Clone)]
+///
+/// Serializes with camelCase keys and `value` as a decimal string.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Feedback {
     /// Signed feedback value (e.g. score).
+    #[serde(with = "i128_string")]
     pub value: i128,
 
     /// Number of decimal places for `value`.
@@ -154,12 +207,16 @@ pub struct Feedback {
 }
 
 /// Aggregated reputation summary as returned by `getSummary`.
-#[derive(Debug, Clone, Copy)]
+///
+/// Serializes with camelCase keys and `summaryValue` as a decimal string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ReputationSummary {
     /// Total number of (non-revoked) feedback entries.
     pub count: u64,
 
     /// Aggregated value across all matching feedback.
+    #[serde(with = "i128_string")]
     pub summary_value: i128,
 
     /// Decimal places for `summary_value`.
@@ -167,7 +224,11 @@ pub struct ReputationSummary {
 }
 
 /// The status of a validation request as returned by `getValidationStatus`.
-#[derive(Debug, Clone)]
+///
+/// Serializes with camelCase keys; the address, hash and `U256` fields are
+/// `0x`-prefixed hex strings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ValidationStatus {
     /// Address of the validator contract / EOA.
     pub validator_address: alloy::primitives::Address,
@@ -188,8 +249,34 @@ pub struct ValidationStatus {
     pub last_update: alloy::primitives::U256,
 }
 
+/// Connectivity report returned by
+/// [`Erc8004::health_check`](crate::Erc8004::health_check).
+///
+/// Serializes with camelCase keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthStatus {
+    /// Latest block number reported by the provider.
+    pub block_number: u64,
+
+    /// EIP-155 chain ID reported by the provider.
+    pub chain_id: u64,
+
+    /// Round-trip time of the `eth_blockNumber` request, in milliseconds.
+    pub latency_ms: u64,
+
+    /// Whether the Identity Registry answered `getVersion()`; `false` if it
+    /// is not configured.
+    pub identity_accessible: bool,
+
+    /// Whether the Reputation Registry answered `getVersion()`; `false` if
+    /// it is not configured.
+    pub reputation_accessible: bool,
+}
+
 /// Aggregated validation summary as returned by `getSummary`.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ValidationSummary {
     /// Number of validation responses.
     pub count: u64,
@@ -198,6 +285,59 @@ pub struct ValidationSummary {
     pub avg_response: u8,
 }
 
+impl ReputationSummary {
+    /// Aggregate already-fetched feedback locally, as an alternative to the
+    /// on-chain `getSummary`.
+    ///
+    /// Revoked entries are skipped.  Values are scaled to the largest
+    /// `value_decimals` among the rest and averaged; the mean is reported at
+    /// their most common `value_decimals` (the larger one on a tie),
+    /// truncated toward zero.  Arithmetic saturates at the `i128` bounds.
+    /// Without any non-revoked entry the summary is all zeros.
+    #[must_use]
+    pub fn from_feedback(entries: &[Feedback]) -> Self {
+        let active: Vec<&Feedback> = entries.iter().filter(|f| !f.is_revoked).collect();
+        let Some(max_decimals) = active.iter().map(|f| f.value_decimals).max() else {
+            return Self {
+                count: 0,
+                summary_value: 0,
+                summary_value_decimals: 0,
+            };
+        };
+        let mut frequency: BTreeMap<u8, usize> = BTreeMap::new();
+        for f in &active {
+            *frequency.entry(f.value_decimals).or_default() += 1;
+        }
+        let decimals = frequency
+            .iter()
+            .max_by_key(|&(&decimals, &n)| (n, decimals))
+            .map_or(max_decimals, |(&decimals, _)| decimals);
+
+        let sum = active.iter().fold(0i128, |sum, f| {
+            sum.saturating_add(rescale(f.value, f.value_decimals, max_decimals))
+        });
+        let count = u64::try_from(active.len()).unwrap_or(u64::MAX);
+        Self {
+            count,
+            summary_value: rescale(sum / i128::from(count), max_decimals, decimals),
+            summary_value_decimals: decimals,
+        }
+    }
+}
+
+/// Rescale a fixed-point value between decimal precisions, truncating toward
+/// zero when digits are dropped and saturating on overflow.
+fn rescale(value: i128, from: u8, to: u8) -> i128 {
+    let factor = 10i128.checked_pow(u32::from(from.abs_diff(to)));
+    match factor {
+        Some(f) if to >= from => value.saturating_mul(f),
+        Some(f) => value / f,
+        None if value == 0 || to < from => 0,
+        None if value < 0 => i128::MIN,
+        None => i128::MAX,
+    }
+}
+
 impl ServiceEndpoint {
     /// Create a new service endpoint with the given protocol name and URL.
     #[must_use]
@@ -317,6 +457,25 @@ pub fn to_json(&self) -> Result<String, serde_json::Error> {
     pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
         serde_json::from_str(json)
     }
+
+    /// Fetch and parse the registration file behind an `agentURI`.
+    ///
+    /// `data:` URIs are decoded inline; any other scheme is passed to
+    /// `resolver` (see [`CompositeUriResolver`](crate::uri::CompositeUriResolver)
+    /// for HTTP(S), `ipfs://` and `ar://` support).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URI cannot be resolved or its content is not
+    /// a valid registration file.
+    pub async fn from_uri(uri: &str, resolver: &dyn UriResolver) -> crate::Result<Self> {
+        let bytes = if uri.starts_with("data:") {
+            decode_data_uri(uri)?
+        } else {
+            resolver.resolve(uri).await?
+        };
+        Ok(serde_json::from_slice(&bytes)?)
+    }
 }
 
 #[cfg(test)]
@@ -368,6 +527,16 @@ fn test_registration_file_deserialize_minimal() {
         assert!(!reg.x402_support, "x402_support should default to false");
     }
 
+    #[tokio::test]
+    async fn test_registration_file_from_data_uri() {
+        let uri = "data:application/json,%7B%22type%22%3A%22t%22%2C%22name%22%3A%22Inline%22%2C%22description%22%3A%22d%22%7D";
+        let resolver = crate::uri::CompositeUriResolver::empty();
+        let reg = RegistrationFile::from_uri(uri, &resolver)
+            .await
+            .expect("data URI should decode");
+        assert_eq!(reg.name, "Inline");
+    }
+
     #[test]
     fn test_registration_deserialize_agent_id_as_string() {
         let json = r#"{"agentId": "42", "agentRegistry": "eip155:1:0xABC"}"#;
@@ -382,6 +551,123 @@ fn test_registration_deserialize_agent_id_as_number() {
         assert_eq!(reg.agent_id, 42);
     }
 
+    #[test]
+    fn test_query_results_json_round_trip() {
+        let feedback = Feedback {
+            value: i128::MIN,
+            value_decimals: 2,
+            tag1: "a2a.task".to_owned(),
+            tag2: String::new(),
+            is_revoked: false,
+        };
+        let json = serde_json::to_value(&feedback).unwrap();
+        assert_eq!(json["value"], i128::MIN.to_string());
+        assert_eq!(json["valueDecimals"], 2);
+        assert_eq!(serde_json::from_value::<Feedback>(json).unwrap(), feedback);
+
+        let summary: ReputationSummary = serde_json::from_str(
+            r#"{"count": 3, "summaryValue": -250, "summaryValueDecimals": 1}"#,
+        )
+        .expect("numeric summaryValue should parse");
+        assert_eq!(summary.summary_value, -250);
+        let json = serde_json::to_string(&summary).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ReputationSummary>(&json).unwrap(),
+            summary
+        );
+
+        let status = ValidationStatus {
+            validator_address: alloy::primitives::Address::repeat_byte(0x11),
+            agent_id: alloy::primitives::U256::from(42),
+            response: 100,
+            response_hash: alloy::primitives::FixedBytes::repeat_byte(0xab),
+            tag: "zk".to_owned(),
+            last_update: alloy::primitives::U256::from(1_700_000_000u64),
+        };
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(
+            json["validatorAddress"],
+            "0x1111111111111111111111111111111111111111"
+        );
+        assert_eq!(json["agentId"], "0x2a");
+        assert_eq!(
+            serde_json::from_value::<ValidationStatus>(json).unwrap(),
+            status
+        );
+
+        let summary = ValidationSummary {
+            count: 5,
+            avg_response: 80,
+        };
+        let json = serde_json::to_string(&summary).unwrap();
+        assert_eq!(json, r#"{"count":5,"avgResponse":80}"#);
+        assert_eq!(
+            serde_json::from_str::<ValidationSummary>(&json).unwrap(),
+            summary
+        );
+    }
+
+    fn feedback(value: i128, value_decimals: u8, is_revoked: bool) -> Feedback {
+        Feedback {
+            value,
+            value_decimals,
+            tag1: String::new(),
+            tag2: String::new(),
+            is_revoked,
+        }
+    }
+
+    #[test]
+    fn test_reputation_summary_from_feedback_skips_revoked() {
+        let entries = [
+            feedback(80, 0, false),
+            feedback(9050, 2, false),
+            feedback(-100, 0, true),
+            feedback(7000, 2, false),
+        ];
+        let summary = ReputationSummary::from_feedback(&entries);
+        assert_eq!(summary.count, 3);
+        // (8000 + 9050 + 7000) / 3 at 2 decimals, truncated.
+        assert_eq!(summary.summary_value, 8016);
+        assert_eq!(summary.summary_value_decimals, 2);
+    }
+
+    #[test]
+    fn test_reputation_summary_from_feedback_decimals() {
+        // Tie between 0 and 1 decimals: the larger one is kept.
+        let summary =
+            ReputationSummary::from_feedback(&[feedback(5, 0, false), feedback(250, 1, false)]);
+        assert_eq!(
+            (summary.summary_value, summary.summary_value_decimals),
+            (150, 1)
+        );
+
+        // Most entries use 0 decimals, so the mean of 40, 60 and 75 (at 1
+        // decimal) is reported at 0.
+        let summary = ReputationSummary::from_feedback(&[
+            feedback(4, 0, false),
+            feedback(6, 0, false),
+            feedback(75, 1, false),
+        ]);
+        assert_eq!(
+            (summary.summary_value, summary.summary_value_decimals),
+            (5, 0)
+        );
+
+        let summary = ReputationSummary::from_feedback(&[feedback(1, 0, true)]);
+        assert_eq!(summary.count, 0);
+        assert_eq!(summary.summary_value, 0);
+        assert_eq!(ReputationSummary::from_feedback(&[]).count, 0);
+    }
+
+    #[test]
+    fn test_rescale_saturates_and_truncates() {
+        assert_eq!(rescale(-199, 2, 0), -1);
+        assert_eq!(rescale(i128::MAX, 0, 18), i128::MAX);
+        assert_eq!(rescale(-1, 0, 255), i128::MIN);
+        assert_eq!(rescale(7, 255, 0), 0);
+    }
+
     #[test]
     fn test_service_endpoint_builder() {
         let ep = ServiceEndpoint::new("MCP", "https://example.com/mcp")
@@ -0,0 +1,465 @@
+//! Resolution of `agentURI` values to raw bytes.
+//!
+//! Agents may point their `agentURI` at HTTPS, `ipfs://`, `ar://` or inline
+//! `data:` URIs.  A [`UriResolver`] fetches the bytes behind a URI;
+//! [`RegistrationFile::from_uri`](crate::types::RegistrationFile::from_uri)
+//! decodes `data:` URIs itself and dispatches everything else to a resolver.
+//!
+//! [`CompositeUriResolver::default`] tries HTTP(S), IPFS and Arweave through
+//! public gateways, in that order.
+//!
+//! Agent URIs are attacker-controlled, so every request gives up after
+//! [`REQUEST_TIMEOUT`] and bodies larger than [`MAX_RESPONSE_BYTES`] are
+//! rejected while they stream in rather than buffered whole.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use alloy::transports::http::reqwest;
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+
+use crate::error::{Erc8004Error, Result};
+
+/// Public IPFS gateway used by [`IpfsUriResolver::new`].
+pub const DEFAULT_IPFS_GATEWAY: &str = "https://ipfs.io/ipfs/";
+
+/// Public Arweave gateway used by [`ArweaveUriResolver::new`].
+pub const DEFAULT_ARWEAVE_GATEWAY: &str = "https://arweave.net/";
+
+/// Timeout of one request, connection and body included.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Largest response body accepted, in bytes.
+pub const MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+
+/// The boxed future returned by [`UriResolver::resolve`].
+pub type ResolveFuture<'a> = Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send + 'a>>;
+
+/// Fetches the raw bytes behind a URI.
+///
+/// Implementations return [`Erc8004Error::UnsupportedUri`] for schemes they
+/// do not handle, so that a [`CompositeUriResolver`] can move on to the
+/// next resolver.
+pub trait UriResolver: Send + Sync {
+    /// Resolve `uri` to its content.
+    fn resolve<'a>(&'a self, uri: &'a str) -> ResolveFuture<'a>;
+}
+
+/// Resolves `http://` and `https://` URIs.
+#[derive(Debug, Clone)]
+pub struct HttpUriResolver {
+    client: reqwest::Client,
+}
+
+impl Default for HttpUriResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpUriResolver {
+    /// Create a resolver with an HTTP client that times out after
+    /// [`REQUEST_TIMEOUT`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_client(client())
+    }
+
+    /// Create a resolver that reuses an existing HTTP client.
+    #[must_use]
+    pub const fn with_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl UriResolver for HttpUriResolver {
+    fn resolve<'a>(&'a self, uri: &'a str) -> ResolveFuture<'a> {
+        Box::pin(async move {
+            if !(uri.starts_with("https://") || uri.starts_with("http://")) {
+                return Err(unsupported(uri));
+            }
+            get(&self.client, uri, uri).await
+        })
+    }
+}
+
+/// Resolves `ipfs://<cid>[/path]` URIs through an HTTP gateway.
+#[derive(Debug, Clone)]
+pub struct IpfsUriResolver {
+    client: reqwest::Client,
+    gateway: String,
+}
+
+impl Default for IpfsUriResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IpfsUriResolver {
+    /// Create a resolver using [`DEFAULT_IPFS_GATEWAY`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_gateway(DEFAULT_IPFS_GATEWAY)
+    }
+
+    /// Create a resolver using a custom gateway, e.g.
+    /// `https://cloudflare-ipfs.com/ipfs/`.
+    #[must_use]
+    pub fn with_gateway(gateway: impl Into<String>) -> Self {
+        Self {
+            client: client(),
+            gateway: gateway.into(),
+        }
+    }
+}
+
+impl UriResolver for IpfsUriResolver {
+    fn resolve<'a>(&'a self, uri: &'a str) -> ResolveFuture<'a> {
+        Box::pin(async move {
+            let Some(path) = uri.strip_prefix("ipfs://") else {
+                return Err(unsupported(uri));
+            };
+            // Tolerate the legacy `ipfs://ipfs/<cid>` form.
+            let path = path.strip_prefix("ipfs/").unwrap_or(path);
+            get(&self.client, uri, &gateway_url(&self.gateway, path)).await
+        })
+    }
+}
+
+/// Resolves `ar://<tx-id>` URIs through an HTTP gateway.
+#[derive(Debug, Clone)]
+pub struct ArweaveUriResolver {
+    client: reqwest::Client,
+    gateway: String,
+}
+
+impl Default for ArweaveUriResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ArweaveUriResolver {
+    /// Create a resolver using [`DEFAULT_ARWEAVE_GATEWAY`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_gateway(DEFAULT_ARWEAVE_GATEWAY)
+    }
+
+    /// Create a resolver using a custom gateway.
+    #[must_use]
+    pub fn with_gateway(gateway: impl Into<String>) -> Self {
+        Self {
+            client: client(),
+            gateway: gateway.into(),
+        }
+    }
+}
+
+impl UriResolver for ArweaveUriResolver {
+    fn resolve<'a>(&'a self, uri: &'a str) -> ResolveFuture<'a> {
+        Box::pin(async move {
+            let Some(path) = uri.strip_prefix("ar://") else {
+                return Err(unsupported(uri));
+            };
+            get(&self.client, uri, &gateway_url(&self.gateway, path)).await
+        })
+    }
+}
+
+/// Tries each resolver in order and returns the first success.
+///
+/// Resolvers that report [`Erc8004Error::UnsupportedUri`] are skipped; if
+/// every resolver fails, the last real failure is returned.
+pub struct CompositeUriResolver {
+    resolvers: Vec<Box<dyn UriResolver>>,
+}
+
+impl fmt::Debug for CompositeUriResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompositeUriResolver")
+            .field("resolvers", &self.resolvers.len())
+            .finish()
+    }
+}
+
+impl Default for CompositeUriResolver {
+    fn default() -> Self {
+        Self::public_gateways()
+    }
+}
+
+impl CompositeUriResolver {
+    /// Create an empty resolver chain.
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self {
+            resolvers: Vec::new(),
+        }
+    }
+
+    /// Append a resolver to the chain.
+    #[must_use]
+    pub fn with(mut self, resolver: impl UriResolver + 'static) -> Self {
+        self.resolvers.push(Box::new(resolver));
+        self
+    }
+
+    /// HTTP(S), IPFS and Arweave resolvers using public gateways.
+    #[must_use]
+    pub fn public_gateways() -> Self {
+        Self::empty()
+            .with(HttpUriResolver::new())
+            .with(IpfsUriResolver::new())
+            .with(ArweaveUriResolver::new())
+    }
+}
+
+impl UriResolver for CompositeUriResolver {
+    fn resolve<'a>(&'a self, uri: &'a str) -> ResolveFuture<'a> {
+        Box::pin(async move {
+            let mut last = unsupported(uri);
+            for resolver in &self.resolvers {
+                match resolver.resolve(uri).await {
+                    Ok(bytes) => return Ok(bytes),
+                    Err(Erc8004Error::UnsupportedUri { .. }) => {}
+                    Err(e) => last = e,
+                }
+            }
+            Err(last)
+        })
+    }
+}
+
+/// Decode an RFC 2397 `data:` URI (`data:[<mediatype>][;base64],<data>`).
+///
+/// # Errors
+///
+/// Returns [`Erc8004Error::UnsupportedUri`] if `uri` is not a `data:` URI and
+/// [`Erc8004Error::UriResolution`] if it is malformed.
+pub fn decode_data_uri(uri: &str) -> Result<Vec<u8>> {
+    let Some(rest) = uri.strip_prefix("data:") else {
+        return Err(unsupported(uri));
+    };
+    let (meta, data) = rest
+        .split_once(',')
+        .ok_or_else(|| failed(uri, "missing `,` separator"))?;
+    if meta.ends_with(";base64") {
+        STANDARD.decode(data).map_err(|e| failed(uri, e))
+    } else {
+        percent_decode(data).ok_or_else(|| failed(uri, "invalid percent-encoding"))
+    }
+}
+
+/// Decode `%XX` escapes; returns `None` on a truncated or non-hex escape.
+fn percent_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find('%') {
+        out.extend_from_slice(rest.get(..pos)?.as_bytes());
+        let hex = rest.get(pos + 1..pos + 3)?;
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        out.push(u8::from_str_radix(hex, 16).ok()?);
+        rest = rest.get(pos + 3..)?;
+    }
+    out.extend_from_slice(rest.as_bytes());
+    Some(out)
+}
+
+fn gateway_url(gateway: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        gateway.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
+
+/// HTTP client of the built-in resolvers, with [`REQUEST_TIMEOUT`].
+fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+}
+
+async fn get(client: &reqwest::Client, uri: &str, url: &str) -> Result<Vec<u8>> {
+    get_capped(client, uri, url, MAX_RESPONSE_BYTES).await
+}
+
+/// `GET` `url`, reading the body chunk by chunk and failing as soon as it
+/// exceeds `limit` bytes.
+async fn get_capped(
+    client: &reqwest::Client,
+    uri: &str,
+    url: &str,
+    limit: usize,
+) -> Result<Vec<u8>> {
+    let too_large = || failed(uri, format!("response exceeds {limit} bytes"));
+    let mut resp = client
+        .get(url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| failed(uri, e))?;
+    if resp.content_length().is_some_and(|len| len > limit as u64) {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(|e| failed(uri, e))? {
+        if body.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+fn unsupported(uri: &str) -> Erc8004Error {
+    Erc8004Error::UnsupportedUri {
+        uri: uri.to_owned(),
+    }
+}
+
+fn failed(uri: &str, reason: impl fmt::Display) -> Erc8004Error {
+    Erc8004Error::UriResolution {
+        uri: uri.to_owned(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str, &'static [u8]);
+
+    impl UriResolver for Fixed {
+        fn resolve<'a>(&'a self, uri: &'a str) -> ResolveFuture<'a> {
+            Box::pin(async move {
+                if uri.starts_with(self.0) {
+                    Ok(self.1.to_vec())
+                } else {
+                    Err(unsupported(uri))
+                }
+            })
+        }
+    }
+
+    #[test]
+    fn test_decode_data_uri_base64() {
+        let bytes = decode_data_uri("data:application/json;base64,eyJhIjoxfQ==").unwrap();
+        assert_eq!(bytes, br#"{"a":1}"#);
+    }
+
+    #[test]
+    fn test_decode_data_uri_percent_encoded() {
+        let bytes = decode_data_uri("data:application/json,%7B%22a%22%3A1%7D").unwrap();
+        assert_eq!(bytes, br#"{"a":1}"#);
+    }
+
+    #[test]
+    fn test_decode_data_uri_rejects_malformed() {
+        assert!(matches!(
+            decode_data_uri("data:text/plain"),
+            Err(Erc8004Error::UriResolution { .. })
+        ));
+        assert!(matches!(
+            decode_data_uri("data:,%4"),
+            Err(Erc8004Error::UriResolution { .. })
+        ));
+        assert!(matches!(
+            decode_data_uri("https://example.com"),
+            Err(Erc8004Error::UnsupportedUri { .. })
+        ));
+    }
+
+    #[test]
+    fn test_gateway_url_joins_slashes() {
+        assert_eq!(
+            gateway_url("https://ipfs.io/ipfs/", "/bafy/agent.json"),
+            "https://ipfs.io/ipfs/bafy/agent.json"
+        );
+        assert_eq!(
+            gateway_url("https://arweave.net", "abc"),
+            "https://arweave.net/abc"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_composite_skips_unsupported_resolvers() {
+        let resolver = CompositeUriResolver::empty()
+            .with(Fixed("ipfs://", b"ipfs"))
+            .with(Fixed("ar://", b"arweave"));
+        assert_eq!(resolver.resolve("ar://tx").await.unwrap(), b"arweave");
+        assert!(matches!(
+            resolver.resolve("ftp://example.com").await,
+            Err(Erc8004Error::UnsupportedUri { .. })
+        ));
+    }
+
+    /// Serve one HTTP response with a `len`-byte body, optionally without a
+    /// `Content-Length` so that it is streamed until the connection closes.
+    fn serve_once(len: usize, content_length: bool) -> String {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let header = if content_length {
+                format!("HTTP/1.1 200 OK\r\nContent-Length: {len}\r\n\r\n")
+            } else {
+                "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n".to_owned()
+            };
+            let stream = reader.get_mut();
+            stream.write_all(header.as_bytes()).unwrap();
+            // The client may hang up as soon as the body is too large.
+            stream.write_all(&vec![b'x'; len]).ok();
+        });
+        format!("http://{addr}/agent.json")
+    }
+
+    #[tokio::test]
+    async fn test_get_caps_response_size() {
+        let client = client();
+        let url = serve_once(64, true);
+        assert_eq!(get_capped(&client, &url, &url, 64).await.unwrap().len(), 64);
+        for content_length in [true, false] {
+            let url = serve_once(65, content_length);
+            let err = get_capped(&client, &url, &url, 64).await.unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("failed to resolve {url}: response exceeds 64 bytes")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scheme_specific_resolvers_reject_other_schemes() {
+        for uri in ["ipfs://bafy", "ar://tx", "data:,x"] {
+            assert!(matches!(
+                HttpUriResolver::new().resolve(uri).await,
+                Err(Erc8004Error::UnsupportedUri { .. })
+            ));
+        }
+        assert!(matches!(
+            IpfsUriResolver::new().resolve("https://example.com").await,
+            Err(Erc8004Error::UnsupportedUri { .. })
+        ));
+        assert!(matches!(
+            ArweaveUriResolver::new().resolve("ipfs://bafy").await,
+            Err(Erc8004Error::UnsupportedUri { .. })
+        ));
+    }
+}
@@ -21,7 +21,7 @@
 /// A handle to the Validation Registry contract bound to a specific provider.
 ///
 /// Created via [`Erc8004::validation()`](crate::Erc8004::validation).
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Validation<P> {
     address: Address,
     provider: P,
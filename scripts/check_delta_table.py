@@ -0,0 +1,40 @@
+"""Read the Delta table written by `delta::tests::test_delta_table_fixture`
+with independent Delta implementations and check what they see."""
+
+import sys
+
+try:
+    import polars as pl
+    from deltalake import DeltaTable
+except ImportError:
+    raise SystemExit("deltalake and polars are required: pip install deltalake polars")
+
+
+def main(path: str) -> None:
+    dt = DeltaTable(path)
+    assert dt.version() == 3, dt.version()
+    assert dt.metadata().partition_columns == ["chain_id", "contract"]
+
+    table = dt.to_pyarrow_table()
+    assert table.num_rows == 4, table.num_rows
+    for column in ("block_number", "tx_index", "log_index", "chain_id"):
+        assert str(table.schema.field(column).type) == "int64", column
+
+    base = dt.to_pyarrow_table(partitions=[("chain_id", "=", "8453")])
+    assert sorted(base.column("block_number").to_pylist()) == [10, 11, 12]
+    assert set(base.column("contract").to_pylist()) == {"identity"}
+
+    # Time travel to the first write.
+    first = DeltaTable(path, version=1).to_pyarrow_table()
+    assert sorted(first.column("block_number").to_pylist()) == [10, 11]
+    assert len(dt.history()) == 4
+
+    df = pl.read_delta(path)
+    assert df.height == 4, df.height
+    assert df.filter(pl.col("contract") == "reputation")["block_number"].to_list() == [30]
+
+    print(f"{path}: version {dt.version()}, {table.num_rows} rows read by deltalake and polars")
+
+
+if __name__ == "__main__":
+    main(sys.argv[1])